@@ -2,6 +2,7 @@ use crate::Error;
 use image::DynamicImage;
 use tf_asset_loader::Loader;
 use tracing::{error, instrument};
+use vmdl::paths;
 use vmt_parser::from_str;
 use vtf::vtf::VTF;
 
@@ -45,14 +46,9 @@ pub fn load_material(
 ) -> Result<MaterialData, Error> {
     let dirs = search_dirs
         .iter()
-        .map(|dir| {
-            format!(
-                "materials/{}",
-                dir.to_ascii_lowercase().trim_start_matches("/")
-            )
-        })
+        .map(|dir| format!("materials/{}", paths::canonical_directory(dir)))
         .collect::<Vec<_>>();
-    let path = format!("{}.vmt", name.to_ascii_lowercase().trim_end_matches(".vmt"));
+    let path = format!("{}.vmt", paths::canonical_material_name(name));
     let path = loader
         .find_in_paths(&path, &dirs)
         .ok_or_else(|| Error::Other(format!("Can't find file {}", path)))?;
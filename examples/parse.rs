@@ -26,7 +26,7 @@ fn main() -> Result<(), vmdl::ModelError> {
         );
     }
 
-    dbg!(Matrix4::from(mdl.bones[1].pose_to_bone.clone()));
+    dbg!(Matrix4::from(mdl.bones[1].pose_to_bone));
 
     // for animation_desc in &mdl.local_animations {
     //     println!(
@@ -5,7 +5,7 @@ mod material;
 
 use crate::error::Error;
 use crate::material::{load_material_fallback, MaterialData};
-use cgmath::{vec3, Matrix4, SquareMatrix, Vector3};
+use cgmath::{vec3, Matrix4, SquareMatrix};
 use std::collections::HashMap;
 use std::env::args_os;
 use std::path::PathBuf;
@@ -52,8 +52,8 @@ fn main() -> Result<(), Error> {
     .unwrap();
     let context = window.gl();
 
-    let (bb_min, bb_max) = source_model.bounding_box();
-    let bb_center = Vector3::from(map_coords((bb_min + bb_max) * 0.5));
+    let (bb_min, bb_max) = source_model.view_box();
+    let bb_center = map_coords((bb_min + bb_max) * 0.5);
 
     let mut camera = Camera::new_perspective(
         window.viewport(),
@@ -294,14 +294,15 @@ fn model_to_model(
         .animations()
         .nth(animation)
         .unwrap_or_else(|| model.animations().next().unwrap());
+    let pose = model.animation_pose(animation, frame as f32);
 
     let geometries = model
         .meshes()
         .map(|mesh| {
             let positions: Vec<Vec3> = mesh
                 .vertices()
-                .map(|vertex| model.apply_animation(animation, vertex, frame))
-                .map(|position| map_coords(position))
+                .map(|vertex| pose.skin_vertex(vertex).position)
+                .map(map_coords)
                 .map(|vertex: Vec3| (transforms * vertex.extend(1.0)).truncate())
                 .collect();
             let normals: Vec<Vec3> = mesh.vertices().map(|vertex| vertex.normal.into()).collect();
@@ -309,7 +310,10 @@ fn model_to_model(
                 .vertices()
                 .map(|vertex| vertex.texture_coordinates.into())
                 .collect();
-            let tangents: Vec<Vec4> = mesh.tangents().map(Vec4::from).collect();
+            let tangents: Vec<Vec4> = mesh
+                .tangents()
+                .map(|tangent| Vec4::from(<[f32; 4]>::from(tangent)))
+                .collect();
 
             let triangles = CpuMesh {
                 positions: Positions::F32(positions),
@@ -0,0 +1,50 @@
+#![no_main]
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use vmdl::mdl::{Patch, WriteError, WriteOptions};
+use vmdl::{Mdl, Vector};
+
+/// Structurally valid model to apply the patches to
+const MODEL: &[u8] = include_bytes!("../../data/barrel01.mdl");
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    patches: Vec<Patch>,
+    options: WriteOptions,
+}
+
+fn bits([min, max]: [Vector; 2]) -> [u32; 6] {
+    [min.x, min.y, min.z, max.x, max.y, max.z].map(f32::to_bits)
+}
+
+fn fuzz(input: Input) {
+    let mut mdl = Mdl::read(MODEL).expect("the base model can be parsed");
+    for patch in &input.patches {
+        mdl.apply(patch);
+    }
+    let written = match mdl.write_with_options(MODEL, input.options) {
+        Ok(written) => written,
+        Err(WriteError::NullByte(_)) => return,
+        Err(error) => panic!("patched model can't be written: {}", error),
+    };
+
+    let read = Mdl::read(&written).expect("written model can't be parsed");
+    assert_eq!(read.surface_prop, mdl.surface_prop);
+    assert_eq!(read.header.flags.bits(), mdl.header.flags.bits());
+    assert_eq!(read.texture_paths, mdl.texture_paths);
+    for (read, bone) in read.bones.iter().zip(&mdl.bones) {
+        assert_eq!(read.surface_prop, bone.surface_prop);
+    }
+    for (read, texture) in read.textures.iter().zip(&mdl.textures) {
+        assert_eq!(read.name, texture.name);
+    }
+    for (read, sequence) in read.animation_sequences.iter().zip(&mdl.animation_sequences) {
+        assert_eq!(bits(read.bounding_box), bits(sequence.bounding_box));
+    }
+
+    // writing the written model without changes doesn't change it
+    let rewritten = read.write(&written).expect("written model can't be written");
+    assert_eq!(rewritten, written);
+}
+
+fuzz_target!(|input: Input| fuzz(input));
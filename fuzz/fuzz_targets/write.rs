@@ -0,0 +1,46 @@
+#![no_main]
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use vmdl::{Mdl, Vector};
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    data: Vec<u8>,
+    surface_prop: String,
+    bounding_box: Option<[Vector; 2]>,
+}
+
+fn bits(vector: Vector) -> [u32; 3] {
+    [vector.x, vector.y, vector.z].map(f32::to_bits)
+}
+
+fn fuzz(input: Input) {
+    let Ok(mut mdl) = Mdl::read(&input.data) else {
+        return;
+    };
+    // strings are stored null terminated
+    mdl.surface_prop = input.surface_prop.replace('\0', "");
+    if let (Some(bounding_box), Some(sequence)) =
+        (input.bounding_box, mdl.animation_sequences.first_mut())
+    {
+        sequence.bounding_box = bounding_box;
+    }
+    let Ok(written) = mdl.write(&input.data) else {
+        return;
+    };
+
+    let read = Mdl::read(&written).expect("written model can't be parsed");
+    assert_eq!(read.surface_prop, mdl.surface_prop);
+    for (read, sequence) in read.animation_sequences.iter().zip(&mdl.animation_sequences) {
+        assert_eq!(
+            read.bounding_box.map(bits),
+            sequence.bounding_box.map(bits)
+        );
+    }
+
+    // writing the written model without changes doesn't change it
+    let rewritten = read.write(&written).expect("written model can't be written");
+    assert_eq!(rewritten, written);
+}
+
+fuzz_target!(|input: Input| fuzz(input));
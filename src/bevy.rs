@@ -0,0 +1,166 @@
+//! Integration with the [Bevy](https://bevyengine.org) game engine
+//!
+//! Add the [`VmdlPlugin`] to the app to load `.mdl` files as [`SourceModel`] assets,
+//! the `.dx90.vtx` and `.vvd` files are loaded from the same directory.
+//!
+//! All geometry is kept in the source engine coordinate space, which is Z-up.
+//!
+//! This feature needs rust 1.85 or newer, above the minimum supported version of the rest of the crate,
+//! because Bevy and its dependencies require it.
+
+use crate::{Mdl, Model, ModelError, Vtx, Vvd};
+use bevy_app::{App, Plugin};
+use bevy_asset::io::Reader;
+use bevy_asset::{Asset, AssetApp, AssetLoader, Handle, LoadContext, RenderAssetUsages};
+use bevy_math::Mat4;
+use bevy_reflect::TypePath;
+use bevy_render::mesh::skinning::SkinnedMeshInverseBindposes;
+use bevy_render::mesh::{Indices, Mesh as BevyMesh, PrimitiveTopology, VertexAttributeValues};
+use std::collections::HashMap;
+use std::io;
+
+/// Registers the [`SourceModel`] asset and its loader
+pub struct VmdlPlugin;
+
+impl Plugin for VmdlPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<SourceModel>()
+            .init_asset_loader::<SourceModelLoader>();
+    }
+}
+
+/// A loaded model, with the meshes and joint data converted for rendering
+#[derive(Asset, TypePath)]
+pub struct SourceModel {
+    pub model: Model,
+    pub meshes: Vec<Handle<BevyMesh>>,
+    pub inverse_bindposes: Handle<SkinnedMeshInverseBindposes>,
+}
+
+#[derive(Default)]
+pub struct SourceModelLoader;
+
+impl AssetLoader for SourceModelLoader {
+    type Asset = SourceModel;
+    type Settings = ();
+    type Error = ModelError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await?;
+        let mdl = Mdl::read(&data)?;
+
+        let path = load_context.path().to_path_buf();
+        let data = load_context
+            .read_asset_bytes(path.with_extension("dx90.vtx"))
+            .await
+            .map_err(io::Error::other)?;
+        let vtx = Vtx::read(&data)?;
+        let data = load_context
+            .read_asset_bytes(path.with_extension("vvd"))
+            .await
+            .map_err(io::Error::other)?;
+        let vvd = Vvd::read(&data)?;
+
+        let model = Model::try_from_parts(mdl, vtx, vvd)?;
+        let meshes = model
+            .meshes()
+            .enumerate()
+            .map(|(i, mesh)| load_context.add_labeled_asset(format!("Mesh{i}"), (&mesh).into()))
+            .collect();
+        let inverse_bindposes = load_context
+            .add_labeled_asset("InverseBindposes".into(), model.bevy_inverse_bindposes());
+
+        Ok(SourceModel {
+            model,
+            meshes,
+            inverse_bindposes,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["mdl"]
+    }
+}
+
+impl Model {
+    /// The inverse bind pose for every bone, the joint indices of the converted meshes index into this list
+    pub fn bevy_inverse_bindposes(&self) -> SkinnedMeshInverseBindposes {
+        self.bones()
+            .map(|bone| {
+                let matrix = bone.pose_to_bone.matrix();
+                let columns: &[f32; 16] = matrix.as_ref();
+                Mat4::from_cols_array(columns)
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+impl From<&crate::Mesh<'_>> for BevyMesh {
+    fn from(mesh: &crate::Mesh<'_>) -> Self {
+        let mut local_indices = HashMap::new();
+        let mut vertex_indices = Vec::new();
+        let indices = mesh
+            .triangles_with_tangents()
+            .flatten()
+            .map(|index| {
+                *local_indices.entry(index).or_insert_with(|| {
+                    vertex_indices.push(index);
+                    vertex_indices.len() as u32 - 1
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // the vertex and tangent of every index are checked by `triangles_with_tangents`
+        let vertices = vertex_indices
+            .iter()
+            .map(|index| &mesh.vertices[*index])
+            .collect::<Vec<_>>();
+        let positions: Vec<[f32; 3]> = vertices.iter().map(|v| v.position.into()).collect();
+        let normals: Vec<[f32; 3]> = vertices.iter().map(|v| v.normal.into()).collect();
+        let uvs: Vec<[f32; 2]> = vertices.iter().map(|v| v.texture_coordinates).collect();
+        let tangents: Vec<[f32; 4]> = vertex_indices
+            .iter()
+            .map(|index| mesh.tangents[*index].into())
+            .collect();
+        let (joints, weights): (Vec<[u16; 4]>, Vec<[f32; 4]>) = vertices
+            .iter()
+            .map(|vertex| {
+                let mut joints = [0; 4];
+                let mut weights = [0.0; 4];
+                for (i, weight) in vertex.bone_weights.weights().enumerate() {
+                    joints[i] = usize::from(weight.bone_id) as u16;
+                    weights[i] = weight.weight;
+                }
+                let total: f32 = weights.iter().sum();
+                if total > 0.0 {
+                    weights.iter_mut().for_each(|weight| *weight /= total);
+                } else {
+                    weights[0] = 1.0;
+                }
+                (joints, weights)
+            })
+            .unzip();
+
+        BevyMesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(BevyMesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(BevyMesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(BevyMesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_attribute(BevyMesh::ATTRIBUTE_TANGENT, tangents)
+        .with_inserted_attribute(
+            BevyMesh::ATTRIBUTE_JOINT_INDEX,
+            VertexAttributeValues::Uint16x4(joints),
+        )
+        .with_inserted_attribute(BevyMesh::ATTRIBUTE_JOINT_WEIGHT, weights)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+}
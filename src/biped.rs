@@ -0,0 +1,203 @@
+//! Classification of bones following the `ValveBiped` naming scheme
+//!
+//! Most humanoid models use bones named like `ValveBiped.Bip01_L_UpperArm`, but the prefix varies between
+//! models (`Bip01 L UpperArm`, `bip01_l_upperarm`, `ValveBiped.Bip01_L_UpperArm`, ...).
+//! [`BipedBone::classify`] maps all those variations onto the same semantic bone.
+
+use crate::mdl::{Bone, BoneId};
+use crate::{Handle, Model};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    fn prefix(&self) -> &'static str {
+        match self {
+            Side::Left => "L",
+            Side::Right => "R",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BipedBone {
+    Pelvis,
+    /// Spine segment, `0` is the lowest segment (`Bip01_Spine`), followed by `Bip01_Spine1` up to `Bip01_Spine4`
+    Spine(u8),
+    Neck,
+    Head,
+    Clavicle(Side),
+    UpperArm(Side),
+    Forearm(Side),
+    Hand(Side),
+    /// Finger segment, fingers are numbered from the thumb (`0`) to the pinky (`4`)
+    /// and segments from the hand outward
+    Finger {
+        side: Side,
+        finger: u8,
+        segment: u8,
+    },
+    Thigh(Side),
+    Calf(Side),
+    Foot(Side),
+    Toe(Side),
+}
+
+impl BipedBone {
+    /// Classify a bone name against the `ValveBiped` naming scheme
+    pub fn classify(name: &str) -> Option<BipedBone> {
+        let name = name.to_ascii_lowercase().replace([' ', '.'], "_");
+        let name = match name.find("bip01_") {
+            Some(start) => &name[start + "bip01_".len()..],
+            None => name.as_str(),
+        };
+
+        match name {
+            "pelvis" => return Some(BipedBone::Pelvis),
+            "spine" => return Some(BipedBone::Spine(0)),
+            "neck" | "neck1" => return Some(BipedBone::Neck),
+            "head" | "head1" => return Some(BipedBone::Head),
+            _ => {}
+        }
+        if let Some(index) = name.strip_prefix("spine") {
+            return index
+                .parse()
+                .ok()
+                .filter(|index| *index <= 4)
+                .map(BipedBone::Spine);
+        }
+
+        let (side, part) = if let Some(part) = name.strip_prefix("l_") {
+            (Side::Left, part)
+        } else if let Some(part) = name.strip_prefix("r_") {
+            (Side::Right, part)
+        } else {
+            return None;
+        };
+
+        Some(match part {
+            "clavicle" => BipedBone::Clavicle(side),
+            "upperarm" => BipedBone::UpperArm(side),
+            "forearm" => BipedBone::Forearm(side),
+            "hand" => BipedBone::Hand(side),
+            "thigh" => BipedBone::Thigh(side),
+            "calf" => BipedBone::Calf(side),
+            "foot" => BipedBone::Foot(side),
+            "toe" | "toe0" => BipedBone::Toe(side),
+            _ => {
+                let digits = part.strip_prefix("finger")?.as_bytes();
+                let digit = |c: u8| c.is_ascii_digit().then(|| c - b'0');
+                let (finger, segment) = match digits {
+                    [finger] => (digit(*finger)?, 0),
+                    [finger, segment] => (digit(*finger)?, digit(*segment)?),
+                    _ => return None,
+                };
+                if finger > 4 || segment > 2 {
+                    return None;
+                }
+                BipedBone::Finger {
+                    side,
+                    finger,
+                    segment,
+                }
+            }
+        })
+    }
+
+    /// The parent of the bone in the standard `ValveBiped` hierarchy
+    pub fn parent(&self) -> Option<BipedBone> {
+        Some(match *self {
+            BipedBone::Pelvis => return None,
+            BipedBone::Spine(0) => BipedBone::Pelvis,
+            BipedBone::Spine(index) => BipedBone::Spine(index - 1),
+            BipedBone::Neck => BipedBone::Spine(4),
+            BipedBone::Head => BipedBone::Neck,
+            BipedBone::Clavicle(_) => BipedBone::Spine(4),
+            BipedBone::UpperArm(side) => BipedBone::Clavicle(side),
+            BipedBone::Forearm(side) => BipedBone::UpperArm(side),
+            BipedBone::Hand(side) => BipedBone::Forearm(side),
+            BipedBone::Finger {
+                side, segment: 0, ..
+            } => BipedBone::Hand(side),
+            BipedBone::Finger {
+                side,
+                finger,
+                segment,
+            } => BipedBone::Finger {
+                side,
+                finger,
+                segment: segment - 1,
+            },
+            BipedBone::Thigh(_) => BipedBone::Pelvis,
+            BipedBone::Calf(side) => BipedBone::Thigh(side),
+            BipedBone::Foot(side) => BipedBone::Calf(side),
+            BipedBone::Toe(side) => BipedBone::Foot(side),
+        })
+    }
+
+    pub fn side(&self) -> Option<Side> {
+        match *self {
+            BipedBone::Clavicle(side)
+            | BipedBone::UpperArm(side)
+            | BipedBone::Forearm(side)
+            | BipedBone::Hand(side)
+            | BipedBone::Finger { side, .. }
+            | BipedBone::Thigh(side)
+            | BipedBone::Calf(side)
+            | BipedBone::Foot(side)
+            | BipedBone::Toe(side) => Some(side),
+            _ => None,
+        }
+    }
+}
+
+impl Display for BipedBone {
+    /// The canonical `ValveBiped` name of the bone
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ValveBiped.Bip01_")?;
+        match self {
+            BipedBone::Pelvis => write!(f, "Pelvis"),
+            BipedBone::Spine(0) => write!(f, "Spine"),
+            BipedBone::Spine(index) => write!(f, "Spine{index}"),
+            BipedBone::Neck => write!(f, "Neck1"),
+            BipedBone::Head => write!(f, "Head1"),
+            BipedBone::Clavicle(side) => write!(f, "{}_Clavicle", side.prefix()),
+            BipedBone::UpperArm(side) => write!(f, "{}_UpperArm", side.prefix()),
+            BipedBone::Forearm(side) => write!(f, "{}_Forearm", side.prefix()),
+            BipedBone::Hand(side) => write!(f, "{}_Hand", side.prefix()),
+            BipedBone::Finger {
+                side,
+                finger,
+                segment: 0,
+            } => write!(f, "{}_Finger{finger}", side.prefix()),
+            BipedBone::Finger {
+                side,
+                finger,
+                segment,
+            } => write!(f, "{}_Finger{finger}{segment}", side.prefix()),
+            BipedBone::Thigh(side) => write!(f, "{}_Thigh", side.prefix()),
+            BipedBone::Calf(side) => write!(f, "{}_Calf", side.prefix()),
+            BipedBone::Foot(side) => write!(f, "{}_Foot", side.prefix()),
+            BipedBone::Toe(side) => write!(f, "{}_Toe0", side.prefix()),
+        }
+    }
+}
+
+impl Model {
+    /// All bones in the model that follow the `ValveBiped` naming scheme
+    pub fn biped_bones(&self) -> impl Iterator<Item = (BipedBone, Handle<'_, Bone, BoneId>)> {
+        self.bones()
+            .filter_map(|bone| Some((BipedBone::classify(&bone.name)?, bone)))
+    }
+
+    /// Find the bone in the model matching a `ValveBiped` bone
+    pub fn biped_bone(&self, biped: BipedBone) -> Option<Handle<'_, Bone, BoneId>> {
+        self.biped_bones()
+            .find(|(bone, _)| *bone == biped)
+            .map(|(_, bone)| bone)
+    }
+}
@@ -0,0 +1,225 @@
+//! Generating convex collision hulls from the vertices of a model
+//!
+//! The hulls can be used as a fallback for models without a `.phy`, approximating the solids the model compiler
+//! creates for every physics bone.
+
+use crate::mdl::BoneId;
+use crate::{Model, Vector};
+use cgmath::{InnerSpace, Point3, Transform, Vector3};
+use std::collections::HashSet;
+use std::f32::consts::PI;
+
+/// Options for generating collision hulls
+#[derive(Debug, Clone, Copy)]
+pub struct HullOptions {
+    /// Maximum number of vertices of a single hull, at least 4
+    pub max_vertices: usize,
+    /// Weight a vertex needs to exceed for a bone to be part of the hull of the bone
+    pub weight_threshold: f32,
+}
+
+impl Default for HullOptions {
+    fn default() -> Self {
+        HullOptions {
+            max_vertices: 64,
+            weight_threshold: 0.25,
+        }
+    }
+}
+
+/// A closed convex triangle mesh
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConvexHull {
+    pub positions: Vec<Vector>,
+    /// Counter-clockwise triangles indexing into the [`positions`](Self::positions), facing outwards
+    pub triangles: Vec<[usize; 3]>,
+}
+
+/// The convex hull of the vertices of a physics bone
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollisionHull {
+    /// The bone the hull is attached to
+    pub bone: BoneId,
+    /// The hull in the space of the bone
+    pub hull: ConvexHull,
+}
+
+impl Model {
+    /// A convex hull for every physics bone of the model, around the vertices weighted to the bones it moves
+    ///
+    /// Bones are grouped by their `physics_bone` with the top most bone of every group getting the hull, bones without
+    /// a physics bone are part of the group of their closest ancestor or of the first root bone.
+    /// Groups without enough vertices to enclose a volume are skipped.
+    pub fn collision_hulls(&self, options: HullOptions) -> Vec<CollisionHull> {
+        // physics bone index and the bones moved by it, in tree order so the first bone is the top most
+        let mut groups: Vec<(Option<usize>, Vec<BoneId>)> = Vec::new();
+        let mut bone_groups: Vec<Option<usize>> = vec![None; self.mdl.bones.len()];
+        for bone in self.bone_tree() {
+            let group = usize::try_from(bone.physics_bone).ok().or_else(|| {
+                let parent = bone.parent()?;
+                bone_groups[usize::from(parent.key())]
+            });
+            bone_groups[usize::from(bone.key())] = group;
+            match groups.iter_mut().find(|(index, _)| *index == group) {
+                Some((_, bones)) => bones.push(bone.key()),
+                None => groups.push((group, vec![bone.key()])),
+            }
+        }
+
+        groups
+            .into_iter()
+            .filter_map(|(_, bones)| {
+                let bone = self.bone(*bones.first()?)?;
+                let pose_to_bone = bone.pose_to_bone.matrix();
+                let mut included = vec![false; self.vertices().len()];
+                for id in &bones {
+                    for index in self.vertices_weighted_to(*id, options.weight_threshold) {
+                        included[index] = true;
+                    }
+                }
+                let points: Vec<Vector> = self
+                    .vertices()
+                    .iter()
+                    .zip(included)
+                    .filter(|(_, included)| *included)
+                    .map(|(vertex, _)| {
+                        let position = vertex.position;
+                        let point = pose_to_bone
+                            .transform_point(Point3::new(position.x, position.y, position.z));
+                        Vector {
+                            x: point.x,
+                            y: point.y,
+                            z: point.z,
+                        }
+                    })
+                    .collect();
+                Some(CollisionHull {
+                    bone: bone.key(),
+                    hull: convex_hull(&points, options.max_vertices)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The convex hull of a set of points with at most `max_vertices` vertices
+///
+/// Hulls with too many vertices are simplified to the points that are furthest out in evenly distributed directions,
+/// making the simplified hull lie within the full hull. Returns `None` if the points don't enclose any volume.
+pub fn convex_hull(points: &[Vector], max_vertices: usize) -> Option<ConvexHull> {
+    if max_vertices < 4 {
+        return None;
+    }
+    let points: Vec<Vector3<f32>> = points.iter().copied().map(Vector3::from).collect();
+    let (mut positions, mut triangles) = compact(&points, &hull_triangles(&points)?);
+    if positions.len() > max_vertices {
+        let reduced = support_points(&positions, max_vertices);
+        (positions, triangles) = compact(&reduced, &hull_triangles(&reduced)?);
+    }
+    Some(ConvexHull {
+        positions: positions.into_iter().map(Vector::from).collect(),
+        triangles,
+    })
+}
+
+/// The triangles of the hull, built by adding one point at a time and replacing the faces it can see
+fn hull_triangles(points: &[Vector3<f32>]) -> Option<Vec<[usize; 3]>> {
+    let extent = points
+        .iter()
+        .map(|point| point.x.abs().max(point.y.abs()).max(point.z.abs()))
+        .fold(0.0, f32::max);
+    let epsilon = extent.max(1.0) * 1e-5;
+
+    let furthest = |distance: &dyn Fn(Vector3<f32>) -> f32| {
+        (0..points.len())
+            .map(|index| (index, distance(points[index])))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .filter(|(_, distance)| *distance > epsilon)
+            .map(|(index, _)| index)
+    };
+    let a = (0..points.len()).min_by(|a, b| points[*a].x.total_cmp(&points[*b].x))?;
+    let b = furthest(&|point| (point - points[a]).magnitude())?;
+    let line = (points[b] - points[a]).normalize();
+    let c = furthest(&|point| (point - points[a]).cross(line).magnitude())?;
+    let plane = (points[b] - points[a])
+        .cross(points[c] - points[a])
+        .normalize();
+    let d = furthest(&|point| plane.dot(point - points[a]).abs())?;
+
+    let normal = |[a, b, c]: [usize; 3]| (points[b] - points[a]).cross(points[c] - points[a]);
+    let outside = |face: [usize; 3], point: Vector3<f32>| {
+        normal(face).normalize().dot(point - points[face[0]]) > epsilon
+    };
+    let (b, c) = if outside([a, b, c], points[d]) {
+        (c, b)
+    } else {
+        (b, c)
+    };
+    let mut faces = vec![[a, b, c], [a, d, b], [b, d, c], [c, d, a]];
+
+    for (index, point) in points.iter().enumerate() {
+        let (visible, hidden): (Vec<[usize; 3]>, Vec<[usize; 3]>) = std::mem::take(&mut faces)
+            .into_iter()
+            .partition(|face| outside(*face, *point));
+        faces = hidden;
+        if visible.is_empty() {
+            continue;
+        }
+        // edges between a visible and a hidden face form the horizon that gets connected to the new point
+        let edges: Vec<(usize, usize)> = visible
+            .iter()
+            .flat_map(|&[a, b, c]| [(a, b), (b, c), (c, a)])
+            .collect();
+        let lookup: HashSet<(usize, usize)> = edges.iter().copied().collect();
+        faces.extend(
+            edges
+                .into_iter()
+                .filter(|(a, b)| !lookup.contains(&(*b, *a)))
+                .map(|(a, b)| [a, b, index]),
+        );
+    }
+    Some(faces)
+}
+
+/// Only the points used by the triangles, with the triangles remapped to the remaining points
+fn compact(
+    points: &[Vector3<f32>],
+    triangles: &[[usize; 3]],
+) -> (Vec<Vector3<f32>>, Vec<[usize; 3]>) {
+    let mut remap = vec![0; points.len()];
+    let mut used = Vec::new();
+    let mut seen = vec![false; points.len()];
+    for index in triangles.iter().flatten() {
+        if !seen[*index] {
+            seen[*index] = true;
+            remap[*index] = used.len();
+            used.push(points[*index]);
+        }
+    }
+    let triangles = triangles
+        .iter()
+        .map(|triangle| triangle.map(|index| remap[index]))
+        .collect();
+    (used, triangles)
+}
+
+/// The points furthest along `count` directions spread evenly over the sphere
+fn support_points(points: &[Vector3<f32>], count: usize) -> Vec<Vector3<f32>> {
+    let golden_angle = PI * (3.0 - 5.0f32.sqrt());
+    let mut indices: Vec<usize> = (0..count)
+        .filter_map(|index| {
+            let z = 1.0 - 2.0 * (index as f32 + 0.5) / count as f32;
+            let radius = (1.0 - z * z).sqrt();
+            let angle = golden_angle * index as f32;
+            let direction = Vector3::new(radius * angle.cos(), radius * angle.sin(), z);
+            (0..points.len()).max_by(|a, b| {
+                direction
+                    .dot(points[*a])
+                    .total_cmp(&direction.dot(points[*b]))
+            })
+        })
+        .collect();
+    indices.sort_unstable();
+    indices.dedup();
+    indices.into_iter().map(|index| points[index]).collect()
+}
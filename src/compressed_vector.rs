@@ -25,6 +25,16 @@ impl Vector48 {
     }
 }
 
+impl From<Vector> for Vector48 {
+    fn from(value: Vector) -> Self {
+        Vector48 {
+            x: f16::from_f32(value.x).to_bits(),
+            y: f16::from_f32(value.y).to_bits(),
+            z: f16::from_f32(value.z).to_bits(),
+        }
+    }
+}
+
 impl From<Vector48> for Vector {
     fn from(value: Vector48) -> Self {
         Vector {
@@ -95,6 +105,20 @@ impl From<Quaternion48> for Quaternion {
     }
 }
 
+impl From<Quaternion> for Quaternion48 {
+    fn from(value: Quaternion) -> Self {
+        let quantize = |value: f32, offset: f32, max: f32| {
+            (value * offset + offset).round().clamp(0.0, max) as u16
+        };
+        let w_neg = if value.w < 0.0 { Self::W_NEG_MASK } else { 0 };
+        Quaternion48 {
+            x: quantize(value.x, 32768.0, 65535.0),
+            y: quantize(value.y, 32768.0, 65535.0),
+            z: quantize(value.z, 16384.0, Self::Z_MASK as f32) | w_neg,
+        }
+    }
+}
+
 #[derive(Zeroable, Pod, Copy, Clone, Debug)]
 #[repr(C)]
 pub struct Quaternion64(u64);
@@ -128,6 +152,18 @@ impl Quaternion64 {
     }
 }
 
+impl From<Quaternion> for Quaternion64 {
+    fn from(value: Quaternion) -> Self {
+        let quantize = |value: f32| {
+            (value * 1048576.5 + 1048576.0)
+                .round()
+                .clamp(0.0, Self::MASK_21_BIT as f32) as u64
+        };
+        let w_neg = if value.w < 0.0 { Self::W_NEG_MASK } else { 0 };
+        Quaternion64(quantize(value.x) | quantize(value.y) << 21 | quantize(value.z) << 42 | w_neg)
+    }
+}
+
 impl From<Quaternion64> for Quaternion {
     fn from(value: Quaternion64) -> Self {
         let normalized = Vector4::new(value.x(), value.y(), value.z(), value.w());
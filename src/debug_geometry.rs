@@ -0,0 +1,186 @@
+//! Geometry for visualizing the skeleton, hitboxes, attachments and bounds of a model
+//!
+//! All geometry is in model space, for the bones in the provided pose. Use [`Model::rest_pose`] for the bind pose.
+
+use crate::mdl::HitBoxSet;
+use crate::{Model, Pose, Vector};
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3, Vector4};
+
+pub const BONE_COLOR: [f32; 3] = [0.9, 0.9, 0.9];
+pub const HITBOX_COLOR: [f32; 3] = [1.0, 0.6, 0.0];
+pub const HULL_COLOR: [f32; 3] = [1.0, 0.0, 1.0];
+pub const VIEW_COLOR: [f32; 3] = [0.0, 1.0, 1.0];
+/// Colors of the x, y and z axes of attachments
+pub const AXIS_COLORS: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// A colored line segment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugLine {
+    pub start: Vector,
+    pub end: Vector,
+    pub color: [f32; 3],
+}
+
+/// A triangle mesh
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DebugMesh {
+    pub positions: Vec<Vector>,
+    /// Counter-clockwise triangles indexing into the [`positions`](Self::positions)
+    pub triangles: Vec<[usize; 3]>,
+}
+
+/// An octahedron for every bone with a parent, pointing from the parent to the bone
+pub fn skeleton(model: &Model, pose: &Pose) -> DebugMesh {
+    let transforms = pose.world_transforms();
+    let mut mesh = DebugMesh::default();
+    for bone in model.bones() {
+        let Some(parent) = bone.parent() else {
+            continue;
+        };
+        let (Some(head), Some(tail)) = (
+            transforms.get(usize::from(parent.key())),
+            transforms.get(usize::from(bone.key())),
+        ) else {
+            continue;
+        };
+        push_octahedron(&mut mesh, head.w.truncate(), tail.w.truncate());
+    }
+    mesh
+}
+
+/// The bones of the skeleton as lines from the parent to the bone
+pub fn skeleton_lines(model: &Model, pose: &Pose) -> Vec<DebugLine> {
+    let transforms = pose.world_transforms();
+    model
+        .bones()
+        .filter_map(|bone| {
+            let parent = bone.parent()?;
+            Some(DebugLine {
+                start: transforms
+                    .get(usize::from(parent.key()))?
+                    .w
+                    .truncate()
+                    .into(),
+                end: transforms.get(usize::from(bone.key()))?.w.truncate().into(),
+                color: BONE_COLOR,
+            })
+        })
+        .collect()
+}
+
+/// The edges of every hitbox in a set
+pub fn hitboxes(model: &Model, set: &HitBoxSet, pose: &Pose) -> Vec<DebugLine> {
+    model
+        .posed_hitboxes(set, pose)
+        .into_iter()
+        .flat_map(|posed| {
+            box_lines(
+                posed.hitbox.min,
+                posed.hitbox.max,
+                posed.transform,
+                HITBOX_COLOR,
+            )
+        })
+        .collect()
+}
+
+/// The axes of every attachment, `size` units long
+///
+/// Attachments with a bone that doesn't exist are skipped.
+pub fn attachment_axes(model: &Model, pose: &Pose, size: f32) -> Vec<DebugLine> {
+    let transforms = pose.world_transforms();
+    model
+        .attachments()
+        .filter_map(|attachment| {
+            let bone = attachment.bone().ok()?;
+            Some(transforms.get(usize::from(bone.key()))? * attachment.local.matrix())
+        })
+        .flat_map(|transform| {
+            let origin = transform.w.truncate();
+            [transform.x, transform.y, transform.z]
+                .into_iter()
+                .zip(AXIS_COLORS)
+                .map(move |(axis, color)| DebugLine {
+                    start: origin.into(),
+                    end: (origin + axis.truncate().normalize_to(size)).into(),
+                    color,
+                })
+        })
+        .collect()
+}
+
+/// The edges of the [`hull_box`](Model::hull_box) and [`view_box`](Model::view_box)
+pub fn bounding_boxes(model: &Model) -> Vec<DebugLine> {
+    let (hull_min, hull_max) = model.hull_box();
+    let (view_min, view_max) = model.view_box();
+    box_lines(hull_min, hull_max, Matrix4::identity(), HULL_COLOR)
+        .into_iter()
+        .chain(box_lines(
+            view_min,
+            view_max,
+            Matrix4::identity(),
+            VIEW_COLOR,
+        ))
+        .collect()
+}
+
+fn box_lines(min: Vector, max: Vector, transform: Matrix4<f32>, color: [f32; 3]) -> Vec<DebugLine> {
+    let corner = |index: usize| -> Vector {
+        let pick = |bit: usize, min: f32, max: f32| if index & bit == 0 { min } else { max };
+        let local = Vector4::new(
+            pick(1, min.x, max.x),
+            pick(2, min.y, max.y),
+            pick(4, min.z, max.z),
+            1.0,
+        );
+        (transform * local).truncate().into()
+    };
+    // corners that differ in a single bit share an edge
+    (0..8)
+        .flat_map(|start| [1, 2, 4].map(|bit| (start, start | bit)))
+        .filter(|(start, end)| start != end)
+        .map(|(start, end)| DebugLine {
+            start: corner(start),
+            end: corner(end),
+            color,
+        })
+        .collect()
+}
+
+fn push_octahedron(mesh: &mut DebugMesh, head: Vector3<f32>, tail: Vector3<f32>) {
+    let direction = tail - head;
+    let length = direction.magnitude();
+    if length <= f32::EPSILON {
+        return;
+    }
+    let forward = direction / length;
+    // any axis that isn't parallel to the bone works for finding the perpendicular axes
+    let reference = if forward.x.abs() < 0.9 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+    let side = forward.cross(reference).normalize();
+    let up = forward.cross(side);
+    let width = length * 0.1;
+    let middle = head + direction * 0.1;
+
+    let start = mesh.positions.len();
+    mesh.positions.extend(
+        [
+            head,
+            middle + side * width,
+            middle + up * width,
+            middle - side * width,
+            middle - up * width,
+            tail,
+        ]
+        .map(Vector::from),
+    );
+    for i in 0..4 {
+        let ring = start + 1 + i;
+        let next = start + 1 + (i + 1) % 4;
+        mesh.triangles.push([start, next, ring]);
+        mesh.triangles.push([start + 5, ring, next]);
+    }
+}
@@ -8,10 +8,52 @@ pub enum ModelError {
     IO(#[from] std::io::Error),
     #[error(transparent)]
     String(#[from] StringError),
+    /// Data referenced at an offset past the end of the file
+    ///
+    /// The offset is relative to the start of the file, or `usize::MAX` for negative offsets.
     #[error("referenced data to {data} is out of bounds at {offset}")]
     OutOfBounds { data: &'static str, offset: usize },
+    /// A read starting at the contained file offset ran past the end of the file
     #[error("Trying to read past the end of the file")]
     Eof(usize),
+    /// An index stored in the model data doesn't point to an existing item
+    #[error("{data} index {index} is out of bounds")]
+    InvalidIndex { data: &'static str, index: usize },
+    #[error("the mdl has {mdl} {data}{location} but the vtx has {vtx}")]
+    Mismatch {
+        data: &'static str,
+        /// The body part and model containing the mismatched data, empty for body parts
+        location: String,
+        mdl: usize,
+        vtx: usize,
+    },
+    #[error("unsupported model version {0}")]
+    UnsupportedVersion(i32),
+    #[error("failed to read {section}: {source}")]
+    Section {
+        section: &'static str,
+        source: Box<ModelError>,
+    },
+}
+
+impl ModelError {
+    /// Rebase the offsets of the error from data starting at `base` to the data containing it
+    pub(crate) fn offset_by(self, base: usize) -> Self {
+        match self {
+            ModelError::OutOfBounds { data, offset } if offset != usize::MAX => {
+                ModelError::OutOfBounds {
+                    data,
+                    offset: offset.saturating_add(base),
+                }
+            }
+            ModelError::Eof(offset) => ModelError::Eof(offset.saturating_add(base)),
+            ModelError::Section { section, source } => ModelError::Section {
+                section,
+                source: Box::new(source.offset_by(base)),
+            },
+            error => error,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -0,0 +1,124 @@
+use crate::mdl::BoneId;
+use crate::vvd::Vertex;
+use crate::{paths, Mesh, Model};
+use cgmath::{Matrix4, SquareMatrix};
+use itertools::Itertools;
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Make a name usable as an identifier, replacing unsupported characters with `_`
+pub fn identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !identifier.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        identifier.insert(0, '_');
+    }
+    identifier
+}
+
+/// The name of the model without directories and extension
+pub fn model_name(model: &Model) -> String {
+    let name = paths::normalize_separators(model.name());
+    let name = name.rsplit('/').next().unwrap_or_default();
+    identifier(name.strip_suffix(".mdl").unwrap_or(name))
+}
+
+/// The bind pose of every bone, in model space
+pub fn bind_transforms(model: &Model) -> Vec<Matrix4<f32>> {
+    model
+        .bones()
+        .map(|bone| {
+            bone.pose_to_bone
+                .matrix()
+                .invert()
+                .unwrap_or_else(Matrix4::identity)
+        })
+        .collect()
+}
+
+/// The bind pose of every bone, relative to its parent
+pub fn local_bind_transforms(model: &Model) -> Vec<Matrix4<f32>> {
+    let bind = bind_transforms(model);
+    model
+        .bones()
+        .map(|bone| {
+            let transform = bind[usize::from(bone.key())];
+            match bone.parent() {
+                Some(parent) => {
+                    let parent = bind[usize::from(parent.key())];
+                    parent.invert().unwrap_or_else(Matrix4::identity) * transform
+                }
+                None => transform,
+            }
+        })
+        .collect()
+}
+
+/// Path of the material texture for a mesh, relative to the `materials` directory
+pub fn material_path(model: &Model, skin: usize, mesh: &Mesh) -> Option<String> {
+    let skin = model.skin_tables().nth(skin)?;
+    let texture = skin.texture_info(mesh.material_index())?;
+    Some(match texture.search_paths.first() {
+        Some(dir) => paths::join(dir, &texture.name),
+        None => texture.canonical_name(),
+    })
+}
+
+/// The vertices used by a mesh and its triangles indexing into those vertices
+pub struct MeshData<'a> {
+    pub vertices: Vec<&'a Vertex>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl<'a> MeshData<'a> {
+    /// Deduplicate the vertices of a mesh
+    ///
+    /// Triangles with a vertex that doesn't exist are skipped with a warning.
+    pub fn new(mesh: &Mesh<'a>) -> Self {
+        let vertices = mesh.vertices;
+        let mut local_indices = HashMap::new();
+        let mut mesh_vertices = Vec::new();
+        let triangles = mesh
+            .vertex_strip_indices()
+            .flatten()
+            .tuples()
+            .filter_map(|(a, b, c)| {
+                let lookup = |index: usize| Some((index, vertices.get(index)?));
+                let (Some(a), Some(b), Some(c)) = (lookup(a), lookup(b), lookup(c)) else {
+                    warn!(a, b, c, "skipping triangle with invalid vertex");
+                    return None;
+                };
+                Some([a, b, c].map(|(index, vertex)| {
+                    *local_indices.entry(index).or_insert_with(|| {
+                        mesh_vertices.push(vertex);
+                        mesh_vertices.len() - 1
+                    })
+                }))
+            })
+            .collect();
+        MeshData {
+            vertices: mesh_vertices,
+            triangles,
+        }
+    }
+}
+
+/// The bone influences of a vertex, normalized to sum up to 1
+pub fn vertex_weights(vertex: &Vertex) -> Vec<(BoneId, f32)> {
+    let weights: Vec<_> = vertex
+        .bone_weights
+        .weights()
+        .map(|weight| (weight.bone_id, weight.weight))
+        .collect();
+    let total: f32 = weights.iter().map(|(_, weight)| weight).sum();
+    if total > 0.0 {
+        weights
+            .into_iter()
+            .map(|(bone, weight)| (bone, weight / total))
+            .collect()
+    } else {
+        vec![(BoneId::default(), 1.0)]
+    }
+}
@@ -0,0 +1,523 @@
+//! Export models as Collada (`.dae`) documents
+//!
+//! The exported document contains the meshes, the skeleton with skinning weights and every
+//! animation of the model as a separate animation clip.
+//! Textures are referenced as `.png` files relative to the `materials` directory.
+
+use super::common::{
+    identifier, local_bind_transforms, material_path, model_name, vertex_weights, MeshData,
+};
+use crate::mdl::{
+    AnimationDescription, AnimationFlags, PositionData, RotationData, StudioAnimFlags,
+};
+use crate::{Handle, Model};
+use cgmath::{Matrix4, SquareMatrix};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Write a model as a Collada document, using the materials of a skin
+pub fn write_dae<W: Write>(model: &Model, skin: usize, mut writer: W) -> io::Result<()> {
+    writer.write_all(to_dae(model, skin).as_bytes())
+}
+
+/// Convert a model to a Collada document, using the materials of a skin
+pub fn to_dae(model: &Model, skin: usize) -> String {
+    let name = model_name(model);
+    let meshes: Vec<_> = model
+        .meshes()
+        .map(|mesh| {
+            let material = material_path(model, skin, &mesh);
+            (MeshData::new(&mesh), material)
+        })
+        .collect();
+    let mut materials: Vec<String> = Vec::new();
+    for path in meshes.iter().filter_map(|(_, material)| material.as_ref()) {
+        if !materials.contains(path) {
+            materials.push(path.clone());
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#).unwrap();
+    writeln!(
+        out,
+        r#"<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">"#
+    )
+    .unwrap();
+    writeln!(out, "  <asset>").unwrap();
+    writeln!(out, r#"    <unit name="inch" meter="0.0254"/>"#).unwrap();
+    writeln!(out, "    <up_axis>Z_UP</up_axis>").unwrap();
+    writeln!(out, "  </asset>").unwrap();
+
+    write_materials(&mut out, &materials);
+
+    writeln!(out, "  <library_geometries>").unwrap();
+    for (index, (data, material)) in meshes.iter().enumerate() {
+        let material = material
+            .as_ref()
+            .and_then(|path| materials.iter().position(|existing| existing == path));
+        write_geometry(&mut out, index, data, material);
+    }
+    writeln!(out, "  </library_geometries>").unwrap();
+
+    writeln!(out, "  <library_controllers>").unwrap();
+    for (index, (data, _)) in meshes.iter().enumerate() {
+        write_skin(&mut out, model, index, data);
+    }
+    writeln!(out, "  </library_controllers>").unwrap();
+
+    write_animations(&mut out, model);
+
+    writeln!(out, "  <library_visual_scenes>").unwrap();
+    writeln!(
+        out,
+        r#"    <visual_scene id="scene" name="{}">"#,
+        escape(&name)
+    )
+    .unwrap();
+    let local = local_bind_transforms(model);
+    for root in model.root_bones() {
+        write_joint(&mut out, &root, &local, 3);
+    }
+    for (index, (_, material)) in meshes.iter().enumerate() {
+        let material = material
+            .as_ref()
+            .and_then(|path| materials.iter().position(|existing| existing == path));
+        writeln!(out, r#"      <node id="mesh_{index}" name="mesh_{index}">"#).unwrap();
+        writeln!(
+            out,
+            r##"        <instance_controller url="#skin_{index}">"##
+        )
+        .unwrap();
+        for root in model.root_bones() {
+            writeln!(
+                out,
+                "          <skeleton>#joint_{}</skeleton>",
+                usize::from(root.key())
+            )
+            .unwrap();
+        }
+        if let Some(material) = material {
+            writeln!(out, "          <bind_material><technique_common>").unwrap();
+            writeln!(
+                out,
+                r##"            <instance_material symbol="material_{material}" target="#material_{material}"/>"##
+            )
+            .unwrap();
+            writeln!(out, "          </technique_common></bind_material>").unwrap();
+        }
+        writeln!(out, "        </instance_controller>").unwrap();
+        writeln!(out, "      </node>").unwrap();
+    }
+    writeln!(out, "    </visual_scene>").unwrap();
+    writeln!(out, "  </library_visual_scenes>").unwrap();
+    writeln!(out, "  <scene>").unwrap();
+    writeln!(out, r##"    <instance_visual_scene url="#scene"/>"##).unwrap();
+    writeln!(out, "  </scene>").unwrap();
+    writeln!(out, "</COLLADA>").unwrap();
+    out
+}
+
+fn write_materials(out: &mut String, materials: &[String]) {
+    writeln!(out, "  <library_images>").unwrap();
+    for (index, path) in materials.iter().enumerate() {
+        writeln!(
+            out,
+            r#"    <image id="image_{index}" name="{}"><init_from>{}.png</init_from></image>"#,
+            escape(path),
+            escape(path)
+        )
+        .unwrap();
+    }
+    writeln!(out, "  </library_images>").unwrap();
+
+    writeln!(out, "  <library_effects>").unwrap();
+    for index in 0..materials.len() {
+        writeln!(out, r#"    <effect id="effect_{index}"><profile_COMMON>"#).unwrap();
+        writeln!(
+            out,
+            r#"      <newparam sid="surface_{index}"><surface type="2D"><init_from>image_{index}</init_from></surface></newparam>"#
+        )
+        .unwrap();
+        writeln!(
+            out,
+            r#"      <newparam sid="sampler_{index}"><sampler2D><source>surface_{index}</source></sampler2D></newparam>"#
+        )
+        .unwrap();
+        writeln!(
+            out,
+            r#"      <technique sid="common"><lambert><diffuse><texture texture="sampler_{index}" texcoord="uv"/></diffuse></lambert></technique>"#
+        )
+        .unwrap();
+        writeln!(out, "    </profile_COMMON></effect>").unwrap();
+    }
+    writeln!(out, "  </library_effects>").unwrap();
+
+    writeln!(out, "  <library_materials>").unwrap();
+    for (index, path) in materials.iter().enumerate() {
+        writeln!(
+            out,
+            r##"    <material id="material_{index}" name="{}"><instance_effect url="#effect_{index}"/></material>"##,
+            escape(&identifier(path))
+        )
+        .unwrap();
+    }
+    writeln!(out, "  </library_materials>").unwrap();
+}
+
+fn write_geometry(out: &mut String, index: usize, data: &MeshData, material: Option<usize>) {
+    let id = format!("geometry_{index}");
+    writeln!(out, r#"    <geometry id="{id}"><mesh>"#).unwrap();
+    write_float_source(
+        out,
+        &format!("{id}_positions"),
+        data.vertices.iter().flat_map(|vertex| {
+            let p = vertex.position;
+            [p.x, p.y, p.z]
+        }),
+        &["X", "Y", "Z"],
+    );
+    write_float_source(
+        out,
+        &format!("{id}_normals"),
+        data.vertices.iter().flat_map(|vertex| {
+            let n = vertex.normal;
+            [n.x, n.y, n.z]
+        }),
+        &["X", "Y", "Z"],
+    );
+    write_float_source(
+        out,
+        &format!("{id}_uvs"),
+        data.vertices.iter().flat_map(|vertex| {
+            let [u, v] = vertex.texture_coordinates;
+            [u, 1.0 - v]
+        }),
+        &["S", "T"],
+    );
+    writeln!(
+        out,
+        r##"      <vertices id="{id}_vertices"><input semantic="POSITION" source="#{id}_positions"/></vertices>"##
+    )
+    .unwrap();
+    let material = material
+        .map(|material| format!(r#" material="material_{material}""#))
+        .unwrap_or_default();
+    writeln!(
+        out,
+        r#"      <triangles count="{}"{material}>"#,
+        data.triangles.len()
+    )
+    .unwrap();
+    writeln!(
+        out,
+        r##"        <input semantic="VERTEX" source="#{id}_vertices" offset="0"/>"##
+    )
+    .unwrap();
+    writeln!(
+        out,
+        r##"        <input semantic="NORMAL" source="#{id}_normals" offset="0"/>"##
+    )
+    .unwrap();
+    writeln!(
+        out,
+        r##"        <input semantic="TEXCOORD" source="#{id}_uvs" offset="0" set="0"/>"##
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "        <p>{}</p>",
+        join(data.triangles.iter().flatten())
+    )
+    .unwrap();
+    writeln!(out, "      </triangles>").unwrap();
+    writeln!(out, "    </mesh></geometry>").unwrap();
+}
+
+fn write_skin(out: &mut String, model: &Model, index: usize, data: &MeshData) {
+    let id = format!("skin_{index}");
+    let bones: Vec<_> = model.bones().collect();
+    writeln!(
+        out,
+        r##"    <controller id="{id}"><skin source="#geometry_{index}">"##
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "      <bind_shape_matrix>{}</bind_shape_matrix>",
+        matrix(&Matrix4::identity())
+    )
+    .unwrap();
+    writeln!(
+        out,
+        r#"      <source id="{id}_joints"><Name_array id="{id}_joints_array" count="{}">{}</Name_array>"#,
+        bones.len(),
+        join((0..bones.len()).map(|bone| format!("joint_{bone}")))
+    )
+    .unwrap();
+    writeln!(
+        out,
+        r##"        <technique_common><accessor source="#{id}_joints_array" count="{}" stride="1"><param name="JOINT" type="name"/></accessor></technique_common>"##,
+        bones.len()
+    )
+    .unwrap();
+    writeln!(out, "      </source>").unwrap();
+
+    let inverse_bind: Vec<_> = bones
+        .iter()
+        .map(|bone| bone.pose_to_bone.matrix())
+        .collect();
+    writeln!(
+        out,
+        r#"      <source id="{id}_bind_poses"><float_array id="{id}_bind_poses_array" count="{}">{}</float_array>"#,
+        bones.len() * 16,
+        inverse_bind.iter().map(matrix).collect::<Vec<_>>().join(" ")
+    )
+    .unwrap();
+    writeln!(
+        out,
+        r##"        <technique_common><accessor source="#{id}_bind_poses_array" count="{}" stride="16"><param name="TRANSFORM" type="float4x4"/></accessor></technique_common>"##,
+        bones.len()
+    )
+    .unwrap();
+    writeln!(out, "      </source>").unwrap();
+
+    let weights: Vec<_> = data
+        .vertices
+        .iter()
+        .map(|vertex| vertex_weights(vertex))
+        .collect();
+    let weight_values: Vec<f32> = weights
+        .iter()
+        .flatten()
+        .map(|(_, weight)| *weight)
+        .collect();
+    write_float_source(out, &format!("{id}_weights"), weight_values, &["WEIGHT"]);
+    writeln!(
+        out,
+        r##"      <joints><input semantic="JOINT" source="#{id}_joints"/><input semantic="INV_BIND_MATRIX" source="#{id}_bind_poses"/></joints>"##
+    )
+    .unwrap();
+    writeln!(out, r#"      <vertex_weights count="{}">"#, weights.len()).unwrap();
+    writeln!(
+        out,
+        r##"        <input semantic="JOINT" source="#{id}_joints" offset="0"/>"##
+    )
+    .unwrap();
+    writeln!(
+        out,
+        r##"        <input semantic="WEIGHT" source="#{id}_weights" offset="1"/>"##
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "        <vcount>{}</vcount>",
+        join(weights.iter().map(Vec::len))
+    )
+    .unwrap();
+    let mut weight_index = 0;
+    let pairs = weights.iter().flatten().map(|(bone, _)| {
+        weight_index += 1;
+        format!("{} {}", usize::from(*bone), weight_index - 1)
+    });
+    writeln!(out, "        <v>{}</v>", join(pairs)).unwrap();
+    writeln!(out, "      </vertex_weights>").unwrap();
+    writeln!(out, "    </skin></controller>").unwrap();
+}
+
+fn write_joint(
+    out: &mut String,
+    bone: &Handle<crate::mdl::Bone, crate::mdl::BoneId>,
+    local: &[Matrix4<f32>],
+    depth: usize,
+) {
+    let indent = "  ".repeat(depth);
+    let index = usize::from(bone.key());
+    writeln!(
+        out,
+        r#"{indent}<node id="joint_{index}" sid="joint_{index}" name="{}" type="JOINT">"#,
+        escape(&bone.name)
+    )
+    .unwrap();
+    writeln!(
+        out,
+        r#"{indent}  <matrix sid="transform">{}</matrix>"#,
+        matrix(&local[index])
+    )
+    .unwrap();
+    for child in bone.children() {
+        write_joint(out, &child, local, depth + 1);
+    }
+    writeln!(out, "{indent}</node>").unwrap();
+}
+
+fn write_animations(out: &mut String, model: &Model) {
+    let animations: Vec<_> = model
+        .animations()
+        .filter(|animation| !animation.flags.contains(StudioAnimFlags::DELTA))
+        .collect();
+    writeln!(out, "  <library_animations>").unwrap();
+    for (index, animation) in animations.iter().enumerate() {
+        write_animation(out, model, index, animation);
+    }
+    writeln!(out, "  </library_animations>").unwrap();
+
+    writeln!(out, "  <library_animation_clips>").unwrap();
+    for (index, animation) in animations.iter().enumerate() {
+        writeln!(
+            out,
+            r##"    <animation_clip id="clip_{index}" name="{}" start="0" end="{}"><instance_animation url="#animation_{index}"/></animation_clip>"##,
+            escape(&animation.name),
+            animation.duration()
+        )
+        .unwrap();
+    }
+    writeln!(out, "  </library_animation_clips>").unwrap();
+}
+
+fn write_animation(
+    out: &mut String,
+    model: &Model,
+    index: usize,
+    animation: &AnimationDescription,
+) {
+    let frames = animation.frame_count.max(1);
+    let times: Vec<f32> = (0..frames)
+        .map(|frame| {
+            if animation.fps > 0.0 {
+                frame as f32 / animation.fps
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    writeln!(
+        out,
+        r#"    <animation id="animation_{index}" name="{}">"#,
+        escape(&animation.name)
+    )
+    .unwrap();
+    for track in animation.animations.iter() {
+        if track.flags.contains(AnimationFlags::STUDIO_ANIM_DELTA) {
+            continue;
+        }
+        let Some(bone) = model.bones().nth(usize::from(track.bone)) else {
+            continue;
+        };
+        let bone_index = usize::from(track.bone);
+        let transforms: Vec<Matrix4<f32>> = (0..frames)
+            .map(|frame| {
+                let rotation = match track.rotation_data() {
+                    RotationData::None => bone.rest_rotation(),
+                    _ => track.rotation(frame),
+                };
+                let position = match track.position_data() {
+                    PositionData::None => bone.rest_position(),
+                    _ => track.position(frame),
+                };
+                Matrix4::from_translation(position.into()) * Matrix4::from(rotation)
+            })
+            .collect();
+
+        let id = format!("animation_{index}_joint_{bone_index}");
+        write_float_source(
+            out,
+            &format!("{id}_input"),
+            times.iter().copied(),
+            &["TIME"],
+        );
+        writeln!(
+            out,
+            r#"      <source id="{id}_output"><float_array id="{id}_output_array" count="{}">{}</float_array>"#,
+            frames * 16,
+            transforms.iter().map(matrix).collect::<Vec<_>>().join(" ")
+        )
+        .unwrap();
+        writeln!(
+            out,
+            r##"        <technique_common><accessor source="#{id}_output_array" count="{frames}" stride="16"><param name="TRANSFORM" type="float4x4"/></accessor></technique_common>"##
+        )
+        .unwrap();
+        writeln!(out, "      </source>").unwrap();
+        writeln!(
+            out,
+            r#"      <source id="{id}_interpolation"><Name_array id="{id}_interpolation_array" count="{frames}">{}</Name_array>"#,
+            vec!["LINEAR"; frames].join(" ")
+        )
+        .unwrap();
+        writeln!(
+            out,
+            r##"        <technique_common><accessor source="#{id}_interpolation_array" count="{frames}" stride="1"><param name="INTERPOLATION" type="name"/></accessor></technique_common>"##
+        )
+        .unwrap();
+        writeln!(out, "      </source>").unwrap();
+        writeln!(
+            out,
+            r##"      <sampler id="{id}_sampler"><input semantic="INPUT" source="#{id}_input"/><input semantic="OUTPUT" source="#{id}_output"/><input semantic="INTERPOLATION" source="#{id}_interpolation"/></sampler>"##
+        )
+        .unwrap();
+        writeln!(
+            out,
+            r##"      <channel source="#{id}_sampler" target="joint_{bone_index}/transform"/>"##
+        )
+        .unwrap();
+    }
+    writeln!(out, "    </animation>").unwrap();
+}
+
+fn write_float_source(
+    out: &mut String,
+    id: &str,
+    values: impl IntoIterator<Item = f32>,
+    params: &[&str],
+) {
+    let values: Vec<f32> = values.into_iter().collect();
+    writeln!(
+        out,
+        r#"      <source id="{id}"><float_array id="{id}_array" count="{}">{}</float_array>"#,
+        values.len(),
+        join(values.iter())
+    )
+    .unwrap();
+    let stride = params.len().max(1);
+    let params: String = params
+        .iter()
+        .map(|param| format!(r#"<param name="{param}" type="float"/>"#))
+        .collect();
+    writeln!(
+        out,
+        r##"        <technique_common><accessor source="#{id}_array" count="{}" stride="{stride}">{params}</accessor></technique_common>"##,
+        values.len() / stride,
+    )
+    .unwrap();
+    writeln!(out, "      </source>").unwrap();
+}
+
+/// Format a matrix in the row-major order used by Collada
+fn matrix(matrix: &Matrix4<f32>) -> String {
+    let m = matrix;
+    join(
+        [
+            m.x.x, m.y.x, m.z.x, m.w.x, //
+            m.x.y, m.y.y, m.z.y, m.w.y, //
+            m.x.z, m.y.z, m.z.z, m.w.z, //
+            m.x.w, m.y.w, m.z.w, m.w.w,
+        ]
+        .iter(),
+    )
+}
+
+fn join<T: ToString>(items: impl Iterator<Item = T>) -> String {
+    items
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,391 @@
+//! Export models and scenes as binary glTF (`.glb`)
+//!
+//! Like OBJ, the geometry is written in the pose of the scene without a skeleton.
+//! Every instance becomes a node with a mesh, with a primitive for every mesh of the model.
+//! Materials are named by their path relative to the `materials` directory, no textures are embedded.
+//!
+//! When a model has multiple skins, every skin is exported as a material variant using the `KHR_materials_variants`
+//! extension. Instances of models with fewer skins than the variant keep the skin of the instance.
+
+use super::common::{material_path, model_name};
+use crate::scene::{Scene, SceneMesh};
+use crate::Model;
+use bytemuck::{offset_of, Pod, Zeroable};
+use cgmath::{Matrix4, SquareMatrix};
+use gltf_json::accessor::{ComponentType, GenericComponentType, Type};
+use gltf_json::buffer::{Target, View};
+use gltf_json::extensions::mesh::{KhrMaterialsVariants, Mapping};
+use gltf_json::extensions::scene::khr_materials_variants::Variant;
+use gltf_json::mesh::{Mode, Primitive, Semantic};
+use gltf_json::validation::Checked::Valid;
+use gltf_json::{Accessor, Buffer, Index, Material, Mesh, Node, Root, Value};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::mem::size_of;
+
+/// Vertex layout of the exported vertex buffers
+#[derive(Copy, Clone, Debug, Default, Zeroable, Pod)]
+#[repr(C)]
+struct GltfVertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    uv: [f32; 2],
+}
+
+impl From<&crate::vvd::Vertex> for GltfVertex {
+    fn from(vertex: &crate::vvd::Vertex) -> Self {
+        GltfVertex {
+            position: vertex.position.into(),
+            normal: vertex.normal.into(),
+            uv: vertex.texture_coordinates,
+        }
+    }
+}
+
+/// Write a model in its rest pose as binary glTF, using the materials of a skin by default
+pub fn write_glb<W: Write>(model: &Model, skin: usize, mut writer: W) -> io::Result<()> {
+    writer.write_all(&to_glb(model, skin))
+}
+
+/// Convert a model in its rest pose to binary glTF, using the materials of a skin by default
+pub fn to_glb(model: &Model, skin: usize) -> Vec<u8> {
+    let mut scene = Scene::new();
+    let instance = scene.add(model, Matrix4::identity());
+    if let Some(instance) = scene.instance_mut(instance) {
+        instance.skin = skin;
+    }
+    scene_to_glb(&scene)
+}
+
+/// Write every instance of a scene as binary glTF
+pub fn write_scene_glb<W: Write>(scene: &Scene, mut writer: W) -> io::Result<()> {
+    writer.write_all(&scene_to_glb(scene))
+}
+
+/// Convert every instance of a scene to binary glTF
+pub fn scene_to_glb(scene: &Scene) -> Vec<u8> {
+    let (root, buffer) = scene_to_gltf(scene);
+    pack_glb(&root, buffer)
+}
+
+/// Convert every instance of a scene to a glTF document and the binary buffer it references
+///
+/// The document references the buffer as the binary chunk of a `.glb` file.
+pub fn scene_to_gltf(scene: &Scene) -> (Root, Vec<u8>) {
+    let mut buffer = Vec::new();
+    let mut views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut material_names: Vec<Option<String>> = Vec::new();
+    let mut meshes: Vec<Mesh> = Vec::new();
+    let mut nodes = Vec::new();
+    let mut material_index =
+        |material: Option<String>| match material_names.iter().position(|name| *name == material) {
+            Some(index) => index as u32,
+            None => {
+                material_names.push(material);
+                material_names.len() as u32 - 1
+            }
+        };
+
+    let scene_meshes = scene.meshes();
+    let variant_count = scene_meshes
+        .iter()
+        .map(|mesh| mesh.model.skin_tables().count())
+        .max()
+        .unwrap_or_default();
+
+    let mut current_instance = None;
+    // accessors can't be empty, meshes without triangles are left out
+    for mesh in scene_meshes
+        .into_iter()
+        .filter(|mesh| !mesh.triangles.is_empty())
+    {
+        if current_instance != Some(mesh.instance) {
+            current_instance = Some(mesh.instance);
+            let name = format!("{}_{}", model_name(mesh.model), mesh.instance.index());
+            nodes.push(Node {
+                mesh: Some(Index::new(meshes.len() as u32)),
+                name: Some(name.clone()),
+                ..node()
+            });
+            meshes.push(Mesh {
+                extensions: Default::default(),
+                extras: Default::default(),
+                name: Some(name),
+                primitives: Vec::new(),
+                weights: None,
+            });
+        }
+        let material = material_index(material_path(mesh.model, mesh.skin, &mesh.mesh));
+        let skin_count = mesh.model.skin_tables().count();
+        let mut mappings: Vec<Mapping> = Vec::new();
+        for variant in (0..variant_count).filter(|_| variant_count > 1) {
+            let skin = if variant < skin_count {
+                variant
+            } else {
+                mesh.skin
+            };
+            let material = material_index(material_path(mesh.model, skin, &mesh.mesh));
+            match mappings
+                .iter_mut()
+                .find(|mapping| mapping.material == material)
+            {
+                Some(mapping) => mapping.variants.push(variant as u32),
+                None => mappings.push(Mapping {
+                    material,
+                    variants: vec![variant as u32],
+                }),
+            }
+        }
+        let mut primitive =
+            push_primitive(&mut buffer, &mut views, &mut accessors, &mesh, material);
+        if !mappings.is_empty() {
+            primitive.extensions = Some(gltf_json::extensions::mesh::Primitive {
+                khr_materials_variants: Some(KhrMaterialsVariants { mappings }),
+            });
+        }
+        if let Some(gltf_mesh) = meshes.last_mut() {
+            gltf_mesh.primitives.push(primitive);
+        }
+    }
+
+    let materials = material_names
+        .into_iter()
+        .map(|name| Material {
+            name,
+            double_sided: true,
+            ..Material::default()
+        })
+        .collect();
+
+    let (extensions, extensions_used) = if variant_count > 1 {
+        let variants = (0..variant_count)
+            .map(|skin| Variant {
+                name: format!("skin {skin}"),
+            })
+            .collect();
+        let extensions = gltf_json::extensions::root::Root {
+            khr_materials_variants: Some(gltf_json::extensions::root::KhrMaterialsVariants {
+                variants,
+            }),
+        };
+        (Some(extensions), vec!["KHR_materials_variants".into()])
+    } else {
+        (None, Vec::new())
+    };
+
+    let root = Root {
+        accessors,
+        buffers: vec![Buffer {
+            byte_length: buffer.len() as u32,
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: None,
+            uri: None,
+        }],
+        buffer_views: views,
+        materials,
+        meshes,
+        scenes: vec![gltf_json::Scene {
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: None,
+            nodes: (0..nodes.len() as u32).map(Index::new).collect(),
+        }],
+        nodes,
+        scene: Some(Index::new(0)),
+        extensions,
+        extensions_used,
+        ..Default::default()
+    };
+    (root, buffer)
+}
+
+fn node() -> Node {
+    Node {
+        camera: None,
+        children: None,
+        extensions: Default::default(),
+        extras: Default::default(),
+        matrix: None,
+        mesh: None,
+        name: None,
+        rotation: None,
+        scale: None,
+        translation: None,
+        skin: None,
+        weights: None,
+    }
+}
+
+/// Push the vertices and triangles of a mesh into the buffer
+fn push_primitive(
+    buffer: &mut Vec<u8>,
+    views: &mut Vec<View>,
+    accessors: &mut Vec<Accessor>,
+    mesh: &SceneMesh,
+    material: u32,
+) -> Primitive {
+    let vertex_view = push_view(
+        buffer,
+        views,
+        mesh.vertices
+            .iter()
+            .map(GltfVertex::from)
+            .flat_map(bytemuck::cast::<_, [u8; size_of::<GltfVertex>()]>),
+        Some(size_of::<GltfVertex>() as u32),
+        Target::ArrayBuffer,
+    );
+    let index_view = push_view(
+        buffer,
+        views,
+        mesh.triangles
+            .iter()
+            .flatten()
+            .flat_map(|index| (*index as u32).to_le_bytes()),
+        None,
+        Target::ElementArrayBuffer,
+    );
+
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for vertex in &mesh.vertices {
+        let position: [f32; 3] = vertex.position.into();
+        for axis in 0..3 {
+            min[axis] = min[axis].min(position[axis]);
+            max[axis] = max[axis].max(position[axis]);
+        }
+    }
+    let bounds = Some((Value::from(Vec::from(min)), Value::from(Vec::from(max))));
+
+    let vertex_count = mesh.vertices.len() as u32;
+    let accessor_start = accessors.len() as u32;
+    accessors.extend([
+        accessor(
+            vertex_view,
+            offset_of!(GltfVertex, position),
+            vertex_count,
+            ComponentType::F32,
+            Type::Vec3,
+            bounds,
+        ),
+        accessor(
+            vertex_view,
+            offset_of!(GltfVertex, normal),
+            vertex_count,
+            ComponentType::F32,
+            Type::Vec3,
+            None,
+        ),
+        accessor(
+            vertex_view,
+            offset_of!(GltfVertex, uv),
+            vertex_count,
+            ComponentType::F32,
+            Type::Vec2,
+            None,
+        ),
+        accessor(
+            index_view,
+            0,
+            mesh.triangles.len() as u32 * 3,
+            ComponentType::U32,
+            Type::Scalar,
+            None,
+        ),
+    ]);
+
+    let attributes = BTreeMap::from([
+        (Valid(Semantic::Positions), Index::new(accessor_start)),
+        (Valid(Semantic::Normals), Index::new(accessor_start + 1)),
+        (
+            Valid(Semantic::TexCoords(0)),
+            Index::new(accessor_start + 2),
+        ),
+    ]);
+    Primitive {
+        attributes,
+        extensions: Default::default(),
+        extras: Default::default(),
+        indices: Some(Index::new(accessor_start + 3)),
+        material: Some(Index::new(material)),
+        mode: Valid(Mode::Triangles),
+        targets: None,
+    }
+}
+
+/// Push data into the buffer with a view for it, returning the index of the view
+fn push_view(
+    buffer: &mut Vec<u8>,
+    views: &mut Vec<View>,
+    data: impl IntoIterator<Item = u8>,
+    byte_stride: Option<u32>,
+    target: Target,
+) -> u32 {
+    let start = buffer.len() as u32;
+    buffer.extend(data);
+    // keep every view aligned for its components
+    while buffer.len() % 4 != 0 {
+        buffer.push(0);
+    }
+    views.push(View {
+        buffer: Index::new(0),
+        byte_length: buffer.len() as u32 - start,
+        byte_offset: Some(start),
+        byte_stride,
+        extensions: Default::default(),
+        extras: Default::default(),
+        name: None,
+        target: Some(Valid(target)),
+    });
+    views.len() as u32 - 1
+}
+
+fn accessor(
+    view: u32,
+    offset: usize,
+    count: u32,
+    component_type: ComponentType,
+    type_: Type,
+    bounds: Option<(Value, Value)>,
+) -> Accessor {
+    let (min, max) = bounds.unzip();
+    Accessor {
+        buffer_view: Some(Index::new(view)),
+        byte_offset: Some(offset as u32),
+        count,
+        component_type: Valid(GenericComponentType(component_type)),
+        extensions: Default::default(),
+        extras: Default::default(),
+        type_: Valid(type_),
+        min,
+        max,
+        name: None,
+        normalized: false,
+        sparse: None,
+    }
+}
+
+/// Pack a glTF document and the buffer it references as binary chunk into a `.glb` file
+pub fn pack_glb(root: &Root, mut buffer: Vec<u8>) -> Vec<u8> {
+    let mut json = gltf_json::serialize::to_vec(root).expect("glTF documents always serialize");
+    // chunks are padded to 4 bytes, json with spaces and binary data with zeros
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+    while buffer.len() % 4 != 0 {
+        buffer.push(0);
+    }
+    let length = 12 + 8 + json.len() + 8 + buffer.len();
+    let mut out = Vec::with_capacity(length);
+    out.extend_from_slice(b"glTF");
+    out.extend_from_slice(&2u32.to_le_bytes());
+    out.extend_from_slice(&(length as u32).to_le_bytes());
+    out.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    out.extend_from_slice(b"JSON");
+    out.extend_from_slice(&json);
+    out.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+    out.extend_from_slice(b"BIN\0");
+    out.extend_from_slice(&buffer);
+    out
+}
@@ -0,0 +1,17 @@
+//! Writers for interchange formats used by content creation tools
+//!
+//! Every format is behind its own feature flag.
+
+#[cfg(feature = "dae")]
+pub mod dae;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+#[cfg(feature = "obj")]
+pub mod obj;
+#[cfg(feature = "usd")]
+pub mod usd;
+#[cfg(feature = "vta")]
+pub mod vta;
+
+#[cfg_attr(not(any(feature = "usd", feature = "dae")), allow(dead_code))]
+pub(crate) mod common;
@@ -0,0 +1,75 @@
+//! Export models and scenes as Wavefront OBJ
+//!
+//! OBJ has no skeletons, the geometry is written in the pose of the scene.
+//! Materials are referenced with `usemtl` by their path relative to the `materials` directory,
+//! no `.mtl` file is written.
+
+use super::common::{identifier, material_path, model_name};
+use crate::scene::Scene;
+use crate::Model;
+use cgmath::{Matrix4, SquareMatrix};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Write a model in its rest pose as OBJ, using the materials of a skin
+pub fn write_obj<W: Write>(model: &Model, skin: usize, mut writer: W) -> io::Result<()> {
+    writer.write_all(to_obj(model, skin).as_bytes())
+}
+
+/// Convert a model in its rest pose to OBJ, using the materials of a skin
+pub fn to_obj(model: &Model, skin: usize) -> String {
+    let mut scene = Scene::new();
+    let instance = scene.add(model, Matrix4::identity());
+    if let Some(instance) = scene.instance_mut(instance) {
+        instance.skin = skin;
+    }
+    scene_to_obj(&scene)
+}
+
+/// Write every instance of a scene as OBJ
+pub fn write_scene_obj<W: Write>(scene: &Scene, mut writer: W) -> io::Result<()> {
+    writer.write_all(scene_to_obj(scene).as_bytes())
+}
+
+/// Convert every instance of a scene to OBJ, with an object for every instance and a group for every mesh
+pub fn scene_to_obj(scene: &Scene) -> String {
+    let mut out = String::new();
+    // indices in OBJ are global and 1-based
+    let mut vertex_offset = 1;
+    let mut current_instance = None;
+    for mesh in scene.meshes() {
+        if current_instance != Some(mesh.instance) {
+            current_instance = Some(mesh.instance);
+            writeln!(
+                out,
+                "o {}_{}",
+                model_name(mesh.model),
+                mesh.instance.index()
+            )
+            .unwrap();
+        }
+        writeln!(out, "g {}", identifier(mesh.mesh.model_name)).unwrap();
+        if let Some(material) = material_path(mesh.model, mesh.skin, &mesh.mesh) {
+            writeln!(out, "usemtl {material}").unwrap();
+        }
+        for vertex in &mesh.vertices {
+            let position = vertex.position;
+            writeln!(out, "v {} {} {}", position.x, position.y, position.z).unwrap();
+        }
+        for vertex in &mesh.vertices {
+            let [u, v] = vertex.texture_coordinates;
+            // OBJ has the origin of the texture coordinates at the bottom
+            writeln!(out, "vt {} {}", u, 1.0 - v).unwrap();
+        }
+        for vertex in &mesh.vertices {
+            let normal = vertex.normal;
+            writeln!(out, "vn {} {} {}", normal.x, normal.y, normal.z).unwrap();
+        }
+        for triangle in &mesh.triangles {
+            let [a, b, c] = triangle.map(|index| index + vertex_offset);
+            writeln!(out, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}").unwrap();
+        }
+        vertex_offset += mesh.vertices.len();
+    }
+    out
+}
@@ -0,0 +1,249 @@
+//! Export models as USDA, the text format of Universal Scene Description
+//!
+//! The skeleton is written using `UsdSkel` and the materials as `UsdPreviewSurface` shaders.
+//! Textures are referenced as `.png` files relative to the `materials` directory,
+//! converting the `.vtf` files is left to the caller.
+
+use super::common::{
+    bind_transforms, identifier, local_bind_transforms, material_path, model_name, vertex_weights,
+    MeshData,
+};
+use crate::Model;
+use cgmath::Matrix4;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Maximum number of bones influencing a single vertex
+const MAX_INFLUENCES: usize = 3;
+
+/// Write a model as USDA, using the materials of a skin
+pub fn write_usda<W: Write>(model: &Model, skin: usize, mut writer: W) -> io::Result<()> {
+    writer.write_all(to_usda(model, skin).as_bytes())
+}
+
+/// Convert a model to USDA, using the materials of a skin
+pub fn to_usda(model: &Model, skin: usize) -> String {
+    let name = model_name(model);
+    let root = format!("/{name}");
+    let mut out = String::new();
+    writeln!(out, "#usda 1.0").unwrap();
+    writeln!(out, "(").unwrap();
+    writeln!(out, "    defaultPrim = \"{name}\"").unwrap();
+    writeln!(out, "    metersPerUnit = 0.0254").unwrap();
+    writeln!(out, "    upAxis = \"Z\"").unwrap();
+    writeln!(out, ")").unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "def SkelRoot \"{name}\" (\n    prepend apiSchemas = [\"SkelBindingAPI\"]\n)\n{{"
+    )
+    .unwrap();
+    writeln!(out, "    rel skel:skeleton = <{root}/Skeleton>").unwrap();
+    writeln!(out).unwrap();
+    write_skeleton(&mut out, model);
+
+    let mut materials = Vec::new();
+    for (index, mesh) in model.meshes().enumerate() {
+        let data = MeshData::new(&mesh);
+        let material = material_path(model, skin, &mesh);
+        let material_name = material.as_deref().map(identifier);
+        if let (Some(path), Some(name)) = (&material, &material_name) {
+            if !materials.iter().any(|(existing, _)| existing == name) {
+                materials.push((name.clone(), path.clone()));
+            }
+        }
+
+        writeln!(
+            out,
+            "    def Mesh \"{}_{index}\" (\n        prepend apiSchemas = [\"SkelBindingAPI\", \"MaterialBindingAPI\"]\n    )\n    {{",
+            identifier(mesh.model_name)
+        )
+        .unwrap();
+        writeln!(out, "        uniform token subdivisionScheme = \"none\"").unwrap();
+        writeln!(
+            out,
+            "        int[] faceVertexCounts = [{}]",
+            join(data.triangles.iter().map(|_| "3".to_string()))
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "        int[] faceVertexIndices = [{}]",
+            join(
+                data.triangles
+                    .iter()
+                    .flatten()
+                    .map(|index| index.to_string())
+            )
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "        point3f[] points = [{}]",
+            join(data.vertices.iter().map(|vertex| {
+                let p = vertex.position;
+                format!("({}, {}, {})", p.x, p.y, p.z)
+            }))
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "        normal3f[] normals = [{}] (\n            interpolation = \"vertex\"\n        )",
+            join(data.vertices.iter().map(|vertex| {
+                let n = vertex.normal;
+                format!("({}, {}, {})", n.x, n.y, n.z)
+            }))
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "        texCoord2f[] primvars:st = [{}] (\n            interpolation = \"vertex\"\n        )",
+            join(data.vertices.iter().map(|vertex| {
+                let [u, v] = vertex.texture_coordinates;
+                format!("({}, {})", u, 1.0 - v)
+            }))
+        )
+        .unwrap();
+
+        let weights: Vec<_> = data
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let mut weights = vertex_weights(vertex);
+                weights.resize(MAX_INFLUENCES, (Default::default(), 0.0));
+                weights
+            })
+            .collect();
+        writeln!(
+            out,
+            "        int[] primvars:skel:jointIndices = [{}] (\n            elementSize = {MAX_INFLUENCES}\n            interpolation = \"vertex\"\n        )",
+            join(weights.iter().flatten().map(|(bone, _)| usize::from(*bone).to_string()))
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "        float[] primvars:skel:jointWeights = [{}] (\n            elementSize = {MAX_INFLUENCES}\n            interpolation = \"vertex\"\n        )",
+            join(weights.iter().flatten().map(|(_, weight)| weight.to_string()))
+        )
+        .unwrap();
+        if let Some(material) = &material_name {
+            writeln!(
+                out,
+                "        rel material:binding = <{root}/Materials/{material}>"
+            )
+            .unwrap();
+        }
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+    }
+
+    writeln!(out, "    def Scope \"Materials\"\n    {{").unwrap();
+    for (material, path) in materials {
+        write_material(
+            &mut out,
+            &format!("{root}/Materials/{material}"),
+            &material,
+            &path,
+        );
+    }
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+fn write_skeleton(out: &mut String, model: &Model) {
+    let joints = model.bones().map(|bone| {
+        let mut path: Vec<String> = bone
+            .ancestors()
+            .map(|bone| identifier(&bone.name))
+            .collect();
+        path.reverse();
+        path.push(identifier(&bone.name));
+        format!("\"{}\"", path.join("/"))
+    });
+    writeln!(out, "    def Skeleton \"Skeleton\"\n    {{").unwrap();
+    writeln!(out, "        uniform token[] joints = [{}]", join(joints)).unwrap();
+    writeln!(
+        out,
+        "        uniform matrix4d[] bindTransforms = [{}]",
+        join(bind_transforms(model).iter().map(matrix))
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "        uniform matrix4d[] restTransforms = [{}]",
+        join(local_bind_transforms(model).iter().map(matrix))
+    )
+    .unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+}
+
+fn write_material(out: &mut String, path: &str, name: &str, texture: &str) {
+    writeln!(out, "        def Material \"{name}\"\n        {{").unwrap();
+    writeln!(
+        out,
+        "            token outputs:surface.connect = <{path}/Surface.outputs:surface>"
+    )
+    .unwrap();
+    writeln!(out, "            def Shader \"Surface\"\n            {{").unwrap();
+    writeln!(
+        out,
+        "                uniform token info:id = \"UsdPreviewSurface\""
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "                color3f inputs:diffuseColor.connect = <{path}/Texture.outputs:rgb>"
+    )
+    .unwrap();
+    writeln!(out, "                float inputs:roughness = 1").unwrap();
+    writeln!(out, "                token outputs:surface").unwrap();
+    writeln!(out, "            }}").unwrap();
+    writeln!(out, "            def Shader \"Texture\"\n            {{").unwrap();
+    writeln!(
+        out,
+        "                uniform token info:id = \"UsdUVTexture\""
+    )
+    .unwrap();
+    writeln!(out, "                asset inputs:file = @{texture}.png@").unwrap();
+    writeln!(
+        out,
+        "                float2 inputs:st.connect = <{path}/TextureCoordinates.outputs:result>"
+    )
+    .unwrap();
+    writeln!(out, "                float3 outputs:rgb").unwrap();
+    writeln!(out, "            }}").unwrap();
+    writeln!(
+        out,
+        "            def Shader \"TextureCoordinates\"\n            {{"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "                uniform token info:id = \"UsdPrimvarReader_float2\""
+    )
+    .unwrap();
+    writeln!(out, "                string inputs:varname = \"st\"").unwrap();
+    writeln!(out, "                float2 outputs:result").unwrap();
+    writeln!(out, "            }}").unwrap();
+    writeln!(out, "        }}").unwrap();
+}
+
+/// Format a matrix in the row-major order used by USD, with the translation in the last row
+fn matrix(matrix: &Matrix4<f32>) -> String {
+    let row = |column: cgmath::Vector4<f32>| {
+        format!("({}, {}, {}, {})", column.x, column.y, column.z, column.w)
+    };
+    format!(
+        "({}, {}, {}, {})",
+        row(matrix.x),
+        row(matrix.y),
+        row(matrix.z),
+        row(matrix.w)
+    )
+}
+
+fn join(items: impl Iterator<Item = String>) -> String {
+    items.collect::<Vec<_>>().join(", ")
+}
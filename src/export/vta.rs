@@ -0,0 +1,102 @@
+//! Export the flexes of a model as VTA, the vertex animation format used by `studiomdl`
+//!
+//! The first frame contains the base positions of all vertices, followed by one frame for every flex
+//! containing only the vertices changed by the flex. `studiomdl` matches the vertices of the base frame
+//! to the reference mesh by position, the indices only need to be consistent within the file.
+
+use crate::{Model, Vector};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Write the flexes of a model as VTA
+pub fn write_vta<W: Write>(model: &Model, mut writer: W) -> io::Result<()> {
+    writer.write_all(to_vta(model).as_bytes())
+}
+
+/// Convert the flexes of a model to VTA
+pub fn to_vta(model: &Model) -> String {
+    let frames = flex_frames(model);
+
+    let mut out = String::new();
+    writeln!(out, "version 1").unwrap();
+    writeln!(out, "nodes").unwrap();
+    for bone in model.bones() {
+        let parent = bone
+            .parent()
+            .map_or(-1, |parent| usize::from(parent.key()) as i32);
+        writeln!(
+            out,
+            "{} \"{}\" {parent}",
+            usize::from(bone.key()),
+            bone.name
+        )
+        .unwrap();
+    }
+    writeln!(out, "end").unwrap();
+
+    writeln!(out, "skeleton").unwrap();
+    for time in 0..=frames.len() {
+        writeln!(out, "time {time}").unwrap();
+    }
+    writeln!(out, "end").unwrap();
+
+    writeln!(out, "vertexanimation").unwrap();
+    writeln!(out, "time 0 # basis").unwrap();
+    for (index, vertex) in model.vertices().iter().enumerate() {
+        write_vertex(&mut out, index, vertex.position, vertex.normal);
+    }
+    for (time, (name, deltas)) in frames.iter().enumerate() {
+        writeln!(out, "time {} # {name}", time + 1).unwrap();
+        for (&index, (delta, normal_delta)) in deltas {
+            let Some(vertex) = model.vertices().get(index) else {
+                continue;
+            };
+            write_vertex(
+                &mut out,
+                index,
+                vertex.position + *delta,
+                vertex.normal + *normal_delta,
+            );
+        }
+    }
+    writeln!(out, "end").unwrap();
+    out
+}
+
+type Deltas = BTreeMap<usize, (Vector, Vector)>;
+
+/// The vertex deltas for every flex descriptor, combined from all meshes
+fn flex_frames(model: &Model) -> Vec<(&str, Deltas)> {
+    let mut frames: BTreeMap<i32, Deltas> = BTreeMap::new();
+    for mesh in model.meshes() {
+        for flex in mesh.flexes() {
+            let deltas = frames.entry(flex.descriptor).or_default();
+            for vertex in &flex.vertices {
+                let index = mesh.vertex_offset() + usize::from(vertex.index);
+                let (delta, normal_delta) = deltas.entry(index).or_default();
+                *delta = *delta + vertex.delta;
+                *normal_delta = *normal_delta + vertex.normal_delta;
+            }
+        }
+    }
+    frames
+        .into_iter()
+        .map(|(descriptor, deltas)| {
+            let name = usize::try_from(descriptor)
+                .ok()
+                .and_then(|descriptor| model.flex_descriptors().get(descriptor))
+                .map_or("unknown", |descriptor| descriptor.name.as_str());
+            (name, deltas)
+        })
+        .collect()
+}
+
+fn write_vertex(out: &mut String, index: usize, position: Vector, normal: Vector) {
+    writeln!(
+        out,
+        "{index} {:.6} {:.6} {:.6} {:.6} {:.6} {:.6}",
+        position.x, position.y, position.z, normal.x, normal.y, normal.z
+    )
+    .unwrap();
+}
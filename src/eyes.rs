@@ -0,0 +1,79 @@
+//! Parameters for rendering eyeballs
+
+use crate::mdl::Eyeball;
+use crate::{Model, Pose, Vector};
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3, Vector4};
+
+/// Everything needed to render an eyeball with a Source style eye shader
+#[derive(Debug, Clone)]
+pub struct EyeRenderInfo<'a> {
+    pub eyeball: &'a Eyeball,
+    /// Center of the eyeball in model space
+    pub origin: Vector,
+    /// Forward direction of the eyeball in model space
+    pub forward: Vector,
+    /// Up direction of the eyeball in model space
+    pub up: Vector,
+    pub radius: f32,
+    /// Inverse of the iris size, as used by the eye shaders
+    pub iris_scale: f32,
+    /// Cosine of the maximum angle the eyes can turn away from forward
+    pub max_deflection: f32,
+}
+
+impl Model {
+    /// All eyeballs of the model
+    pub fn eyeballs(&self) -> impl Iterator<Item = &Eyeball> {
+        self.mdl
+            .body_parts
+            .iter()
+            .flat_map(|part| part.models.iter())
+            .flat_map(|model| model.eyeballs.iter())
+    }
+
+    /// The cosine of the maximum angle the eyes can turn away from forward, defaults to 30 degrees
+    pub fn max_eye_deflection(&self) -> f32 {
+        match &self.mdl.header2 {
+            Some(header2) => header2.max_eye_deflection(),
+            None => 30.0f32.to_radians().cos(),
+        }
+    }
+
+    /// The render parameters of every eyeball with the bones in the provided pose
+    ///
+    /// Use [`rest_pose`](Self::rest_pose) for the bind pose.
+    pub fn eye_render_info(&self, pose: &Pose) -> Vec<EyeRenderInfo<'_>> {
+        let transforms = pose.world_transforms();
+        let max_deflection = self.max_eye_deflection();
+        self.eyeballs()
+            .map(|eyeball| {
+                let transform = usize::try_from(eyeball.bone)
+                    .ok()
+                    .and_then(|bone| transforms.get(bone))
+                    .copied()
+                    .unwrap_or_else(Matrix4::identity);
+                let point = |vector: Vector, w: f32| {
+                    let transformed = transform * Vector4::new(vector.x, vector.y, vector.z, w);
+                    Vector3::new(transformed.x, transformed.y, transformed.z)
+                };
+                let direction = |vector: Vector| {
+                    let direction = point(vector, 0.0);
+                    if direction.magnitude2() > 0.0 {
+                        direction.normalize().into()
+                    } else {
+                        direction.into()
+                    }
+                };
+                EyeRenderInfo {
+                    eyeball,
+                    origin: point(eyeball.origin, 1.0).into(),
+                    forward: direction(eyeball.forward),
+                    up: direction(eyeball.up),
+                    radius: eyeball.radius,
+                    iris_scale: eyeball.iris_scale,
+                    max_deflection,
+                }
+            })
+            .collect()
+    }
+}
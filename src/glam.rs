@@ -0,0 +1,53 @@
+//! Conversions between the math types used in the model and [`glam`]
+
+use crate::{Quaternion, RadianEuler, Transform3x4, Vector};
+use cgmath::Matrix4;
+use glam::{Mat4, Quat, Vec3};
+
+impl From<Vector> for Vec3 {
+    fn from(v: Vector) -> Self {
+        Vec3::new(v.x, v.y, v.z)
+    }
+}
+
+impl From<Vec3> for Vector {
+    fn from(v: Vec3) -> Self {
+        Vector {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+}
+
+impl From<Quaternion> for Quat {
+    fn from(q: Quaternion) -> Self {
+        Quat::from_xyzw(q.x, q.y, q.z, q.w)
+    }
+}
+
+impl From<Quat> for Quaternion {
+    fn from(q: Quat) -> Self {
+        Quaternion {
+            x: q.x,
+            y: q.y,
+            z: q.z,
+            w: q.w,
+        }
+    }
+}
+
+impl From<RadianEuler> for Quat {
+    fn from(e: RadianEuler) -> Self {
+        Quaternion::from(e).into()
+    }
+}
+
+impl From<Transform3x4> for Mat4 {
+    /// Uses the same axis mapping as the conversion into a cgmath [`Matrix4`]
+    fn from(t: Transform3x4) -> Self {
+        let matrix: Matrix4<f32> = t.into();
+        let cols: &[f32; 16] = matrix.as_ref();
+        Mat4::from_cols_array(cols)
+    }
+}
@@ -0,0 +1,901 @@
+//! Half-Life 1 (GoldSrc) models, version 10 of the mdl format
+//!
+//! Unlike Source models, the vertices, triangles and textures are all stored in the `.mdl` file itself.
+//! Models that keep their textures in a separate `T.mdl` file or their sequences in separate `01.mdl`
+//! files can still be loaded, but those textures and animations are left empty.
+
+mod raw;
+
+pub use raw::*;
+
+use crate::mdl::{BoneId, BoneSample};
+use crate::vvd::{BoneWeights, Vertex};
+use crate::{
+    bounding_box, read_at, read_relative, read_single, FixedString, ModelError, Quaternion,
+    ReadRelative, Readable, Vector,
+};
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector4};
+use std::collections::HashMap;
+use std::fs;
+use std::mem::size_of;
+use std::path::Path;
+
+type Result<T> = std::result::Result<T, ModelError>;
+
+const STUDIO_LOOPING: i32 = 0x0001;
+
+#[derive(Debug, Clone)]
+pub struct Bone {
+    pub name: String,
+    pub parent: Option<BoneId>,
+    pub flags: i32,
+    pub bone_controller: [i32; 6],
+    /// Rest position followed by the rest rotation as euler angles
+    pub value: [f32; 6],
+    /// Scale of the compressed animation values
+    pub scale: [f32; 6],
+}
+
+impl ReadRelative for Bone {
+    type Header = GoldSrcBoneHeader;
+
+    fn read(_data: &[u8], header: Self::Header) -> Result<Self> {
+        Ok(Bone {
+            name: FixedString::try_from(header.name)?.as_str().into(),
+            parent: (header.parent >= 0).then(|| header.parent.into()),
+            flags: header.flags,
+            bone_controller: header.bone_controller,
+            value: header.value,
+            scale: header.scale,
+        })
+    }
+}
+
+impl Bone {
+    /// Rest position of the bone relative to its parent
+    pub fn rest_position(&self) -> Vector {
+        Vector {
+            x: self.value[0],
+            y: self.value[1],
+            z: self.value[2],
+        }
+    }
+
+    /// Rest rotation of the bone relative to its parent
+    pub fn rest_rotation(&self) -> Quaternion {
+        angle_quaternion([self.value[3], self.value[4], self.value[5]])
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BoneController {
+    pub bone: i32,
+    pub ty: i32,
+    pub start: f32,
+    pub end: f32,
+    pub rest: i32,
+    pub index: i32,
+}
+
+impl ReadRelative for BoneController {
+    type Header = GoldSrcBoneControllerHeader;
+
+    fn read(_data: &[u8], header: Self::Header) -> Result<Self> {
+        Ok(BoneController {
+            bone: header.bone,
+            ty: header.ty,
+            start: header.start,
+            end: header.end,
+            rest: header.rest,
+            index: header.index,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HitBox {
+    pub bone: i32,
+    pub group: i32,
+    pub min: Vector,
+    pub max: Vector,
+}
+
+impl ReadRelative for HitBox {
+    type Header = GoldSrcHitBoxHeader;
+
+    fn read(_data: &[u8], header: Self::Header) -> Result<Self> {
+        Ok(HitBox {
+            bone: header.bone,
+            group: header.group,
+            min: header.min,
+            max: header.max,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub name: String,
+    pub ty: i32,
+    pub bone: i32,
+    /// Position of the attachment relative to the bone
+    pub origin: Vector,
+    pub vectors: [Vector; 3],
+}
+
+impl ReadRelative for Attachment {
+    type Header = GoldSrcAttachmentHeader;
+
+    fn read(_data: &[u8], header: Self::Header) -> Result<Self> {
+        Ok(Attachment {
+            name: FixedString::try_from(header.name)?.as_str().into(),
+            ty: header.ty,
+            bone: header.bone,
+            origin: header.origin,
+            vectors: header.vectors,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SequenceGroup {
+    pub label: String,
+    /// File containing the animations for the group, the first group is stored in the model itself
+    pub name: String,
+}
+
+impl ReadRelative for SequenceGroup {
+    type Header = GoldSrcSequenceGroupHeader;
+
+    fn read(_data: &[u8], header: Self::Header) -> Result<Self> {
+        Ok(SequenceGroup {
+            label: FixedString::try_from(header.label)?.as_str().into(),
+            name: FixedString::try_from(header.name)?.as_str().into(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub frame: i32,
+    pub event: i32,
+    pub ty: i32,
+    pub options: String,
+}
+
+impl ReadRelative for Event {
+    type Header = GoldSrcEventHeader;
+
+    fn read(_data: &[u8], header: Self::Header) -> Result<Self> {
+        Ok(Event {
+            frame: header.frame,
+            event: header.event,
+            ty: header.ty,
+            options: FixedString::try_from(header.options)?.as_str().into(),
+        })
+    }
+}
+
+/// The decoded frames of a single bone
+#[derive(Debug, Clone, Default)]
+pub struct BoneTrack {
+    pub positions: Vec<Vector>,
+    pub rotations: Vec<Quaternion>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    pub label: String,
+    pub fps: f32,
+    pub flags: i32,
+    pub activity: i32,
+    pub activity_weight: i32,
+    pub events: Vec<Event>,
+    pub frame_count: usize,
+    pub motion_type: i32,
+    pub motion_bone: i32,
+    pub linear_movement: Vector,
+    pub bounding_box: [Vector; 2],
+    pub blend_type: [i32; 2],
+    pub blend_start: [f32; 2],
+    pub blend_end: [f32; 2],
+    pub sequence_group: i32,
+    pub entry_node: i32,
+    pub exit_node: i32,
+    pub node_flags: i32,
+    pub next_sequence: i32,
+    /// The tracks of every bone for every blend
+    ///
+    /// Empty for sequences stored in an external sequence group.
+    pub blends: Vec<Vec<BoneTrack>>,
+}
+
+impl Sequence {
+    fn read(data: &[u8], header: GoldSrcSequenceHeader, bones: &[Bone]) -> Result<Self> {
+        let frame_count = header.frame_count.max(0) as usize;
+        let blends = if header.sequence_group == 0 {
+            // every 2 bytes of run length encoded values cover at most 255 frames,
+            // more frames than that can't be stored in the remaining data
+            let offset = header.animation_offset.max(0) as usize;
+            let max_frames =
+                data.len().saturating_sub(offset) / size_of::<u16>() * u8::MAX as usize;
+            if frame_count > max_frames {
+                return Err(ModelError::OutOfBounds {
+                    data: "GoldSrc sequence frames",
+                    offset,
+                });
+            }
+            let tracks = header
+                .animation_indexes(bones.len())
+                .zip(bones.iter().cycle())
+                .map(|(index, bone)| read_track(data, index, bone, frame_count))
+                .collect::<Result<Vec<_>>>()?;
+            tracks
+                .chunks(bones.len().max(1))
+                .map(|chunk| chunk.to_vec())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok(Sequence {
+            label: FixedString::try_from(header.label)?.as_str().into(),
+            fps: header.fps,
+            flags: header.flags,
+            activity: header.activity,
+            activity_weight: header.activity_weight,
+            events: read_relative(data, header.event_indexes())?,
+            frame_count,
+            motion_type: header.motion_type,
+            motion_bone: header.motion_bone,
+            linear_movement: header.linear_movement,
+            bounding_box: header.bounding_box,
+            blend_type: header.blend_type,
+            blend_start: header.blend_start,
+            blend_end: header.blend_end,
+            sequence_group: header.sequence_group,
+            entry_node: header.entry_node,
+            exit_node: header.exit_node,
+            node_flags: header.node_flags,
+            next_sequence: header.next_sequence,
+            blends,
+        })
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.flags & STUDIO_LOOPING != 0
+    }
+
+    /// Duration of the sequence in seconds
+    pub fn duration(&self) -> f32 {
+        if self.fps > 0.0 {
+            self.frame_count.saturating_sub(1) as f32 / self.fps
+        } else {
+            0.0
+        }
+    }
+
+    /// Sample all bones of a blend at a fractional frame, interpolating between the surrounding frames
+    ///
+    /// Looping sequences wrap around, other sequences are clamped to the first and last frame.
+    pub fn sample_frame(&self, frame: f32, blend: usize) -> Vec<BoneSample> {
+        let Some(tracks) = self.blends.get(blend) else {
+            return Vec::new();
+        };
+        let count = self.frame_count.max(1);
+        let frame = if self.is_looping() {
+            frame.rem_euclid(count as f32)
+        } else {
+            frame.clamp(0.0, (count - 1) as f32)
+        };
+        let current = (frame.floor() as usize).min(count - 1);
+        let next = if self.is_looping() {
+            (current + 1) % count
+        } else {
+            (current + 1).min(count - 1)
+        };
+        let t = frame - frame.floor();
+
+        tracks
+            .iter()
+            .enumerate()
+            .map(|(bone, track)| {
+                let sample = |frame: usize| BoneSample {
+                    bone: bone.into(),
+                    rotation: track.rotations.get(frame).copied().unwrap_or_default(),
+                    position: track.positions.get(frame).copied().unwrap_or_default(),
+                };
+                sample(current).blend(&sample(next), t)
+            })
+            .collect()
+    }
+
+    /// Sample all bones of a blend at a point in time, in seconds
+    pub fn sample(&self, time: f32, blend: usize) -> Vec<BoneSample> {
+        self.sample_frame(time * self.fps, blend)
+    }
+}
+
+/// Decode the 6 channels of a bone for all frames
+fn read_track(data: &[u8], index: usize, bone: &Bone, frame_count: usize) -> Result<BoneTrack> {
+    let header: GoldSrcAnimationHeader = read_single(data, index)?;
+    let mut channels: [Vec<f32>; 6] = Default::default();
+    for ((values, offset), (value, scale)) in channels
+        .iter_mut()
+        .zip(header.offsets)
+        .zip(bone.value.into_iter().zip(bone.scale))
+    {
+        *values = if offset == 0 {
+            vec![value; frame_count]
+        } else {
+            let offset = index + offset as usize;
+            read_at(data, offset, "GoldSrc animation value", |data| {
+                decode_values(data, frame_count)
+            })?
+            .into_iter()
+            .map(|raw| value + raw as f32 * scale)
+            .collect()
+        };
+    }
+    let [x, y, z, rx, ry, rz] = channels;
+    Ok(BoneTrack {
+        positions: (0..frame_count)
+            .map(|frame| Vector {
+                x: x[frame],
+                y: y[frame],
+                z: z[frame],
+            })
+            .collect(),
+        rotations: (0..frame_count)
+            .map(|frame| angle_quaternion([rx[frame], ry[frame], rz[frame]]))
+            .collect(),
+    })
+}
+
+/// Decode run length encoded animation values
+///
+/// Every run starts with the number of stored values and the number of frames covered by the run,
+/// frames after the stored values repeat the last stored value.
+fn decode_values(data: &[u8], frame_count: usize) -> Result<Vec<i16>> {
+    let mut values = Vec::with_capacity(frame_count);
+    let mut offset = 0;
+    while values.len() < frame_count {
+        let [valid, total]: [u8; 2] = read_single(data, offset)?;
+        if total == 0 {
+            break;
+        }
+        for frame in 0..total as usize {
+            let value_index = frame.min((valid as usize).saturating_sub(1));
+            let value = if valid == 0 {
+                0
+            } else {
+                read_single(data, offset + (value_index + 1) * size_of::<i16>())?
+            };
+            values.push(value);
+        }
+        offset += (valid as usize + 1) * size_of::<i16>();
+    }
+    let last = values.last().copied().unwrap_or_default();
+    values.resize(frame_count, last);
+    Ok(values)
+}
+
+/// Convert euler angles to a quaternion, the same way the engine does
+fn angle_quaternion(angles: [f32; 3]) -> Quaternion {
+    let (sy, cy) = (angles[2] * 0.5).sin_cos();
+    let (sp, cp) = (angles[1] * 0.5).sin_cos();
+    let (sr, cr) = (angles[0] * 0.5).sin_cos();
+    Quaternion {
+        x: sr * cp * cy - cr * sp * sy,
+        y: cr * sp * cy + sr * cp * sy,
+        z: cr * cp * sy - sr * sp * cy,
+        w: cr * cp * cy + sr * sp * sy,
+    }
+}
+
+/// A texture embedded in the model, stored as palette indices
+#[derive(Debug, Clone)]
+pub struct Texture {
+    pub name: String,
+    pub flags: TextureFlags,
+    pub width: usize,
+    pub height: usize,
+    /// Palette index of every pixel
+    pub indices: Vec<u8>,
+    pub palette: Vec<[u8; 3]>,
+}
+
+impl Texture {
+    fn read(data: &[u8], header: GoldSrcTextureHeader) -> Result<Self> {
+        let width = header.width.max(0) as usize;
+        let height = header.height.max(0) as usize;
+        let pixels = width * height;
+        let start = header.index.max(0) as usize;
+        let (indices, palette) = if start == 0 {
+            // the texture data is stored in a separate file
+            (Vec::new(), Vec::new())
+        } else {
+            let indices = data
+                .get(start..start + pixels)
+                .ok_or(ModelError::OutOfBounds {
+                    data: "GoldSrc texture",
+                    offset: start,
+                })?;
+            let palette = data.get(start + pixels..start + pixels + 256 * 3).ok_or(
+                ModelError::OutOfBounds {
+                    data: "GoldSrc palette",
+                    offset: start + pixels,
+                },
+            )?;
+            (
+                indices.to_vec(),
+                palette
+                    .chunks_exact(3)
+                    .map(|color| [color[0], color[1], color[2]])
+                    .collect(),
+            )
+        };
+        Ok(Texture {
+            name: FixedString::try_from(header.name)?.as_str().into(),
+            flags: header.flags,
+            width,
+            height,
+            indices,
+            palette,
+        })
+    }
+
+    /// Whether the pixel data is available in the model
+    pub fn is_embedded(&self) -> bool {
+        !self.indices.is_empty()
+    }
+
+    /// The texture as RGBA pixels
+    ///
+    /// For masked textures the last palette color is transparent.
+    pub fn rgba(&self) -> Vec<u8> {
+        let masked = self.flags.contains(TextureFlags::MASKED);
+        self.indices
+            .iter()
+            .flat_map(|&index| {
+                let [r, g, b] = self
+                    .palette
+                    .get(index as usize)
+                    .copied()
+                    .unwrap_or_default();
+                let a = if masked && index == 255 { 0 } else { 255 };
+                [r, g, b, a]
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BodyPart {
+    pub name: String,
+    pub base: i32,
+    pub models: Vec<SubModel>,
+}
+
+impl BodyPart {
+    fn read(data: &[u8], header: GoldSrcBodyPartHeader) -> Result<Self> {
+        Ok(BodyPart {
+            name: FixedString::try_from(header.name)?.as_str().into(),
+            base: header.base,
+            models: header
+                .model_indexes()
+                .map(|index| {
+                    let header = read_single::<GoldSrcModelHeader, _>(data, index)?;
+                    SubModel::read(data, header)
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// One of the models that can be selected for a body part
+#[derive(Debug, Clone)]
+pub struct SubModel {
+    pub name: String,
+    pub ty: i32,
+    pub bounding_radius: f32,
+    /// Vertex positions, relative to the bone the vertex belongs to
+    pub vertices: Vec<Vector>,
+    pub vertex_bones: Vec<BoneId>,
+    /// Vertex normals, relative to the bone the normal belongs to
+    pub normals: Vec<Vector>,
+    pub normal_bones: Vec<BoneId>,
+    pub meshes: Vec<SubMesh>,
+}
+
+impl SubModel {
+    fn read(data: &[u8], header: GoldSrcModelHeader) -> Result<Self> {
+        let bones = |indexes: &mut dyn Iterator<Item = usize>| {
+            indexes
+                .map(|index| read_single::<u8, _>(data, index).map(BoneId::from))
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(SubModel {
+            name: FixedString::try_from(header.name)?.as_str().into(),
+            ty: header.ty,
+            bounding_radius: header.bounding_radius,
+            vertices: read_relative(data, header.vertex_indexes())?,
+            vertex_bones: bones(&mut header.vertex_bone_indexes())?,
+            normals: read_relative(data, header.normal_indexes())?,
+            normal_bones: bones(&mut header.normal_bone_indexes())?,
+            meshes: header
+                .mesh_indexes()
+                .map(|index| {
+                    let header = read_single::<GoldSrcMeshHeader, _>(data, index)?;
+                    SubMesh::read(data, header)
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SubMesh {
+    /// Index into the skin table
+    pub skin_reference: i32,
+    /// The triangles of the mesh, converted from the triangle strips and fans in the file
+    pub triangles: Vec<[TriangleCommandVertex; 3]>,
+}
+
+impl SubMesh {
+    fn read(data: &[u8], header: GoldSrcMeshHeader) -> Result<Self> {
+        let mut offset = header.triangle_offset.max(0) as usize;
+        // every triangle takes at least one command vertex
+        let capacity = (header.triangle_count.max(0) as usize)
+            .min(data.len().saturating_sub(offset) / size_of::<TriangleCommandVertex>());
+        let mut triangles = Vec::with_capacity(capacity);
+        loop {
+            let command: i16 = read_single(data, offset)?;
+            offset += size_of::<i16>();
+            if command == 0 {
+                break;
+            }
+            let count = command.unsigned_abs() as usize;
+            let vertices = (0..count)
+                .map(|i| read_single(data, offset + i * size_of::<TriangleCommandVertex>()))
+                .collect::<Result<Vec<TriangleCommandVertex>>>()?;
+            offset += count * size_of::<TriangleCommandVertex>();
+            for i in 2..count {
+                triangles.push(if command < 0 {
+                    // fan
+                    [vertices[0], vertices[i - 1], vertices[i]]
+                } else if i % 2 == 1 {
+                    // strip, every other triangle is flipped to keep the winding consistent
+                    [vertices[i - 1], vertices[i - 2], vertices[i]]
+                } else {
+                    [vertices[i - 2], vertices[i - 1], vertices[i]]
+                });
+            }
+        }
+        Ok(SubMesh {
+            skin_reference: header.skin_reference,
+            triangles,
+        })
+    }
+}
+
+/// Position of a mesh in the body parts and its triangles as indices into the model vertices
+#[derive(Debug, Clone)]
+struct MeshIndex {
+    body_part: usize,
+    model: usize,
+    mesh: usize,
+    indices: Vec<usize>,
+}
+
+/// A parsed GoldSrc model
+#[derive(Debug, Clone)]
+pub struct Model {
+    pub name: FixedString<64>,
+    pub header: GoldSrcHeader,
+    pub bones: Vec<Bone>,
+    pub bone_controllers: Vec<BoneController>,
+    pub hit_boxes: Vec<HitBox>,
+    pub sequences: Vec<Sequence>,
+    pub sequence_groups: Vec<SequenceGroup>,
+    pub textures: Vec<Texture>,
+    pub skin_table: Vec<u16>,
+    pub body_parts: Vec<BodyPart>,
+    pub attachments: Vec<Attachment>,
+    vertices: Vec<Vertex>,
+    meshes: Vec<MeshIndex>,
+}
+
+impl Model {
+    pub fn read(data: &[u8]) -> Result<Self> {
+        let header = <GoldSrcHeader as Readable>::read(data)?;
+        if header.version != GOLDSRC_VERSION {
+            return Err(ModelError::UnsupportedVersion(header.version));
+        }
+        let bones: Vec<Bone> = read_relative(data, header.bone_indexes())?;
+        let sequences = header
+            .sequence_indexes()
+            .map(|index| {
+                let sequence_header = read_single::<GoldSrcSequenceHeader, _>(data, index)?;
+                Sequence::read(data, sequence_header, &bones)
+            })
+            .collect::<Result<_>>()?;
+        let textures = header
+            .texture_indexes()
+            .map(|index| {
+                let texture_header = read_single::<GoldSrcTextureHeader, _>(data, index)?;
+                Texture::read(data, texture_header)
+            })
+            .collect::<Result<_>>()?;
+        let body_parts = header
+            .body_part_indexes()
+            .map(|index| {
+                let body_part_header = read_single::<GoldSrcBodyPartHeader, _>(data, index)?;
+                BodyPart::read(data, body_part_header)
+            })
+            .collect::<Result<_>>()?;
+
+        let mut model = Model {
+            name: header.name.try_into()?,
+            bone_controllers: read_relative(data, header.bone_controller_indexes())?,
+            hit_boxes: read_relative(data, header.hitbox_indexes())?,
+            sequence_groups: read_relative(data, header.sequence_group_indexes())?,
+            skin_table: read_relative(data, header.skin_reference_indexes())?,
+            attachments: read_relative(data, header.attachment_indexes())?,
+            header,
+            bones,
+            sequences,
+            textures,
+            body_parts,
+            vertices: Vec::new(),
+            meshes: Vec::new(),
+        };
+        model.build_vertices();
+        Ok(model)
+    }
+
+    /// Load the model from path
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let data = fs::read(path)?;
+        Self::read(&data)
+    }
+
+    /// Build the vertex list for all meshes in the bind pose
+    fn build_vertices(&mut self) {
+        let transforms = self.bone_transforms(&self.rest_pose());
+        let mut vertices = Vec::new();
+        let mut meshes = Vec::new();
+        for (body_part_index, body_part) in self.body_parts.iter().enumerate() {
+            for (model_index, model) in body_part.models.iter().enumerate() {
+                let mut unique = HashMap::new();
+                for (mesh_index, mesh) in model.meshes.iter().enumerate() {
+                    let texture = self.texture_for_skin_reference(0, mesh.skin_reference);
+                    let (width, height) = texture
+                        .map(|texture| (texture.width.max(1) as f32, texture.height.max(1) as f32))
+                        .unwrap_or((1.0, 1.0));
+                    let indices = mesh
+                        .triangles
+                        .iter()
+                        .flatten()
+                        .map(|vertex| {
+                            let key = (vertex.vertex, vertex.normal, vertex.s, vertex.t);
+                            *unique.entry(key).or_insert_with(|| {
+                                vertices.push(bind_vertex(
+                                    model,
+                                    &transforms,
+                                    vertex,
+                                    [width, height],
+                                ));
+                                vertices.len() - 1
+                            })
+                        })
+                        .collect();
+                    meshes.push(MeshIndex {
+                        body_part: body_part_index,
+                        model: model_index,
+                        mesh: mesh_index,
+                        indices,
+                    });
+                }
+            }
+        }
+        self.vertices = vertices;
+        self.meshes = meshes;
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Position of the player viewpoint relative to the model origin
+    pub fn eye_position(&self) -> Vector {
+        self.header.eye_position
+    }
+
+    /// Bounds used for collision and movement
+    pub fn hull_box(&self) -> (Vector, Vector) {
+        (self.header.hull_box[0], self.header.hull_box[1])
+    }
+
+    /// Bounds used for visibility culling
+    pub fn view_box(&self) -> (Vector, Vector) {
+        (self.header.view_box[0], self.header.view_box[1])
+    }
+
+    pub fn bones(&self) -> impl Iterator<Item = (BoneId, &Bone)> {
+        self.bones
+            .iter()
+            .enumerate()
+            .map(|(index, bone)| (index.into(), bone))
+    }
+
+    /// The rest pose of every bone, indexed by bone id
+    pub fn rest_pose(&self) -> Vec<BoneSample> {
+        self.bones()
+            .map(|(id, bone)| BoneSample {
+                bone: id,
+                rotation: bone.rest_rotation(),
+                position: bone.rest_position(),
+            })
+            .collect()
+    }
+
+    /// The model space transform of every bone in a pose
+    ///
+    /// The pose is indexed by bone id, bones missing from the pose use their rest pose.
+    pub fn bone_transforms(&self, pose: &[BoneSample]) -> Vec<Matrix4<f32>> {
+        let mut transforms: Vec<Matrix4<f32>> = Vec::with_capacity(self.bones.len());
+        for (id, bone) in self.bones() {
+            let (rotation, position) = pose
+                .get(usize::from(id))
+                .filter(|sample| sample.bone == id)
+                .map(|sample| (sample.rotation, sample.position))
+                .unwrap_or_else(|| (bone.rest_rotation(), bone.rest_position()));
+            let local = Matrix4::from_translation(position.into()) * Matrix4::from(rotation);
+            // parents are always stored before their children
+            let parent = bone
+                .parent
+                .and_then(|parent| transforms.get(usize::from(parent)));
+            transforms.push(match parent {
+                Some(parent) => parent * local,
+                None => local,
+            });
+        }
+        transforms
+    }
+
+    pub fn textures(&self) -> &[Texture] {
+        &self.textures
+    }
+
+    /// The texture indices for every skin family
+    pub fn skin_tables(&self) -> impl Iterator<Item = &[u16]> {
+        self.skin_table
+            .chunks(self.header.skin_reference_count.max(1) as usize)
+    }
+
+    /// The texture used by a skin reference in a skin family
+    pub fn texture_for_skin_reference(&self, skin: usize, skin_reference: i32) -> Option<&Texture> {
+        let table = self.skin_tables().nth(skin)?;
+        let index = table.get(usize::try_from(skin_reference).ok()?)?;
+        self.textures.get(*index as usize)
+    }
+
+    pub fn sequences(&self) -> &[Sequence] {
+        &self.sequences
+    }
+
+    pub fn body_parts(&self) -> &[BodyPart] {
+        &self.body_parts
+    }
+
+    pub fn attachments(&self) -> &[Attachment] {
+        &self.attachments
+    }
+
+    pub fn hit_boxes(&self) -> &[HitBox] {
+        &self.hit_boxes
+    }
+
+    /// The vertices of all meshes in the bind pose
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    pub fn meshes(&self) -> impl Iterator<Item = Mesh<'_>> {
+        self.meshes.iter().map(|index| Mesh {
+            mesh: &self.body_parts[index.body_part].models[index.model].meshes[index.mesh],
+            index,
+            vertices: &self.vertices,
+        })
+    }
+
+    /// The bounding box of all vertices in the bind pose
+    pub fn computed_bounding_box(&self) -> Option<(Vector, Vector)> {
+        bounding_box(&self.vertices)
+    }
+}
+
+fn bind_vertex(
+    model: &SubModel,
+    transforms: &[Matrix4<f32>],
+    vertex: &TriangleCommandVertex,
+    [width, height]: [f32; 2],
+) -> Vertex {
+    let transform = |bones: &[BoneId], index: i16| {
+        let bone = bones
+            .get(index.max(0) as usize)
+            .copied()
+            .unwrap_or_default();
+        let transform = transforms
+            .get(usize::from(bone))
+            .copied()
+            .unwrap_or_else(Matrix4::identity);
+        (bone, transform)
+    };
+    let (bone, vertex_transform) = transform(&model.vertex_bones, vertex.vertex);
+    let (_, normal_transform) = transform(&model.normal_bones, vertex.normal);
+    let position = model
+        .vertices
+        .get(vertex.vertex.max(0) as usize)
+        .copied()
+        .unwrap_or_default();
+    let normal = model
+        .normals
+        .get(vertex.normal.max(0) as usize)
+        .copied()
+        .unwrap_or_default();
+    let position = vertex_transform * Vector4::new(position.x, position.y, position.z, 1.0);
+    let normal = normal_transform * Vector4::new(normal.x, normal.y, normal.z, 0.0);
+    let normal = normal.truncate();
+    let normal = if normal.magnitude2() > 0.0 {
+        normal.normalize()
+    } else {
+        normal
+    };
+    Vertex {
+        bone_weights: BoneWeights::single(bone),
+        position: position.truncate().into(),
+        normal: normal.into(),
+        texture_coordinates: [vertex.s as f32 / width, vertex.t as f32 / height],
+    }
+}
+
+/// A mesh of a GoldSrc model
+#[derive(Debug, Clone, Copy)]
+pub struct Mesh<'a> {
+    mesh: &'a SubMesh,
+    index: &'a MeshIndex,
+    vertices: &'a [Vertex],
+}
+
+impl<'a> Mesh<'a> {
+    pub fn body_part_index(&self) -> usize {
+        self.index.body_part
+    }
+
+    pub fn model_index(&self) -> usize {
+        self.index.model
+    }
+
+    /// Index into the skin table
+    pub fn skin_reference(&self) -> i32 {
+        self.mesh.skin_reference
+    }
+
+    /// The triangles of the mesh as indices into the model's vertex list
+    pub fn triangle_indices(&self) -> impl Iterator<Item = [usize; 3]> + 'a {
+        self.index
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+    }
+
+    pub fn vertices(&self) -> impl Iterator<Item = &'a Vertex> + 'a {
+        let vertices = self.vertices;
+        self.index
+            .indices
+            .iter()
+            .filter_map(move |index| vertices.get(*index))
+    }
+}
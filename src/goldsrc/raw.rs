@@ -0,0 +1,400 @@
+use crate::{index_range, ReadableRelative, Vector};
+use bitflags::bitflags;
+use bytemuck::{Pod, Zeroable};
+use std::mem::size_of;
+
+pub const GOLDSRC_VERSION: i32 = 10;
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcHeader {
+    pub id: i32,
+    pub version: i32,
+    pub name: [u8; 64],
+    pub length: i32,
+
+    pub eye_position: Vector,
+    /// Bounds used for collision and movement
+    pub hull_box: [Vector; 2],
+    /// Bounds used for visibility culling
+    pub view_box: [Vector; 2],
+    pub flags: i32,
+
+    bone_count: i32,
+    bone_offset: i32,
+    bone_controller_count: i32,
+    bone_controller_offset: i32,
+    hitbox_count: i32,
+    hitbox_offset: i32,
+    sequence_count: i32,
+    sequence_offset: i32,
+    sequence_group_count: i32,
+    sequence_group_offset: i32,
+    texture_count: i32,
+    texture_offset: i32,
+    pub texture_data_offset: i32,
+    pub skin_reference_count: i32,
+    skin_family_count: i32,
+    skin_offset: i32,
+    body_part_count: i32,
+    body_part_offset: i32,
+    attachment_count: i32,
+    attachment_offset: i32,
+    _sound_table: i32,
+    _sound_offset: i32,
+    _sound_groups: i32,
+    _sound_group_offset: i32,
+    _transition_count: i32,
+    _transition_offset: i32,
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcHeader>(), 244);
+
+impl GoldSrcHeader {
+    pub fn bone_count(&self) -> usize {
+        self.bone_count.max(0) as usize
+    }
+
+    pub fn bone_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.bone_offset,
+            self.bone_count,
+            size_of::<GoldSrcBoneHeader>(),
+        )
+    }
+
+    pub fn bone_controller_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.bone_controller_offset,
+            self.bone_controller_count,
+            size_of::<GoldSrcBoneControllerHeader>(),
+        )
+    }
+
+    pub fn hitbox_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.hitbox_offset,
+            self.hitbox_count,
+            size_of::<GoldSrcHitBoxHeader>(),
+        )
+    }
+
+    pub fn sequence_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.sequence_offset,
+            self.sequence_count,
+            size_of::<GoldSrcSequenceHeader>(),
+        )
+    }
+
+    pub fn sequence_group_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.sequence_group_offset,
+            self.sequence_group_count,
+            size_of::<GoldSrcSequenceGroupHeader>(),
+        )
+    }
+
+    pub fn texture_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.texture_offset,
+            self.texture_count,
+            size_of::<GoldSrcTextureHeader>(),
+        )
+    }
+
+    pub fn skin_reference_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.skin_offset,
+            self.skin_reference_count * self.skin_family_count,
+            size_of::<u16>(),
+        )
+    }
+
+    pub fn body_part_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.body_part_offset,
+            self.body_part_count,
+            size_of::<GoldSrcBodyPartHeader>(),
+        )
+    }
+
+    pub fn attachment_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.attachment_offset,
+            self.attachment_count,
+            size_of::<GoldSrcAttachmentHeader>(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcBoneHeader {
+    pub name: [u8; 32],
+    pub parent: i32,
+    pub flags: i32,
+    pub bone_controller: [i32; 6],
+    /// Rest position followed by the rest rotation as euler angles
+    pub value: [f32; 6],
+    /// Scale of the compressed animation values
+    pub scale: [f32; 6],
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcBoneHeader>(), 112);
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcBoneControllerHeader {
+    pub bone: i32,
+    pub ty: i32,
+    pub start: f32,
+    pub end: f32,
+    pub rest: i32,
+    pub index: i32,
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcBoneControllerHeader>(), 24);
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcHitBoxHeader {
+    pub bone: i32,
+    pub group: i32,
+    pub min: Vector,
+    pub max: Vector,
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcHitBoxHeader>(), 32);
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcSequenceHeader {
+    pub label: [u8; 32],
+    pub fps: f32,
+    pub flags: i32,
+    pub activity: i32,
+    pub activity_weight: i32,
+    event_count: i32,
+    event_offset: i32,
+    pub frame_count: i32,
+    _pivot_count: i32,
+    _pivot_offset: i32,
+    pub motion_type: i32,
+    pub motion_bone: i32,
+    pub linear_movement: Vector,
+    _auto_move_position_offset: i32,
+    _auto_move_angle_offset: i32,
+    pub bounding_box: [Vector; 2],
+    pub blend_count: i32,
+    pub animation_offset: i32,
+    pub blend_type: [i32; 2],
+    pub blend_start: [f32; 2],
+    pub blend_end: [f32; 2],
+    _blend_parent: i32,
+    pub sequence_group: i32,
+    pub entry_node: i32,
+    pub exit_node: i32,
+    pub node_flags: i32,
+    pub next_sequence: i32,
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcSequenceHeader>(), 176);
+
+impl ReadableRelative for GoldSrcSequenceHeader {}
+
+impl GoldSrcSequenceHeader {
+    /// Event offsets, relative to the start of the file
+    pub fn event_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.event_offset,
+            self.event_count,
+            size_of::<GoldSrcEventHeader>(),
+        )
+    }
+
+    /// Offsets of the animations for every blend and bone, relative to the start of the sequence group
+    pub fn animation_indexes(&self, bone_count: usize) -> impl Iterator<Item = usize> {
+        index_range(
+            self.animation_offset,
+            self.blend_count.max(0) * bone_count as i32,
+            size_of::<GoldSrcAnimationHeader>(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcEventHeader {
+    pub frame: i32,
+    pub event: i32,
+    pub ty: i32,
+    pub options: [u8; 64],
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcEventHeader>(), 76);
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcSequenceGroupHeader {
+    pub label: [u8; 32],
+    pub name: [u8; 64],
+    _unused: [i32; 2],
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcSequenceGroupHeader>(), 104);
+
+/// Offsets to the run length encoded values of the 3 position and 3 rotation channels of a bone,
+/// relative to the start of this struct
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcAnimationHeader {
+    pub offsets: [u16; 6],
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcAnimationHeader>(), 12);
+
+impl ReadableRelative for GoldSrcAnimationHeader {}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcTextureHeader {
+    pub name: [u8; 64],
+    pub flags: TextureFlags,
+    pub width: i32,
+    pub height: i32,
+    /// Offset of the palette indices, the palette follows directly after the indices
+    pub index: i32,
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcTextureHeader>(), 80);
+
+impl ReadableRelative for GoldSrcTextureHeader {}
+
+#[derive(Zeroable, Pod, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct TextureFlags(i32);
+
+bitflags! {
+    impl TextureFlags: i32 {
+        const FLAT_SHADE = 0x0001;
+        const CHROME = 0x0002;
+        const FULLBRIGHT = 0x0004;
+        const NO_MIPS = 0x0008;
+        const ALPHA = 0x0010;
+        const ADDITIVE = 0x0020;
+        /// Palette index 255 is transparent
+        const MASKED = 0x0040;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcBodyPartHeader {
+    pub name: [u8; 64],
+    model_count: i32,
+    pub base: i32,
+    model_offset: i32,
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcBodyPartHeader>(), 76);
+
+impl ReadableRelative for GoldSrcBodyPartHeader {}
+
+impl GoldSrcBodyPartHeader {
+    pub fn model_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.model_offset,
+            self.model_count,
+            size_of::<GoldSrcModelHeader>(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcModelHeader {
+    pub name: [u8; 64],
+    pub ty: i32,
+    pub bounding_radius: f32,
+    mesh_count: i32,
+    mesh_offset: i32,
+    vertex_count: i32,
+    vertex_bone_offset: i32,
+    vertex_offset: i32,
+    normal_count: i32,
+    normal_bone_offset: i32,
+    normal_offset: i32,
+    _group_count: i32,
+    _group_offset: i32,
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcModelHeader>(), 112);
+
+impl ReadableRelative for GoldSrcModelHeader {}
+
+impl GoldSrcModelHeader {
+    pub fn mesh_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.mesh_offset,
+            self.mesh_count,
+            size_of::<GoldSrcMeshHeader>(),
+        )
+    }
+
+    pub fn vertex_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(self.vertex_offset, self.vertex_count, size_of::<Vector>())
+    }
+
+    pub fn vertex_bone_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(self.vertex_bone_offset, self.vertex_count, size_of::<u8>())
+    }
+
+    pub fn normal_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(self.normal_offset, self.normal_count, size_of::<Vector>())
+    }
+
+    pub fn normal_bone_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(self.normal_bone_offset, self.normal_count, size_of::<u8>())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcMeshHeader {
+    pub triangle_count: i32,
+    /// Offset of the triangle commands
+    pub triangle_offset: i32,
+    pub skin_reference: i32,
+    _normal_count: i32,
+    _normal_offset: i32,
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcMeshHeader>(), 20);
+
+impl ReadableRelative for GoldSrcMeshHeader {}
+
+/// A vertex of a triangle command
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct TriangleCommandVertex {
+    pub vertex: i16,
+    pub normal: i16,
+    /// Texture coordinates in pixels
+    pub s: i16,
+    pub t: i16,
+}
+
+impl ReadableRelative for TriangleCommandVertex {}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct GoldSrcAttachmentHeader {
+    pub name: [u8; 32],
+    pub ty: i32,
+    pub bone: i32,
+    pub origin: Vector,
+    pub vectors: [Vector; 3],
+}
+
+static_assertions::const_assert_eq!(size_of::<GoldSrcAttachmentHeader>(), 88);
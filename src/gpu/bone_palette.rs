@@ -0,0 +1,47 @@
+use crate::mdl::BoneId;
+use crate::Model;
+
+/// A compact list of the bones used by a level of detail
+///
+/// Lower levels of detail often only use a subset of the model's bones, remapping the bone indices
+/// of the vertices into the palette allows uploading a smaller set of bone matrices to the gpu.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BonePalette {
+    bones: Vec<BoneId>,
+}
+
+impl BonePalette {
+    /// Build the palette for a level of detail
+    ///
+    /// Next to the bones flagged as used by the level of detail, any bone referenced by the vertices
+    /// of the level of detail is included, in case the flags of the model are incomplete.
+    pub fn for_lod(model: &Model, lod: usize) -> Self {
+        let mut bones: Vec<BoneId> = model.bones_for_lod(lod).map(|bone| bone.key()).collect();
+        for mesh in model.meshes_for_lod(lod) {
+            for vertex in mesh.vertices() {
+                bones.extend(vertex.bone_weights.weights().map(|weight| weight.bone_id));
+            }
+        }
+        bones.sort();
+        bones.dedup();
+        BonePalette { bones }
+    }
+
+    /// The model bones in the palette, ordered by their palette index
+    pub fn bones(&self) -> &[BoneId] {
+        &self.bones
+    }
+
+    pub fn len(&self) -> usize {
+        self.bones.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bones.is_empty()
+    }
+
+    /// The palette index for a model bone
+    pub fn index(&self, bone: BoneId) -> Option<usize> {
+        self.bones.binary_search(&bone).ok()
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{Mesh, Vector};
+use cgmath::{InnerSpace, Vector3};
+use std::collections::HashMap;
+
+/// Limits for the size of generated meshlets
+#[derive(Debug, Clone, Copy)]
+pub struct MeshletOptions {
+    /// Maximum number of unique vertices in a meshlet, at most 256
+    pub max_vertices: usize,
+    /// Maximum number of triangles in a meshlet
+    pub max_triangles: usize,
+}
+
+impl Default for MeshletOptions {
+    fn default() -> Self {
+        MeshletOptions {
+            max_vertices: 64,
+            max_triangles: 124,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Meshlet {
+    /// Indices into the model's vertex list
+    pub vertices: Vec<usize>,
+    /// Triangles as indices into the [`vertices`](Self::vertices) of the meshlet
+    pub triangles: Vec<[u8; 3]>,
+    /// Center of the bounding sphere
+    pub center: Vector,
+    /// Radius of the bounding sphere
+    pub radius: f32,
+    /// Normal cone for backface culling, `None` when the triangles face in too many directions to be culled together
+    pub cone: Option<MeshletCone>,
+}
+
+/// Normal cone of a meshlet
+///
+/// The meshlet can be culled when `dot(normalize(apex - camera_position), axis) >= cutoff`.
+#[derive(Debug, Clone, Copy)]
+pub struct MeshletCone {
+    pub apex: Vector,
+    pub axis: Vector,
+    pub cutoff: f32,
+}
+
+/// Split a mesh into meshlets for mesh-shader based rendering
+///
+/// Triangles are added to meshlets in the order they are stored in the mesh,
+/// triangles with a vertex that doesn't exist are skipped.
+pub fn meshlets(mesh: &Mesh, options: MeshletOptions) -> Vec<Meshlet> {
+    let max_vertices = options.max_vertices.clamp(3, 256);
+    let max_triangles = options.max_triangles.max(1);
+
+    let mut meshlets = Vec::new();
+    let mut vertices = Vec::new();
+    let mut positions = Vec::new();
+    let mut local = HashMap::new();
+    let mut triangles = Vec::new();
+
+    for triangle in mesh.triangles() {
+        let lookup = |index: usize| Some((index, mesh.vertices.get(index)?.position));
+        let [a, b, c] = triangle;
+        let (Some(a), Some(b), Some(c)) = (lookup(a), lookup(b), lookup(c)) else {
+            continue;
+        };
+        let new_vertices = triangle
+            .iter()
+            .enumerate()
+            .filter(|(i, index)| !local.contains_key(*index) && !triangle[..*i].contains(index))
+            .count();
+        if vertices.len() + new_vertices > max_vertices || triangles.len() >= max_triangles {
+            meshlets.push(build_meshlet(&vertices, &positions, &triangles));
+            vertices.clear();
+            positions.clear();
+            local.clear();
+            triangles.clear();
+        }
+
+        triangles.push([a, b, c].map(|(index, position)| {
+            *local.entry(index).or_insert_with(|| {
+                vertices.push(index);
+                positions.push(position);
+                (vertices.len() - 1) as u8
+            })
+        }));
+    }
+    if !triangles.is_empty() {
+        meshlets.push(build_meshlet(&vertices, &positions, &triangles));
+    }
+    meshlets
+}
+
+/// Build a meshlet from its vertices, their positions and the triangles indexing into them
+fn build_meshlet(vertices: &[usize], positions: &[Vector], triangles: &[[u8; 3]]) -> Meshlet {
+    let position = |index: u8| -> Vector3<f32> { positions[index as usize].into() };
+
+    let (min, max) = positions.iter().fold(
+        (Vector::from([f32::MAX; 3]), Vector::from([f32::MIN; 3])),
+        |(min, max), position| (min.min(*position), max.max(*position)),
+    );
+    let center = (min + max) * 0.5;
+    let radius = positions
+        .iter()
+        .map(|position| (*position - center).length())
+        .fold(0.0, f32::max);
+
+    let normals = triangles
+        .iter()
+        .filter_map(|[a, b, c]| {
+            let (a, b, c) = (position(*a), position(*b), position(*c));
+            let normal = (b - a).cross(c - a);
+            (normal.magnitude2() > f32::EPSILON).then(|| (a, normal.normalize()))
+        })
+        .collect::<Vec<_>>();
+
+    Meshlet {
+        vertices: vertices.to_vec(),
+        triangles: triangles.to_vec(),
+        center,
+        radius,
+        cone: normal_cone(center.into(), &normals),
+    }
+}
+
+fn normal_cone(
+    center: Vector3<f32>,
+    normals: &[(Vector3<f32>, Vector3<f32>)],
+) -> Option<MeshletCone> {
+    let sum = normals
+        .iter()
+        .fold(Vector3::new(0.0, 0.0, 0.0), |sum, (_, normal)| sum + normal);
+    if sum.magnitude2() <= f32::EPSILON {
+        return None;
+    }
+    let axis = sum.normalize();
+
+    let min_dot = normals
+        .iter()
+        .map(|(_, normal)| normal.dot(axis))
+        .fold(1.0, f32::min);
+    // a cone spanning (almost) a hemisphere or more can't be used to cull anything
+    if min_dot <= 0.1 {
+        return None;
+    }
+
+    // move the apex back along the axis so that all triangle planes are in front of it
+    let max_distance = normals
+        .iter()
+        .map(|(point, normal)| (center - point).dot(*normal) / axis.dot(*normal))
+        .fold(0.0, f32::max);
+
+    Some(MeshletCone {
+        apex: (center - axis * max_distance).into(),
+        axis: axis.into(),
+        cutoff: (1.0 - min_dot * min_dot).sqrt(),
+    })
+}
@@ -0,0 +1,9 @@
+//! Utilities for preparing model data for the gpu
+
+mod bone_palette;
+mod meshlets;
+mod vertex_buffer;
+
+pub use bone_palette::*;
+pub use meshlets::*;
+pub use vertex_buffer::*;
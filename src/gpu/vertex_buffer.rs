@@ -0,0 +1,508 @@
+use super::BonePalette;
+use crate::mdl::BoneId;
+use crate::vvd::Vertex;
+use crate::{skip_invalid_triangles, vtx, Mesh, ModelError, Vector};
+use half::f16;
+use std::collections::HashMap;
+use std::ops::Range;
+use tracing::warn;
+
+/// Vertex data that can be stored in a vertex buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VertexAttribute {
+    /// Position, with `1.0` as fourth component
+    Position,
+    /// Normal, with `0.0` as fourth component
+    Normal,
+    /// Tangent with the handedness as fourth component
+    Tangent,
+    TextureCoordinates,
+    /// Indices of the (up to 3) bones influencing the vertex
+    BoneIndices,
+    /// Weights of the bones influencing the vertex, normalized to sum up to 1
+    BoneWeights,
+}
+
+/// Format an attribute is stored as
+///
+/// Formats with fewer components than the attribute only store the first components,
+/// missing components are filled with `0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VertexFormat {
+    Float32x2,
+    Float32x3,
+    Float32x4,
+    Float16x2,
+    Float16x4,
+    /// Octahedral encoded unit vector in two `snorm16` values, see [`octahedral_encode`]
+    Octahedral16x2,
+    Snorm8x4,
+    Unorm8x4,
+    Uint8x4,
+    Uint16x4,
+}
+
+impl VertexFormat {
+    /// Size of the format in bytes
+    pub fn size(&self) -> usize {
+        match self {
+            VertexFormat::Float32x2 => 8,
+            VertexFormat::Float32x3 => 12,
+            VertexFormat::Float32x4 => 16,
+            VertexFormat::Float16x2 => 4,
+            VertexFormat::Float16x4 => 8,
+            VertexFormat::Octahedral16x2 => 4,
+            VertexFormat::Snorm8x4 => 4,
+            VertexFormat::Unorm8x4 => 4,
+            VertexFormat::Uint8x4 => 4,
+            VertexFormat::Uint16x4 => 8,
+        }
+    }
+
+    fn write(&self, value: [f32; 4], out: &mut Vec<u8>) {
+        match self {
+            VertexFormat::Float32x2 | VertexFormat::Float32x3 | VertexFormat::Float32x4 => {
+                for component in &value[..self.size() / 4] {
+                    out.extend_from_slice(&component.to_le_bytes());
+                }
+            }
+            VertexFormat::Float16x2 | VertexFormat::Float16x4 => {
+                for component in &value[..self.size() / 2] {
+                    out.extend_from_slice(&f16::from_f32(*component).to_le_bytes());
+                }
+            }
+            VertexFormat::Octahedral16x2 => {
+                let [x, y, z, _] = value;
+                for component in octahedral_encode(Vector { x, y, z }) {
+                    out.extend_from_slice(&component.to_le_bytes());
+                }
+            }
+            VertexFormat::Snorm8x4 => {
+                out.extend(value.map(|c| ((c.clamp(-1.0, 1.0) * 127.0).round() as i8) as u8))
+            }
+            VertexFormat::Unorm8x4 => {
+                out.extend(value.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+            }
+            VertexFormat::Uint8x4 => out.extend(value.map(|c| c as u8)),
+            VertexFormat::Uint16x4 => {
+                for component in value {
+                    out.extend_from_slice(&(component as u16).to_le_bytes());
+                }
+            }
+        }
+    }
+}
+
+/// A single attribute in a [`VertexLayout`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexElement {
+    pub attribute: VertexAttribute,
+    pub format: VertexFormat,
+    /// Offset of the element from the start of the vertex in bytes
+    pub offset: usize,
+}
+
+/// Description of the interleaved vertex data to build
+///
+/// ```
+/// # use vmdl::gpu::{VertexAttribute, VertexFormat, VertexLayout};
+/// let layout = VertexLayout::new()
+///     .attribute(VertexAttribute::Position, VertexFormat::Float32x3)
+///     .padding(4)
+///     .attribute(VertexAttribute::TextureCoordinates, VertexFormat::Float16x2);
+/// assert_eq!(layout.stride(), 20);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VertexLayout {
+    elements: Vec<VertexElement>,
+    stride: usize,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an attribute after the previous attributes
+    pub fn attribute(mut self, attribute: VertexAttribute, format: VertexFormat) -> Self {
+        self.elements.push(VertexElement {
+            attribute,
+            format,
+            offset: self.stride,
+        });
+        self.stride += format.size();
+        self
+    }
+
+    /// Add unused bytes after the previous attributes
+    pub fn padding(mut self, bytes: usize) -> Self {
+        self.stride += bytes;
+        self
+    }
+
+    /// Pad the stride of the vertex to a multiple of `alignment` bytes
+    pub fn aligned(mut self, alignment: usize) -> Self {
+        self.stride = self.stride.next_multiple_of(alignment.max(1));
+        self
+    }
+
+    pub fn elements(&self) -> &[VertexElement] {
+        &self.elements
+    }
+
+    /// Size of a single vertex in bytes
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+}
+
+/// Precision of the attributes in an interleaved vertex buffer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VertexPrecision {
+    /// `f32` positions, normals and uvs, 32 bytes per vertex
+    #[default]
+    Full,
+    /// Half-float positions and uvs with octahedral encoded normals, 16 bytes per vertex
+    ///
+    /// Positions are stored as `f16x4` with the last component set to `1.0`, normals as `snorm16x2`
+    /// and uvs as `f16x2`.
+    Quantized,
+}
+
+impl VertexPrecision {
+    /// Size of a single vertex in bytes
+    pub fn stride(&self) -> usize {
+        self.layout().stride()
+    }
+
+    /// The position, normal, uv layout for the precision
+    pub fn layout(&self) -> VertexLayout {
+        let (position, normal, uv) = match self {
+            VertexPrecision::Full => (
+                VertexFormat::Float32x3,
+                VertexFormat::Float32x3,
+                VertexFormat::Float32x2,
+            ),
+            VertexPrecision::Quantized => (
+                VertexFormat::Float16x4,
+                VertexFormat::Octahedral16x2,
+                VertexFormat::Float16x2,
+            ),
+        };
+        VertexLayout::new()
+            .attribute(VertexAttribute::Position, position)
+            .attribute(VertexAttribute::Normal, normal)
+            .attribute(VertexAttribute::TextureCoordinates, uv)
+    }
+}
+
+/// Interleaved vertex data with an index buffer
+#[derive(Debug, Clone)]
+pub struct VertexBuffer {
+    pub data: Vec<u8>,
+    pub stride: usize,
+    /// Triangle list indices into the vertices of the buffer
+    pub indices: Vec<u32>,
+    /// For every vertex in the buffer, the index into the model's vertex list it was created from
+    pub vertex_indices: Vec<usize>,
+    /// The ranges of the indices drawn with their own set of hardware bones
+    ///
+    /// Only filled when building with [`BoneIndexMode::Hardware`].
+    pub draws: Vec<HardwareDraw>,
+}
+
+/// How the [`BoneIndices`](VertexAttribute::BoneIndices) of the vertices are written
+#[derive(Debug, Clone, Copy, Default)]
+pub enum BoneIndexMode<'a> {
+    /// The ids of the bones in the model
+    #[default]
+    Model,
+    /// Indices into a [`BonePalette`], bones that aren't part of the palette are mapped to the first bone of the palette
+    Palette(&'a BonePalette),
+    /// The strip-local hardware bone indices stored in the vtx
+    ///
+    /// Every strip is drawn with its own small set of bones, listed in the [`draws`](VertexBuffer::draws)
+    /// of the buffer.
+    Hardware,
+}
+
+/// A range of the index buffer drawn with a single set of hardware bones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HardwareDraw {
+    /// Range in the [`indices`](VertexBuffer::indices) of the buffer
+    pub indices: Range<usize>,
+    /// The model bone for every hardware bone index used by the vertices in the range
+    ///
+    /// Hardware bones that aren't assigned by the strip are mapped to the first bone of the model.
+    pub bones: Vec<BoneId>,
+}
+
+/// Build an interleaved position, normal, uv vertex buffer for a mesh
+///
+/// Vertices shared between triangles are only stored once.
+pub fn build_vertex_buffer(mesh: &Mesh, precision: VertexPrecision) -> VertexBuffer {
+    mesh.build_vertex_buffer(&precision.layout())
+}
+
+impl Mesh<'_> {
+    /// Build an interleaved vertex buffer for the mesh matching the provided layout
+    ///
+    /// Vertices shared between triangles are only stored once.
+    pub fn build_vertex_buffer(&self, layout: &VertexLayout) -> VertexBuffer {
+        self.build_vertex_buffer_with_bones(layout, BoneIndexMode::Model)
+    }
+
+    /// Build an interleaved vertex buffer for the mesh with the bone indices remapped into a [`BonePalette`]
+    ///
+    /// Bones that aren't part of the palette are mapped to the first bone of the palette.
+    pub fn build_vertex_buffer_with_palette(
+        &self,
+        layout: &VertexLayout,
+        palette: &BonePalette,
+    ) -> VertexBuffer {
+        self.build_vertex_buffer_with_bones(layout, BoneIndexMode::Palette(palette))
+    }
+
+    /// Build an interleaved vertex buffer for the mesh with the bone indices written as selected by `mode`
+    ///
+    /// With [`BoneIndexMode::Hardware`] vertices are only shared between the strips of a strip group,
+    /// since the hardware bones of a vertex are stored per strip group.
+    pub fn build_vertex_buffer_with_bones(
+        &self,
+        layout: &VertexLayout,
+        mode: BoneIndexMode,
+    ) -> VertexBuffer {
+        let vertices = match mode {
+            BoneIndexMode::Model => self.model_bone_vertices(usize::from),
+            BoneIndexMode::Palette(palette) => {
+                self.model_bone_vertices(|bone| palette.index(bone).unwrap_or_default())
+            }
+            BoneIndexMode::Hardware => self.hardware_bone_vertices(),
+        };
+        self.write_vertex_buffer(layout, vertices)
+    }
+
+    fn model_bone_vertices(&self, bone_index: impl Fn(BoneId) -> usize) -> BufferVertices {
+        let (vertex_indices, indices) = deduplicated_indices(self);
+        // the indices are checked by `deduplicated_indices`
+        let bone_indices = vertex_indices
+            .iter()
+            .map(|index| bone_indices(&self.vertices[*index], &bone_index))
+            .collect();
+        BufferVertices {
+            vertex_indices,
+            bone_indices,
+            indices,
+            draws: Vec::new(),
+        }
+    }
+
+    fn hardware_bone_vertices(&self) -> BufferVertices {
+        let vertex_offset = self.vertex_offset();
+        let mut local_indices = HashMap::new();
+        let mut vertices = BufferVertices::default();
+        for (group_index, group) in self.vtx.strip_groups.iter().enumerate() {
+            // state changes are applied on top of the bones of the previous strips
+            let mut hardware_bones: Vec<BoneId> = Vec::new();
+            for strip in &group.strips {
+                for change in &strip.bone_state_changes {
+                    match usize::try_from(change.hardware_id) {
+                        Ok(hardware) if hardware <= u8::MAX as usize => {
+                            if hardware_bones.len() <= hardware {
+                                hardware_bones.resize(hardware + 1, BoneId::default());
+                            }
+                            hardware_bones[hardware] = BoneId::from(change.new_bone_id);
+                        }
+                        _ => warn!(
+                            hardware_id = change.hardware_id,
+                            "skipping invalid hardware bone"
+                        ),
+                    }
+                }
+
+                let start = vertices.indices.len();
+                let mut bone_count = 1;
+                let strip_vertices = skip_invalid_triangles(strip.indices().map(|index| {
+                    let group_vertex =
+                        *group.indices.get(index).ok_or(ModelError::InvalidIndex {
+                            data: "strip group index",
+                            index,
+                        })?;
+                    let vertex = group.vertices.get(group_vertex as usize).ok_or(
+                        ModelError::InvalidIndex {
+                            data: "strip group vertex",
+                            index: group_vertex as usize,
+                        },
+                    )?;
+                    let vertex_index = vertex.original_mesh_vertex_id as usize + vertex_offset;
+                    self.check_vertex(vertex_index)?;
+                    Ok((group_vertex, vertex, vertex_index))
+                }));
+                for (group_vertex, vertex, vertex_index) in strip_vertices {
+                    let hardware = hardware_indices(vertex);
+                    bone_count = hardware
+                        .iter()
+                        .map(|index| *index as usize + 1)
+                        .fold(bone_count, usize::max);
+                    let local = *local_indices
+                        .entry((group_index, group_vertex))
+                        .or_insert_with(|| {
+                            vertices.vertex_indices.push(vertex_index);
+                            vertices.bone_indices.push(hardware);
+                            vertices.vertex_indices.len() as u32 - 1
+                        });
+                    vertices.indices.push(local);
+                }
+
+                let mut bones = hardware_bones.clone();
+                if bones.len() < bone_count {
+                    bones.resize(bone_count, BoneId::default());
+                }
+                vertices.draws.push(HardwareDraw {
+                    indices: start..vertices.indices.len(),
+                    bones,
+                });
+            }
+        }
+        vertices
+    }
+
+    fn write_vertex_buffer(&self, layout: &VertexLayout, vertices: BufferVertices) -> VertexBuffer {
+        let stride = layout.stride();
+        let mut data = Vec::with_capacity(vertices.vertex_indices.len() * stride);
+        // the vertex and tangent of every index are checked when building the indices
+        for (index, bone_indices) in vertices.vertex_indices.iter().zip(&vertices.bone_indices) {
+            let vertex = &self.vertices[*index];
+            let start = data.len();
+            for element in layout.elements() {
+                data.resize(start + element.offset, 0);
+                let value = match element.attribute {
+                    VertexAttribute::Position => {
+                        let [x, y, z] = vertex.position.into();
+                        [x, y, z, 1.0]
+                    }
+                    VertexAttribute::Normal => {
+                        let [x, y, z] = vertex.normal.into();
+                        [x, y, z, 0.0]
+                    }
+                    VertexAttribute::Tangent => self.tangents[*index].into(),
+                    VertexAttribute::TextureCoordinates => {
+                        let [u, v] = vertex.texture_coordinates;
+                        [u, v, 0.0, 0.0]
+                    }
+                    VertexAttribute::BoneIndices => *bone_indices,
+                    VertexAttribute::BoneWeights => bone_weights(vertex),
+                };
+                element.format.write(value, &mut data);
+            }
+            data.resize(start + stride, 0);
+        }
+
+        VertexBuffer {
+            data,
+            stride,
+            indices: vertices.indices,
+            vertex_indices: vertices.vertex_indices,
+            draws: vertices.draws,
+        }
+    }
+}
+
+/// The vertices and indices to write into a vertex buffer
+#[derive(Default)]
+struct BufferVertices {
+    vertex_indices: Vec<usize>,
+    bone_indices: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+    draws: Vec<HardwareDraw>,
+}
+
+fn bone_indices(vertex: &Vertex, bone_index: impl Fn(BoneId) -> usize) -> [f32; 4] {
+    let mut indices = [0.0; 4];
+    for (i, weight) in vertex.bone_weights.weights().enumerate() {
+        indices[i] = bone_index(weight.bone_id) as f32;
+    }
+    indices
+}
+
+/// The hardware bone for every weight of the vertex, in the order of the weights
+fn hardware_indices(vertex: &vtx::Vertex) -> [f32; 4] {
+    let mut indices = [0.0; 4];
+    let weight_indexes = vertex.bone_weight_indexes;
+    let bone_ids = vertex.bone_id;
+    for (weight, hardware) in weight_indexes
+        .into_iter()
+        .zip(bone_ids)
+        .take(vertex.bone_count as usize)
+    {
+        if let Some(index) = indices.get_mut(weight as usize) {
+            *index = hardware as f32;
+        }
+    }
+    indices
+}
+
+fn bone_weights(vertex: &Vertex) -> [f32; 4] {
+    let mut weights = [0.0; 4];
+    for (i, weight) in vertex.bone_weights.weights().enumerate() {
+        weights[i] = weight.weight;
+    }
+    let total: f32 = weights.iter().sum();
+    if total > 0.0 {
+        weights.iter_mut().for_each(|weight| *weight /= total);
+    } else {
+        weights[0] = 1.0;
+    }
+    weights
+}
+
+/// Encode a unit normal into two `snorm16` values using octahedral mapping
+pub fn octahedral_encode(normal: Vector) -> [i16; 2] {
+    let sum = normal.x.abs() + normal.y.abs() + normal.z.abs();
+    if sum <= f32::EPSILON {
+        return [0, 0];
+    }
+    let (x, y) = (normal.x / sum, normal.y / sum);
+    let (x, y) = if normal.z < 0.0 {
+        ((1.0 - y.abs()) * x.signum(), (1.0 - x.abs()) * y.signum())
+    } else {
+        (x, y)
+    };
+    let quantize = |value: f32| (value.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+    [quantize(x), quantize(y)]
+}
+
+/// Decode a normal encoded with [`octahedral_encode`]
+pub fn octahedral_decode(encoded: [i16; 2]) -> Vector {
+    let x = (encoded[0] as f32 / i16::MAX as f32).max(-1.0);
+    let y = (encoded[1] as f32 / i16::MAX as f32).max(-1.0);
+    let z = 1.0 - x.abs() - y.abs();
+    let t = (-z).max(0.0);
+    let x = x - t * x.signum();
+    let y = y - t * y.signum();
+    let length = (x * x + y * y + z * z).sqrt();
+    Vector {
+        x: x / length,
+        y: y / length,
+        z: z / length,
+    }
+}
+
+/// Remove duplicate vertices from the triangle list of a mesh
+///
+/// Returns the used indices into the model's vertex list and the triangle list indexing into those
+fn deduplicated_indices(mesh: &Mesh) -> (Vec<usize>, Vec<u32>) {
+    let mut local_indices = HashMap::new();
+    let mut vertex_indices = Vec::new();
+    let indices = mesh
+        .triangles_with_tangents()
+        .flatten()
+        .map(|index| {
+            *local_indices.entry(index).or_insert_with(|| {
+                vertex_indices.push(index);
+                vertex_indices.len() as u32 - 1
+            })
+        })
+        .collect();
+    (vertex_indices, indices)
+}
@@ -1,4 +1,8 @@
-use crate::mdl::{Bone, BoneId, Mdl};
+use crate::mdl::{
+    AnimationBlock, AnimationDescription, AnimationSequence, AutoLayer, Bone, BoneId, ContentFlags,
+    Mdl, SampleOptions, StudioAttachment, ValidationError,
+};
+use crate::Pose;
 use std::collections::VecDeque;
 use std::ops::Deref;
 
@@ -23,6 +27,16 @@ impl<'a, T, K> Handle<'a, T, K> {
     pub fn new(mdl: &'a Mdl, data: &'a T, key: K) -> Self {
         Self { mdl, data, key }
     }
+
+    /// The mdl containing the referenced data
+    pub(crate) fn mdl(&self) -> &'a Mdl {
+        self.mdl
+    }
+
+    /// The referenced data, with the lifetime of the mdl instead of the handle
+    pub fn data(&self) -> &'a T {
+        self.data
+    }
 }
 
 impl<T, K: Clone> Handle<'_, T, K> {
@@ -46,6 +60,10 @@ impl<T, K> Deref for Handle<'_, T, K> {
 }
 
 impl<'a> Handle<'a, Bone, BoneId> {
+    pub fn contents(&self) -> ContentFlags {
+        self.data.contents
+    }
+
     pub fn parent(&self) -> Option<Self> {
         Some(Self::new(
             self.mdl,
@@ -65,12 +83,50 @@ impl<'a> Handle<'a, Bone, BoneId> {
             .map(move |(i, bone)| Self::new(mdl, bone, i.into()))
     }
 
+    /// The bone and all its descendants, parents before their children
     pub fn tree(&self) -> impl Iterator<Item = Self> {
-        BoneTreeIter::new(self.clone())
+        BoneTreeIter::new(self.mdl, [self.clone()])
     }
 
     pub fn ancestors(&self) -> impl Iterator<Item = Self> {
-        BoneAncestorsIter { bone: self.clone() }
+        BoneAncestorsIter {
+            bone: self.clone(),
+            remaining: self.mdl.bones.len(),
+        }
+    }
+
+    /// The closest bone that is an ancestor of both bones
+    ///
+    /// A bone counts as its own ancestor, so if one bone is an ancestor of the other it is returned.
+    /// Returns `None` if the bones are part of different trees.
+    pub fn lowest_common_ancestor(&self, other: &Self) -> Option<Self> {
+        let other_lineage: Vec<BoneId> = other.lineage().map(|bone| bone.key).collect();
+        self.lineage()
+            .find(|bone| other_lineage.contains(&bone.key))
+    }
+
+    /// The bones from this bone to `other`, up to their lowest common ancestor and back down
+    ///
+    /// Both bones are included, returns `None` if the bones are part of different trees.
+    pub fn path_to(&self, other: &Self) -> Option<Vec<Self>> {
+        let ancestor = self.lowest_common_ancestor(other)?;
+        let mut path: Vec<Self> = self
+            .lineage()
+            .take_while(|bone| bone.key != ancestor.key)
+            .collect();
+        let mut down: Vec<Self> = other
+            .lineage()
+            .take_while(|bone| bone.key != ancestor.key)
+            .collect();
+        path.push(ancestor);
+        down.reverse();
+        path.extend(down);
+        Some(path)
+    }
+
+    /// The bone followed by its ancestors
+    fn lineage(&self) -> impl Iterator<Item = Self> {
+        std::iter::once(self.clone()).chain(self.ancestors())
     }
 
     pub fn is_affected_by(&self, bone_id: BoneId) -> bool {
@@ -78,15 +134,127 @@ impl<'a> Handle<'a, Bone, BoneId> {
     }
 }
 
+impl<'a> Handle<'a, AnimationSequence, usize> {
+    /// Iterate over the animations used by the sequence's blend grid
+    pub fn animations(&self) -> impl Iterator<Item = &'a AnimationDescription> + 'a {
+        let mdl = self.mdl;
+        self.data
+            .animation_indices
+            .iter()
+            .flat_map(move |index| mdl.local_animations.get(usize::try_from(*index).ok()?))
+    }
+
+    /// The animation blocks of the `.ani` file containing the animations of the sequence
+    ///
+    /// Blocks are ordered by index and only included once, animations stored in the mdl don't add any blocks.
+    pub fn animation_blocks(&self) -> impl Iterator<Item = &'a AnimationBlock> + 'a {
+        let mut blocks: Vec<usize> = self
+            .animations()
+            .filter_map(|animation| Some(animation.animation_block?.block))
+            .collect();
+        blocks.sort_unstable();
+        blocks.dedup();
+        let mdl = self.mdl;
+        blocks
+            .into_iter()
+            .filter_map(move |block| mdl.animation_blocks.get(block))
+    }
+
+    /// Duration of a single playthrough of the sequence in seconds, excluding fade times
+    pub fn duration(&self) -> f32 {
+        self.animations()
+            .next()
+            .map(AnimationDescription::duration)
+            .unwrap_or_default()
+    }
+
+    /// Time in seconds the first frame of the sequence is held before the playback advances
+    pub fn zero_frame_stall_time(&self) -> f32 {
+        self.animations()
+            .next()
+            .map(|animation| animation.zero_frame_stall_time)
+            .unwrap_or_default()
+    }
+
+    /// Duration of the sequence in seconds including the time spent fading in and out
+    pub fn duration_with_fades(&self) -> f32 {
+        self.fade_in_time + self.duration() + self.fade_out_time
+    }
+
+    /// The auto layers of the sequence with the sequence they play
+    ///
+    /// Layers referencing a missing sequence are skipped.
+    pub fn auto_layers(&self) -> impl Iterator<Item = (&'a AutoLayer, Self)> + 'a {
+        let mdl = self.mdl;
+        self.data.auto_layers.iter().flat_map(move |layer| {
+            let index = usize::try_from(layer.sequence).ok()?;
+            let sequence = mdl.animation_sequences.get(index)?;
+            Some((layer, Handle::new(mdl, sequence, index)))
+        })
+    }
+
+    /// Blend the first animation of the sequence onto a pose
+    ///
+    /// Every bone is blended by `weight` multiplied with the weight of the bone in the sequence's weight list.
+    pub fn blend_onto(&self, pose: &mut Pose, time: f32, weight: f32, options: SampleOptions) {
+        let Some(animation) = self.animations().next() else {
+            return;
+        };
+        for sample in animation.sample(&self.mdl.bones, time, options) {
+            let bone_weight = weight * self.bone_weight(sample.bone);
+            if bone_weight > 0.0 {
+                pose.blend(&sample, bone_weight);
+            }
+        }
+    }
+}
+
+impl<'a> Handle<'a, StudioAttachment, usize> {
+    /// The bone the attachment is attached to
+    ///
+    /// Errors if the bone doesn't exist, which can happen with hand edited models.
+    pub fn bone(&self) -> Result<Handle<'a, Bone, BoneId>, ValidationError> {
+        let bone = self.mdl.attachment_bone(self.data).ok_or_else(|| {
+            ValidationError::AttachmentBoneOutOfRange {
+                attachment: self.data.name.clone(),
+                bone: self.data.local_bone,
+                count: self.mdl.bones.len(),
+            }
+        })?;
+        Ok(Handle::new(
+            self.mdl,
+            &self.mdl.bones[usize::from(bone)],
+            bone,
+        ))
+    }
+}
+
+/// Every bone of the mdl, starting from all root bones with parents before their children
+///
+/// Bones that can't be reached from a root because of a cyclic hierarchy are skipped.
+pub(crate) fn bone_forest(mdl: &Mdl) -> impl Iterator<Item = Handle<'_, Bone, BoneId>> {
+    let roots = mdl
+        .bones
+        .iter()
+        .enumerate()
+        .map(|(i, bone)| Handle::new(mdl, bone, i.into()))
+        .filter(|bone| bone.parent().is_none());
+    BoneTreeIter::new(mdl, roots)
+}
+
 struct BoneTreeIter<'a> {
     queue: VecDeque<Handle<'a, Bone, BoneId>>,
+    visited: Vec<bool>,
 }
 
 impl<'a> BoneTreeIter<'a> {
-    pub fn new(root: Handle<'a, Bone, BoneId>) -> Self {
+    pub fn new(mdl: &Mdl, roots: impl IntoIterator<Item = Handle<'a, Bone, BoneId>>) -> Self {
         let mut queue = VecDeque::with_capacity(16);
-        queue.push_back(root);
-        BoneTreeIter { queue }
+        queue.extend(roots);
+        BoneTreeIter {
+            queue,
+            visited: vec![false; mdl.bones.len()],
+        }
     }
 }
 
@@ -94,7 +262,16 @@ impl<'a> Iterator for BoneTreeIter<'a> {
     type Item = Handle<'a, Bone, BoneId>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let next = self.queue.pop_front()?;
+        // visited bones are skipped to not get stuck on bones that are their own ancestor
+        let next = loop {
+            let next = self.queue.pop_front()?;
+            if let Some(visited) = self.visited.get_mut(usize::from(next.key())) {
+                if !*visited {
+                    *visited = true;
+                    break next;
+                }
+            }
+        };
         self.queue.extend(next.children());
 
         Some(next)
@@ -103,12 +280,15 @@ impl<'a> Iterator for BoneTreeIter<'a> {
 
 struct BoneAncestorsIter<'a> {
     bone: Handle<'a, Bone, BoneId>,
+    /// Limit on the number of ancestors, to stop on cyclic hierarchies
+    remaining: usize,
 }
 
 impl<'a> Iterator for BoneAncestorsIter<'a> {
     type Item = Handle<'a, Bone, BoneId>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        self.remaining = self.remaining.checked_sub(1)?;
         let next = self.bone.parent()?;
         self.bone = next.clone();
         Some(next)
@@ -0,0 +1,440 @@
+//! Stable hashing of the parsed model content
+
+use crate::mdl::{
+    AiMatBone, AxisInterpBone, Bone, BoneId, JiggleBone, ProceduralBone, QuaternionInterpBone,
+    RotationData,
+};
+use crate::vvd::{Tangent, Vertex};
+use crate::{Mdl, Model, Quaternion, RadianEuler, Transform3x4, Vector};
+
+/// FNV-1a hasher, unlike the hasher from the standard library its output is stable between
+/// platforms, processes and releases
+struct ContentHasher(u64);
+
+/// Values that are hashed field by field, with numbers in little endian byte order
+trait ContentHash {
+    fn hash(&self, hasher: &mut ContentHasher);
+}
+
+macro_rules! impl_number_hash {
+    ($($ty:ty),*) => {
+        $(impl ContentHash for $ty {
+            fn hash(&self, hasher: &mut ContentHasher) {
+                hasher.bytes(&self.to_le_bytes());
+            }
+        })*
+    };
+}
+
+impl_number_hash!(u8, i8, u16, i16, u32, i32, u64, f32);
+
+impl<T: ContentHash, const N: usize> ContentHash for [T; N] {
+    fn hash(&self, hasher: &mut ContentHasher) {
+        for value in self {
+            value.hash(hasher);
+        }
+    }
+}
+
+impl ContentHash for BoneId {
+    fn hash(&self, hasher: &mut ContentHasher) {
+        (usize::from(*self) as u8).hash(hasher);
+    }
+}
+
+impl ContentHash for Vector {
+    fn hash(&self, hasher: &mut ContentHasher) {
+        [self.x, self.y, self.z].hash(hasher);
+    }
+}
+
+impl ContentHash for RadianEuler {
+    fn hash(&self, hasher: &mut ContentHasher) {
+        [self.x, self.y, self.z].hash(hasher);
+    }
+}
+
+impl ContentHash for Quaternion {
+    fn hash(&self, hasher: &mut ContentHasher) {
+        [self.x, self.y, self.z, self.w].hash(hasher);
+    }
+}
+
+impl ContentHash for Transform3x4 {
+    fn hash(&self, hasher: &mut ContentHasher) {
+        let matrix = self.matrix();
+        AsRef::<[[f32; 4]; 4]>::as_ref(&matrix).hash(hasher);
+    }
+}
+
+impl ContentHash for AxisInterpBone {
+    fn hash(&self, hasher: &mut ContentHasher) {
+        [self.control, self.axis].hash(hasher);
+        self.position.hash(hasher);
+        self.quaternion.hash(hasher);
+    }
+}
+
+impl ContentHash for QuaternionInterpBone {
+    fn hash(&self, hasher: &mut ContentHasher) {
+        self.inverse_tolerance.hash(hasher);
+        self.trigger.hash(hasher);
+        self.position.hash(hasher);
+        self.quaternion.hash(hasher);
+    }
+}
+
+impl ContentHash for AiMatBone {
+    fn hash(&self, hasher: &mut ContentHasher) {
+        [self.parent, self.aim].hash(hasher);
+        [self.aim_vector, self.up_vector, self.base_position].hash(hasher);
+    }
+}
+
+impl ContentHash for JiggleBone {
+    fn hash(&self, hasher: &mut ContentHasher) {
+        self.flags.bits().hash(hasher);
+        [
+            self.length,
+            self.tip_mass,
+            self.yaw_stiffness,
+            self.yaw_damping,
+            self.pitch_stiffness,
+            self.pitch_damping,
+            self.along_stiffness,
+            self.along_damping,
+            self.angle_limit,
+            self.min_yaw,
+            self.max_yaw,
+            self.yaw_friction,
+            self.yaw_bound,
+            self.min_pitch,
+            self.max_pitch,
+            self.pitch_friction,
+            self.pitch_bounce,
+            self.base_mass,
+            self.base_stiffness,
+            self.base_damping,
+            self.base_min_left,
+            self.base_max_left,
+            self.base_left_friction,
+            self.base_min_up,
+            self.base_max_up,
+            self.base_up_friction,
+            self.base_min_forward,
+            self.base_max_forward,
+            self.base_forward_friction,
+            self.boing_impact_speed,
+            self.boing_impact_angle,
+            self.boing_damping_rate,
+            self.boing_frequency,
+            self.boing_amplitute,
+        ]
+        .hash(hasher);
+    }
+}
+
+impl ContentHash for Vertex {
+    fn hash(&self, hasher: &mut ContentHasher) {
+        let weights: Vec<_> = self.bone_weights.weights().collect();
+        hasher.len(weights.len());
+        for weight in weights {
+            weight.bone_id.hash(hasher);
+            weight.weight.hash(hasher);
+        }
+        self.position.hash(hasher);
+        self.normal.hash(hasher);
+        self.texture_coordinates.hash(hasher);
+    }
+}
+
+impl ContentHash for Tangent {
+    fn hash(&self, hasher: &mut ContentHasher) {
+        [self.x, self.y, self.z, self.w].hash(hasher);
+    }
+}
+
+impl ContentHasher {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    fn new() -> Self {
+        ContentHasher(Self::OFFSET)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn len(&mut self, len: usize) {
+        (len as u64).hash(self);
+    }
+
+    fn value<T: ContentHash>(&mut self, value: &T) {
+        value.hash(self);
+    }
+
+    fn slice<T: ContentHash>(&mut self, values: &[T]) {
+        self.len(values.len());
+        for value in values {
+            value.hash(self);
+        }
+    }
+
+    fn str(&mut self, value: &str) {
+        self.len(value.len());
+        self.bytes(value.as_bytes());
+    }
+
+    fn bone(&mut self, bone: &Bone) {
+        self.str(&bone.name);
+        self.value(&bone.parent);
+        self.value(&bone.bone_controller);
+        self.value(&bone.pos);
+        self.value(&bone.quaternion);
+        self.value(&bone.rot);
+        self.value(&bone.pos_scale);
+        self.value(&bone.rot_scale);
+        self.value(&bone.pose_to_bone);
+        self.value(&bone.q_alignment);
+        self.value(&bone.flags.bits());
+        self.value(&bone.physics_bone);
+        self.str(&bone.surface_prop);
+        self.value(&bone.contents.bits());
+        match &bone.procedural_rules {
+            None => self.value(&0u8),
+            Some(ProceduralBone::AxisInterp(rules)) => {
+                self.value(&1u8);
+                self.value(rules);
+            }
+            Some(ProceduralBone::QuaternionInterp(rules)) => {
+                self.value(&2u8);
+                self.value(rules);
+            }
+            Some(ProceduralBone::AiMatBone(rules)) => {
+                self.value(&3u8);
+                self.value(rules);
+            }
+            Some(ProceduralBone::AiMatAttach(rules)) => {
+                self.value(&4u8);
+                self.value(rules);
+            }
+            Some(ProceduralBone::Jiggle(rules)) => {
+                self.value(&5u8);
+                self.value(rules);
+            }
+            Some(ProceduralBone::Unknown { ty, raw }) => {
+                self.value(&6u8);
+                self.value(ty);
+                self.slice(raw);
+            }
+        }
+    }
+
+    fn mdl(&mut self, mdl: &Mdl) {
+        let header = &mdl.header;
+        self.str(mdl.name.as_str());
+        self.str(mdl.full_name.as_deref().unwrap_or_default());
+        self.value(&header.version);
+        self.value(&header.eye_position);
+        self.value(&header.illumination_position);
+        self.value(&header.bounding_box);
+        self.value(&header.view_bounding_box);
+        self.value(&header.flags.bits());
+        self.str(&mdl.surface_prop);
+        self.str(mdl.key_values.as_deref().unwrap_or_default());
+
+        self.len(mdl.bones.len());
+        for bone in &mdl.bones {
+            self.bone(bone);
+        }
+        self.len(mdl.bone_controllers.len());
+        for controller in &mdl.bone_controllers {
+            self.value(&controller.bone);
+            self.value(&(controller.ty.clone() as i32));
+            self.value(&[controller.start, controller.end]);
+            self.value(&[controller.rest, controller.input_field]);
+        }
+
+        self.len(mdl.textures.len());
+        for texture in &mdl.textures {
+            self.str(&texture.name);
+        }
+        self.len(mdl.texture_paths.len());
+        for path in &mdl.texture_paths {
+            self.str(path);
+        }
+        self.slice(&mdl.skin_table);
+
+        self.len(mdl.body_parts.len());
+        for part in &mdl.body_parts {
+            self.str(&part.name);
+            self.len(part.models.len());
+            for model in &part.models {
+                self.str(model.name.as_str());
+                self.value(&model.bounding_radius);
+                self.len(model.meshes.len());
+                for mesh in &model.meshes {
+                    self.value(&[mesh.material, mesh.vertex_offset, mesh.material_type]);
+                    self.value(&[mesh.material_param, mesh.mesh_id]);
+                    self.value(&mesh.center);
+                    self.len(mesh.flexes.len());
+                    for flex in &mesh.flexes {
+                        self.value(&[flex.descriptor, flex.pair]);
+                        self.value(&flex.targets);
+                        self.len(flex.vertices.len());
+                        for vertex in &flex.vertices {
+                            self.value(&vertex.index);
+                            self.value(&[vertex.speed, vertex.side]);
+                            self.value(&vertex.delta);
+                            self.value(&vertex.normal_delta);
+                            self.value(&vertex.wrinkle_delta);
+                        }
+                    }
+                }
+                self.len(model.eyeballs.len());
+                for eyeball in &model.eyeballs {
+                    self.str(&eyeball.name);
+                    self.value(&[eyeball.bone, eyeball.texture]);
+                    self.value(&[eyeball.origin, eyeball.up, eyeball.forward]);
+                    self.value(&[eyeball.z_offset, eyeball.radius, eyeball.iris_scale]);
+                    self.value(&[
+                        eyeball.upper_flex_descriptors,
+                        eyeball.lower_flex_descriptors,
+                    ]);
+                    self.value(&[eyeball.upper_targets, eyeball.lower_targets]);
+                    self.value(&[
+                        eyeball.upper_lid_flex_descriptor,
+                        eyeball.lower_lid_flex_descriptor,
+                    ]);
+                }
+            }
+        }
+
+        self.len(mdl.local_animations.len());
+        for animation in &mdl.local_animations {
+            self.str(&animation.name);
+            self.value(&animation.fps);
+            self.value(&animation.flags.bits());
+            self.len(animation.frame_count);
+            self.value(&animation.zero_frame_stall_time);
+            self.len(animation.animations.len());
+            for track in &animation.animations {
+                self.value(&track.bone);
+                self.value(&track.flags.bits());
+                let frames = match track.rotation_data() {
+                    RotationData::Animated(values) => values.len(),
+                    RotationData::Quaternions(values) => values.len(),
+                    _ => 1,
+                }
+                .max(track.position_data().frame_count().unwrap_or(1));
+                self.len(frames);
+                for frame in 0..frames {
+                    self.value(&track.rotation(frame));
+                    self.value(&track.position(frame));
+                }
+            }
+        }
+
+        self.len(mdl.animation_sequences.len());
+        for sequence in &mdl.animation_sequences {
+            self.str(&sequence.name);
+            self.str(&sequence.label);
+            self.value(&sequence.flags.bits());
+            self.value(&sequence.activity_weight);
+            self.value(&[sequence.fade_in_time, sequence.fade_out_time]);
+            self.value(&[sequence.entry_node, sequence.exit_node, sequence.node_flags]);
+            self.value(&[sequence.entry_phase, sequence.exit_phase]);
+            self.len(sequence.blend_size[0]);
+            self.len(sequence.blend_size[1]);
+            self.slice(&sequence.animation_indices);
+            self.slice(&sequence.pose_keys[0]);
+            self.slice(&sequence.pose_keys[1]);
+            self.slice(&sequence.bone_weights);
+            self.len(sequence.events.len());
+            for event in &sequence.events {
+                self.value(&event.cycle);
+                self.value(&[event.event, event.ty]);
+                self.str(event.options.as_str());
+                self.str(&event.name);
+            }
+            self.len(sequence.auto_layers.len());
+            for layer in &sequence.auto_layers {
+                self.value(&[layer.sequence, layer.pose]);
+                self.value(&layer.flags.bits());
+                self.value(&[layer.start, layer.peak, layer.tail, layer.end]);
+            }
+        }
+
+        self.len(mdl.pose_parameters.len());
+        for parameter in &mdl.pose_parameters {
+            self.str(&parameter.name);
+            self.value(&parameter.flags);
+            self.value(&[parameter.start, parameter.end, parameter.loop_range]);
+        }
+        self.len(mdl.attachments.len());
+        for attachment in &mdl.attachments {
+            self.str(&attachment.name);
+            self.value(&attachment.flags.bits());
+            self.value(&attachment.local_bone);
+            self.value(&attachment.local);
+        }
+        self.len(mdl.hit_boxes.len());
+        for set in &mdl.hit_boxes {
+            self.str(&set.name);
+            self.len(set.boxes.len());
+            for hitbox in &set.boxes {
+                self.str(&hitbox.name);
+                self.value(&[hitbox.bone, hitbox.group]);
+                self.value(&[hitbox.min, hitbox.max]);
+            }
+        }
+        self.len(mdl.include_models.len());
+        for include in &mdl.include_models {
+            self.str(&include.label);
+            self.str(&include.name);
+        }
+        self.len(mdl.flex_descriptors.len());
+        for descriptor in &mdl.flex_descriptors {
+            self.str(&descriptor.name);
+        }
+    }
+}
+
+impl Mdl {
+    /// Hash of the parsed content of the model
+    ///
+    /// The hash is stable between platforms and runs, it only changes when the parsed content changes.
+    /// Offsets and padding in the file don't affect the hash.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::new();
+        hasher.mdl(self);
+        hasher.0
+    }
+}
+
+impl Model {
+    /// Hash of the parsed content of the model, including the vertex and mesh data
+    ///
+    /// The hash is stable between platforms and runs, it only changes when the parsed content changes.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = ContentHasher::new();
+        hasher.mdl(&self.mdl);
+        hasher.slice(&self.vvd.vertices);
+        hasher.slice(&self.vvd.tangents);
+        let lods = self.lods().count();
+        hasher.len(lods);
+        for lod in 0..lods {
+            for mesh in self.meshes_for_lod(lod) {
+                for strip in mesh.vertex_strip_indices() {
+                    let strip = strip.map(|index| index as u32).collect::<Vec<_>>();
+                    hasher.slice(&strip);
+                }
+            }
+        }
+        hasher.0
+    }
+}
@@ -0,0 +1,43 @@
+//! Selecting hitbox sets and placing their boxes in a pose
+
+use crate::mdl::{BoundingBox, HitBoxSet};
+use crate::{Model, Pose};
+use cgmath::Matrix4;
+
+/// A hitbox together with the transform of its bone in a pose
+#[derive(Debug, Clone)]
+pub struct PosedHitBox<'a> {
+    pub hitbox: &'a BoundingBox,
+    /// Transform from the bone space the box is defined in to model space
+    pub transform: Matrix4<f32>,
+}
+
+impl Model {
+    /// All hitbox sets of the model, the first set is used by default
+    pub fn hitbox_sets(&self) -> impl Iterator<Item = &HitBoxSet> {
+        self.mdl.hit_boxes.iter()
+    }
+
+    /// Find a hitbox set by name, ignoring case
+    pub fn hitbox_set(&self, name: &str) -> Option<&HitBoxSet> {
+        self.hitbox_sets()
+            .find(|set| set.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The hitboxes of a set with the bones in the provided pose
+    ///
+    /// Hitboxes attached to a bone that doesn't exist are skipped.
+    pub fn posed_hitboxes<'a>(&self, set: &'a HitBoxSet, pose: &Pose) -> Vec<PosedHitBox<'a>> {
+        let transforms = pose.world_transforms();
+        set.boxes
+            .iter()
+            .filter_map(|hitbox| {
+                let bone = usize::try_from(hitbox.bone).ok()?;
+                Some(PosedHitBox {
+                    hitbox,
+                    transform: *transforms.get(bone)?,
+                })
+            })
+            .collect()
+    }
+}
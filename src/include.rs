@@ -0,0 +1,90 @@
+use crate::mdl::{AnimationDescription, AnimationSequence, IncludeModel};
+use crate::{Handle, Mdl, Model, ModelError};
+use std::fs;
+use std::path::Path;
+
+/// The model an animation or sequence is stored in
+#[derive(Debug, Clone, Copy)]
+pub enum AnimationSource<'a> {
+    /// Stored in the model itself
+    Local,
+    /// Stored in a model referenced with `$includemodel`
+    Included(&'a IncludeModel),
+}
+
+impl Model {
+    /// Load the models referenced with `$includemodel`
+    ///
+    /// `load` gets called with the path of every included model, relative to the game directory.
+    /// Models included by included models are loaded as well, every model is only loaded once.
+    pub fn resolve_include_models<F>(&mut self, mut load: F) -> Result<(), ModelError>
+    where
+        F: FnMut(&str) -> Result<Mdl, ModelError>,
+    {
+        let mut pending = self.mdl.include_models.clone();
+        while let Some(include) = pending.pop() {
+            let loaded = self
+                .included
+                .iter()
+                .any(|(existing, _)| existing.name.eq_ignore_ascii_case(&include.name));
+            if loaded {
+                continue;
+            }
+            let mdl = load(&include.name)?;
+            pending.extend(mdl.include_models.iter().cloned());
+            self.included.push((include, mdl));
+        }
+        Ok(())
+    }
+
+    /// Load the models referenced with `$includemodel` from a game directory
+    pub fn resolve_include_models_from_path<P: AsRef<Path>>(
+        &mut self,
+        game_dir: P,
+    ) -> Result<(), ModelError> {
+        let game_dir = game_dir.as_ref();
+        self.resolve_include_models(|name| Mdl::read(&fs::read(game_dir.join(name))?))
+    }
+
+    /// The models referenced with `$includemodel` that have been loaded
+    pub fn included_models(&self) -> impl Iterator<Item = (&IncludeModel, &Mdl)> {
+        self.included.iter().map(|(include, mdl)| (include, mdl))
+    }
+
+    /// All animations of the model, followed by the animations of the included models
+    ///
+    /// Included models need to be loaded first with [`resolve_include_models`](Self::resolve_include_models).
+    pub fn animations_with_includes(
+        &self,
+    ) -> impl Iterator<Item = (AnimationSource<'_>, &AnimationDescription)> {
+        self.animations()
+            .map(|animation| (AnimationSource::Local, animation))
+            .chain(self.included.iter().flat_map(|(include, mdl)| {
+                mdl.local_animations
+                    .iter()
+                    .map(move |animation| (AnimationSource::Included(include), animation))
+            }))
+    }
+
+    /// All sequences of the model, followed by the sequences of the included models
+    ///
+    /// The handles of included sequences reference the included model, so their animations resolve correctly.
+    /// Included models need to be loaded first with [`resolve_include_models`](Self::resolve_include_models).
+    pub fn sequences_with_includes(
+        &self,
+    ) -> impl Iterator<Item = (AnimationSource<'_>, Handle<'_, AnimationSequence, usize>)> {
+        self.sequences()
+            .map(|sequence| (AnimationSource::Local, sequence))
+            .chain(self.included.iter().flat_map(|(include, mdl)| {
+                mdl.animation_sequences
+                    .iter()
+                    .enumerate()
+                    .map(move |(i, sequence)| {
+                        (
+                            AnimationSource::Included(include),
+                            Handle::new(mdl, sequence, i),
+                        )
+                    })
+            }))
+    }
+}
@@ -1,65 +1,231 @@
+#[cfg(feature = "bevy")]
+pub mod bevy;
+pub mod biped;
+pub mod collision;
 mod compressed_vector;
+pub mod debug_geometry;
 mod error;
+pub mod export;
+mod eyes;
+#[cfg(feature = "glam")]
+mod glam;
+pub mod goldsrc;
+pub mod gpu;
 mod handle;
+mod hash;
+mod hitbox;
+mod include;
 pub mod mdl;
+mod memory;
+#[cfg(feature = "miette")]
+mod miette;
+#[cfg(feature = "nalgebra")]
+mod nalgebra;
+pub mod paths;
+pub mod phy;
+mod physics;
+mod player;
+mod pose;
+mod references;
+pub mod retarget;
+pub mod scene;
 mod shared;
+pub mod skeleton;
+mod strips;
+mod tangents;
+mod transform;
+mod volume;
 pub mod vtx;
 pub mod vvd;
 
 pub use crate::mdl::Mdl;
 use crate::mdl::{
-    AnimationDescription, Bone, BoneId, ModelFlags, PoseParameterDescription, TextureInfo,
+    AnimationDescription, AnimationSequence, Bone, BoneFlags, BoneId, ContentFlags, FlexDescriptor,
+    IncludeModel, ModelFlags, ParseOptions, ParseProgress, PoseParameterDescription,
+    SectionProgress, StudioAttachment, TextureInfo,
 };
+#[cfg(feature = "miette")]
+pub use crate::miette::SourcedModelError;
+pub use crate::phy::Phy;
 pub use crate::vtx::Vtx;
-use crate::vvd::Vertex;
 pub use crate::vvd::Vvd;
+use crate::vvd::{Tangent, Vertex};
 use bytemuck::{pod_read_unaligned, Contiguous, Pod};
 use cgmath::{Matrix4, SquareMatrix, Transform, Vector3};
 pub use error::*;
+pub use eyes::EyeRenderInfo;
 pub use handle::Handle;
-use itertools::Either;
+pub use hitbox::PosedHitBox;
+pub use include::AnimationSource;
+use itertools::{Either, Itertools};
+pub use memory::MemoryUsage;
+pub use physics::PhysicsBone;
+pub use player::{PlaybackEvent, SequencePlayer};
+pub use pose::{Pose, PoseContext};
+pub use references::{FileKind, ReferencedFile};
 pub use shared::*;
 use std::any::type_name;
+use std::collections::BTreeMap;
 use std::fs;
 use std::iter::once;
 use std::mem::size_of;
 use std::path::Path;
+pub use strips::{MeshStrips, PrimitiveType, StripRange};
+pub use tangents::TangentReport;
+pub use transform::Axis;
 
+#[derive(Clone)]
 pub struct Model {
     #[allow(dead_code)]
     mdl: Mdl,
     vtx: Vtx,
     vvd: Vvd,
+    included: Vec<(IncludeModel, Mdl)>,
+    phy: Option<Phy>,
+    meshes: Vec<MeshIndex>,
+}
+
+/// Position of a mesh that exists in both the mdl and vtx data
+#[derive(Debug, Clone, Copy)]
+struct MeshIndex {
+    body_part: usize,
+    model: usize,
+    mesh: usize,
 }
 
 impl Model {
+    /// Combine the parsed files into a model
+    ///
+    /// Meshes that only exist in either the mdl or vtx are skipped, use [`try_from_parts`](Self::try_from_parts)
+    /// to detect files that don't belong together.
     pub fn from_parts(mdl: Mdl, vtx: Vtx, vvd: Vvd) -> Self {
-        Model { mdl, vtx, vvd }
+        Model {
+            meshes: pair_meshes(&mdl, &vtx),
+            mdl,
+            vtx,
+            vvd,
+            included: Vec::new(),
+            phy: None,
+        }
+    }
+
+    /// Combine the parsed files into a model, checking that the body parts, models and meshes of the mdl and vtx match
+    pub fn try_from_parts(mdl: Mdl, vtx: Vtx, vvd: Vvd) -> Result<Self, ModelError> {
+        validate_pairing(&mdl, &vtx)?;
+        Ok(Model::from_parts(mdl, vtx, vvd))
     }
 
     /// Load the model from path
     ///
-    /// Requires a path to the `.mdl` file and the `.dx90.vtx` and `.vvd` files for the model to be in the same directory.
+    /// Requires a path to the `.mdl` file and the `.dx90.vtx` and `.vvd` files for the model to be in the same directory,
+    /// the `.phy` file is loaded when it exists.
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, ModelError> {
+        Self::from_path_with_options(path, ParseOptions::default())
+    }
+
+    /// Load the model from path, skipping the parts of the `.mdl` excluded by the options
+    pub fn from_path_with_options<P: AsRef<Path>>(
+        path: P,
+        options: ParseOptions,
+    ) -> Result<Self, ModelError> {
+        Self::from_path_with_progress(path, options, |_| {})
+    }
+
+    /// Load the model from path, calling `progress` after every parsed section and file
+    ///
+    /// The `.vtx`, `.vvd` and `.phy` files are reported as a single section each.
+    pub fn from_path_with_progress<P: AsRef<Path>>(
+        path: P,
+        options: ParseOptions,
+        mut progress: impl FnMut(ParseProgress),
+    ) -> Result<Self, ModelError> {
         let path = path.as_ref();
-        let data = fs::read(path)?;
-        let mdl = Mdl::read(&data)?;
-        let data = fs::read(path.with_extension("dx90.vtx"))?;
+        let phy_path = path.with_extension("phy");
+        let files = [
+            path.to_path_buf(),
+            path.with_extension("dx90.vtx"),
+            path.with_extension("vvd"),
+            phy_path.clone(),
+        ];
+        let total_bytes = files
+            .iter()
+            .filter_map(|file| fs::metadata(file).ok())
+            .map(|metadata| metadata.len() as usize)
+            .sum();
+
+        let data = fs::read(&files[0])?;
+        let mdl = Mdl::read_sections_with_progress(
+            &data,
+            options,
+            Some(SectionProgress::new(&mut progress, 0, total_bytes)),
+        )?;
+        let mut bytes_processed = data.len();
+        let mut file_done = |section: &'static str, size: usize| {
+            bytes_processed += size;
+            progress(ParseProgress {
+                section,
+                bytes_processed,
+                total_bytes,
+            });
+        };
+        let data = fs::read(&files[1])?;
         let vtx = Vtx::read(&data)?;
-        let data = fs::read(path.with_extension("vvd"))?;
+        file_done("vtx", data.len());
+        let data = fs::read(&files[2])?;
         let vvd = Vvd::read(&data)?;
+        file_done("vvd", data.len());
 
-        Ok(Model::from_parts(mdl, vtx, vvd))
+        let mut model = Model::try_from_parts(mdl, vtx, vvd)?;
+        if phy_path.exists() {
+            let data = fs::read(phy_path)?;
+            // the physics data is optional, a broken phy doesn't prevent using the model
+            match Phy::read(&data) {
+                Ok(phy) => model.set_phy(phy),
+                Err(error) => tracing::warn!(%error, "skipping invalid physics data"),
+            }
+            file_done("phy", data.len());
+        }
+        Ok(model)
     }
 
     pub fn vertices(&self) -> &[Vertex] {
         &self.vvd.vertices
     }
 
-    pub fn tangents(&self) -> &[[f32; 4]] {
+    pub fn tangents(&self) -> &[Tangent] {
         &self.vvd.tangents
     }
 
+    /// The index of a vertex in the vertex data as stored in the `.vvd`, before the fixups were applied
+    ///
+    /// See [`Vvd::source_index`].
+    pub fn source_vertex_index(&self, index: usize) -> Option<usize> {
+        self.vvd.source_index(index)
+    }
+
+    /// All indices into the [`vertices`](Self::vertices) for a vertex as stored in the `.vvd`
+    ///
+    /// See [`Vvd::fixed_indices`].
+    pub fn fixed_vertex_indices(&self, source: usize) -> impl Iterator<Item = usize> + '_ {
+        self.vvd.fixed_indices(source)
+    }
+
+    /// Indices into the [`vertices`](Self::vertices) of all vertices with a weight to `bone` above `threshold`
+    ///
+    /// Pass a threshold of `0.0` for every vertex influenced by the bone.
+    pub fn vertices_weighted_to(
+        &self,
+        bone: BoneId,
+        threshold: f32,
+    ) -> impl Iterator<Item = usize> + '_ {
+        self.vvd
+            .vertices
+            .iter()
+            .enumerate()
+            .filter(move |(_, vertex)| vertex.bone_weights.get_weight(bone) > threshold)
+            .map(|(index, _)| index)
+    }
+
     pub fn texture_directories(&self) -> &[String] {
         &self.mdl.texture_paths
     }
@@ -68,7 +234,21 @@ impl Model {
         &self.mdl.textures
     }
 
-    pub fn skin_tables(&self) -> impl Iterator<Item = SkinTable> {
+    /// Find a texture by name, ignoring case and path separator differences
+    ///
+    /// The name can either be the bare texture name or include one of the texture directories.
+    pub fn texture_by_name(&self, name: &str) -> Option<(usize, &TextureInfo)> {
+        let name = paths::canonical_material_name(name);
+        self.mdl.textures.iter().enumerate().find(|(_, texture)| {
+            texture.canonical_name() == name
+                || texture
+                    .search_paths
+                    .iter()
+                    .any(|dir| paths::join(dir, &texture.name) == name)
+        })
+    }
+
+    pub fn skin_tables(&self) -> impl Iterator<Item = SkinTable<'_>> {
         if self.mdl.header.skin_reference_count > 0 {
             Either::Left(
                 self.mdl
@@ -87,48 +267,271 @@ impl Model {
         }
     }
 
+    /// The textures used by the meshes of the model with a skin, with their index, ordered by index
+    ///
+    /// Textures that are only referenced by the skin table but not by any mesh are left out,
+    /// an out of range skin uses no textures.
+    pub fn materials_for_skin(&self, skin: usize) -> Vec<(usize, &TextureInfo)> {
+        let Some(table) = self.skin_tables().nth(skin) else {
+            return Vec::new();
+        };
+        let used: BTreeMap<usize, &TextureInfo> = self
+            .meshes()
+            .filter_map(|mesh| {
+                let index = table.texture_index(mesh.material_index())?;
+                Some((index, table.texture_info(mesh.material_index())?))
+            })
+            .collect();
+        used.into_iter().collect()
+    }
+
+    /// The triangles of a level of detail grouped by the texture they use with a skin, ordered by texture index
+    ///
+    /// The indices of all meshes using the same texture are merged into a single triangle list indexing into
+    /// [`vertices`](Self::vertices). Meshes with a material that isn't in the skin table are grouped without a texture,
+    /// an out of range skin returns no groups.
+    pub fn triangles_by_material(&self, skin: usize, lod: usize) -> Vec<MaterialTriangles<'_>> {
+        let Some(table) = self.skin_tables().nth(skin) else {
+            return Vec::new();
+        };
+        let mut groups: BTreeMap<Option<usize>, MaterialTriangles> = BTreeMap::new();
+        for mesh in self.meshes_for_lod(lod) {
+            let texture_index = table.texture_index(mesh.material_index());
+            let group = groups
+                .entry(texture_index)
+                .or_insert_with(|| MaterialTriangles {
+                    texture_index,
+                    texture: table.texture_info(mesh.material_index()),
+                    indices: Vec::new(),
+                });
+            group.indices.extend(mesh.triangles().flatten());
+        }
+        groups.into_values().collect()
+    }
+
     pub fn animations(&self) -> impl Iterator<Item = &AnimationDescription> {
         self.mdl.local_animations.iter()
     }
 
-    pub fn meshes(&self) -> impl Iterator<Item = Mesh> {
-        let mdl_meshes = self
-            .mdl
-            .body_parts
+    pub fn sequences(&self) -> impl Iterator<Item = Handle<'_, AnimationSequence, usize>> {
+        self.mdl
+            .animation_sequences
             .iter()
-            .flat_map(|part| part.models.iter())
-            .flat_map(|model| {
-                model
-                    .meshes
-                    .iter()
-                    .map(|mesh| (mesh, model.name.as_str(), model.vertex_offset as usize))
-            });
+            .enumerate()
+            .map(|(i, sequence)| Handle::new(&self.mdl, sequence, i))
+    }
 
-        let vtx_meshes = self
-            .vtx
-            .body_parts
-            .iter()
-            .flat_map(|part| part.models.iter())
-            .flat_map(|model| model.lods.first())
-            .flat_map(|lod| lod.meshes.iter());
-
-        mdl_meshes
-            .zip(vtx_meshes)
-            .map(|((mdl, model_name, model_vertex_offset), vtx)| Mesh {
-                model_vertex_offset,
-                model_name,
+    /// All sequences belonging to an activity, matched by name ignoring case
+    pub fn sequences_for_activity<'a>(
+        &'a self,
+        activity: &'a str,
+    ) -> impl Iterator<Item = Handle<'a, AnimationSequence, usize>> {
+        self.sequences()
+            .filter(move |sequence| sequence.name.eq_ignore_ascii_case(activity))
+    }
+
+    /// Pick a random sequence for an activity, weighted by the activity weight of the sequences
+    ///
+    /// `rng` is called with an upper bound and should return a random number in `0..bound`.
+    pub fn select_sequence(
+        &self,
+        activity: &str,
+        rng: impl FnMut(u32) -> u32,
+    ) -> Option<Handle<'_, AnimationSequence, usize>> {
+        self.select_sequence_with_current(activity, None, rng)
+    }
+
+    /// Pick a random sequence for an activity, taking the currently playing sequence into account
+    ///
+    /// This follows the same rules as the engine:
+    ///  - if the current sequence belongs to the activity and has a negative weight it is kept
+    ///  - otherwise sequences are picked with a probability proportional to the absolute value of their weight
+    ///  - sequences with a weight of 0 are only picked if all sequences for the activity have a weight of 0
+    pub fn select_sequence_with_current(
+        &self,
+        activity: &str,
+        current: Option<usize>,
+        mut rng: impl FnMut(u32) -> u32,
+    ) -> Option<Handle<'_, AnimationSequence, usize>> {
+        let mut total = 0;
+        let mut selected = None;
+        for sequence in self
+            .sequences()
+            .filter(|sequence| sequence.name.eq_ignore_ascii_case(activity))
+        {
+            let weight = sequence.activity_weight;
+            if current == Some(sequence.key()) && weight < 0 {
+                return Some(sequence);
+            }
+            total += weight.unsigned_abs();
+            if total == 0 || rng(total) < weight.unsigned_abs() {
+                selected = Some(sequence);
+            }
+        }
+        selected
+    }
+
+    /// Information about all levels of detail in the model
+    pub fn lods(&self) -> impl Iterator<Item = LodInfo> + '_ {
+        let lod_count = self.vtx.header.lod_count.max(0) as usize;
+        (0..lod_count).map(move |index| {
+            let lods = self
+                .vtx
+                .body_parts
+                .iter()
+                .flat_map(|part| part.models.iter())
+                .filter_map(move |model| model.lods.get(index));
+            let switch_point = lods
+                .clone()
+                .next()
+                .map(|lod| lod.switch_point)
+                .unwrap_or_default();
+            let triangle_count = lods
+                .flat_map(|lod| lod.meshes.iter())
+                .flat_map(|mesh| mesh.strip_groups.iter())
+                .flat_map(|group| group.strips.iter())
+                .map(|strip| strip.triangle_count())
+                .sum();
+            LodInfo {
+                index,
+                vertex_count: self.vvd.header.lod_vertex_count(index).unwrap_or_default(),
+                triangle_count,
+                switch_point,
+            }
+        })
+    }
+
+    pub fn meshes(&self) -> impl Iterator<Item = Mesh<'_>> {
+        self.meshes_for_lod(0)
+    }
+
+    /// The meshes of a level of detail
+    ///
+    /// The material of the meshes takes the material replacements for the level of detail into account.
+    pub fn meshes_for_lod(&self, lod: usize) -> impl Iterator<Item = Mesh<'_>> {
+        self.meshes.iter().filter_map(move |index| {
+            let model = &self.mdl.body_parts[index.body_part].models[index.model];
+            let mdl = &model.meshes[index.mesh];
+            let vtx = self.vtx.body_parts[index.body_part].models[index.model]
+                .lods
+                .get(lod)?
+                .meshes
+                .get(index.mesh)?;
+            Some(Mesh {
+                body_part_index: index.body_part,
+                model_index: index.model,
+                mesh_index: index.mesh,
+                model_vertex_offset: model.vertex_offset as usize,
+                model_name: model.name.as_str(),
+                model_bounding_radius: model.bounding_radius,
+                material: self.lod_material(lod, mdl.material),
                 vertices: self.vertices(),
                 tangents: self.tangents(),
                 mdl,
                 vtx,
             })
+        })
+    }
+
+    /// Number of meshes in the model, summed over all body parts and models
+    pub fn mesh_count(&self) -> usize {
+        self.meshes.len()
+    }
+
+    /// The skin reference used for the skin reference `material` at a level of detail
+    ///
+    /// Material replacements refer to textures, the replacement texture is mapped back to its reference in the
+    /// default skin. Replacements that aren't a texture of the model or aren't in the default skin are ignored
+    /// with a warning.
+    fn lod_material(&self, lod: usize, material: i32) -> i32 {
+        let Some(skin) = self.skin_tables().next() else {
+            return material;
+        };
+        let Some(name) = skin
+            .texture_index(material)
+            .and_then(|texture| self.vtx.material_replacement(lod, texture as i32))
+        else {
+            return material;
+        };
+        let Some((texture, _)) = self.texture_by_name(name) else {
+            tracing::warn!(
+                lod,
+                name,
+                "material replacement isn't a texture of the model"
+            );
+            return material;
+        };
+        match skin
+            .table
+            .iter()
+            .position(|reference| *reference as usize == texture)
+        {
+            Some(reference) => reference as i32,
+            None => {
+                tracing::warn!(
+                    lod,
+                    name,
+                    "material replacement isn't used by the default skin"
+                );
+                material
+            }
+        }
     }
 
     /// Calculate bounding coordinates of the model
+    #[deprecated(note = "use `hull_box` for collision or `view_box` for culling")]
     pub fn bounding_box(&self) -> (Vector, Vector) {
-        (
-            self.mdl.header.bounding_box[0],
-            self.mdl.header.bounding_box[1],
+        self.hull_box()
+    }
+
+    /// The bounds of the model used for collision and movement
+    ///
+    /// For physics props this is usually the bounding box of the collision model, use [`view_box`](Self::view_box)
+    /// when deciding whether the model is visible.
+    pub fn hull_box(&self) -> (Vector, Vector) {
+        let [min, max] = self.mdl.header.bounding_box;
+        (min, max)
+    }
+
+    /// The bounds of the model used for visibility culling
+    ///
+    /// These include the full extent of the rendered geometry, models without view bounds fall back to
+    /// the [`hull_box`](Self::hull_box) like the engine does.
+    pub fn view_box(&self) -> (Vector, Vector) {
+        let [min, max] = self.mdl.header.view_bounding_box;
+        if min == Vector::default() && max == Vector::default() {
+            self.hull_box()
+        } else {
+            (min, max)
+        }
+    }
+
+    /// Calculate the bounding box from the vertex data of all meshes in the model
+    ///
+    /// Unlike [`hull_box`](Self::hull_box) and [`view_box`](Self::view_box) this doesn't rely on the bounds stored in the model,
+    /// which are often padded or outdated.
+    pub fn computed_bounding_box(&self) -> Option<(Vector, Vector)> {
+        bounding_box(self.meshes().flat_map(|mesh| mesh.vertices()))
+    }
+
+    /// Calculate the bounding box from the vertex data for a body group configuration
+    ///
+    /// `body_groups` contains the index of the selected model for every body part,
+    /// body parts without an entry use their first model.
+    pub fn computed_bounding_box_for_body_groups(
+        &self,
+        body_groups: &[usize],
+    ) -> Option<(Vector, Vector)> {
+        bounding_box(
+            self.meshes()
+                .filter(|mesh| {
+                    mesh.model_index()
+                        == body_groups
+                            .get(mesh.body_part_index())
+                            .copied()
+                            .unwrap_or_default()
+                })
+                .flat_map(|mesh| mesh.vertices()),
         )
     }
 
@@ -136,7 +539,37 @@ impl Model {
         self.mdl.name.as_str()
     }
 
-    pub fn bones(&self) -> impl Iterator<Item = Handle<Bone, BoneId>> {
+    /// The full name of the model, falling back to the [`name`](Self::name) from the main header
+    ///
+    /// The main header only has room for 64 bytes, longer names are stored in the secondary header.
+    pub fn full_name(&self) -> &str {
+        self.mdl.full_name.as_deref().unwrap_or(self.name())
+    }
+
+    /// Summary of the metadata stored in the model headers
+    pub fn info(&self) -> ModelInfo<'_> {
+        ModelInfo {
+            name: self.full_name(),
+            version: self.mdl.header.version,
+            checksum: self.mdl.header.checksum(),
+            flags: self.flags(),
+            surface_prop: self.surface_prop(),
+            mass: self.mdl.header.mass,
+            eye_position: self.eye_position(),
+            illumination_position: self.illumination_position(),
+            illumination_attachment: self.illumination_attachment(),
+            max_eye_deflection: self.max_eye_deflection(),
+            hull_box: self.hull_box(),
+            view_box: self.view_box(),
+        }
+    }
+
+    /// The flex controller targets of the model
+    pub fn flex_descriptors(&self) -> &[FlexDescriptor] {
+        &self.mdl.flex_descriptors
+    }
+
+    pub fn bones(&self) -> impl Iterator<Item = Handle<'_, Bone, BoneId>> {
         self.mdl
             .bones
             .iter()
@@ -144,19 +577,110 @@ impl Model {
             .map(|(i, bone)| Handle::new(&self.mdl, bone, i.into()))
     }
 
-    pub fn bone(&self, id: BoneId) -> Option<Handle<Bone, BoneId>> {
+    /// All bones without a parent
+    ///
+    /// Models can have multiple root bones, like props with independently moving parts.
+    pub fn root_bones(&self) -> impl Iterator<Item = Handle<'_, Bone, BoneId>> {
+        self.bones().filter(|bone| bone.parent().is_none())
+    }
+
+    /// All bones starting from every root bone, with parents before their children
+    ///
+    /// Bones that are part of a cyclic hierarchy are skipped.
+    pub fn bone_tree(&self) -> impl Iterator<Item = Handle<'_, Bone, BoneId>> {
+        handle::bone_forest(&self.mdl)
+    }
+
+    pub fn bone(&self, id: BoneId) -> Option<Handle<'_, Bone, BoneId>> {
         self.mdl
             .bones
             .get(usize::from(id))
             .map(|bone| Handle::new(&self.mdl, bone, id))
     }
 
+    /// All bones used by the vertices of a level of detail
+    pub fn bones_for_lod(&self, lod: usize) -> impl Iterator<Item = Handle<'_, Bone, BoneId>> {
+        let flag = BoneFlags::used_by_vertex_lod(lod);
+        self.bones()
+            .filter(move |bone| !flag.is_empty() && bone.flags.contains(flag))
+    }
+
+    /// All bones that have any of the provided content flags
+    pub fn bones_with_contents(
+        &self,
+        contents: ContentFlags,
+    ) -> impl Iterator<Item = Handle<'_, Bone, BoneId>> {
+        self.bones()
+            .filter(move |bone| bone.contents().intersects(contents))
+    }
+
+    /// The combined content flags of all bones in the model
+    pub fn contents(&self) -> ContentFlags {
+        self.bones().fold(ContentFlags::empty(), |contents, bone| {
+            contents | bone.contents()
+        })
+    }
+
+    /// Position of the player viewpoint relative to the model origin
+    pub fn eye_position(&self) -> Vector {
+        self.mdl.header.eye_position
+    }
+
+    /// Position relative to the model origin used to calculate the ambient lighting and cubemap reflections
+    pub fn illumination_position(&self) -> Vector {
+        self.mdl.header.illumination_position
+    }
+
+    /// The attachment points of the model
+    ///
+    /// The bone of an attachment is checked against the bones of the model when getting it from the handle.
+    pub fn attachments(&self) -> impl Iterator<Item = Handle<'_, StudioAttachment, usize>> {
+        self.mdl
+            .attachments
+            .iter()
+            .enumerate()
+            .map(|(i, attachment)| Handle::new(&self.mdl, attachment, i))
+    }
+
+    /// The attachment that overrides the [`illumination_position`](Self::illumination_position), if set
+    pub fn illumination_attachment(&self) -> Option<&StudioAttachment> {
+        let index = self
+            .mdl
+            .header2
+            .as_ref()?
+            .illumination_position_attachment_index;
+        // the index is stored 1-based, with 0 meaning no attachment
+        let index = usize::try_from(index).ok()?.checked_sub(1)?;
+        self.mdl.attachments.get(index)
+    }
+
+    /// The point to sample ambient lighting and cubemaps from, relative to the model origin in the bind pose
+    ///
+    /// Like the engine, the illumination attachment takes precedence over the illumination position when set.
+    pub fn lighting_origin(&self) -> Vector {
+        let Some(attachment) = self.illumination_attachment() else {
+            return self.illumination_position();
+        };
+        let bone_to_model = usize::try_from(attachment.local_bone)
+            .ok()
+            .and_then(|bone| self.mdl.bones.get(bone))
+            .and_then(|bone| bone.pose_to_bone.matrix().invert())
+            .unwrap_or(Matrix4::identity());
+        let origin = (bone_to_model * attachment.local.matrix()).w;
+        Vector {
+            x: origin.x,
+            y: origin.y,
+            z: origin.z,
+        }
+    }
+
+    /// The rotation of the first root bone
     pub fn root_transform(&self) -> Matrix4<f32> {
         if self.mdl.header.flags.contains(ModelFlags::STATIC_PROP) {
             return Matrix4::identity();
         }
 
-        self.bones()
+        self.root_bones()
             .next()
             .map(|bone| Matrix4::from(bone.rot))
             .unwrap_or_else(Matrix4::identity)
@@ -185,6 +709,44 @@ impl Model {
         self.mdl.surface_prop.as_str()
     }
 
+    /// The surface property of every bone, falling back to the model [`surface_prop`](Self::surface_prop)
+    /// for bones that don't set one
+    pub fn surface_props(&self) -> BTreeMap<BoneId, &str> {
+        self.bones()
+            .map(|bone| {
+                let surface_prop = match bone.data().surface_prop.as_str() {
+                    "" => self.surface_prop(),
+                    surface_prop => surface_prop,
+                };
+                (bone.key(), surface_prop)
+            })
+            .collect()
+    }
+
+    pub fn flags(&self) -> ModelFlags {
+        self.mdl.header.flags
+    }
+
+    pub fn is_static_prop(&self) -> bool {
+        self.flags().contains(ModelFlags::STATIC_PROP)
+    }
+
+    pub fn casts_shadows(&self) -> bool {
+        !self.flags().contains(ModelFlags::DO_NOT_CAST_SHADOWS)
+    }
+
+    pub fn is_translucent_twopass(&self) -> bool {
+        self.flags().contains(ModelFlags::TRANSLUCENT_TWOPASS)
+    }
+
+    pub fn uses_bumpmapping(&self) -> bool {
+        self.flags().contains(ModelFlags::USES_BUMPMAPPING)
+    }
+
+    pub fn has_shadow_lod(&self) -> bool {
+        self.flags().contains(ModelFlags::HASSHADOWLOD)
+    }
+
     pub fn poses(&self) -> impl Iterator<Item = &PoseParameterDescription> {
         self.mdl.pose_parameters.iter()
     }
@@ -194,6 +756,7 @@ impl Model {
         transform.transform_vector(Vector3::from(vec)).into()
     }
 
+    #[deprecated(note = "use `animation_pose` and `Pose::skin_vertex`")]
     pub fn apply_animation(
         &self,
         animation: &AnimationDescription,
@@ -247,44 +810,360 @@ impl<'a> SkinTable<'a> {
     }
 }
 
+/// Metadata from the headers of a model, see [`Model::info`]
+#[derive(Debug, Clone, Copy)]
+pub struct ModelInfo<'a> {
+    /// The full name of the model
+    pub name: &'a str,
+    pub version: i32,
+    pub checksum: [u8; 4],
+    pub flags: ModelFlags,
+    pub surface_prop: &'a str,
+    pub mass: f32,
+    pub eye_position: Vector,
+    pub illumination_position: Vector,
+    /// The attachment that overrides the illumination position, if set
+    pub illumination_attachment: Option<&'a StudioAttachment>,
+    /// The cosine of the maximum angle the eyes can turn away from forward
+    pub max_eye_deflection: f32,
+    pub hull_box: (Vector, Vector),
+    pub view_box: (Vector, Vector),
+}
+
+/// The triangles using a single texture, as returned by [`Model::triangles_by_material`]
+#[derive(Debug, Clone)]
+pub struct MaterialTriangles<'a> {
+    /// Index of the texture in the model, `None` for meshes with a material that isn't in the skin table
+    pub texture_index: Option<usize>,
+    pub texture: Option<&'a TextureInfo>,
+    /// Triangle list indices into the vertices of the model
+    pub indices: Vec<usize>,
+}
+
+/// Summary of a single level of detail of a model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodInfo {
+    pub index: usize,
+    pub vertex_count: usize,
+    pub triangle_count: usize,
+    /// Switch distance at which this level of detail starts being used, `0` for the base level
+    pub switch_point: f32,
+}
+
 pub struct Mesh<'a> {
     pub model_name: &'a str,
+    body_part_index: usize,
+    model_index: usize,
+    mesh_index: usize,
     model_vertex_offset: usize,
+    model_bounding_radius: f32,
+    material: i32,
     vertices: &'a [Vertex],
-    tangents: &'a [[f32; 4]],
+    tangents: &'a [Tangent],
     mdl: &'a mdl::Mesh,
     vtx: &'a vtx::Mesh,
 }
 
 impl<'a> Mesh<'a> {
     /// Vertex indices into the model's vertex list
+    ///
+    /// Triangles with an index referencing data that doesn't exist in the vtx are skipped with a warning.
     pub fn vertex_strip_indices(&self) -> impl Iterator<Item = impl Iterator<Item = usize> + 'a> {
+        self.checked_strip_indices().map(skip_invalid_triangles)
+    }
+
+    fn checked_strip_indices(
+        &self,
+    ) -> impl Iterator<Item = impl Iterator<Item = Result<usize, ModelError>> + 'a> {
         let mdl_offset = self.mdl.vertex_offset as usize + self.model_vertex_offset;
         self.vtx.strip_groups.iter().flat_map(move |strip_group| {
             let group_indices = &strip_group.indices;
             let vertices = &strip_group.vertices;
             strip_group.strips.iter().map(move |strip| {
-                strip
-                    .indices()
-                    .map(move |index| group_indices[index] as usize)
-                    .map(move |index| vertices[index].original_mesh_vertex_id as usize + mdl_offset)
+                strip.indices().map(move |index| {
+                    let index = *group_indices.get(index).ok_or(ModelError::InvalidIndex {
+                        data: "strip group index",
+                        index,
+                    })? as usize;
+                    let vertex = vertices.get(index).ok_or(ModelError::InvalidIndex {
+                        data: "strip group vertex",
+                        index,
+                    })?;
+                    Ok(vertex.original_mesh_vertex_id as usize + mdl_offset)
+                })
             })
         })
     }
 
     pub fn material_index(&self) -> i32 {
-        self.mdl.material
+        self.material
+    }
+
+    /// Index of the first vertex of the mesh in the model's vertex list
+    pub fn vertex_offset(&self) -> usize {
+        self.mdl.vertex_offset as usize + self.model_vertex_offset
+    }
+
+    /// The flexes of the mesh, vertex indices are relative to the [`vertex_offset`](Self::vertex_offset)
+    pub fn flexes(&self) -> &'a [mdl::Flex] {
+        &self.mdl.flexes
+    }
+
+    pub fn mesh_id(&self) -> i32 {
+        self.mdl.mesh_id
+    }
+
+    /// Whether the mesh is rendered with the eyeball shader
+    pub fn is_eyeball(&self) -> bool {
+        self.mdl.material_type == 1
     }
 
+    /// Index of the body part containing the mesh
+    pub fn body_part_index(&self) -> usize {
+        self.body_part_index
+    }
+
+    /// Index of the model containing the mesh within its body part
+    pub fn model_index(&self) -> usize {
+        self.model_index
+    }
+
+    /// Calculate the bounding box of the mesh from its vertex data
+    pub fn computed_bounding_box(&self) -> Option<(Vector, Vector)> {
+        bounding_box(self.vertices())
+    }
+
+    /// Center of the mesh as stored in the model
+    pub fn center(&self) -> Vector {
+        self.mdl.center
+    }
+
+    /// Bounding radius of the model the mesh belongs to, as stored in the model
+    pub fn model_bounding_radius(&self) -> f32 {
+        self.model_bounding_radius
+    }
+
+    /// Bounding sphere of the mesh as center and radius
+    ///
+    /// Uses the center of the mesh and the radius of its model stored in the model when available, falling back to
+    /// [`computed_bounding_sphere`](Self::computed_bounding_sphere) for models that don't have them set.
+    /// Since the stored radius covers the whole model, the stored sphere is usually larger than the mesh.
+    pub fn bounding_sphere(&self) -> (Vector, f32) {
+        if self.model_bounding_radius > 0.0 {
+            return (self.mdl.center, self.model_bounding_radius);
+        }
+        self.computed_bounding_sphere()
+    }
+
+    /// Bounding sphere of the mesh as center and radius, computed from the vertex data
+    ///
+    /// The center is the center of the bounding box of the mesh, meshes without vertices
+    /// have the center stored in the model and a radius of `0.0`.
+    pub fn computed_bounding_sphere(&self) -> (Vector, f32) {
+        let Some((min, max)) = self.computed_bounding_box() else {
+            return (self.mdl.center, 0.0);
+        };
+        let center = (min + max) * 0.5;
+        let radius = self
+            .vertices()
+            .map(|vertex| (vertex.position - center).length())
+            .fold(0.0, f32::max);
+        (center, radius)
+    }
+
+    /// The triangles of the mesh as indices into the vertices of the model
+    ///
+    /// Triangles with a vertex that doesn't exist in the vvd are skipped with a warning like in [`vertices`](Self::vertices).
+    pub fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + 'a {
+        let vertices = self.vertices;
+        self.checked_triangles(move |index| lookup(vertices, index, "vertex").map(drop))
+    }
+
+    /// The triangles of the mesh, also skipping triangles with a vertex without tangent
+    pub(crate) fn triangles_with_tangents(&self) -> impl Iterator<Item = [usize; 3]> + 'a {
+        let vertices = self.vertices;
+        let tangents = self.tangents;
+        self.checked_triangles(move |index| check_vertex(vertices, tangents, index))
+    }
+
+    /// Check that both the vertex and tangent for an index into the vertices of the model exist
+    pub(crate) fn check_vertex(&self, index: usize) -> Result<(), ModelError> {
+        check_vertex(self.vertices, self.tangents, index)
+    }
+
+    fn checked_triangles(
+        &self,
+        check: impl Fn(usize) -> Result<(), ModelError> + Copy + 'a,
+    ) -> impl Iterator<Item = [usize; 3]> + 'a {
+        self.checked_strip_indices()
+            .flat_map(move |strip| {
+                skip_invalid_triangles(strip.map(move |index| {
+                    let index = index?;
+                    check(index)?;
+                    Ok(index)
+                }))
+            })
+            .tuples()
+            .map(|(a, b, c)| [a, b, c])
+    }
+
+    /// The vertices of the mesh in strip order
+    ///
+    /// Triangles with a vertex that doesn't exist in the vvd, like when the vtx and vvd don't belong together, are skipped
+    /// with a warning, use [`try_vertices`](Self::try_vertices) to handle them as an error instead.
     pub fn vertices(&self) -> impl Iterator<Item = &'a Vertex> + 'a {
-        self.vertex_strip_indices()
-            .flat_map(|strip| strip.map(|index| &self.vertices[index]))
+        let vertices = self.vertices;
+        self.checked_strip_indices().flat_map(move |strip| {
+            skip_invalid_triangles(strip.map(move |index| lookup(vertices, index?, "vertex")))
+        })
     }
 
-    pub fn tangents(&self) -> impl Iterator<Item = [f32; 4]> + '_ {
-        self.vertex_strip_indices()
-            .flat_map(|strip| strip.map(|index| self.tangents[index]))
+    /// The vertices of the mesh in strip order, erroring if any index is out of bounds
+    pub fn try_vertices(&self) -> Result<Vec<&'a Vertex>, ModelError> {
+        self.checked_strip_indices()
+            .flatten()
+            .map(|index| lookup(self.vertices, index?, "vertex"))
+            .collect()
     }
+
+    /// The tangents of the mesh in strip order, triangles with out of bounds tangents are skipped like in [`vertices`](Self::vertices)
+    pub fn tangents(&self) -> impl Iterator<Item = Tangent> + 'a {
+        let tangents = self.tangents;
+        self.checked_strip_indices().flat_map(move |strip| {
+            skip_invalid_triangles(
+                strip.map(move |index| lookup(tangents, index?, "tangent").copied()),
+            )
+        })
+    }
+
+    /// The tangents of the mesh in strip order, erroring if any index is out of bounds
+    pub fn try_tangents(&self) -> Result<Vec<Tangent>, ModelError> {
+        self.checked_strip_indices()
+            .flatten()
+            .map(|index| lookup(self.tangents, index?, "tangent").copied())
+            .collect()
+    }
+
+    /// The tangent and bitangent of every vertex in strip order
+    ///
+    /// Triangles with an out of bounds vertex or tangent are skipped like in [`vertices`](Self::vertices).
+    pub fn tangent_vectors(&self) -> impl Iterator<Item = (Vector, Vector)> + 'a {
+        let vertices = self.vertices;
+        let tangents = self.tangents;
+        self.checked_strip_indices().flat_map(move |strip| {
+            skip_invalid_triangles(strip.map(move |index| {
+                let index = index?;
+                let vertex = lookup(vertices, index, "vertex")?;
+                let tangent = lookup(tangents, index, "tangent")?;
+                Ok((tangent.direction(), tangent.bitangent(vertex.normal)))
+            }))
+        })
+    }
+}
+
+fn check_vertex(vertices: &[Vertex], tangents: &[Tangent], index: usize) -> Result<(), ModelError> {
+    lookup(vertices, index, "vertex")?;
+    lookup(tangents, index, "tangent")?;
+    Ok(())
+}
+
+fn lookup<'a, T>(values: &'a [T], index: usize, data: &'static str) -> Result<&'a T, ModelError> {
+    values
+        .get(index)
+        .ok_or(ModelError::InvalidIndex { data, index })
+}
+
+/// Group the values of a triangle list into triangles, skipping every triangle with an invalid value with a warning
+///
+/// Skipping only the invalid value would shift all following triangles.
+pub(crate) fn skip_invalid_triangles<T>(
+    values: impl Iterator<Item = Result<T, ModelError>>,
+) -> impl Iterator<Item = T> {
+    values
+        .tuples()
+        .filter_map(|(a, b, c)| match (a, b, c) {
+            (Ok(a), Ok(b), Ok(c)) => Some([a, b, c]),
+            (Err(error), _, _) | (_, Err(error), _) | (_, _, Err(error)) => {
+                tracing::warn!(%error, "skipping invalid mesh triangle");
+                None
+            }
+        })
+        .flatten()
+}
+
+/// Pair the meshes of the mdl and vtx data by body part, model and mesh index
+///
+/// Meshes that only exist in one of the two are skipped.
+fn pair_meshes(mdl: &Mdl, vtx: &Vtx) -> Vec<MeshIndex> {
+    let mut meshes = Vec::new();
+    for (body_part, (mdl_part, vtx_part)) in mdl.body_parts.iter().zip(&vtx.body_parts).enumerate()
+    {
+        for (model, (mdl_model, vtx_model)) in
+            mdl_part.models.iter().zip(&vtx_part.models).enumerate()
+        {
+            let vtx_mesh_count = vtx_model.lods.first().map_or(0, |lod| lod.meshes.len());
+            let mesh_count = mdl_model.meshes.len().min(vtx_mesh_count);
+            meshes.extend((0..mesh_count).map(|mesh| MeshIndex {
+                body_part,
+                model,
+                mesh,
+            }));
+        }
+    }
+    meshes
+}
+
+/// Check that the mdl and vtx data contain the same body parts, models and meshes
+fn validate_pairing(mdl: &Mdl, vtx: &Vtx) -> Result<(), ModelError> {
+    let check = |data, location: &dyn Fn() -> String, mdl, vtx| {
+        if mdl == vtx {
+            Ok(())
+        } else {
+            Err(ModelError::Mismatch {
+                data,
+                location: location(),
+                mdl,
+                vtx,
+            })
+        }
+    };
+
+    check(
+        "body parts",
+        &String::new,
+        mdl.body_parts.len(),
+        vtx.body_parts.len(),
+    )?;
+    for (body_part, (mdl_part, vtx_part)) in mdl.body_parts.iter().zip(&vtx.body_parts).enumerate()
+    {
+        check(
+            "models",
+            &|| format!(" in body part {body_part}"),
+            mdl_part.models.len(),
+            vtx_part.models.len(),
+        )?;
+        for (model, (mdl_model, vtx_model)) in
+            mdl_part.models.iter().zip(&vtx_part.models).enumerate()
+        {
+            for lod in vtx_model.lods.iter() {
+                check(
+                    "meshes",
+                    &|| format!(" in body part {body_part}, model {model}"),
+                    mdl_model.meshes.len(),
+                    lod.meshes.len(),
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn bounding_box<'a>(vertices: impl IntoIterator<Item = &'a Vertex>) -> Option<(Vector, Vector)> {
+    let mut positions = vertices.into_iter().map(|vertex| vertex.position);
+    let first = positions.next()?;
+    Some(positions.fold((first, first), |(min, max), position| {
+        (min.min(position), max.max(position))
+    }))
 }
 
 fn index_range(index: i32, count: i32, size: usize) -> impl Iterator<Item = usize> {
@@ -298,12 +1177,10 @@ fn read_relative_iter<'a, T: ReadRelative, I: 'a + Iterator<Item = usize>>(
     indexes: I,
 ) -> impl Iterator<Item = Result<T, ModelError>> + 'a {
     indexes.map(|index| {
-        let data = data.get(index..).ok_or_else(|| ModelError::OutOfBounds {
-            data: type_name::<T>(),
-            offset: index,
-        })?;
-        let header = <T::Header as Readable>::read(data)?;
-        T::read(data, header)
+        read_at(data, index, type_name::<T>(), |data| {
+            let header = <T::Header as Readable>::read(data)?;
+            T::read(data, header)
+        })
     })
 }
 
@@ -319,12 +1196,24 @@ fn read_single<T: ReadRelative, I: TryInto<usize>>(data: &[u8], index: I) -> Res
         data: type_name::<T>(),
         offset: usize::MAX_VALUE,
     })?;
-    let data = data.get(index..).ok_or_else(|| ModelError::OutOfBounds {
-        data: type_name::<T>(),
+    read_at(data, index, type_name::<T>(), |data| {
+        let header = <T::Header as Readable>::read(data)?;
+        T::read(data, header)
+    })
+}
+
+/// Read from the data starting at `index`, making the offsets of any error relative to `data`
+fn read_at<'a, T>(
+    data: &'a [u8],
+    index: usize,
+    name: &'static str,
+    read: impl FnOnce(&'a [u8]) -> Result<T, ModelError>,
+) -> Result<T, ModelError> {
+    let data = data.get(index..).ok_or(ModelError::OutOfBounds {
+        data: name,
         offset: index,
     })?;
-    let header = <T::Header as Readable>::read(data)?;
-    T::read(data, header)
+    read(data).map_err(|error| error.offset_by(index))
 }
 
 trait Readable: Sized {
@@ -333,9 +1222,7 @@ trait Readable: Sized {
 
 impl<T: Pod> Readable for T {
     fn read(data: &[u8]) -> Result<Self, ModelError> {
-        let data = data
-            .get(0..size_of::<Self>())
-            .ok_or(ModelError::Eof(size_of::<Self>()))?;
+        let data = data.get(0..size_of::<Self>()).ok_or(ModelError::Eof(0))?;
         Ok(pod_read_unaligned(data))
     }
 }
@@ -355,6 +1242,7 @@ impl ReadableRelative for i8 {}
 impl ReadableRelative for i16 {}
 impl ReadableRelative for i32 {}
 impl ReadableRelative for f32 {}
+impl ReadableRelative for Vector {}
 impl<T: ReadableRelative + Pod> ReadableRelative for [T; 1] {}
 impl<T: ReadableRelative + Pod> ReadableRelative for [T; 2] {}
 impl<T: ReadableRelative + Pod> ReadableRelative for [T; 3] {}
@@ -1,14 +1,24 @@
+mod patch;
 mod raw;
+#[cfg(feature = "respawn")]
+pub mod respawn;
+mod validate;
+mod write;
 
+use bytemuck::Zeroable;
+use half::f16;
+pub use patch::Patch;
 pub use raw::header::*;
 pub use raw::header2::*;
 pub use raw::*;
 use std::mem::size_of;
+pub use validate::*;
+pub use write::{LinkedFiles, WriteError, WriteOptions};
 
 use crate::vvd::Vertex;
 use crate::{
-    read_relative, read_relative_iter, read_single, FixedString, ModelError, ReadRelative,
-    Readable, Transform3x4, Vector,
+    paths, read_at, read_relative, read_relative_iter, read_single, FixedString, ModelError,
+    ReadRelative, Readable, Transform3x4, Vector,
 };
 
 type Result<T> = std::result::Result<T, ModelError>;
@@ -18,6 +28,8 @@ pub struct Mdl {
     pub name: FixedString<64>,
     pub header: StudioHeader,
     pub header2: Option<StudioHeader2>,
+    /// The full name of the model from the secondary header, not limited to the 64 bytes of [`name`](Self::name)
+    pub full_name: Option<String>,
     pub bones: Vec<Bone>,
     pub bone_controllers: Vec<BoneController>,
     pub body_table_by_name: Vec<u8>,
@@ -34,82 +46,415 @@ pub struct Mdl {
     pub pose_parameters: Vec<PoseParameterDescription>,
     pub attachments: Vec<StudioAttachment>,
     pub hit_boxes: Vec<HitBoxSet>,
+    pub include_models: Vec<IncludeModel>,
+    pub flex_descriptors: Vec<FlexDescriptor>,
+}
+
+/// A model parsed with [`Mdl::read_partial`]
+#[derive(Debug, Clone)]
+pub struct PartialMdl {
+    /// The parsed model, failed sections are left empty
+    pub mdl: Mdl,
+    /// The names of the sections that failed to parse
+    pub failed_sections: Vec<&'static str>,
+}
+
+impl PartialMdl {
+    pub fn is_complete(&self) -> bool {
+        self.failed_sections.is_empty()
+    }
+}
+
+/// Options to skip parsing parts of the model that aren't needed
+///
+/// Skipped sections are left empty in the parsed model.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    /// Skip animations, sequences and pose parameters
+    pub skip_animations: bool,
+    pub skip_hitboxes: bool,
+    /// Only parse the data needed to build the geometry of the model
+    ///
+    /// The bones, textures, skins and body parts are kept, everything else is skipped.
+    pub geometry_only: bool,
+}
+
+impl ParseOptions {
+    /// Options that only parse the data needed to build the geometry of the model
+    pub fn geometry_only() -> Self {
+        ParseOptions {
+            geometry_only: true,
+            ..ParseOptions::default()
+        }
+    }
+
+    fn includes(&self, section: &str) -> bool {
+        match section {
+            "animations" | "animation blocks" | "sequences" | "pose parameters" => {
+                !(self.skip_animations || self.geometry_only)
+            }
+            "hitboxes" => !(self.skip_hitboxes || self.geometry_only),
+            "bone controllers" | "key values" | "attachments" | "include models" => {
+                !self.geometry_only
+            }
+            _ => true,
+        }
+    }
+}
+
+fn read_bones(data: &[u8], header: &StudioHeader) -> Result<Vec<Bone>> {
+    #[cfg(feature = "legacy")]
+    if header.is_legacy() {
+        return read_legacy::<LegacyBoneHeader, _, _>(data, header.legacy_bone_indexes());
+    }
+    read_relative(data, header.bone_indexes())
+}
+
+fn read_textures(data: &[u8], header: &StudioHeader) -> Result<Vec<TextureInfo>> {
+    #[cfg(feature = "legacy")]
+    if header.is_legacy() {
+        return read_legacy::<LegacyTextureHeader, _, _>(data, header.legacy_texture_indexes());
+    }
+    read_relative(data, header.texture_indexes())
+}
+
+/// Progress of parsing a model, reported after every parsed section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseProgress {
+    /// Name of the section that has been parsed
+    pub section: &'static str,
+    /// Number of bytes processed so far
+    ///
+    /// The sections of a file aren't stored in order, so within a file the processed bytes are
+    /// estimated from the number of parsed sections.
+    pub bytes_processed: usize,
+    /// Total number of bytes that will be processed
+    pub total_bytes: usize,
+}
+
+/// The sections read by [`Mdl::read_sections`], in the order they are read
+const SECTIONS: [&str; 18] = [
+    "header2",
+    "name",
+    "textures",
+    "skin table",
+    "bones",
+    "bone controllers",
+    "bone table",
+    "surface property",
+    "key values",
+    "animations",
+    "animation blocks",
+    "sequences",
+    "pose parameters",
+    "attachments",
+    "hitboxes",
+    "include models",
+    "flex descriptors",
+    "body parts",
+];
+
+/// Progress reporting for the sections of a single file
+pub(crate) struct SectionProgress<'a> {
+    pub callback: &'a mut dyn FnMut(ParseProgress),
+    /// Bytes processed before this file
+    pub offset: usize,
+    pub total_bytes: usize,
+    completed: usize,
+}
+
+impl<'a> SectionProgress<'a> {
+    pub fn new(
+        callback: &'a mut dyn FnMut(ParseProgress),
+        offset: usize,
+        total_bytes: usize,
+    ) -> Self {
+        SectionProgress {
+            callback,
+            offset,
+            total_bytes,
+            completed: 0,
+        }
+    }
+
+    fn report(&mut self, section: &'static str, file_size: usize, section_count: usize) {
+        self.completed += 1;
+        (self.callback)(ParseProgress {
+            section,
+            bytes_processed: self.offset
+                + file_size * self.completed.min(section_count) / section_count.max(1),
+            total_bytes: self.total_bytes,
+        });
+    }
+}
+
+/// Error handling for the sections of the model, errors are either returned or recorded
+struct Sections<'a> {
+    errors: Option<&'a mut Vec<ModelError>>,
+    options: ParseOptions,
+    progress: Option<SectionProgress<'a>>,
+    file_size: usize,
+}
+
+impl Sections<'_> {
+    /// Number of sections that will be read with the options, skipped sections don't report progress
+    fn count(&self) -> usize {
+        SECTIONS
+            .iter()
+            .filter(|section| self.options.includes(section))
+            .count()
+    }
+
+    fn read<T: Default>(
+        &mut self,
+        section: &'static str,
+        read: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        debug_assert!(SECTIONS.contains(&section), "unlisted section {section}");
+        if !self.options.includes(section) {
+            return Ok(T::default());
+        }
+        let result = match (read(), self.errors.as_mut()) {
+            (Ok(value), _) => Ok(value),
+            (Err(error), Some(errors)) => {
+                errors.push(ModelError::Section {
+                    section,
+                    source: Box::new(error),
+                });
+                Ok(T::default())
+            }
+            (Err(error), None) => Err(error),
+        };
+        let count = self.count();
+        if let (Some(progress), Ok(_)) = (self.progress.as_mut(), &result) {
+            progress.report(section, self.file_size, count);
+        }
+        result
+    }
 }
 
 impl Mdl {
     pub fn read(data: &[u8]) -> Result<Self> {
-        let header = <StudioHeader as Readable>::read(data)?;
-        let header2 = header
-            .header2_index()
-            .map(|index| read_single::<StudioHeader2, _>(data, index))
-            .transpose()?;
-        let name = header.name.try_into()?;
-        let mut textures = read_relative_iter(data, header.texture_indexes())
-            .collect::<Result<Vec<TextureInfo>>>()?;
-        let texture_dirs_indexes =
-            read_relative_iter(data, header.texture_dir_indexes()).collect::<Result<Vec<u32>>>()?;
-        let texture_paths = read_relative_iter::<String, _>(
+        Self::read_with_options(data, ParseOptions::default())
+    }
+
+    /// Parse the model, skipping the sections excluded by the options
+    pub fn read_with_options(data: &[u8], options: ParseOptions) -> Result<Self> {
+        Self::read_sections_with_progress(data, options, None)
+    }
+
+    /// Parse the model, calling `progress` after every parsed section
+    pub fn read_with_progress(
+        data: &[u8],
+        options: ParseOptions,
+        mut progress: impl FnMut(ParseProgress),
+    ) -> Result<Self> {
+        Self::read_sections_with_progress(
             data,
-            texture_dirs_indexes.into_iter().map(|index| index as usize),
+            options,
+            Some(SectionProgress::new(&mut progress, 0, data.len())),
         )
-        .map(|path| path.map(|path| path.replace('\\', "/")))
-        .collect::<Result<Vec<_>>>()?;
-        for texture in textures.iter_mut() {
-            texture.search_paths = texture_paths.clone();
+    }
+
+    pub(crate) fn read_sections_with_progress(
+        data: &[u8],
+        options: ParseOptions,
+        progress: Option<SectionProgress>,
+    ) -> Result<Self> {
+        let header = <StudioHeader as Readable>::read(data)?;
+        if RESPAWN_VERSIONS.contains(&header.version) {
+            return Err(ModelError::UnsupportedVersion(header.version));
         }
+        let mut sections = Sections {
+            errors: None,
+            options,
+            progress,
+            file_size: data.len(),
+        };
+        Self::read_sections(data, header, &mut sections)
+    }
 
-        let skin_table = read_relative::<u16, _>(data, header.skin_reference_indexes())?;
-        let bones = read_relative(data, header.bone_indexes())?;
-        let bone_controllers = read_relative(data, header.bone_controller_indexes())?;
-        let body_table_by_name = read_relative(data, header.bone_table_by_name_indexes())?;
-
-        let surface_prop = read_single(data, header.surface_prop_index)?;
-        let key_values = (header.key_value_size > 0)
-            .then(|| read_single(data, header.key_value_index))
-            .transpose()?;
-        let mut local_animations: Vec<AnimationDescription> =
-            read_relative(data, header.local_animation_indexes())?;
-        local_animations
-            .iter_mut()
-            .flat_map(|desc| desc.animations.iter_mut())
-            .for_each(|animation| {
-                if let Some(bone) = bones.get(usize::from(animation.bone)) {
-                    animation.apply_bone_data(bone);
-                }
-            });
-        let animation_block_source: String = read_single(data, header.anim_blocks_name_index)?;
-        let animation_blocks = read_relative(data, header.animation_block_indexes())?;
-        let mut animation_sequences: Vec<AnimationSequence> =
-            read_relative(data, header.animation_sequence_indexes())?;
-        animation_sequences
-            .iter_mut()
-            .for_each(|seq| seq.bone_weights.truncate(bones.len()));
+    /// Parse as much of the model as possible
+    ///
+    /// Sections that fail to parse are left empty and the error for every failed section is returned.
+    pub fn read_partial(data: &[u8]) -> Result<(PartialMdl, Vec<ModelError>)> {
+        let mut errors = Vec::new();
+        let header = <StudioHeader as Readable>::read(data).and_then(|header| {
+            if RESPAWN_VERSIONS.contains(&header.version) {
+                Err(ModelError::UnsupportedVersion(header.version))
+            } else {
+                Ok(header)
+            }
+        });
+        let (header, data) = match header {
+            Ok(header) => (header, data),
+            Err(error) => {
+                errors.push(ModelError::Section {
+                    section: "header",
+                    source: Box::new(error),
+                });
+                // without a header no other section can be found
+                (StudioHeader::zeroed(), &[][..])
+            }
+        };
+        let mut sections = Sections {
+            errors: Some(&mut errors),
+            options: ParseOptions::default(),
+            progress: None,
+            file_size: data.len(),
+        };
+        let mdl = Self::read_sections(data, header, &mut sections)?;
+        let failed_sections = errors
+            .iter()
+            .filter_map(|error| match error {
+                ModelError::Section { section, .. } => Some(*section),
+                _ => None,
+            })
+            .collect();
+        Ok((
+            PartialMdl {
+                mdl,
+                failed_sections,
+            },
+            errors,
+        ))
+    }
 
-        let pose_parameters = read_relative(data, header.local_pose_param_indexes())?;
-        let attachments = read_relative(data, header.attachment_indexes())?;
-        let hit_boxes = read_relative(data, header.hitbox_set_indexes())?;
+    fn read_sections(data: &[u8], header: StudioHeader, sections: &mut Sections) -> Result<Self> {
+        let (header2, full_name) = sections.read("header2", || {
+            let Some(index) = header.header2_index() else {
+                return Ok((None, None));
+            };
+            let header2 = read_single::<StudioHeader2, _>(data, index)?;
+            // the name offset is relative to the secondary header
+            let full_name = header2
+                .name_index()
+                .map(|name_index| read_single::<String, _>(data, index + name_index))
+                .transpose()?
+                .filter(|name| !name.is_empty());
+            Ok((Some(header2), full_name))
+        })?;
+        let name = sections.read("name", || header.name.try_into())?;
+        let (textures, texture_paths) = sections.read("textures", || {
+            let mut textures = read_textures(data, &header)?;
+            let texture_dirs_indexes = read_relative_iter(data, header.texture_dir_indexes())
+                .collect::<Result<Vec<u32>>>()?;
+            let texture_paths = paths::canonical_directories(
+                read_relative_iter::<String, _>(
+                    data,
+                    texture_dirs_indexes.into_iter().map(|index| index as usize),
+                )
+                .collect::<Result<Vec<_>>>()?,
+            );
+            for texture in textures.iter_mut() {
+                texture.search_paths = texture_paths.clone();
+            }
+            Ok((textures, texture_paths))
+        })?;
+
+        let skin_table = sections.read("skin table", || {
+            read_relative::<u16, _>(data, header.skin_reference_indexes())
+        })?;
+        let bones = sections.read("bones", || read_bones(data, &header))?;
+        let bone_controllers = sections.read("bone controllers", || {
+            read_relative(data, header.bone_controller_indexes())
+        })?;
+        let body_table_by_name = sections.read("bone table", || {
+            read_relative(data, header.bone_table_by_name_indexes())
+        })?;
+
+        let surface_prop = sections.read("surface property", || {
+            read_single(data, header.surface_prop_index)
+        })?;
+        let key_values = sections.read("key values", || {
+            (header.key_value_size > 0)
+                .then(|| read_single(data, header.key_value_index))
+                .transpose()
+        })?;
+        let local_animations = sections.read("animations", || {
+            let mut local_animations: Vec<AnimationDescription> =
+                read_relative(data, header.local_animation_indexes())?;
+            local_animations
+                .iter_mut()
+                .flat_map(|desc| desc.animations.iter_mut())
+                .for_each(|animation| {
+                    if let Some(bone) = bones.get(usize::from(animation.bone)) {
+                        animation.apply_bone_data(bone);
+                    }
+                });
+            Ok(local_animations)
+        })?;
+        let (animation_block_source, animation_blocks) =
+            sections.read("animation blocks", || {
+                Ok((
+                    read_single(data, header.anim_blocks_name_index)?,
+                    read_relative(data, header.animation_block_indexes())?,
+                ))
+            })?;
+        let animation_sequences = sections.read("sequences", || {
+            header
+                .animation_sequence_indexes()
+                .map(|index| {
+                    read_at(data, index, "AnimationSequence", |data| {
+                        let sequence_header = <AnimationSequenceHeader as Readable>::read(data)?;
+                        let mut sequence = AnimationSequence::read(data, sequence_header)?;
+                        sequence.bone_weights =
+                            read_relative(data, sequence_header.bone_weight_indices(bones.len()))?;
+                        Ok(sequence)
+                    })
+                })
+                .collect::<Result<_>>()
+        })?;
+
+        let pose_parameters = sections.read("pose parameters", || {
+            read_relative(data, header.local_pose_param_indexes())
+        })?;
+        let attachments = sections.read("attachments", || {
+            read_relative(data, header.attachment_indexes())
+        })?;
+        let hit_boxes = sections.read("hitboxes", || {
+            read_relative(data, header.hitbox_set_indexes())
+        })?;
+        let include_models = sections.read("include models", || {
+            read_relative(data, header.include_model_indexes())
+        })?;
+        let flex_descriptors = sections.read("flex descriptors", || {
+            read_relative(data, header.flex_descriptor_indexes())
+        })?;
+        let body_parts = sections.read("body parts", || {
+            let mut body_parts: Vec<BodyPart> = header
+                .body_part_indexes()
+                .map(|index| {
+                    read_at(data, index, "BodyPart", |data| {
+                        let header = <BodyPartHeader as Readable>::read(data)?;
+                        BodyPart::read(data, header)
+                    })
+                })
+                .collect::<Result<_>>()?;
+            if let Some(scale) = header.vert_anim_fixed_point_scale() {
+                body_parts
+                    .iter_mut()
+                    .flat_map(|part| part.models.iter_mut())
+                    .flat_map(|model| model.meshes.iter_mut())
+                    .flat_map(|mesh| mesh.flexes.iter_mut())
+                    .flat_map(|flex| flex.vertices.iter_mut())
+                    .for_each(|vertex| vertex.apply_fixed_point_scale(scale));
+            }
+            Ok(body_parts)
+        })?;
 
         Ok(Mdl {
             name,
             bones,
             bone_controllers,
             body_table_by_name,
-            body_parts: header
-                .body_part_indexes()
-                .map(|index| {
-                    let data = data.get(index..).ok_or(ModelError::OutOfBounds {
-                        data: "BodyPart",
-                        offset: index,
-                    })?;
-                    let header = <BodyPartHeader as Readable>::read(data)?;
-                    BodyPart::read(data, header)
-                })
-                .collect::<Result<_>>()?,
+            body_parts,
             textures,
             texture_paths,
             skin_table,
             header,
             header2,
+            full_name,
             surface_prop,
             key_values,
             pose_parameters,
@@ -119,12 +464,70 @@ impl Mdl {
             animation_sequences,
             attachments,
             hit_boxes,
+            include_models,
+            flex_descriptors,
         })
     }
+
+    /// Scale all positions and distances in the model
+    pub fn scale(&mut self, factor: f32) {
+        let header = &mut self.header;
+        header.eye_position = header.eye_position * factor;
+        header.illumination_position = header.illumination_position * factor;
+        for corner in header
+            .bounding_box
+            .iter_mut()
+            .chain(header.view_bounding_box.iter_mut())
+        {
+            *corner = *corner * factor;
+        }
+
+        self.bones.iter_mut().for_each(|bone| bone.scale(factor));
+        self.bone_controllers
+            .iter_mut()
+            .for_each(|controller| controller.scale(factor));
+        for model in self
+            .body_parts
+            .iter_mut()
+            .flat_map(|part| part.models.iter_mut())
+        {
+            model.bounding_radius *= factor;
+            for mesh in model.meshes.iter_mut() {
+                mesh.center = mesh.center * factor;
+                for vertex in mesh
+                    .flexes
+                    .iter_mut()
+                    .flat_map(|flex| flex.vertices.iter_mut())
+                {
+                    vertex.delta = vertex.delta * factor;
+                }
+            }
+            for eyeball in model.eyeballs.iter_mut() {
+                eyeball.origin = eyeball.origin * factor;
+                eyeball.z_offset *= factor;
+                eyeball.radius *= factor;
+            }
+        }
+        self.local_animations
+            .iter_mut()
+            .for_each(|animation| animation.scale(factor));
+        self.attachments
+            .iter_mut()
+            .for_each(|attachment| attachment.local.scale_translation(factor));
+        for hitbox in self
+            .hit_boxes
+            .iter_mut()
+            .flat_map(|set| set.boxes.iter_mut())
+        {
+            hitbox.min = hitbox.min * factor;
+            hitbox.max = hitbox.max * factor;
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct BodyPart {
+    pub name: String,
     pub name_index: i32,
     pub models: Vec<Model>,
 }
@@ -135,6 +538,7 @@ impl ReadRelative for BodyPart {
     fn read(data: &[u8], header: Self::Header) -> Result<Self> {
         Ok(BodyPart {
             models: read_relative(data, header.model_indexes())?,
+            name: read_single(data, header.name_index)?,
             name_index: header.name_index,
         })
     }
@@ -148,6 +552,7 @@ pub struct Model {
     pub meshes: Vec<Mesh>,
     /// Base offset of the model's vertices
     pub vertex_offset: i32,
+    pub eyeballs: Vec<Eyeball>,
 }
 
 impl ReadRelative for Model {
@@ -160,6 +565,56 @@ impl ReadRelative for Model {
             ty: header.ty,
             bounding_radius: header.bounding_radius,
             vertex_offset: header.vertex_index / (size_of::<Vertex>() as i32),
+            eyeballs: read_relative(data, header.eyeball_indexes())?,
+        })
+    }
+}
+
+/// An eyeball of the model, rendered by meshes with the eyeball material type
+#[derive(Debug, Clone)]
+pub struct Eyeball {
+    pub name: String,
+    pub bone: i32,
+    /// Center of the eyeball, relative to the bone
+    pub origin: Vector,
+    pub z_offset: f32,
+    pub radius: f32,
+    /// Up direction of the eyeball, relative to the bone
+    pub up: Vector,
+    /// Forward direction of the eyeball, relative to the bone
+    pub forward: Vector,
+    /// Index of the eyeball texture
+    pub texture: i32,
+    /// Inverse of the iris size, as used by the eye shaders
+    pub iris_scale: f32,
+    pub upper_flex_descriptors: [i32; 3],
+    pub lower_flex_descriptors: [i32; 3],
+    pub upper_targets: [f32; 3],
+    pub lower_targets: [f32; 3],
+    pub upper_lid_flex_descriptor: i32,
+    pub lower_lid_flex_descriptor: i32,
+}
+
+impl ReadRelative for Eyeball {
+    type Header = EyeballHeader;
+
+    fn read(data: &[u8], header: Self::Header) -> Result<Self> {
+        Ok(Eyeball {
+            name: read_single(data, header.name_index)?,
+            bone: header.bone,
+            origin: header.origin,
+            z_offset: header.z_offset,
+            radius: header.radius,
+            up: header.up,
+            forward: header.forward,
+            texture: header.texture,
+            iris_scale: header.iris_scale,
+            upper_flex_descriptors: header.upper_flex_descriptors,
+            lower_flex_descriptors: header.lower_flex_descriptors,
+            upper_targets: header.upper_targets,
+            lower_targets: header.lower_targets,
+            upper_lid_flex_descriptor: header.upper_lid_flex_descriptor,
+            lower_lid_flex_descriptor: header.lower_lid_flex_descriptor,
         })
     }
 }
@@ -168,19 +623,131 @@ impl ReadRelative for Model {
 pub struct Mesh {
     pub material: i32,
     pub vertex_offset: i32,
+    /// Special shader type for the mesh, `1` for eyeballs
+    pub material_type: i32,
+    /// Extra parameter for the material type, the eyeball index for eyeball meshes
+    pub material_param: i32,
+    pub mesh_id: i32,
+    pub center: Vector,
+    pub flexes: Vec<Flex>,
 }
 
 impl ReadRelative for Mesh {
     type Header = MeshHeader;
 
-    fn read(_data: &[u8], header: Self::Header) -> Result<Self> {
+    fn read(data: &[u8], header: Self::Header) -> Result<Self> {
         Ok(Mesh {
+            flexes: read_relative(data, header.flex_indexes())?,
             material: header.material,
             vertex_offset: header.vertex_index,
+            material_type: header.material_type,
+            material_param: header.material_param,
+            mesh_id: header.mesh_id,
+            center: header.center,
         })
     }
 }
 
+/// A flex controller target, the shapes of the flexes are stored per mesh
+#[derive(Debug, Clone)]
+pub struct FlexDescriptor {
+    pub name: String,
+}
+
+impl ReadRelative for FlexDescriptor {
+    type Header = FlexDescriptorHeader;
+
+    fn read(data: &[u8], header: Self::Header) -> Result<Self> {
+        Ok(FlexDescriptor {
+            name: read_single(data, header.name_index)?,
+        })
+    }
+}
+
+/// The vertex deltas of a mesh for a single flex
+#[derive(Debug, Clone)]
+pub struct Flex {
+    /// Index into the flex descriptors of the model
+    pub descriptor: i32,
+    /// The flex descriptor for the other side of a stereo flex
+    pub pair: i32,
+    /// The control values at which the flex starts, peaks, stays at its peak until and ends
+    pub targets: [f32; 4],
+    pub vertices: Vec<VertexAnimation>,
+}
+
+impl ReadRelative for Flex {
+    type Header = FlexHeader;
+
+    fn read(data: &[u8], header: Self::Header) -> Result<Self> {
+        let wrinkles = if header.is_wrinkle() {
+            let offset = size_of::<VertexAnimationHeader>();
+            read_relative(data, header.vertex_indexes().map(|index| index + offset))?
+        } else {
+            vec![0; header.vertex_indexes().count()]
+        };
+        let vertices = read_relative_iter(data, header.vertex_indexes())
+            .zip(wrinkles)
+            .map(|(vertex, wrinkle)| Ok(VertexAnimation::new(vertex?, wrinkle)))
+            .collect::<Result<_>>()?;
+        Ok(Flex {
+            descriptor: header.descriptor,
+            pair: header.pair,
+            targets: header.targets,
+            vertices,
+        })
+    }
+}
+
+/// Default scale for the wrinkle deltas of models that don't set a fixed point scale
+const DEFAULT_FIXED_POINT_SCALE: f32 = 1.0 / 4096.0;
+
+/// The change of a single vertex for a flex
+#[derive(Debug, Clone)]
+pub struct VertexAnimation {
+    /// Index of the vertex, relative to the first vertex of the mesh
+    pub index: u16,
+    pub speed: u8,
+    pub side: u8,
+    pub delta: Vector,
+    pub normal_delta: Vector,
+    pub wrinkle_delta: f32,
+    raw: VertexAnimationHeader,
+    raw_wrinkle: i16,
+}
+
+impl VertexAnimation {
+    fn new(raw: VertexAnimationHeader, raw_wrinkle: i16) -> Self {
+        let half = |value: [u16; 3]| Vector {
+            x: f16::from_bits(value[0]).into(),
+            y: f16::from_bits(value[1]).into(),
+            z: f16::from_bits(value[2]).into(),
+        };
+        VertexAnimation {
+            index: raw.index,
+            speed: raw.speed,
+            side: raw.side,
+            delta: half(raw.delta),
+            normal_delta: half(raw.normal_delta),
+            wrinkle_delta: raw_wrinkle as f32 * DEFAULT_FIXED_POINT_SCALE,
+            raw,
+            raw_wrinkle,
+        }
+    }
+
+    /// Decode the deltas as fixed point numbers instead of half floats
+    fn apply_fixed_point_scale(&mut self, scale: f32) {
+        let fixed = |value: [u16; 3]| Vector {
+            x: value[0] as i16 as f32 * scale,
+            y: value[1] as i16 as f32 * scale,
+            z: value[2] as i16 as f32 * scale,
+        };
+        self.delta = fixed(self.raw.delta);
+        self.normal_delta = fixed(self.raw.normal_delta);
+        self.wrinkle_delta = self.raw_wrinkle as f32 * scale;
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TextureInfo {
     pub name: String,
@@ -188,16 +755,22 @@ pub struct TextureInfo {
     pub search_paths: Vec<String>,
 }
 
+impl TextureInfo {
+    /// The name of the texture in the canonical form from [`paths::canonical_material_name`]
+    pub fn canonical_name(&self) -> String {
+        paths::canonical_material_name(&self.name)
+    }
+}
+
 impl ReadRelative for TextureInfo {
     type Header = MeshTexture;
 
     fn read(data: &[u8], header: Self::Header) -> Result<Self> {
         Ok(TextureInfo {
-            name: String::read(
+            name: paths::normalize_separators(&String::read(
                 data.get(header.name_index as usize..).unwrap_or_default(),
                 (),
-            )?
-            .replace('\\', "/"),
+            )?),
             name_index: header.name_index,
             search_paths: Vec::new(),
         })
@@ -217,11 +790,10 @@ impl ReadRelative for StudioAttachment {
 
     fn read(data: &[u8], header: Self::Header) -> Result<Self> {
         Ok(StudioAttachment {
-            name: String::read(
+            name: paths::normalize_separators(&String::read(
                 data.get(header.name_index as usize..).unwrap_or_default(),
                 (),
-            )?
-            .replace('\\', "/"),
+            )?),
             flags: header.flags,
             local: header.local,
             local_bone: header.local_bone,
@@ -229,6 +801,25 @@ impl ReadRelative for StudioAttachment {
     }
 }
 
+/// A model referenced with `$includemodel`, usually containing shared animations
+#[derive(Debug, Clone)]
+pub struct IncludeModel {
+    pub label: String,
+    /// Path of the included `.mdl` file, relative to the game directory
+    pub name: String,
+}
+
+impl ReadRelative for IncludeModel {
+    type Header = IncludeModelHeader;
+
+    fn read(data: &[u8], header: Self::Header) -> Result<Self> {
+        Ok(IncludeModel {
+            label: read_single(data, header.label_index)?,
+            name: paths::normalize_separators(&read_single::<String, _>(data, header.name_index)?),
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HitBoxSet {
     pub name: String,
@@ -240,11 +831,10 @@ impl ReadRelative for HitBoxSet {
 
     fn read(data: &[u8], header: Self::Header) -> Result<Self> {
         Ok(HitBoxSet {
-            name: String::read(
+            name: paths::normalize_separators(&String::read(
                 data.get(header.name_index as usize..).unwrap_or_default(),
                 (),
-            )?
-            .replace('\\', "/"),
+            )?),
             boxes: read_relative(data, header.hitbox_indexes())?,
         })
     }
@@ -264,11 +854,10 @@ impl ReadRelative for BoundingBox {
 
     fn read(data: &[u8], header: Self::Header) -> Result<Self> {
         Ok(BoundingBox {
-            name: String::read(
+            name: paths::normalize_separators(&String::read(
                 data.get(header.name_index as usize..).unwrap_or_default(),
                 (),
-            )?
-            .replace('\\', "/"),
+            )?),
             bone: header.bone,
             group: header.group,
             min: header.bounding_box_min,
@@ -276,3 +865,60 @@ impl ReadRelative for BoundingBox {
         })
     }
 }
+
+impl BoundingBox {
+    /// The body area this hitbox belongs to
+    pub fn hit_group(&self) -> HitGroup {
+        HitGroup::from(self.group)
+    }
+}
+
+/// The standard hit groups used by the engine to scale damage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HitGroup {
+    Generic,
+    Head,
+    Chest,
+    Stomach,
+    LeftArm,
+    RightArm,
+    LeftLeg,
+    RightLeg,
+    Gear,
+    /// A game specific hit group
+    Other(i32),
+}
+
+impl From<i32> for HitGroup {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => HitGroup::Generic,
+            1 => HitGroup::Head,
+            2 => HitGroup::Chest,
+            3 => HitGroup::Stomach,
+            4 => HitGroup::LeftArm,
+            5 => HitGroup::RightArm,
+            6 => HitGroup::LeftLeg,
+            7 => HitGroup::RightLeg,
+            10 => HitGroup::Gear,
+            other => HitGroup::Other(other),
+        }
+    }
+}
+
+impl From<HitGroup> for i32 {
+    fn from(value: HitGroup) -> Self {
+        match value {
+            HitGroup::Generic => 0,
+            HitGroup::Head => 1,
+            HitGroup::Chest => 2,
+            HitGroup::Stomach => 3,
+            HitGroup::LeftArm => 4,
+            HitGroup::RightArm => 5,
+            HitGroup::LeftLeg => 6,
+            HitGroup::RightLeg => 7,
+            HitGroup::Gear => 10,
+            HitGroup::Other(other) => other,
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! Editing the parts of a model that can be written back with [`Mdl::write`]
+
+use super::{BoneId, BoneSample, Mdl, ModelFlags, SampleOptions, StudioAnimFlags};
+use crate::vvd::{Vertex, Vvd};
+use crate::{paths, Pose, Vector};
+use cgmath::{Matrix4, Point3, Transform};
+
+/// Flags that change how the data of the model is interpreted
+const LAYOUT_FLAGS: ModelFlags = ModelFlags::VERT_ANIM_FIXED_POINT_SCALE;
+
+/// A single edit of a model, applied with [`Mdl::apply`]
+///
+/// Every variant corresponds to one of the editing methods of [`Mdl`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+pub enum Patch {
+    RenameTexture {
+        name: String,
+        new_name: String,
+    },
+    SetFlags {
+        flags: ModelFlags,
+        enabled: bool,
+    },
+    SetCastShadows(bool),
+    SetStaticProp(bool),
+    SetSurfaceProp(String),
+    SetBoneSurfaceProp {
+        bone: BoneId,
+        surface_prop: String,
+    },
+    SetTextureDirectories(Vec<String>),
+    RepathMaterials {
+        from: String,
+        to: String,
+    },
+    SetSequenceBoundingBox {
+        sequence: usize,
+        bounding_box: [Vector; 2],
+    },
+}
+
+impl Mdl {
+    /// Apply an edit to the model
+    ///
+    /// Returns `false` if the edit didn't apply to the model, see the corresponding method for details.
+    pub fn apply(&mut self, patch: &Patch) -> bool {
+        match patch {
+            Patch::RenameTexture { name, new_name } => self.rename_texture(name, new_name),
+            Patch::SetFlags { flags, enabled } => self.set_flags(*flags, *enabled),
+            Patch::SetCastShadows(enabled) => {
+                self.set_cast_shadows(*enabled);
+                true
+            }
+            Patch::SetStaticProp(enabled) => self.set_static_prop(*enabled),
+            Patch::SetSurfaceProp(surface_prop) => {
+                self.set_surface_prop(surface_prop);
+                true
+            }
+            Patch::SetBoneSurfaceProp { bone, surface_prop } => {
+                self.set_bone_surface_prop(*bone, surface_prop)
+            }
+            Patch::SetTextureDirectories(dirs) => {
+                self.set_texture_directories(dirs);
+                true
+            }
+            Patch::RepathMaterials { from, to } => self.repath_materials(from, to) > 0,
+            Patch::SetSequenceBoundingBox {
+                sequence,
+                bounding_box,
+            } => match self.animation_sequences.get_mut(*sequence) {
+                Some(sequence) => {
+                    sequence.bounding_box = *bounding_box;
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    /// Rename a texture, the texture is found by comparing the names in their canonical form
+    ///
+    /// Returns `false` if the model has no texture with the name.
+    pub fn rename_texture(&mut self, name: &str, new_name: &str) -> bool {
+        let name = paths::canonical_material_name(name);
+        let new_name = paths::normalize_separators(new_name);
+        let mut renamed = false;
+        for texture in self
+            .textures
+            .iter_mut()
+            .filter(|texture| texture.canonical_name() == name)
+        {
+            texture.name = new_name.clone();
+            renamed = true;
+        }
+        renamed
+    }
+
+    /// Enable or disable model flags
+    ///
+    /// Flags that describe how the data of the model is stored can't be changed, returns `false` without changing
+    /// any flags if `flags` contains one of them.
+    pub fn set_flags(&mut self, flags: ModelFlags, enabled: bool) -> bool {
+        if flags.intersects(LAYOUT_FLAGS) {
+            return false;
+        }
+        self.header.flags.set(flags, enabled);
+        true
+    }
+
+    /// Whether the model casts shadows, disabled with `$donotcastshadows`
+    pub fn set_cast_shadows(&mut self, enabled: bool) {
+        self.header
+            .flags
+            .set(ModelFlags::DO_NOT_CAST_SHADOWS, !enabled);
+    }
+
+    /// Mark the model as a static prop, like `$staticprop`
+    ///
+    /// Static props can't be animated so only models with a single bone can be marked as static prop,
+    /// returns `false` without changing the flags for models with multiple bones.
+    pub fn set_static_prop(&mut self, enabled: bool) -> bool {
+        if enabled && self.bones.len() > 1 {
+            return false;
+        }
+        self.header.flags.set(ModelFlags::STATIC_PROP, enabled);
+        true
+    }
+
+    /// Change the surface property of the model
+    ///
+    /// Like `$surfaceprop` this also changes the bones that use the surface property of the model,
+    /// bones with their own surface property keep it.
+    pub fn set_surface_prop(&mut self, surface_prop: &str) {
+        for bone in self.bones.iter_mut() {
+            // bones without a surface property already fall back to the one of the model
+            if !bone.surface_prop.is_empty() && bone.surface_prop == self.surface_prop {
+                bone.surface_prop = surface_prop.into();
+            }
+        }
+        self.surface_prop = surface_prop.into();
+    }
+
+    /// Change the surface property of a single bone, like `$jointsurfaceprop`
+    ///
+    /// Returns `false` if the bone doesn't exist.
+    pub fn set_bone_surface_prop(&mut self, bone: BoneId, surface_prop: &str) -> bool {
+        match self.bones.get_mut(usize::from(bone)) {
+            Some(bone) => {
+                bone.surface_prop = surface_prop.into();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace the directories textures are searched in
+    pub fn set_texture_directories<I: IntoIterator<Item = S>, S: AsRef<str>>(&mut self, dirs: I) {
+        self.texture_paths = paths::canonical_directories(dirs);
+        for texture in self.textures.iter_mut() {
+            texture.search_paths = self.texture_paths.clone();
+        }
+    }
+
+    /// Move materials from one directory to another
+    ///
+    /// Replaces the directory `from` with `to` at the start of every texture directory and texture name,
+    /// returning the number of directories and names that changed.
+    pub fn repath_materials(&mut self, from: &str, to: &str) -> usize {
+        let from = paths::canonical_directory(from);
+        let to = paths::canonical_directory(to);
+        let repath = |path: &str| {
+            path.strip_prefix(from.as_str())
+                .map(|rest| format!("{to}{rest}"))
+        };
+
+        let mut changed = 0;
+        let dirs: Vec<String> = self
+            .texture_paths
+            .iter()
+            .map(|dir| match repath(dir) {
+                Some(dir) => {
+                    changed += 1;
+                    dir
+                }
+                None => dir.clone(),
+            })
+            .collect();
+        self.set_texture_directories(dirs);
+        for texture in self.textures.iter_mut() {
+            if let Some(name) = repath(&texture.canonical_name()) {
+                texture.name = name;
+                changed += 1;
+            }
+        }
+        changed
+    }
+}
+
+impl Mdl {
+    /// Recalculate the bounding boxes from the vertices of the model
+    ///
+    /// The hull box is set to the bounds of the vertices in the rest pose. The box of every sequence is set to the bounds
+    /// of the vertices over every frame of the animations the sequence blends between, and the view box to the union
+    /// of the hull box and all sequence boxes. Animations stored in animation blocks aren't loaded and use the rest pose,
+    /// delta animations are added onto the rest pose.
+    ///
+    /// Instead of skinning every vertex for every frame, the bounds of the vertices are computed in the space of every bone
+    /// they are attached to, and the animated boxes are bounds of these boxes moved by the bones.
+    /// This makes the sequence boxes slightly larger than the animated vertices when bones are rotated.
+    ///
+    /// Nothing is changed if the vvd has no vertices.
+    pub fn recompute_hulls(&mut self, vvd: &Vvd) {
+        let Some(rest) = bounds(vvd.vertices.iter().copied()) else {
+            return;
+        };
+        let rest_pose = Pose::rest(self);
+        let bone_boxes = bone_space_bounds(self, vvd);
+        let mut view = rest;
+        let mut boxes = Vec::with_capacity(self.animation_sequences.len());
+        for sequence in &self.animation_sequences {
+            let animations = sequence
+                .animation_indices
+                .iter()
+                .filter_map(|index| self.local_animations.get(usize::try_from(*index).ok()?));
+            let mut sequence_box = None;
+            for animation in animations {
+                let delta = animation.flags.contains(StudioAnimFlags::DELTA);
+                for frame in 0..animation.frame_count.max(1) {
+                    let mut pose = rest_pose.clone();
+                    let samples = animation
+                        .sample_frame(&self.bones, frame as f32, SampleOptions::default())
+                        .map(|sample| match rest_pose.sample(sample.bone) {
+                            Some(rest) if delta => BoneSample {
+                                bone: sample.bone,
+                                rotation: (cgmath::Quaternion::from(rest.rotation)
+                                    * cgmath::Quaternion::from(sample.rotation))
+                                .into(),
+                                position: rest.position + sample.position,
+                            },
+                            _ => sample,
+                        });
+                    pose.apply(samples);
+                    let frame_box = bone_boxes
+                        .iter()
+                        .zip(pose.world_transforms())
+                        .filter_map(|(bone_box, transform)| {
+                            Some(transform_bounds(bone_box.as_ref()?, transform))
+                        })
+                        .reduce(|a, b| union(Some(a), Some(b)).unwrap_or(a));
+                    sequence_box = union(sequence_box, frame_box);
+                }
+            }
+            let sequence_box = sequence_box.unwrap_or(rest);
+            view = union(Some(view), Some(sequence_box)).unwrap_or(rest);
+            boxes.push(sequence_box);
+        }
+        for (sequence, sequence_box) in self.animation_sequences.iter_mut().zip(boxes) {
+            sequence.bounding_box = sequence_box;
+        }
+        self.header.bounding_box = rest;
+        self.header.view_bounding_box = view;
+    }
+}
+
+/// The bounds of the rest pose vertices in the space of every bone they are attached to
+fn bone_space_bounds(mdl: &Mdl, vvd: &Vvd) -> Vec<Option<[Vector; 2]>> {
+    let pose_to_bone: Vec<Matrix4<f32>> = mdl
+        .bones
+        .iter()
+        .map(|bone| bone.pose_to_bone.matrix())
+        .collect();
+    let mut boxes = vec![None; mdl.bones.len()];
+    for vertex in &vvd.vertices {
+        let mut bones = vertex
+            .bone_weights
+            .weights()
+            .filter(|weight| weight.weight > 0.0)
+            .map(|weight| usize::from(weight.bone_id))
+            .filter(|bone| *bone < boxes.len())
+            .peekable();
+        // like when skinning, vertices without valid weights follow the first bone
+        let bones: Vec<usize> = match bones.peek() {
+            Some(_) => bones.collect(),
+            None => vec![0],
+        };
+        for bone in bones {
+            let (Some(bone_box), Some(pose_to_bone)) =
+                (boxes.get_mut(bone), pose_to_bone.get(bone))
+            else {
+                continue;
+            };
+            let position =
+                pose_to_bone.transform_point(Point3::from(<[f32; 3]>::from(vertex.position)));
+            let position = Vector::from([position.x, position.y, position.z]);
+            *bone_box = union(*bone_box, Some([position, position]));
+        }
+    }
+    boxes
+}
+
+/// The bounds of a box after transforming it
+fn transform_bounds([min, max]: &[Vector; 2], transform: &Matrix4<f32>) -> [Vector; 2] {
+    let corners = (0..8).map(|corner| {
+        let pick = |bit: usize, min: f32, max: f32| if corner & bit == 0 { min } else { max };
+        let point = transform.transform_point(Point3::new(
+            pick(1, min.x, max.x),
+            pick(2, min.y, max.y),
+            pick(4, min.z, max.z),
+        ));
+        Vector::from([point.x, point.y, point.z])
+    });
+    corners
+        .map(|corner| [corner, corner])
+        .reduce(|a, b| union(Some(a), Some(b)).unwrap_or(a))
+        .unwrap_or([*min, *max])
+}
+
+fn bounds(vertices: impl IntoIterator<Item = Vertex>) -> Option<[Vector; 2]> {
+    let mut positions = vertices.into_iter().map(|vertex| vertex.position);
+    let first = positions.next()?;
+    Some(positions.fold([first, first], |[min, max], position| {
+        [min.min(position), max.max(position)]
+    }))
+}
+
+fn union(a: Option<[Vector; 2]>, b: Option<[Vector; 2]>) -> Option<[Vector; 2]> {
+    match (a, b) {
+        (Some([a_min, a_max]), Some([b_min, b_max])) => Some([a_min.min(b_min), a_max.max(b_max)]),
+        (a, b) => a.or(b),
+    }
+}
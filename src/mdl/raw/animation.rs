@@ -1,13 +1,15 @@
 use crate::compressed_vector::{Quaternion48, Quaternion64, Vector48};
 use crate::mdl::{Bone, BoneId};
 use crate::{
-    index_range, read_relative, read_single, ModelError, Quaternion, RadianEuler, ReadRelative,
-    Readable, ReadableRelative, Vector,
+    index_range, read_at, read_relative, read_single, FixedString, ModelError, Quaternion,
+    RadianEuler, ReadRelative, Readable, ReadableRelative, Vector,
 };
 use bitflags::bitflags;
 use bytemuck::{Pod, Zeroable};
-use cgmath::Matrix4;
+use cgmath::{InnerSpace, Matrix4, One};
 use std::mem::size_of;
+use std::ops::Range;
+use thiserror::Error;
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
@@ -44,15 +46,40 @@ impl ReadRelative for PoseParameterDescription {
     }
 }
 
+impl PoseParameterDescription {
+    /// Map a value in the parameter's range to `0..=1`
+    ///
+    /// Looping parameters are first wrapped into the range, other values are clamped.
+    pub fn normalize(&self, value: f32) -> f32 {
+        let value = if self.loop_range != 0.0 {
+            let wrap = (self.start + self.end) / 2.0 + self.loop_range / 2.0;
+            let shift = self.loop_range - wrap;
+            value - self.loop_range * ((value + shift) / self.loop_range).floor()
+        } else {
+            value
+        };
+        let range = self.end - self.start;
+        if range == 0.0 {
+            return 0.0;
+        }
+        ((value - self.start) / range).clamp(0.0, 1.0)
+    }
+
+    /// Map a normalized value back into the parameter's range
+    pub fn denormalize(&self, t: f32) -> f32 {
+        t * (self.end - self.start) + self.start
+    }
+}
+
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 pub struct AnimationDescriptionHeader {
     base_ptr: i32,
     name_offset: i32,
     fps: f32,
-    flags: i32,
+    flags: StudioAnimFlags,
 
-    frame_count: i32,
+    pub(crate) frame_count: i32,
 
     movement_count: i32,
     movement_offset: i32,
@@ -60,7 +87,7 @@ pub struct AnimationDescriptionHeader {
     _padding: [i32; 6],
 
     animation_block: i32,
-    animation_index: i32, // non-zero when anim data isn't in sections
+    pub(crate) animation_index: i32, // non-zero when anim data isn't in sections
 
     ik_rule_count: i32,
     ik_rule_offset: i32,
@@ -69,12 +96,12 @@ pub struct AnimationDescriptionHeader {
     local_hierarchy_count: i32,
     local_hierarchy_offset: i32,
 
-    section_offset: i32,
-    section_frames: i32,
+    pub(crate) section_offset: i32,
+    pub(crate) section_frames: i32,
 
-    zero_frame_span: i16,
-    zero_frame_count: i16,
-    zero_frame_offset: i32,
+    pub(crate) zero_frame_span: i16,
+    pub(crate) zero_frame_count: i16,
+    pub(crate) zero_frame_offset: i32,
 
     zero_frame_stall_time: f32,
 }
@@ -85,22 +112,55 @@ static_assertions::const_assert_eq!(size_of::<AnimationDescriptionHeader>(), 100
 pub struct AnimationDescription {
     pub name: String,
     pub fps: f32,
+    pub flags: StudioAnimFlags,
     pub frame_count: usize,
+    /// Time in seconds the first frame is held at the start of the playback
+    pub zero_frame_stall_time: f32,
+    /// Location of the animation data when it is stored in an animation block of the `.ani` file
+    ///
+    /// The [`animations`](Self::animations) of animations stored in a block are not loaded.
+    pub animation_block: Option<AnimationBlockLocation>,
     pub animations: Vec<Animation>,
 }
 
+/// Reasons two animations can't be concatenated
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ConcatError {
+    #[error("animation {name} is stored in an animation block, its animation data needs to be loaded first")]
+    AnimationBlock { name: String },
+    #[error("animations have different frame rates: {fps} and {other}")]
+    FrameRate { fps: f32, other: f32 },
+    #[error("bone {0} is only animated by one of the animations")]
+    MissingBone(BoneId),
+    #[error("bone {0} is animated differently by the animations")]
+    TrackMismatch(BoneId),
+}
+
+/// Location of animation data inside the `.ani` file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationBlockLocation {
+    /// Index into the animation blocks of the model
+    pub block: usize,
+    /// Offset of the animation data from the start of the block
+    pub offset: usize,
+}
+
 impl ReadRelative for AnimationDescription {
     type Header = AnimationDescriptionHeader;
 
     fn read(data: &[u8], header: Self::Header) -> Result<Self, ModelError> {
         let mut animations = Vec::with_capacity(1);
+        let animation_block = match usize::try_from(header.animation_block) {
+            Ok(0) | Err(_) => None,
+            Ok(block) => Some(AnimationBlockLocation {
+                block,
+                offset: header.animation_index.max(0) as usize,
+            }),
+        };
         let mut offset = header.animation_index as usize;
-        loop {
-            let (animation, next_offset) = if header.animation_block == 0 {
-                read_animation(data, offset, header.frame_count as usize)?
-            } else {
-                todo!("read animation from animation block");
-            };
+        while animation_block.is_none() {
+            let (animation, next_offset) =
+                read_animation(data, offset, header.frame_count.max(0) as usize)?;
             animations.push(animation);
             if next_offset == 0 {
                 break;
@@ -111,21 +171,416 @@ impl ReadRelative for AnimationDescription {
         Ok(AnimationDescription {
             name: read_single(data, header.name_offset)?,
             fps: header.fps,
+            flags: header.flags,
             frame_count: header.frame_count as usize,
+            zero_frame_stall_time: header.zero_frame_stall_time.max(0.0),
+            animation_block,
+            animations,
+        })
+    }
+}
+
+impl AnimationDescription {
+    /// Duration of a single playthrough of the animation in seconds
+    ///
+    /// Like the engine this is the time between the first and last frame, so an animation
+    /// with a single frame has a duration of 0
+    pub fn duration(&self) -> f32 {
+        if self.frame_count > 1 && self.fps > 0.0 {
+            (self.frame_count - 1) as f32 / self.fps
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.flags.contains(StudioAnimFlags::LOOPING)
+    }
+
+    /// Scale the positions of all bones in the animation
+    pub fn scale(&mut self, factor: f32) {
+        self.animations
+            .iter_mut()
+            .for_each(|animation| animation.scale(factor));
+    }
+
+    /// Which bones are affected by the animation and whether their tracks change over time
+    ///
+    /// Only bones with a track in the animation are included.
+    pub fn animated_bones(&self) -> impl Iterator<Item = BoneCoverage> + '_ {
+        self.animations.iter().map(|animation| BoneCoverage {
+            bone: animation.bone,
+            rotation: animation.rotation_data.track_kind(),
+            position: animation.position_data.track_kind(),
+        })
+    }
+
+    /// Statistics about the track data of the animation, to find animations that are expensive to store or play
+    pub fn stats(&self) -> AnimationStats {
+        let mut stats = AnimationStats {
+            bones: self.animations.len(),
+            ..AnimationStats::default()
+        };
+        for animation in &self.animations {
+            if animation.rotation_data.track_kind() == TrackKind::Animated
+                || animation.position_data.track_kind() == TrackKind::Animated
+            {
+                stats.animated_bones += 1;
+            }
+
+            stats.track_bytes += size_of::<AnimationHeader>()
+                + animation.rotation_data.size()
+                + animation.position_data.size();
+            match &animation.rotation_data {
+                RotationData::Quaternion48(_) | RotationData::Quaternion64(_) => {
+                    stats.raw_rotations += 1
+                }
+                RotationData::Animated(values) => {
+                    stats.compressed_rotations += 1;
+                    stats.track_bytes +=
+                        compressed_size(values, |value| [value.x, value.y, value.z]);
+                }
+                RotationData::Quaternions(values) => {
+                    stats.compressed_rotations += 1;
+                    stats.track_bytes +=
+                        compressed_size(values, |value| [value.x, value.y, value.z]);
+                }
+                RotationData::None => {}
+            }
+            match &animation.position_data {
+                PositionData::Vector48(_) => stats.raw_positions += 1,
+                PositionData::PositionValues(values) => {
+                    stats.compressed_positions += 1;
+                    stats.track_bytes +=
+                        compressed_size(values, |value| [value.x, value.y, value.z]);
+                }
+                PositionData::None => {}
+            }
+
+            if let Some(frames) = animation.rotation_data.frame_count() {
+                for frame in 1..frames {
+                    let previous: cgmath::Quaternion<f32> = animation.rotation(frame - 1).into();
+                    let current: cgmath::Quaternion<f32> = animation.rotation(frame).into();
+                    let angle = 2.0 * previous.dot(current).abs().min(1.0).acos();
+                    stats.max_rotation_delta = stats.max_rotation_delta.max(angle);
+                }
+            }
+            if let Some(frames) = animation.position_data.frame_count() {
+                for frame in 1..frames {
+                    let delta =
+                        (animation.position(frame) - animation.position(frame - 1)).length();
+                    stats.max_position_delta = stats.max_position_delta.max(delta);
+                }
+            }
+        }
+        stats
+    }
+
+    /// A new animation containing only the frames in `frames`
+    ///
+    /// The range is clamped to the frames of the animation. Events of sequences using the animation
+    /// can be kept in sync with [`AnimationSequence::trim_events`]. The animations of descriptions stored in an
+    /// animation block need to be loaded first, the result always stores its animations in the mdl.
+    pub fn slice(&self, frames: Range<usize>) -> AnimationDescription {
+        let end = frames.end.min(self.frame_count);
+        let frames = frames.start.min(end)..end;
+        AnimationDescription {
+            name: self.name.clone(),
+            fps: self.fps,
+            flags: self.flags,
+            frame_count: frames.len(),
+            zero_frame_stall_time: self.zero_frame_stall_time,
+            animation_block: None,
+            animations: self
+                .animations
+                .iter()
+                .map(|animation| animation.slice(frames.clone()))
+                .collect(),
+        }
+    }
+
+    /// A new animation playing this animation followed by `other`
+    ///
+    /// `blend_frames` frames interpolating from the last frame of this animation to the first frame of `other` are
+    /// inserted at the seam. Both animations need to have the same frame rate and need to have the same tracks
+    /// for the same bones, all tracks of the result are stored per frame.
+    pub fn concat(
+        &self,
+        other: &AnimationDescription,
+        blend_frames: usize,
+    ) -> Result<AnimationDescription, ConcatError> {
+        for description in [self, other] {
+            if description.animation_block.is_some() {
+                return Err(ConcatError::AnimationBlock {
+                    name: description.name.clone(),
+                });
+            }
+        }
+        if self.fps != other.fps {
+            return Err(ConcatError::FrameRate {
+                fps: self.fps,
+                other: other.fps,
+            });
+        }
+        if let Some(extra) = other
+            .animations
+            .iter()
+            .find(|track| !self.animations.iter().any(|own| own.bone == track.bone))
+        {
+            return Err(ConcatError::MissingBone(extra.bone));
+        }
+        let animations = self
+            .animations
+            .iter()
+            .map(|track| {
+                let other_track = other
+                    .animations
+                    .iter()
+                    .find(|other_track| other_track.bone == track.bone)
+                    .ok_or(ConcatError::MissingBone(track.bone))?;
+                track.concat(
+                    other_track,
+                    [self.frame_count, other.frame_count],
+                    blend_frames,
+                )
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(AnimationDescription {
+            name: self.name.clone(),
+            fps: self.fps,
+            flags: self.flags,
+            frame_count: self.frame_count + blend_frames + other.frame_count,
+            zero_frame_stall_time: self.zero_frame_stall_time,
+            animation_block: None,
             animations,
         })
     }
+
+    /// Sample all bone tracks at a point in time, in seconds
+    ///
+    /// See [`sample_frame`](Self::sample_frame) for how `bones` is used.
+    pub fn sample<'a>(
+        &'a self,
+        bones: &'a [Bone],
+        time: f32,
+        options: SampleOptions,
+    ) -> impl Iterator<Item = BoneSample> + 'a {
+        self.sample_frame(bones, time * self.fps, options)
+    }
+
+    /// Sample all bone tracks at a fractional frame
+    ///
+    /// Looping animations wrap around after [`duration`](Self::duration), like the engine the last frame of a
+    /// looping animation is expected to match the first frame. Other animations are clamped to the first and last frame.
+    ///
+    /// Tracks without rotation or position data use the rest pose from `bones`, or no rotation and position for
+    /// delta animations and bones that aren't in `bones`.
+    pub fn sample_frame<'a>(
+        &'a self,
+        bones: &'a [Bone],
+        frame: f32,
+        options: SampleOptions,
+    ) -> impl Iterator<Item = BoneSample> + 'a {
+        let looping = options.looping.unwrap_or_else(|| self.is_looping());
+        let frame = FrameSample::new(frame, self.frame_count, looping, options.interpolation);
+        let remove_loop_motion = looping && options.remove_loop_motion;
+        let delta = self.flags.contains(StudioAnimFlags::DELTA);
+        self.animations.iter().map(move |animation| {
+            let rest = bones.get(usize::from(animation.bone)).filter(|_| !delta);
+            let rest_position = || rest.map(Bone::rest_position).unwrap_or_default();
+            let mut position = match animation.position_data() {
+                PositionData::None => rest_position(),
+                _ => animation.sample_position(frame, remove_loop_motion),
+            };
+            if options.remove_root_motion == Some(animation.bone) {
+                let start = match animation.position_data() {
+                    PositionData::None => rest_position(),
+                    _ => animation.position(0),
+                };
+                position.x = start.x;
+                position.y = start.y;
+            }
+            let rotation = match animation.rotation_data() {
+                RotationData::None => rest
+                    .map(Bone::rest_rotation)
+                    .unwrap_or_else(|| cgmath::Quaternion::one().into()),
+                _ => animation.sample_rotation(frame),
+            };
+            BoneSample {
+                bone: animation.bone,
+                rotation,
+                position,
+            }
+        })
+    }
+}
+
+/// Options for sampling animations between frames
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SampleOptions {
+    /// Override the looping flag of the animation
+    pub looping: Option<bool>,
+    /// Remove the offset between the first and last frame of looping animations
+    ///
+    /// The offset is spread over the frames of the animation, so the loop seam doesn't jump back to the
+    /// starting position.
+    pub remove_loop_motion: bool,
+    /// Keep the horizontal position of a bone at its position in the first frame
+    ///
+    /// Set to the root bone to play locomotion animations in place, vertical movement of the bone is kept.
+    pub remove_root_motion: Option<BoneId>,
+    /// How to interpolate between frames
+    pub interpolation: Interpolation,
+}
+
+/// How values are interpolated between the frames of an animation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Use the closest frame
+    Nearest,
+    /// Interpolate linearly between the two surrounding frames
+    #[default]
+    Linear,
+    /// Interpolate with a Catmull-Rom spline through the four surrounding frames
+    CatmullRom,
+}
+
+/// The rotation and position of a single bone sampled from an animation
+#[derive(Debug, Clone, Copy)]
+pub struct BoneSample {
+    pub bone: BoneId,
+    pub rotation: Quaternion,
+    pub position: Vector,
+}
+
+impl BoneSample {
+    /// The rest pose of a bone
+    pub fn rest(bone: BoneId, data: &Bone) -> Self {
+        BoneSample {
+            bone,
+            rotation: data.rest_rotation(),
+            position: data.rest_position(),
+        }
+    }
+
+    /// Interpolate from this sample towards another sample by `weight`
+    pub fn blend(&self, other: &BoneSample, weight: f32) -> BoneSample {
+        let from = cgmath::Quaternion::from(self.rotation);
+        let to = cgmath::Quaternion::from(other.rotation);
+        let to = if from.dot(to) < 0.0 { -to } else { to };
+        BoneSample {
+            bone: self.bone,
+            rotation: from.slerp(to, weight).into(),
+            position: self.position + (other.position - self.position) * weight,
+        }
+    }
+}
+
+/// The frames surrounding a fractional frame and the interpolation factor between them
+#[derive(Debug, Clone, Copy)]
+struct FrameSample {
+    previous: usize,
+    frame: usize,
+    next: usize,
+    after: usize,
+    t: f32,
+    frame_count: usize,
+    interpolation: Interpolation,
+}
+
+impl FrameSample {
+    fn new(frame: f32, frame_count: usize, looping: bool, interpolation: Interpolation) -> Self {
+        let frame_count = frame_count.max(1);
+        let last = frame_count - 1;
+        // the last frame of a looping animation is the same as the first, so the animation loops every `last` frames
+        let looping = looping && last > 0;
+        let frame = if looping {
+            frame.rem_euclid(last as f32)
+        } else {
+            frame.clamp(0.0, last as f32)
+        };
+        let index = (frame.floor() as usize).min(last);
+        let step = |index: usize, forward: bool| match (looping, forward) {
+            (true, true) if index >= last => 1.min(last),
+            (true, false) if index == 0 => last.saturating_sub(1),
+            (_, true) => (index + 1).min(last),
+            (_, false) => index.saturating_sub(1),
+        };
+        let next = step(index, true);
+        FrameSample {
+            previous: step(index, false),
+            frame: index,
+            next,
+            after: step(next, true),
+            t: frame - index as f32,
+            frame_count,
+            interpolation,
+        }
+    }
+}
+
+#[derive(Zeroable, Pod, Copy, Clone, Debug, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[repr(C)]
+pub struct StudioAnimFlags(u32);
+
+bitflags! {
+    impl StudioAnimFlags: u32 {
+        /// ending frame should be the same as the starting frame
+        const LOOPING =         0x00001;
+        /// do not interpolate between previous animation and this one
+        const SNAP =            0x00002;
+        /// this animation "adds" to the base animations, not slerp blends
+        const DELTA =           0x00004;
+        /// always play this animation
+        const AUTOPLAY =        0x00008;
+        const POST =            0x00010;
+        /// this animation has no real animation data
+        const ALLZEROS =        0x00020;
+        /// animation is encoded as by frame x bone instead of RLE bone x frame
+        const FRAMEANIM =       0x00040;
+        /// cycle index is taken from a pose parameter index
+        const CYCLEPOSE =       0x00080;
+        /// cycle index is taken from a real-time clock, not the animations cycle index
+        const REALTIME =        0x00100;
+        /// animation has a local context
+        const LOCAL =           0x00200;
+        /// don't show in default selection views
+        const HIDDEN =          0x00400;
+        /// a forward declared animation (empty)
+        const OVERRIDE =        0x00800;
+        /// has been updated at runtime to activity index
+        const ACTIVITY =        0x01000;
+        /// has been updated at runtime to event index
+        const EVENT =           0x02000;
+        /// animation blends in worldspace
+        const WORLD =           0x04000;
+        /// do not force the animation loop
+        const NOFORCELOOP =     0x08000;
+        /// has been updated at runtime to event index on client
+        const EVENT_CLIENT =    0x10000;
+    }
 }
 
+/// Byte range of a block of animation data in the `.ani` file
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 pub struct AnimationBlock {
-    start: i32,
-    end: i32,
+    pub start: i32,
+    pub end: i32,
 }
 
 impl ReadableRelative for AnimationBlock {}
 
+impl AnimationBlock {
+    /// The bytes of the `.ani` file containing the block
+    pub fn range(&self) -> Range<usize> {
+        let start = self.start.max(0) as usize;
+        start..(self.end.max(0) as usize).max(start)
+    }
+}
+
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 pub struct AnimationHeader {
@@ -135,6 +590,7 @@ pub struct AnimationHeader {
 }
 
 #[derive(Zeroable, Pod, Copy, Clone, Debug)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct AnimationFlags(u8);
 
@@ -177,17 +633,26 @@ fn read_animation_values(
         if base_pointer == 0 {
             *out = 0.0;
         } else {
-            let header: ValueHeader = read_single(data, base_pointer)?;
             let values = FrameValues {
-                header,
-                data: &data[base_pointer as usize..],
+                data: value_data(data, base_pointer as usize)?,
             };
-            *out = values.get(frame as u8).map(|val| val as f32)?;
+            *out = values
+                .get(frame)
+                .map(|val| val as f32)
+                .map_err(|error| error.offset_by(base_pointer as usize))?;
         }
     }
     Ok(result)
 }
 
+/// The data starting at `offset`, erroring instead of panicking for truncated data
+fn value_data(data: &[u8], offset: usize) -> Result<&[u8], ModelError> {
+    data.get(offset..).ok_or(ModelError::OutOfBounds {
+        data: "animation values",
+        offset,
+    })
+}
+
 /// I hate this data structure
 ///
 /// Seems to be an array of
@@ -200,41 +665,84 @@ fn read_animation_values(
 /// each item containing `header.total` worth of frames (for frames larger than `header.valid` it re-uses the last valid data)
 /// when looking up frame `k` we skip through the list of values until we find the value range for the frame
 struct FrameValues<'a> {
-    header: ValueHeader,
-    data: &'a [u8], // data starting at self.header
+    data: &'a [u8], // data starting at the first header
 }
 
 impl FrameValues<'_> {
-    pub fn get(&self, index: u8) -> Result<i16, ModelError> {
-        if self.header.total <= index {
-            let offset_count = self.header.valid + 1;
-            let offset = (offset_count as usize) * size_of::<u16>();
-            let next_header: ValueHeader = read_single(self.data, offset)?;
-            let next = FrameValues {
-                header: next_header,
-                data: &self.data[offset..],
-            };
-            if next_header.total == 0 {
+    pub fn get(&self, mut index: usize) -> Result<i16, ModelError> {
+        let mut offset = 0;
+        let mut header: ValueHeader = read_single(self.data, offset)?;
+        while usize::from(header.total) <= index {
+            index -= usize::from(header.total);
+            offset += (usize::from(header.valid) + 1) * size_of::<u16>();
+            header = read_single(self.data, offset)?;
+            if header.total == 0 {
                 return Ok(0);
             }
-            next.get(index - self.header.total)
+        }
+        let offset_count = if usize::from(header.valid) > index {
+            index + 1
         } else {
-            let offset_count = if self.header.valid > index {
-                index + 1
-            } else {
-                self.header.valid
+            usize::from(header.valid)
+        };
+        read_single(self.data, offset + offset_count * size_of::<u16>())
+    }
+
+    /// Total number of frames covered by the runs, reading runs until they cover at least `frames` frames
+    fn run_total(&self, frames: usize) -> usize {
+        let mut data = self.data;
+        let mut total = 0;
+        while total < frames {
+            let Ok(header) = read_single::<ValueHeader, _>(data, 0) else {
+                break;
             };
-            let offset = (offset_count as usize) * size_of::<u16>();
-            read_single(self.data, offset)
+            if header.total == 0 {
+                break;
+            }
+            total += usize::from(header.total);
+            let offset = (usize::from(header.valid) + 1) * size_of::<u16>();
+            let Some(next) = data.get(offset..) else {
+                break;
+            };
+            data = next;
         }
+        total
     }
 }
 
+/// Number of frames covered by the compressed values of a track, `None` if no axis has compressed values
+///
+/// When the axes disagree, the first one that doesn't cover exactly `frames` frames is used.
+fn run_total(
+    data: &[u8], // data starting at the AnimationValuePointer
+    frames: usize,
+    base_pointers: AnimationValuePointer,
+) -> Option<usize> {
+    let totals = base_pointers
+        .0
+        .into_iter()
+        .filter(|base_pointer| *base_pointer != 0)
+        .map(|base_pointer| {
+            value_data(data, base_pointer as usize)
+                .map(|data| FrameValues { data }.run_total(frames))
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    totals
+        .iter()
+        .copied()
+        .find(|total| *total != frames)
+        .or(totals.first().copied())
+}
+
+#[non_exhaustive]
 #[derive(Clone, Debug)]
 pub enum RotationData {
     Quaternion48(Quaternion),
     Quaternion64(Quaternion),
     Animated(Vec<RadianEuler>),
+    /// Per frame rotations that aren't stored in the file but computed from other animation data
+    Quaternions(Vec<Quaternion>),
     None,
 }
 
@@ -260,6 +768,15 @@ impl From<Vec<RadianEuler>> for RotationData {
 }
 
 impl RotationData {
+    pub fn track_kind(&self) -> TrackKind {
+        match self {
+            RotationData::Quaternion48(_) | RotationData::Quaternion64(_) => TrackKind::Constant,
+            RotationData::Animated(values) => TrackKind::from_values(values),
+            RotationData::Quaternions(values) => TrackKind::from_values(values),
+            RotationData::None => TrackKind::None,
+        }
+    }
+
     pub fn rotation(&self, frame: usize) -> Quaternion {
         match self {
             RotationData::Quaternion48(q) => *q,
@@ -269,19 +786,46 @@ impl RotationData {
                 .copied()
                 .unwrap_or_else(|| values.last().copied().unwrap_or_default())
                 .into(),
+            RotationData::Quaternions(values) => values
+                .get(frame)
+                .or_else(|| values.last())
+                .copied()
+                .unwrap_or_default(),
             RotationData::None => Quaternion::default(),
         }
     }
 
+    /// Number of frames stored in the track, `None` for constant rotations
+    pub fn frame_count(&self) -> Option<usize> {
+        match self {
+            RotationData::Animated(values) => Some(values.len()),
+            RotationData::Quaternions(values) => Some(values.len()),
+            _ => None,
+        }
+    }
+
     pub fn size(&self) -> usize {
         match self {
             RotationData::Quaternion48(_) => size_of::<Quaternion48>(),
             RotationData::Quaternion64(_) => size_of::<Quaternion64>(),
-            RotationData::Animated(_) => size_of::<AnimationValuePointer>(),
+            RotationData::Animated(_) | RotationData::Quaternions(_) => {
+                size_of::<AnimationValuePointer>()
+            }
             RotationData::None => 0,
         }
     }
 
+    /// The track with only the frames in `frames`, constant rotations are kept as is
+    pub fn slice(&self, frames: Range<usize>) -> Self {
+        match self {
+            RotationData::Animated(values) => RotationData::Animated(slice_frames(values, frames)),
+            RotationData::Quaternions(values) => {
+                RotationData::Quaternions(slice_frames(values, frames))
+            }
+            _ => self.clone(),
+        }
+    }
+
     fn set_scale(&mut self, scale: RadianEuler) {
         if let RotationData::Animated(values) = self {
             values.iter_mut().for_each(|value| {
@@ -307,6 +851,106 @@ impl RotationData {
     }
 }
 
+/// How a single property of a bone is affected by an animation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackKind {
+    /// The animation doesn't contain the property, the rest pose of the bone is used
+    None,
+    /// The same value is used for every frame
+    Constant,
+    /// The value changes between frames
+    Animated,
+}
+
+impl TrackKind {
+    fn from_values<T: Pod>(values: &[T]) -> Self {
+        let constant = values
+            .windows(2)
+            .all(|pair| bytemuck::bytes_of(&pair[0]) == bytemuck::bytes_of(&pair[1]));
+        if constant {
+            TrackKind::Constant
+        } else {
+            TrackKind::Animated
+        }
+    }
+}
+
+/// The tracks an animation has for a bone, as returned by [`AnimationDescription::animated_bones`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoneCoverage {
+    pub bone: BoneId,
+    pub rotation: TrackKind,
+    pub position: TrackKind,
+}
+
+impl BoneCoverage {
+    /// Whether the bone moves during the animation
+    pub fn is_animated(&self) -> bool {
+        self.rotation == TrackKind::Animated || self.position == TrackKind::Animated
+    }
+
+    /// Whether the bone is posed differently from its rest pose
+    pub fn is_touched(&self) -> bool {
+        self.rotation != TrackKind::None || self.position != TrackKind::None
+    }
+}
+
+/// Statistics about the track data of an animation, as returned by [`AnimationDescription::stats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AnimationStats {
+    /// Number of bones with a track in the animation
+    pub bones: usize,
+    /// Number of bones that move during the animation
+    pub animated_bones: usize,
+    /// Rotations stored as a single uncompressed quaternion
+    pub raw_rotations: usize,
+    /// Rotations stored as run length encoded values per frame
+    pub compressed_rotations: usize,
+    /// Positions stored as a single uncompressed vector
+    pub raw_positions: usize,
+    /// Positions stored as run length encoded values per frame
+    pub compressed_positions: usize,
+    /// Size of the track data in bytes
+    ///
+    /// The size of run length encoded values is estimated from the decoded frames the way the model compiler encodes them.
+    pub track_bytes: usize,
+    /// Largest rotation of a bone between two consecutive frames, in radians
+    pub max_rotation_delta: f32,
+    /// Largest distance a bone moves between two consecutive frames
+    pub max_position_delta: f32,
+}
+
+/// Size of the run length encoded values of a track
+///
+/// Every run is stored as a header followed by the distinct values, repeats of the last value of a run are
+/// only counted in the header. Components that are zero for every frame aren't stored.
+fn compressed_size<T, const N: usize>(values: &[T], components: impl Fn(&T) -> [f32; N]) -> usize {
+    let values: Vec<[f32; N]> = values.iter().map(components).collect();
+    (0..N)
+        .map(|component| {
+            let values: Vec<f32> = values.iter().map(|value| value[component]).collect();
+            if values.iter().all(|value| *value == 0.0) {
+                return 0;
+            }
+            let mut size = 0;
+            let mut start = 0;
+            while start < values.len() {
+                let mut end = start + 1;
+                while end < values.len() && end - start < 255 && values[end] != values[end - 1] {
+                    end += 1;
+                }
+                let valid = end - start;
+                while end < values.len() && end - start < 255 && values[end] == values[end - 1] {
+                    end += 1;
+                }
+                size += size_of::<ValueHeader>() + valid * size_of::<u16>();
+                start = end;
+            }
+            size
+        })
+        .sum()
+}
+
 #[derive(Clone, Debug)]
 pub enum PositionData {
     Vector48(Vector48),
@@ -315,6 +959,14 @@ pub enum PositionData {
 }
 
 impl PositionData {
+    pub fn track_kind(&self) -> TrackKind {
+        match self {
+            PositionData::Vector48(_) => TrackKind::Constant,
+            PositionData::PositionValues(values) => TrackKind::from_values(values),
+            PositionData::None => TrackKind::None,
+        }
+    }
+
     pub fn position(&self, frame: usize) -> Vector {
         match self {
             PositionData::Vector48(vector) => Vector::from(*vector),
@@ -323,6 +975,43 @@ impl PositionData {
         }
     }
 
+    /// Number of frames stored in the track, `None` for constant positions
+    pub fn frame_count(&self) -> Option<usize> {
+        match self {
+            PositionData::PositionValues(values) => Some(values.len()),
+            _ => None,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            PositionData::Vector48(_) => size_of::<Vector48>(),
+            PositionData::PositionValues(_) => size_of::<AnimationValuePointer>(),
+            PositionData::None => 0,
+        }
+    }
+
+    /// The track with only the frames in `frames`, constant positions are kept as is
+    pub fn slice(&self, frames: Range<usize>) -> Self {
+        match self {
+            PositionData::PositionValues(values) => {
+                PositionData::PositionValues(slice_frames(values, frames))
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// Scale all positions in the track
+    pub fn scale(&mut self, factor: f32) {
+        match self {
+            PositionData::Vector48(vector) => *vector = (Vector::from(*vector) * factor).into(),
+            PositionData::PositionValues(values) => {
+                values.iter_mut().for_each(|value| *value = *value * factor)
+            }
+            PositionData::None => {}
+        }
+    }
+
     fn set_scale(&mut self, scale: Vector) {
         if let PositionData::PositionValues(values) = self {
             values.iter_mut().for_each(|value| {
@@ -343,53 +1032,257 @@ pub struct Animation {
     pub flags: AnimationFlags,
     rotation_data: RotationData,
     position_data: PositionData,
+    /// Reference rotation decoded rotations are aligned to, taken from the bone
+    alignment: Option<Quaternion>,
+    /// Frames covered by the compressed rotation and position values in the file
+    run_totals: [Option<usize>; 2],
 }
 
 impl Animation {
+    pub fn new(
+        bone: BoneId,
+        flags: AnimationFlags,
+        rotation_data: RotationData,
+        position_data: PositionData,
+    ) -> Self {
+        Animation {
+            bone,
+            flags,
+            rotation_data,
+            position_data,
+            alignment: None,
+            run_totals: [None; 2],
+        }
+    }
+
     pub fn rotation(&self, frame: usize) -> Quaternion {
-        self.rotation_data.rotation(frame)
+        let rotation = self.rotation_data.rotation(frame);
+        match self.alignment {
+            Some(alignment) => rotation.aligned(alignment),
+            None => rotation,
+        }
     }
 
     pub fn position(&self, frame: usize) -> Vector {
         self.position_data.position(frame)
     }
 
+    pub fn rotation_data(&self) -> &RotationData {
+        &self.rotation_data
+    }
+
+    pub fn position_data(&self) -> &PositionData {
+        &self.position_data
+    }
+
+    /// Number of frames covered by the compressed rotation and position values as stored in the file
+    ///
+    /// `None` for tracks that aren't stored as compressed values or weren't read from a file.
+    /// The decoded tracks always have the frame count of the animation, frames missing from the file are filled
+    /// with the last stored value.
+    pub fn stored_frame_counts(&self) -> [Option<usize>; 2] {
+        self.run_totals
+    }
+
     pub fn transform(&self, frame: usize) -> Matrix4<f32> {
         Matrix4::from_translation(self.position(frame).into()) * Matrix4::from(self.rotation(frame))
     }
 
+    /// Scale the positions of the animation
+    pub fn scale(&mut self, factor: f32) {
+        self.position_data.scale(factor);
+    }
+
+    /// The animation with only the frames in `frames`
+    pub fn slice(&self, frames: Range<usize>) -> Animation {
+        Animation {
+            rotation_data: self.rotation_data.slice(frames.clone()),
+            position_data: self.position_data.slice(frames),
+            run_totals: [None; 2],
+            ..self.clone()
+        }
+    }
+
+    /// The animation followed by `other`, with `blend_frames` interpolated frames in between
+    fn concat(
+        &self,
+        other: &Animation,
+        [frames, other_frames]: [usize; 2],
+        blend_frames: usize,
+    ) -> Result<Animation, ConcatError> {
+        let delta = AnimationFlags::STUDIO_ANIM_DELTA;
+        if self.flags.contains(delta) != other.flags.contains(delta)
+            || matches!(self.rotation_data, RotationData::None)
+                != matches!(other.rotation_data, RotationData::None)
+            || matches!(self.position_data, PositionData::None)
+                != matches!(other.position_data, PositionData::None)
+        {
+            return Err(ConcatError::TrackMismatch(self.bone));
+        }
+
+        let last = frames.saturating_sub(1);
+        let blend = |frame: usize| (frame + 1) as f32 / (blend_frames + 1) as f32;
+        let rotation_data = match self.rotation_data {
+            RotationData::None => RotationData::None,
+            _ => {
+                let end = cgmath::Quaternion::from(self.rotation(last));
+                let start = cgmath::Quaternion::from(other.rotation(0));
+                // take the shortest path between the rotations
+                let start = if end.dot(start) < 0.0 { -start } else { start };
+                RotationData::Quaternions(
+                    (0..frames)
+                        .map(|frame| self.rotation(frame))
+                        .chain((0..blend_frames).map(|frame| end.slerp(start, blend(frame)).into()))
+                        .chain((0..other_frames).map(|frame| other.rotation(frame)))
+                        .collect(),
+                )
+            }
+        };
+        let position_data = match self.position_data {
+            PositionData::None => PositionData::None,
+            _ => {
+                let end = self.position(last);
+                let start = other.position(0);
+                PositionData::PositionValues(
+                    (0..frames)
+                        .map(|frame| self.position(frame))
+                        .chain((0..blend_frames).map(|frame| end + (start - end) * blend(frame)))
+                        .chain((0..other_frames).map(|frame| other.position(frame)))
+                        .collect(),
+                )
+            }
+        };
+
+        let mut flags = self.flags & delta;
+        if !matches!(rotation_data, RotationData::None) {
+            flags |= AnimationFlags::STUDIO_ANIM_ANIMROT;
+        }
+        if !matches!(position_data, PositionData::None) {
+            flags |= AnimationFlags::STUDIO_ANIM_ANIMPOS;
+        }
+        Ok(Animation::new(
+            self.bone,
+            flags,
+            rotation_data,
+            position_data,
+        ))
+    }
+
+    fn sample_rotation(&self, sample: FrameSample) -> Quaternion {
+        let from = cgmath::Quaternion::from(self.rotation(sample.frame));
+        let to = cgmath::Quaternion::from(self.rotation(sample.next));
+        if sample.t == 0.0 || sample.frame == sample.next {
+            return from.into();
+        }
+        // take the shortest path between the rotations
+        let align = |q: cgmath::Quaternion<f32>| if from.dot(q) < 0.0 { -q } else { q };
+        let to = align(to);
+        match sample.interpolation {
+            Interpolation::Nearest if sample.t < 0.5 => from.into(),
+            Interpolation::Nearest => to.into(),
+            Interpolation::Linear => from.slerp(to, sample.t).into(),
+            Interpolation::CatmullRom => {
+                let previous = align(self.rotation(sample.previous).into());
+                let after = align(self.rotation(sample.after).into());
+                catmull_rom(previous, from, to, after, sample.t)
+                    .normalize()
+                    .into()
+            }
+        }
+    }
+
+    fn sample_position(&self, sample: FrameSample, remove_loop_motion: bool) -> Vector {
+        let loop_motion = if remove_loop_motion && sample.frame_count > 1 {
+            let first = self.position(0);
+            let last = self.position(sample.frame_count - 1);
+            (last - first) * (1.0 / (sample.frame_count - 1) as f32)
+        } else {
+            Vector::default()
+        };
+        let position = |frame: usize| self.position(frame) - loop_motion * frame as f32;
+        let from = position(sample.frame);
+        let to = position(sample.next);
+        match sample.interpolation {
+            Interpolation::Nearest if sample.t < 0.5 => from,
+            Interpolation::Nearest => to,
+            Interpolation::Linear => from + (to - from) * sample.t,
+            Interpolation::CatmullRom => {
+                let previous = position(sample.previous);
+                let after = position(sample.after);
+                catmull_rom(
+                    cgmath::Vector3::from(previous),
+                    from.into(),
+                    to.into(),
+                    after.into(),
+                    sample.t,
+                )
+                .into()
+            }
+        }
+    }
+
     pub(crate) fn apply_bone_data(&mut self, bone: &Bone) {
         self.rotation_data.set_scale(bone.rot_scale);
         if self.flags.contains(AnimationFlags::STUDIO_ANIM_DELTA) {
             self.rotation_data.set_base_rotation(bone.rot);
         }
         self.position_data.set_scale(bone.pos_scale);
+        self.alignment = Some(bone.q_alignment);
     }
 }
 
-fn read_animation(
+/// The values of the frames in the range, clamped to the stored frames
+fn slice_frames<T: Clone>(values: &[T], frames: Range<usize>) -> Vec<T> {
+    let end = frames.end.min(values.len());
+    values[frames.start.min(end)..end].to_vec()
+}
+
+/// Evaluate a uniform Catmull-Rom spline between `p1` and `p2`
+fn catmull_rom<V>(p0: V, p1: V, p2: V, p3: V, t: f32) -> V
+where
+    V: Copy
+        + std::ops::Add<Output = V>
+        + std::ops::Sub<Output = V>
+        + std::ops::Mul<f32, Output = V>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+pub(super) fn read_animation(
     data: &[u8],
     header_offset: usize,
     frames: usize,
 ) -> Result<(Animation, usize), ModelError> {
-    let data = data.get(header_offset..).ok_or(ModelError::OutOfBounds {
-        data: "animation data",
-        offset: header_offset,
-    })?;
+    read_at(data, header_offset, "animation data", |data| {
+        read_animation_data(data, frames)
+    })
+}
+
+/// Read an animation from the data starting at its header
+fn read_animation_data(data: &[u8], frames: usize) -> Result<(Animation, usize), ModelError> {
     let header = <AnimationHeader as Readable>::read(data)?;
 
     let offset = size_of::<AnimationHeader>();
 
+    let mut run_totals = [None; 2];
     let rotation_data = if header.flags.contains(AnimationFlags::STUDIO_ANIM_RAWROT) {
         RotationData::from(read_single::<Quaternion48, _>(data, offset)?)
     } else if header.flags.contains(AnimationFlags::STUDIO_ANIM_RAWROT2) {
         RotationData::from(read_single::<Quaternion64, _>(data, offset)?)
     } else if header.flags.contains(AnimationFlags::STUDIO_ANIM_ANIMROT) {
         let pointers: AnimationValuePointer = read_single(data, offset)?;
-        let value_data = &data[offset..];
+        let value_data = value_data(data, offset)?;
+        run_totals[0] = run_total(value_data, frames, pointers);
         let values: Vec<RadianEuler> = (0..frames)
             .map(|frame| read_animation_values(value_data, frame, pointers))
             .map(|r| r.map(|[y, z, x]| RadianEuler { x, z, y }))
+            .map(|r| r.map_err(|error| error.offset_by(offset)))
             .collect::<Result<_, ModelError>>()?;
         RotationData::from(values)
     } else {
@@ -401,10 +1294,12 @@ fn read_animation(
         PositionData::Vector48(read_single(data, position_offset)?)
     } else if header.flags.contains(AnimationFlags::STUDIO_ANIM_ANIMPOS) {
         let pointers: AnimationValuePointer = read_single(data, position_offset)?;
-        let value_data = &data[position_offset..];
+        let value_data = value_data(data, position_offset)?;
+        run_totals[1] = run_total(value_data, frames, pointers);
         let values = (0..frames)
             .map(|frame| read_animation_values(value_data, frame, pointers))
             .map(|r| r.map(Vector::from))
+            .map(|r| r.map_err(|error| error.offset_by(position_offset)))
             .collect::<Result<_, ModelError>>()?;
         PositionData::PositionValues(values)
     } else {
@@ -417,6 +1312,8 @@ fn read_animation(
             flags: header.flags,
             rotation_data,
             position_data,
+            alignment: None,
+            run_totals,
         },
         header.next_offset as usize,
     ))
@@ -428,13 +1325,13 @@ pub struct AnimationSequenceHeader {
     base: i32,
     label_index: i32,
     activity_name_index: i32,
-    flags: i32, // todo
+    flags: StudioAnimFlags,
     activity: i32,
     weight: i32,
     event_count: i32,
     event_offset: i32,
-    bounding_box_min: Vector,
-    bounding_box_max: Vector,
+    pub(crate) bounding_box_min: Vector,
+    pub(crate) bounding_box_max: Vector,
     blend_count: i32,
     animation_index_index: i32,
     movement_index: i32,
@@ -483,47 +1380,392 @@ pub struct AnimationSequenceHeader {
 }
 
 impl AnimationSequenceHeader {
-    fn bone_weight_indices(&self) -> impl Iterator<Item = usize> {
-        // weight/bone count isn't stored here, so we assume the next indexed values is stored after it in the file
-        // we trim down the list of weights later
-        let other_indices = [
-            self.pose_key_offset,
-            self.ik_lock_offset,
-            self.key_value_offset,
-            self.activity_modifiers_offset,
-        ];
-        let weight_count = if let Some(next_index) = other_indices
-            .iter()
-            .copied()
-            .find(|index| *index > self.weight_list_offset)
-        {
-            (next_index - self.weight_list_offset) as usize / size_of::<f32>()
+    fn animation_indices(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.animation_index_index,
+            self.group_size[0].max(0) * self.group_size[1].max(0),
+            size_of::<i16>(),
+        )
+    }
+
+    fn event_indices(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.event_offset,
+            self.event_count,
+            size_of::<AnimationEventHeader>(),
+        )
+    }
+
+    fn auto_layer_indices(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.auto_layer_offset,
+            self.auto_layer_count,
+            size_of::<AutoLayerHeader>(),
+        )
+    }
+
+    /// The pose keys of both blend axes are stored directly after each other
+    fn pose_key_indices(&self) -> impl Iterator<Item = usize> {
+        let count = if self.pose_key_offset > 0 {
+            self.group_size[0].max(0) + self.group_size[1].max(0)
         } else {
             0
         };
-        index_range(
-            self.weight_list_offset,
-            weight_count as i32,
-            size_of::<f32>(),
-        )
+        index_range(self.pose_key_offset, count, size_of::<f32>())
+    }
+
+    /// The weight list contains a weight for every bone of the model
+    pub(crate) fn bone_weight_indices(&self, bone_count: usize) -> impl Iterator<Item = usize> {
+        let count = if self.weight_list_offset > 0 {
+            bone_count as i32
+        } else {
+            0
+        };
+        index_range(self.weight_list_offset, count, size_of::<f32>())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct AnimationEventHeader {
+    cycle: f32,
+    event: i32,
+    ty: i32,
+    options: [u8; 64],
+    name_index: i32,
+}
+
+static_assertions::const_assert_eq!(size_of::<AnimationEventHeader>(), 80);
+
+/// An event triggered when playback of a sequence passes a point in the sequence
+#[derive(Debug, Clone)]
+pub struct AnimationEvent {
+    /// Point in the sequence at which the event triggers, from `0` to `1`
+    pub cycle: f32,
+    /// Numeric id of the event, for old style events
+    pub event: i32,
+    pub ty: i32,
+    pub options: FixedString<64>,
+    /// Name of the event, for new style events
+    pub name: String,
+}
+
+impl ReadRelative for AnimationEvent {
+    type Header = AnimationEventHeader;
+
+    fn read(data: &[u8], header: Self::Header) -> Result<Self, ModelError> {
+        Ok(AnimationEvent {
+            cycle: header.cycle,
+            event: header.event,
+            ty: header.ty,
+            options: header.options.try_into()?,
+            name: read_single(data, header.name_index)?,
+        })
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct AnimationSequence {
+    /// Name of the activity the sequence belongs to
     pub name: String,
     pub label: String,
+    pub flags: StudioAnimFlags,
+    /// Weight of the sequence when randomly picking a sequence for the activity
+    ///
+    /// A negative weight means the sequence keeps playing when it's already the current sequence.
+    pub activity_weight: i32,
+    /// Time in seconds the sequence takes to fade in when it replaces another sequence
+    pub fade_in_time: f32,
+    /// Time in seconds the sequence takes to fade out when it gets replaced
+    pub fade_out_time: f32,
+    /// Bounds of the model while playing the sequence, used for visibility culling
+    pub bounding_box: [Vector; 2],
+    /// Transition node the sequence starts at, sequences that move between nodes have a different entry and exit node
+    pub entry_node: i32,
+    /// Transition node the sequence ends at
+    pub exit_node: i32,
+    /// Flags for the transition, `1` if the sequence can be played in reverse to go from the exit to the entry node
+    pub node_flags: i32,
+    /// Cycle at which the sequence starts when entering it from another sequence
+    pub entry_phase: f32,
+    /// Cycle at which the sequence can be exited
+    pub exit_phase: f32,
+    /// Size of the blend grid, the animations are indexed as `[y * blend_size[0] + x]`
+    pub blend_size: [usize; 2],
+    /// Number of animations in the blend grid as stored in the header, should match the size of the grid
+    pub blend_count: usize,
+    /// Indices into the local animations for each point in the blend grid
+    pub animation_indices: Vec<i16>,
+    /// Value of the pose parameter at every column and row of the blend grid, empty if the sequence has no pose keys
+    pub pose_keys: [Vec<f32>; 2],
+    /// How much the sequence affects every bone, indexed by bone id
     pub bone_weights: Vec<f32>,
+    pub events: Vec<AnimationEvent>,
+    /// Sequences that are automatically layered on top of this sequence
+    pub auto_layers: Vec<AutoLayer>,
 }
 
 impl ReadRelative for AnimationSequence {
     type Header = AnimationSequenceHeader;
 
     fn read(data: &[u8], header: Self::Header) -> Result<Self, ModelError> {
+        let mut pose_keys: Vec<f32> = read_relative(data, header.pose_key_indices())?;
+        let row_keys =
+            pose_keys.split_off(pose_keys.len().min(header.group_size[0].max(0) as usize));
         Ok(AnimationSequence {
             name: read_single(data, header.activity_name_index)?,
             label: read_single(data, header.label_index)?,
-            bone_weights: read_relative(data, header.bone_weight_indices())?,
+            flags: header.flags,
+            activity_weight: header.weight,
+            fade_in_time: header.fade_in_time,
+            fade_out_time: header.fade_out_time,
+            bounding_box: [header.bounding_box_min, header.bounding_box_max],
+            entry_node: header.local_entry_node,
+            exit_node: header.local_exit_node,
+            node_flags: header.node_flags,
+            entry_phase: header.entry_phase,
+            exit_phase: header.exit_phase,
+            blend_size: header.group_size.map(|size| size.max(0) as usize),
+            blend_count: header.blend_count.max(0) as usize,
+            animation_indices: read_relative(data, header.animation_indices())?,
+            pose_keys: [pose_keys, row_keys],
+            // the weight list is read with the model, since the bone count is stored in the model header
+            bone_weights: Vec::new(),
+            events: read_relative(data, header.event_indices())?,
+            auto_layers: read_relative(data, header.auto_layer_indices())?,
+        })
+    }
+}
+
+#[derive(Zeroable, Pod, Copy, Clone, Debug)]
+#[repr(C)]
+pub struct AutoLayerHeader {
+    sequence: i16,
+    pose: i16,
+    flags: AutoLayerFlags,
+    start: f32,
+    peak: f32,
+    tail: f32,
+    end: f32,
+}
+
+static_assertions::const_assert_eq!(size_of::<AutoLayerHeader>(), 24);
+
+#[derive(Zeroable, Pod, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[repr(C)]
+pub struct AutoLayerFlags(u32);
+
+bitflags! {
+    impl AutoLayerFlags: u32 {
+        /// apply the layer after the other layers
+        const POST =    0x0010;
+        /// ease the ramps in and out
+        const SPLINE =  0x0040;
+        /// cross fade the layer with the other layers while ramping out
+        const XFADE =   0x0080;
+        /// use the ramp as the weight, ignoring the weight of the sequence
+        const NOBLEND = 0x0200;
+        /// apply the layer in the local context, after the other layers
+        const LOCAL =   0x1000;
+        /// the ramps are driven by a pose parameter instead of the cycle
+        const POSE =    0x4000;
+    }
+}
+
+/// A sequence that is automatically played as a layer on top of another sequence
+///
+/// The layer fades in from `start` to `peak`, stays at full weight until `tail` and fades out until `end`.
+/// These are cycles of the base sequence, or normalized values of the pose parameter for [`AutoLayerFlags::POSE`].
+#[derive(Debug, Clone)]
+pub struct AutoLayer {
+    /// Index of the layered sequence
+    pub sequence: i16,
+    /// Index of the pose parameter driving the layer
+    pub pose: i16,
+    pub flags: AutoLayerFlags,
+    pub start: f32,
+    pub peak: f32,
+    pub tail: f32,
+    pub end: f32,
+}
+
+impl ReadRelative for AutoLayer {
+    type Header = AutoLayerHeader;
+
+    fn read(_data: &[u8], header: Self::Header) -> Result<Self, ModelError> {
+        Ok(AutoLayer {
+            sequence: header.sequence,
+            pose: header.pose,
+            flags: header.flags,
+            start: header.start,
+            peak: header.peak,
+            tail: header.tail,
+            end: header.end,
         })
     }
 }
+
+impl AutoLayer {
+    /// Whether the layer is applied after the other layers
+    pub fn is_local(&self) -> bool {
+        self.flags.contains(AutoLayerFlags::LOCAL)
+    }
+
+    /// The weight and cycle of the layer when the base sequence is at `cycle` with `weight`
+    ///
+    /// `pose_parameters` are the normalized pose parameter values and `parameters` the descriptions of the model's pose parameters.
+    /// Layers driven by a pose parameter compare the value of the parameter in its own range against the ramp of the layer
+    /// and use the normalized value as the cycle.
+    /// Returns `None` when the layer is inactive.
+    pub fn evaluate(
+        &self,
+        cycle: f32,
+        weight: f32,
+        pose_parameters: &[f32],
+        parameters: &[PoseParameterDescription],
+    ) -> Option<(f32, f32)> {
+        if self.start == self.end {
+            return Some((weight, cycle));
+        }
+
+        let pose_driven = self.flags.contains(AutoLayerFlags::POSE);
+        let (index, pose_value) = if pose_driven {
+            let pose = usize::try_from(self.pose).ok();
+            let value = pose
+                .and_then(|pose| pose_parameters.get(pose))
+                .copied()
+                .unwrap_or_default();
+            let index = match pose.and_then(|pose| parameters.get(pose)) {
+                Some(parameter) => parameter.denormalize(value),
+                None => value,
+            };
+            (index, value)
+        } else {
+            (cycle, cycle)
+        };
+        if index < self.start || index >= self.end {
+            return None;
+        }
+
+        let mut scale = if index < self.peak && self.start != self.peak {
+            (index - self.start) / (self.peak - self.start)
+        } else if index > self.tail && self.end != self.tail {
+            (self.end - index) / (self.end - self.tail)
+        } else {
+            1.0
+        };
+        if self.flags.contains(AutoLayerFlags::SPLINE) {
+            scale = scale * scale * (3.0 - 2.0 * scale);
+        }
+        let weight = if self.flags.contains(AutoLayerFlags::XFADE) && index > self.tail {
+            (scale * weight) / (1.0 - weight + scale * weight)
+        } else if self.flags.contains(AutoLayerFlags::NOBLEND) {
+            scale
+        } else {
+            weight * scale
+        };
+        // pose driven layers use the value of the pose parameter as their cycle
+        let cycle = if pose_driven {
+            pose_value
+        } else {
+            (cycle - self.start) / (self.end - self.start)
+        };
+        Some((weight, cycle))
+    }
+}
+
+impl AnimationSequence {
+    pub fn is_looping(&self) -> bool {
+        self.flags.contains(StudioAnimFlags::LOOPING)
+    }
+
+    /// Whether the sequence moves between two different transition nodes
+    pub fn is_transition(&self) -> bool {
+        self.entry_node != self.exit_node
+    }
+
+    /// Change the cycle of every event to `cycle * scale + offset`
+    ///
+    /// Events that end up before the start or after the end of the sequence are removed,
+    /// the remaining events are kept sorted by cycle.
+    pub fn retime_events(&mut self, scale: f32, offset: f32) {
+        // allow for rounding errors for events at the very start or end
+        const TOLERANCE: f32 = 0.0001;
+        self.events.retain_mut(|event| {
+            let cycle = event.cycle * scale + offset;
+            event.cycle = cycle.clamp(0.0, 1.0);
+            (-TOLERANCE..=1.0 + TOLERANCE).contains(&cycle)
+        });
+        self.events.sort_by(|a, b| a.cycle.total_cmp(&b.cycle));
+    }
+
+    /// Update the event cycles for an animation of `frame_count` frames being trimmed to `frames`
+    ///
+    /// Events outside of the kept frames are removed.
+    pub fn trim_events(&mut self, frame_count: usize, frames: Range<usize>) {
+        let old_last = frame_count.saturating_sub(1) as f32;
+        let new_last = frames.len().saturating_sub(1) as f32;
+        let start = frames.start as f32;
+        if new_last > 0.0 {
+            self.retime_events(old_last / new_last, -start / new_last);
+        } else {
+            // a single frame is left, keep the events of that frame
+            self.events.retain(|event| {
+                let frame = event.cycle * old_last;
+                frame >= start && frame < start + 1.0
+            });
+            self.events.iter_mut().for_each(|event| event.cycle = 0.0);
+        }
+    }
+
+    /// The weights of this sequence and the sequence it replaces, `elapsed` seconds after the switch
+    ///
+    /// Like the engine, the new sequence fades in over its [`fade_in_time`](Self::fade_in_time) following an
+    /// ease-in-out curve while the previous sequence fades out by the same amount.
+    /// Returns the weights as `(previous, self)`.
+    pub fn transition_weights(&self, elapsed: f32) -> (f32, f32) {
+        let progress = if self.fade_in_time > 0.0 {
+            (elapsed / self.fade_in_time).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let weight = progress * progress * (3.0 - 2.0 * progress);
+        (1.0 - weight, weight)
+    }
+
+    /// Find the blend grid cell for a pose parameter value along an axis of the blend grid
+    ///
+    /// Returns the index of the first column or row of the cell and how far the value is between it and the next one.
+    /// The value is in the same units as the [`pose_keys`](Self::pose_keys), without pose keys the grid is assumed
+    /// to be spaced uniformly between `0` and `1`.
+    pub fn blend_cell(&self, axis: usize, value: f32) -> (usize, f32) {
+        let size = self.blend_size.get(axis).copied().unwrap_or_default();
+        if size < 2 {
+            return (0, 0.0);
+        }
+        let keys = self.pose_keys.get(axis).filter(|keys| keys.len() == size);
+        let key = |index: usize| match keys {
+            Some(keys) => keys[index],
+            None => index as f32 / (size - 1) as f32,
+        };
+        let cell = (0..size - 2)
+            .find(|index| value < key(index + 1))
+            .unwrap_or(size - 2);
+        let (start, end) = (key(cell), key(cell + 1));
+        let fraction = if end != start {
+            ((value - start) / (end - start)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (cell, fraction)
+    }
+
+    /// How much the sequence affects a bone, bones without a weight are fully affected
+    pub fn bone_weight(&self, bone: BoneId) -> f32 {
+        self.bone_weights
+            .get(usize::from(bone))
+            .copied()
+            .unwrap_or(1.0)
+    }
+}
@@ -1,13 +1,17 @@
+use crate::mdl::Mdl;
 use crate::{
-    read_single, ModelError, Quaternion, RadianEuler, ReadRelative, Readable, Transform3x4, Vector,
+    read_at, read_single, ModelError, Quaternion, RadianEuler, ReadRelative, Readable,
+    Transform3x4, Vector,
 };
 use bitflags::bitflags;
 use bytemuck::{Pod, Zeroable};
+use cgmath::Matrix4;
 use num_enum::TryFromPrimitive;
 use std::fmt::Display;
 use std::mem::size_of;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Zeroable, Pod, Default)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Zeroable, Pod, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(transparent)]
 pub struct BoneId(u8);
 
@@ -95,34 +99,37 @@ impl ReadRelative for Bone {
     type Header = BoneHeader;
 
     fn read(data: &[u8], header: Self::Header) -> Result<Self, ModelError> {
-        let prop_type = ProceduralBoneType::try_from(header.proc_type).ok();
-        let proc_bytes = (header.proc_index != 0)
+        let procedural_rules = (header.proc_type != 0 && header.proc_index != 0)
             .then(|| {
-                data.get(header.proc_index as usize..)
-                    .ok_or(ModelError::OutOfBounds {
-                        data: "bone surface property",
-                        offset: header.proc_index as usize,
-                    })
-            })
-            .transpose()?;
-        let procedural_rules = prop_type
-            .zip(proc_bytes)
-            .map(|(ty, bytes)| {
-                Result::<_, ModelError>::Ok(match ty {
-                    ProceduralBoneType::AxisInterp => {
-                        ProceduralBone::AxisInterp(AxisInterpBone::read(bytes)?)
-                    }
-                    ProceduralBoneType::QuaternionInterp => {
-                        ProceduralBone::QuaternionInterp(QuaternionInterpBone::read(bytes)?)
-                    }
-                    ProceduralBoneType::AiMatBone => {
-                        ProceduralBone::AiMatBone(AiMatBone::read(bytes)?)
-                    }
-                    ProceduralBoneType::AiMatAttach => {
-                        ProceduralBone::AiMatAttach(AiMatBone::read(bytes)?)
-                    }
-                    ProceduralBoneType::Jiggle => ProceduralBone::Jiggle(JiggleBone::read(bytes)?),
-                })
+                read_at(
+                    data,
+                    header.proc_index as usize,
+                    "procedural bone",
+                    |bytes| {
+                        Ok(match ProceduralBoneType::try_from(header.proc_type) {
+                            Ok(ProceduralBoneType::AxisInterp) => {
+                                ProceduralBone::AxisInterp(AxisInterpBone::read(bytes)?)
+                            }
+                            Ok(ProceduralBoneType::QuaternionInterp) => {
+                                ProceduralBone::QuaternionInterp(QuaternionInterpBone::read(bytes)?)
+                            }
+                            Ok(ProceduralBoneType::AiMatBone) => {
+                                ProceduralBone::AiMatBone(AiMatBone::read(bytes)?)
+                            }
+                            Ok(ProceduralBoneType::AiMatAttach) => {
+                                ProceduralBone::AiMatAttach(AiMatBone::read(bytes)?)
+                            }
+                            Ok(ProceduralBoneType::Jiggle) => {
+                                ProceduralBone::Jiggle(JiggleBone::read(bytes)?)
+                            }
+                            Err(_) => ProceduralBone::Unknown {
+                                ty: header.proc_type,
+                                raw: bytes[..bytes.len().min(ProceduralBone::UNKNOWN_SIZE)]
+                                    .to_vec(),
+                            },
+                        })
+                    },
+                )
             })
             .transpose()?;
 
@@ -154,7 +161,138 @@ impl ReadRelative for Bone {
     }
 }
 
+impl Bone {
+    /// Rest position of the bone relative to its parent
+    ///
+    /// Unlike [`pos`](Self::pos) this uses the same axis order as the position tracks of animations.
+    pub fn rest_position(&self) -> Vector {
+        Vector {
+            x: self.pos.y,
+            y: self.pos.z,
+            z: self.pos.x,
+        }
+    }
+
+    /// Rest rotation of the bone relative to its parent
+    pub fn rest_rotation(&self) -> Quaternion {
+        self.quaternion
+    }
+
+    /// Rest transform of the bone relative to its parent
+    ///
+    /// The bone is rotated by [`rest_rotation`](Self::rest_rotation) first and then moved by
+    /// [`rest_position`](Self::rest_position), `T * R`. Combined with the transform of the parent as
+    /// `parent * local` this gives the [`world_transform`](Self::world_transform).
+    pub fn local_transform(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.rest_position().into()) * Matrix4::from(self.rest_rotation())
+    }
+
+    /// Rest transform of the bone in model space
+    ///
+    /// This is the inverse of [`pose_to_bone`](Self::pose_to_bone), transforming from the space of the bone into the
+    /// space the vertices of the model are stored in.
+    pub fn world_transform(&self, mdl: &Mdl) -> Matrix4<f32> {
+        let mut transform = self.local_transform();
+        let mut parent = self.parent;
+        // the bone count limits the depth for malformed models with a cycle in the hierarchy
+        for _ in 0..mdl.bones.len() {
+            let Some(bone) = mdl.bones.get(usize::from(parent)) else {
+                break;
+            };
+            transform = bone.local_transform() * transform;
+            parent = bone.parent;
+        }
+        transform
+    }
+
+    /// Set the rest position of the bone relative to its parent, in the same axis order as [`rest_position`](Self::rest_position)
+    pub fn set_rest_position(&mut self, position: Vector) {
+        self.pos = Vector {
+            x: position.z,
+            y: position.x,
+            z: position.y,
+        };
+    }
+
+    /// Set the rest rotation of the bone relative to its parent
+    pub fn set_rest_rotation(&mut self, rotation: Quaternion) {
+        self.quaternion = rotation;
+        self.rot = rotation.into();
+    }
+
+    /// Scale the rest pose and procedural rules of the bone
+    pub fn scale(&mut self, factor: f32) {
+        self.pos = self.pos * factor;
+        self.pos_scale = self.pos_scale * factor;
+        self.pose_to_bone.scale_translation(factor);
+        if let Some(rules) = self.procedural_rules.as_mut() {
+            rules.scale(factor);
+        }
+    }
+}
+
+impl ProceduralBone {
+    /// The size of the rules isn't stored, for unknown types as many bytes as the largest known rules are kept
+    const UNKNOWN_SIZE: usize = max_size(&[
+        size_of::<AxisInterpBone>(),
+        size_of::<QuaternionInterpBone>(),
+        size_of::<AiMatBone>(),
+        size_of::<JiggleBone>(),
+    ]);
+
+    /// The `proc_type` value of the rules
+    pub fn ty(&self) -> i32 {
+        match self {
+            ProceduralBone::AxisInterp(_) => ProceduralBoneType::AxisInterp as i32,
+            ProceduralBone::QuaternionInterp(_) => ProceduralBoneType::QuaternionInterp as i32,
+            ProceduralBone::AiMatBone(_) => ProceduralBoneType::AiMatBone as i32,
+            ProceduralBone::AiMatAttach(_) => ProceduralBoneType::AiMatAttach as i32,
+            ProceduralBone::Jiggle(_) => ProceduralBoneType::Jiggle as i32,
+            ProceduralBone::Unknown { ty, .. } => *ty,
+        }
+    }
+
+    /// Scale all distances in the procedural rules
+    pub fn scale(&mut self, factor: f32) {
+        match self {
+            ProceduralBone::AxisInterp(bone) => {
+                bone.position
+                    .iter_mut()
+                    .for_each(|pos| *pos = *pos * factor);
+            }
+            ProceduralBone::QuaternionInterp(bone) => bone.position = bone.position * factor,
+            ProceduralBone::AiMatBone(bone) | ProceduralBone::AiMatAttach(bone) => {
+                bone.base_position = bone.base_position * factor
+            }
+            ProceduralBone::Jiggle(bone) => {
+                bone.length *= factor;
+                bone.base_min_left *= factor;
+                bone.base_max_left *= factor;
+                bone.base_min_up *= factor;
+                bone.base_max_up *= factor;
+                bone.base_min_forward *= factor;
+                bone.base_max_forward *= factor;
+            }
+            // without knowing the layout, the distances can't be found
+            ProceduralBone::Unknown { .. } => {}
+        }
+    }
+}
+
+const fn max_size(sizes: &[usize]) -> usize {
+    let mut max = 0;
+    let mut i = 0;
+    while i < sizes.len() {
+        if sizes[i] > max {
+            max = sizes[i];
+        }
+        i += 1;
+    }
+    max
+}
+
 #[derive(Zeroable, Pod, Copy, Clone, Debug)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct BoneFlags(u32);
 
@@ -187,6 +325,18 @@ bitflags! {
     }
 }
 
+impl BoneFlags {
+    /// The `BONE_USED_BY_VERTEX_LODn` flag for a level of detail, empty for levels above 7
+    pub fn used_by_vertex_lod(lod: usize) -> Self {
+        if lod < 8 {
+            BoneFlags::from_bits_retain(BoneFlags::BONE_USED_BY_VERTEX_LOD0.bits() << lod)
+        } else {
+            BoneFlags::empty()
+        }
+    }
+}
+
+#[non_exhaustive]
 #[derive(Debug, Clone)]
 pub enum ProceduralBone {
     AxisInterp(AxisInterpBone),
@@ -194,6 +344,13 @@ pub enum ProceduralBone {
     AiMatBone(AiMatBone),
     AiMatAttach(AiMatBone),
     Jiggle(JiggleBone),
+    /// Rules of a type this crate doesn't know, kept as raw bytes starting at the rules offset
+    ///
+    /// Since the size isn't stored, `raw` might include trailing data that isn't part of the rules.
+    Unknown {
+        ty: i32,
+        raw: Vec<u8>,
+    },
 }
 
 #[derive(TryFromPrimitive, Copy, Clone)]
@@ -207,6 +364,7 @@ pub enum ProceduralBoneType {
 }
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct AxisInterpBone {
     pub control: i32,
@@ -216,6 +374,7 @@ pub struct AxisInterpBone {
 }
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct QuaternionInterpBone {
     /// 1 / radian angle of trigger influence
@@ -229,6 +388,7 @@ pub struct QuaternionInterpBone {
 }
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct AiMatBone {
     pub parent: i32,
@@ -239,6 +399,7 @@ pub struct AiMatBone {
 }
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct JiggleBone {
     pub flags: JiggleBoneFlags,
@@ -285,6 +446,7 @@ pub struct JiggleBone {
 }
 
 #[derive(Zeroable, Pod, Copy, Clone, Debug)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct JiggleBoneFlags(u32);
 
@@ -302,7 +464,8 @@ bitflags! {
     }
 }
 
-#[derive(Zeroable, Pod, Copy, Clone, Debug)]
+#[derive(Zeroable, Pod, Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct ContentFlags(u32);
 
@@ -427,6 +590,19 @@ pub struct BoneController {
     pub input_field: i32,
 }
 
+impl BoneController {
+    /// Scale the range of translating controllers
+    pub fn scale(&mut self, factor: f32) {
+        if matches!(
+            self.ty,
+            BoneControllerType::X | BoneControllerType::Y | BoneControllerType::Z
+        ) {
+            self.start *= factor;
+            self.end *= factor;
+        }
+    }
+}
+
 impl ReadRelative for BoneController {
     type Header = BoneControllerHeader;
 
@@ -0,0 +1,329 @@
+use super::animation::read_animation;
+use crate::compressed_vector::{Quaternion48, Quaternion64, Vector48};
+use crate::mdl::{
+    Animation, AnimationDescription, AnimationFlags, Bone, BoneId, PositionData, RotationData,
+    TrackKind,
+};
+use crate::{ModelError, RadianEuler, Vector};
+use std::mem::size_of;
+use thiserror::Error;
+
+/// Options for encoding animation tracks
+///
+/// With the default options only frames with identical quantized values are merged.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct CompressionOptions {
+    /// Maximum error in radians when merging rotation frames or storing a rotation as constant
+    pub rotation_tolerance: f32,
+    /// Maximum error in units when merging position frames or storing a position as constant
+    pub position_tolerance: f32,
+    /// Store constant rotations as 64 bit quaternions instead of 48 bit ones
+    pub high_precision_rotations: bool,
+}
+
+/// Reasons an animation can't be encoded
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum EncodeError {
+    #[error("the animation values for bone {bone} are too large, offset {offset} doesn't fit in 16 bits")]
+    ValueOffset { bone: BoneId, offset: usize },
+    #[error("the animation data for bone {bone} is {size} bytes, which doesn't fit in the 16 bit offset to the next bone")]
+    AnimationSize { bone: BoneId, size: usize },
+    #[error("the animation references bone {0} which doesn't exist")]
+    InvalidBone(BoneId),
+    #[error("animation {0} has no bone tracks")]
+    NoTracks(String),
+}
+
+impl AnimationDescription {
+    /// Encode the animations of all bones in the format used by the mdl file
+    ///
+    /// The animations of the bones are stored one after the other, linked by the offset to the next animation.
+    pub fn encode(
+        &self,
+        bones: &[Bone],
+        options: &CompressionOptions,
+    ) -> Result<Vec<u8>, EncodeError> {
+        if self.animations.is_empty() {
+            return Err(EncodeError::NoTracks(self.name.clone()));
+        }
+        let mut data = Vec::new();
+        let mut previous = None;
+        for animation in &self.animations {
+            let without_tracks = animation.rotation_data().track_kind() == TrackKind::None
+                && animation.position_data().track_kind() == TrackKind::None;
+            let encoded = match bones.get(usize::from(animation.bone)) {
+                Some(bone) => animation.encode(bone, options)?,
+                // animations without any bone tracks are stored as a single entry for bone 255
+                None if without_tracks => {
+                    vec![
+                        usize::from(animation.bone) as u8,
+                        animation.flags.bits(),
+                        0,
+                        0,
+                    ]
+                }
+                None => return Err(EncodeError::InvalidBone(animation.bone)),
+            };
+            if let Some((start, bone)) = previous.replace((data.len(), animation.bone)) {
+                let size = data.len() - start;
+                let next_offset =
+                    u16::try_from(size).map_err(|_| EncodeError::AnimationSize { bone, size })?;
+                data[start + 2..start + 4].copy_from_slice(&next_offset.to_le_bytes());
+            }
+            data.extend(encoded);
+        }
+        Ok(data)
+    }
+}
+
+impl Animation {
+    /// Encode the animation of a bone in the format used by the mdl file
+    ///
+    /// Tracks that stay within the tolerance are stored as a single constant value, other tracks are stored
+    /// as run length encoded frame values. The offset to the next animation in the returned data is left at `0`.
+    ///
+    /// [`Mdl::write`](crate::mdl::Mdl::write) uses the encoder for animations that changed, see
+    /// [`WriteOptions::compression`](crate::mdl::WriteOptions::compression).
+    /// The values are addressed with 16 bit offsets, tracks with too many values can't be encoded.
+    pub fn encode(
+        &self,
+        bone: &Bone,
+        options: &CompressionOptions,
+    ) -> Result<Vec<u8>, EncodeError> {
+        let mut flags = self.flags
+            & !(AnimationFlags::STUDIO_ANIM_RAWROT
+                | AnimationFlags::STUDIO_ANIM_RAWROT2
+                | AnimationFlags::STUDIO_ANIM_ANIMROT
+                | AnimationFlags::STUDIO_ANIM_RAWPOS
+                | AnimationFlags::STUDIO_ANIM_ANIMPOS);
+
+        let rotation = encode_rotation(self, bone, options);
+        let position = encode_position(self, bone, options);
+
+        // the value offsets are relative to the pointer, the values are stored after the pointers
+        let pointer_size = size_of::<[u16; 3]>();
+        let rotation_size = match &rotation {
+            EncodedRotation::None => 0,
+            EncodedRotation::Quaternion48(_) => size_of::<Quaternion48>(),
+            EncodedRotation::Quaternion64(_) => size_of::<Quaternion64>(),
+            EncodedRotation::Animated(_) => pointer_size,
+        };
+        let position_size = match &position {
+            EncodedPosition::None => 0,
+            EncodedPosition::Vector48(_) | EncodedPosition::Animated(_) => pointer_size,
+        };
+        let values_start = rotation_size + position_size;
+        let mut values = Vec::new();
+        let mut pointers = |pointer: usize, streams: [Vec<u8>; 3]| {
+            let mut offsets = [0u16; 3];
+            for (offset, stream) in offsets.iter_mut().zip(streams) {
+                if stream.is_empty() {
+                    continue;
+                }
+                let value_offset = values_start - pointer + values.len();
+                *offset = u16::try_from(value_offset).map_err(|_| EncodeError::ValueOffset {
+                    bone: self.bone,
+                    offset: value_offset,
+                })?;
+                values.extend(stream);
+            }
+            Ok(offsets)
+        };
+
+        let mut fixed = Vec::with_capacity(values_start);
+        match rotation {
+            EncodedRotation::None => {}
+            EncodedRotation::Quaternion48(q) => {
+                flags |= AnimationFlags::STUDIO_ANIM_RAWROT;
+                fixed.extend_from_slice(bytemuck::bytes_of(&q));
+            }
+            EncodedRotation::Quaternion64(q) => {
+                flags |= AnimationFlags::STUDIO_ANIM_RAWROT2;
+                fixed.extend_from_slice(bytemuck::bytes_of(&q));
+            }
+            EncodedRotation::Animated(streams) => {
+                flags |= AnimationFlags::STUDIO_ANIM_ANIMROT;
+                fixed.extend_from_slice(bytemuck::bytes_of(&pointers(0, streams)?));
+            }
+        }
+        match position {
+            EncodedPosition::None => {}
+            EncodedPosition::Vector48(vector) => {
+                flags |= AnimationFlags::STUDIO_ANIM_RAWPOS;
+                fixed.extend_from_slice(bytemuck::bytes_of(&vector));
+            }
+            EncodedPosition::Animated(streams) => {
+                flags |= AnimationFlags::STUDIO_ANIM_ANIMPOS;
+                let pointers = pointers(rotation_size, streams)?;
+                fixed.extend_from_slice(bytemuck::bytes_of(&pointers));
+            }
+        }
+
+        let mut data = vec![usize::from(self.bone) as u8, flags.bits(), 0, 0];
+        data.extend(fixed);
+        data.extend(values);
+        Ok(data)
+    }
+
+    /// Decode animation data as written by [`encode`](Self::encode)
+    pub fn decode(data: &[u8], bone: &Bone, frame_count: usize) -> Result<Animation, ModelError> {
+        let (mut animation, _) = read_animation(data, 0, frame_count)?;
+        animation.apply_bone_data(bone);
+        Ok(animation)
+    }
+}
+
+enum EncodedRotation {
+    None,
+    Quaternion48(Quaternion48),
+    Quaternion64(Quaternion64),
+    Animated([Vec<u8>; 3]),
+}
+
+enum EncodedPosition {
+    None,
+    Vector48(Vector48),
+    Animated([Vec<u8>; 3]),
+}
+
+fn encode_rotation(
+    animation: &Animation,
+    bone: &Bone,
+    options: &CompressionOptions,
+) -> EncodedRotation {
+    let eulers: Vec<RadianEuler> = match animation.rotation_data() {
+        RotationData::None => return EncodedRotation::None,
+        RotationData::Quaternion48(_) | RotationData::Quaternion64(_) => Vec::new(),
+        RotationData::Animated(values) => values.clone(),
+        RotationData::Quaternions(values) => {
+            values.iter().copied().map(RadianEuler::from).collect()
+        }
+    };
+
+    let tolerance = options.rotation_tolerance;
+    let first = eulers.first().copied().unwrap_or_default();
+    let constant = eulers.iter().all(|euler| {
+        (euler.x - first.x).abs() <= tolerance
+            && (euler.y - first.y).abs() <= tolerance
+            && (euler.z - first.z).abs() <= tolerance
+    });
+    if constant {
+        let rotation = animation.rotation_data().rotation(0);
+        return if options.high_precision_rotations {
+            EncodedRotation::Quaternion64(rotation.into())
+        } else {
+            EncodedRotation::Quaternion48(rotation.into())
+        };
+    }
+
+    // delta animations have the bone rotation added when decoding
+    let delta = animation.flags.contains(AnimationFlags::STUDIO_ANIM_DELTA);
+    let base = if delta {
+        bone.rot
+    } else {
+        RadianEuler::default()
+    };
+    let scale = bone.rot_scale;
+    // the values are stored in y, z, x order
+    let channel = |value: fn(&RadianEuler) -> f32, scale: f32, base: f32| {
+        let values: Vec<f32> = eulers.iter().map(|euler| value(euler) - base).collect();
+        encode_values(&values, scale, tolerance)
+    };
+    EncodedRotation::Animated([
+        channel(|euler| euler.y, scale.y, base.y),
+        channel(|euler| euler.z, scale.z, base.z),
+        channel(|euler| euler.x, scale.x, base.x),
+    ])
+}
+
+fn encode_position(
+    animation: &Animation,
+    bone: &Bone,
+    options: &CompressionOptions,
+) -> EncodedPosition {
+    let positions: &[Vector] = match animation.position_data() {
+        PositionData::None => return EncodedPosition::None,
+        PositionData::Vector48(vector) => return EncodedPosition::Vector48(*vector),
+        PositionData::PositionValues(values) => values,
+    };
+
+    let tolerance = options.position_tolerance;
+    let first = positions.first().copied().unwrap_or_default();
+    let constant = positions.iter().all(|position| {
+        (position.x - first.x).abs() <= tolerance
+            && (position.y - first.y).abs() <= tolerance
+            && (position.z - first.z).abs() <= tolerance
+    });
+    if constant {
+        return EncodedPosition::Vector48(first.into());
+    }
+
+    let scale = bone.pos_scale;
+    let channel = |value: fn(&Vector) -> f32, scale: f32| {
+        let values: Vec<f32> = positions.iter().map(value).collect();
+        encode_values(&values, scale, tolerance)
+    };
+    EncodedPosition::Animated([
+        channel(|position| position.x, scale.x),
+        channel(|position| position.y, scale.y),
+        channel(|position| position.z, scale.z),
+    ])
+}
+
+/// Quantize and run length encode the values of a single channel
+///
+/// Every run starts with the number of stored values and the number of frames covered by the run,
+/// frames after the stored values repeat the last stored value.
+/// Returns no data if all values quantize to `0`, which is stored as an empty pointer.
+fn encode_values(values: &[f32], scale: f32, tolerance: f32) -> Vec<u8> {
+    let quantized: Vec<i16> = values
+        .iter()
+        .map(|value| {
+            if scale == 0.0 {
+                0
+            } else {
+                (value / scale)
+                    .round()
+                    .clamp(i16::MIN as f32, i16::MAX as f32) as i16
+            }
+        })
+        .collect();
+    if quantized.iter().all(|value| *value == 0) {
+        return Vec::new();
+    }
+
+    let tolerance = if scale == 0.0 {
+        0.0
+    } else {
+        tolerance / scale.abs()
+    };
+    let close = |a: i16, b: i16| f32::from(a.abs_diff(b)) <= tolerance;
+
+    let mut data = Vec::new();
+    let mut index = 0;
+    while index < quantized.len() {
+        let mut run = vec![quantized[index]];
+        let mut total = 1u8;
+        index += 1;
+        while index < quantized.len() && total < u8::MAX {
+            let last = run[run.len() - 1];
+            if close(quantized[index], last) {
+                while index < quantized.len() && total < u8::MAX && close(quantized[index], last) {
+                    total += 1;
+                    index += 1;
+                }
+                break;
+            }
+            run.push(quantized[index]);
+            total += 1;
+            index += 1;
+        }
+        data.push(run.len() as u8);
+        data.push(total);
+        for value in run {
+            data.extend(value.to_le_bytes());
+        }
+    }
+    data
+}
@@ -4,19 +4,23 @@ use std::mem::size_of;
 
 pub const FILETYPE_ID: i32 = i32::from_be_bytes(*b"IDST");
 pub const MDL_VERSION: i32 = 48;
+/// The versions used by Respawn games, which can't be parsed as regular models
+pub const RESPAWN_VERSIONS: [i32; 2] = [52, 53];
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 pub struct StudioHeader {
     pub id: i32,
     pub version: i32,
-    checksum: [u8; 4], // This has to be the same in the phy and vtx files to load!
+    pub(crate) checksum: [u8; 4], // This has to be the same in the phy and vtx files to load!
     pub name: [u8; 64],
-    data_length: i32,
+    pub(crate) data_length: i32,
 
     pub eye_position: Vector, // Position of player viewpoint relative to model origin
     pub illumination_position: Vector, // Position (relative to model origin) used to calculate ambient light contribution and cubemap reflections for the entire model.
+    /// Bounds used for collision and movement, `hull_min`/`hull_max` in the engine
     pub bounding_box: [Vector; 2],
+    /// Bounds used for visibility culling, `view_bbmin`/`view_bbmax` in the engine
     pub view_bounding_box: [Vector; 2],
 
     pub flags: ModelFlags,
@@ -52,8 +56,8 @@ pub struct StudioHeader {
     // This offset points to a series of ints.
     // Each int value, in turn, is an offset relative to the start of this header/the-file,
     // At which there is a null-terminated string.
-    texture_dir_count: i32,
-    texture_dir_offset: i32,
+    pub(crate) texture_dir_count: i32,
+    pub(crate) texture_dir_offset: i32,
 
     // Each skin-family assigns a texture-id to a skin location
     pub(crate) skin_reference_count: i32,
@@ -171,6 +175,7 @@ pub struct StudioHeader {
 }
 
 #[derive(Zeroable, Pod, Copy, Clone, Debug)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct ModelFlags(u32);
 
@@ -200,16 +205,64 @@ bitflags! {
 }
 
 impl StudioHeader {
+    /// The bounds used for collision and movement, stored as `bounding_box`
+    pub fn hull_box(&self) -> [Vector; 2] {
+        self.bounding_box
+    }
+
+    /// The bounds used for visibility culling, stored as `view_bounding_box`
+    pub fn view_box(&self) -> [Vector; 2] {
+        self.view_bounding_box
+    }
+
     pub(crate) fn header2_index(&self) -> Option<usize> {
         (self.studio_hdr2_index > 0)
             .then_some(self.studio_hdr2_index)
             .and_then(|index| usize::try_from(index).ok())
     }
 
+    /// Scale for vertex animation deltas stored as fixed point numbers
+    ///
+    /// `None` if the deltas are stored as half floats.
+    pub fn vert_anim_fixed_point_scale(&self) -> Option<f32> {
+        self.flags
+            .contains(ModelFlags::VERT_ANIM_FIXED_POINT_SCALE)
+            .then_some(self.vert_anim_fixed_point_scale)
+    }
+
+    /// Checksum linking the mdl to the matching vtx, vvd and phy files
+    pub fn checksum(&self) -> [u8; 4] {
+        self.checksum
+    }
+
     pub fn bone_indexes(&self) -> impl Iterator<Item = usize> {
         index_range(self.bone_offset, self.bone_count, size_of::<BoneHeader>())
     }
 
+    /// Whether the model is a pre-release model using the legacy struct layouts
+    #[cfg(feature = "legacy")]
+    pub fn is_legacy(&self) -> bool {
+        self.version < FIRST_RELEASE_VERSION
+    }
+
+    #[cfg(feature = "legacy")]
+    pub fn legacy_bone_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.bone_offset,
+            self.bone_count,
+            size_of::<LegacyBoneHeader>(),
+        )
+    }
+
+    #[cfg(feature = "legacy")]
+    pub fn legacy_texture_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.texture_offset,
+            self.texture_count,
+            size_of::<LegacyTextureHeader>(),
+        )
+    }
+
     pub fn bone_controller_indexes(&self) -> impl Iterator<Item = usize> {
         index_range(
             self.bone_controller_offset,
@@ -291,7 +344,11 @@ impl StudioHeader {
     }
 
     pub fn flex_descriptor_indexes(&self) -> impl Iterator<Item = usize> {
-        index_range(self.flex_desc_index, self.flex_desc_count, 1)
+        index_range(
+            self.flex_desc_index,
+            self.flex_desc_count,
+            size_of::<FlexDescriptorHeader>(),
+        )
     }
 
     pub fn flex_controller_indexes(&self) -> impl Iterator<Item = usize> {
@@ -323,7 +380,11 @@ impl StudioHeader {
     }
 
     pub fn include_model_indexes(&self) -> impl Iterator<Item = usize> {
-        index_range(self.include_model_index, self.include_model_count, 1)
+        index_range(
+            self.include_model_index,
+            self.include_model_count,
+            size_of::<IncludeModelHeader>(),
+        )
     }
 
     pub fn animation_block_indexes(&self) -> impl Iterator<Item = usize> {
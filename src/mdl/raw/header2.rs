@@ -40,9 +40,16 @@ impl StudioHeader2 {
         self.bone_flex_driver_index..(self.bone_flex_driver_index + self.bone_flex_driver_count)
     }
 
+    /// Offset of the full model name, relative to the start of this header
+    pub(crate) fn name_index(&self) -> Option<usize> {
+        (self.sz_name_index > 0)
+            .then_some(self.sz_name_index)
+            .and_then(|index| usize::try_from(index).ok())
+    }
+
     pub fn max_eye_deflection(&self) -> f32 {
         if self.fl_max_exe_deflection == 0.0 {
-            (30.0f32).cos()
+            30.0f32.to_radians().cos()
         } else {
             self.fl_max_exe_deflection
         }
@@ -0,0 +1,109 @@
+//! Struct layouts used by pre-release models, before version 44
+//!
+//! Only the structs that differ from the current layout are defined here,
+//! they are converted to the current headers so the rest of the parsing is shared.
+
+use super::{BoneFlags, BoneHeader, ContentFlags, MeshTexture};
+use crate::{
+    read_at, ModelError, Quaternion, RadianEuler, ReadRelative, Readable, Transform3x4, Vector,
+};
+use bytemuck::{Pod, Zeroable};
+use std::any::type_name;
+use std::mem::size_of;
+
+/// The first version using the current struct layouts
+pub const FIRST_RELEASE_VERSION: i32 = 44;
+
+/// Bone layout without the reserved space at the end
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct LegacyBoneHeader {
+    pub sz_name_index: i32,
+    pub parent: i32,
+    pub bone_controller: [i32; 6],
+    pub pos: Vector,
+    pub quaternion: Quaternion,
+    pub rot: RadianEuler,
+    pub pos_scale: Vector,
+    pub rot_scale: [f32; 3],
+    pub pose_to_bone: Transform3x4,
+    pub q_alignment: Quaternion,
+    pub flags: BoneFlags,
+    pub proc_type: i32,
+    pub proc_index: i32,
+    pub physics_bone: i32,
+    pub surface_prop_idx: i32,
+    pub contents: ContentFlags,
+}
+
+static_assertions::const_assert_eq!(size_of::<LegacyBoneHeader>(), 184);
+
+impl From<LegacyBoneHeader> for BoneHeader {
+    fn from(legacy: LegacyBoneHeader) -> Self {
+        let mut header = BoneHeader::zeroed();
+        header.sz_name_index = legacy.sz_name_index;
+        header.parent = legacy.parent;
+        header.bone_controller = legacy.bone_controller;
+        header.pos = legacy.pos;
+        header.quaternion = legacy.quaternion;
+        header.rot = legacy.rot;
+        header.pos_scale = legacy.pos_scale;
+        header.rot_scale = legacy.rot_scale;
+        header.pose_to_bone = legacy.pose_to_bone;
+        header.q_alignment = legacy.q_alignment;
+        header.flags = legacy.flags;
+        header.proc_type = legacy.proc_type;
+        header.proc_index = legacy.proc_index;
+        header.physics_bone = legacy.physics_bone;
+        header.surface_prop_idx = legacy.surface_prop_idx;
+        header.contents = legacy.contents;
+        header
+    }
+}
+
+/// Texture layout with the texture dimensions stored in the mdl
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+#[allow(dead_code)]
+pub struct LegacyTextureHeader {
+    pub name_index: i32,
+    pub flags: i32,
+    pub width: f32,
+    pub height: f32,
+    pub world_units_per_u: f32,
+    pub world_units_per_v: f32,
+    pub material_ptr: i32,
+    pub client_material_ptr: i32,
+    _padding: [i32; 10],
+}
+
+static_assertions::const_assert_eq!(size_of::<LegacyTextureHeader>(), 72);
+
+impl From<LegacyTextureHeader> for MeshTexture {
+    fn from(legacy: LegacyTextureHeader) -> Self {
+        let mut header = MeshTexture::zeroed();
+        header.name_index = legacy.name_index;
+        header.flags = legacy.flags;
+        header.material_ptr = legacy.material_ptr;
+        header.client_material_ptr = legacy.client_material_ptr;
+        header
+    }
+}
+
+/// Read structs stored with a legacy header layout, converting the headers to the current layout
+pub(crate) fn read_legacy<L, T, I>(data: &[u8], indexes: I) -> Result<Vec<T>, ModelError>
+where
+    L: Readable,
+    T: ReadRelative,
+    T::Header: From<L>,
+    I: Iterator<Item = usize>,
+{
+    indexes
+        .map(|index| {
+            read_at(data, index, type_name::<T>(), |data| {
+                let header = L::read(data)?;
+                T::read(data, header.into())
+            })
+        })
+        .collect()
+}
@@ -1,16 +1,22 @@
 use crate::Vector;
-use crate::{index_range, FixedString, Transform3x4};
+use crate::{index_range, FixedString, ReadableRelative, Transform3x4};
 use bitflags::bitflags;
 use bytemuck::{Pod, Zeroable};
 use std::mem::size_of;
 
 mod animation;
 mod bones;
+mod compress;
 pub mod header;
 pub mod header2;
+#[cfg(feature = "legacy")]
+mod legacy;
 
 pub use animation::*;
 pub use bones::*;
+pub use compress::*;
+#[cfg(feature = "legacy")]
+pub use legacy::*;
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
@@ -57,8 +63,45 @@ impl ModelHeader {
     pub fn mesh_indexes(&self) -> impl Iterator<Item = usize> {
         index_range(self.mesh_index, self.mesh_count, size_of::<MeshHeader>())
     }
+
+    pub fn eyeball_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.eyeball_index,
+            self.eyeball_count,
+            size_of::<EyeballHeader>(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+#[allow(dead_code)]
+pub struct EyeballHeader {
+    pub name_index: i32,
+    pub bone: i32,
+    pub origin: Vector,
+    pub z_offset: f32,
+    pub radius: f32,
+    pub up: Vector,
+    pub forward: Vector,
+    pub texture: i32,
+    _unused1: i32,
+    pub iris_scale: f32,
+    _unused2: i32,
+    pub upper_flex_descriptors: [i32; 3],
+    pub lower_flex_descriptors: [i32; 3],
+    pub upper_targets: [f32; 3],
+    pub lower_targets: [f32; 3],
+    pub upper_lid_flex_descriptor: i32,
+    pub lower_lid_flex_descriptor: i32,
+    _unused3: [i32; 4],
+    pub non_facs: u8,
+    _unused4: [u8; 3],
+    _unused5: [i32; 7],
 }
 
+static_assertions::const_assert_eq!(size_of::<EyeballHeader>(), 172);
+
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 #[allow(dead_code)]
@@ -78,14 +121,20 @@ pub struct MeshHeader {
     pub vertex_index: i32,
     flex_count: i32,
     flex_index: i32,
-    material_type: i32,
-    material_param: i32,
-    mesh_id: i32,
-    center: Vector,
+    pub material_type: i32,
+    pub material_param: i32,
+    pub mesh_id: i32,
+    pub center: Vector,
     vertex_data: MeshVertexData,
     padding: [i32; 8],
 }
 
+impl MeshHeader {
+    pub fn flex_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(self.flex_index, self.flex_count, size_of::<FlexHeader>())
+    }
+}
+
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 #[allow(dead_code)]
@@ -95,6 +144,60 @@ pub struct MeshVertexData {
     lod_vertex_count: [i32; 8],
 }
 
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct FlexDescriptorHeader {
+    pub name_index: i32,
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+#[allow(dead_code)]
+pub struct FlexHeader {
+    pub descriptor: i32,
+    pub targets: [f32; 4],
+    vertex_count: i32,
+    vertex_index: i32,
+    pub pair: i32,
+    pub vertex_animation_type: u8,
+    _padding: [u8; 3],
+    _unused: [i32; 6],
+}
+
+static_assertions::const_assert_eq!(size_of::<FlexHeader>(), 60);
+
+impl FlexHeader {
+    /// Whether the vertex animations have an extra wrinkle delta
+    pub fn is_wrinkle(&self) -> bool {
+        self.vertex_animation_type == 1
+    }
+
+    pub fn vertex_indexes(&self) -> impl Iterator<Item = usize> {
+        let size = if self.is_wrinkle() {
+            size_of::<VertexAnimationHeader>() + size_of::<i16>()
+        } else {
+            size_of::<VertexAnimationHeader>()
+        };
+        index_range(self.vertex_index, self.vertex_count, size)
+    }
+}
+
+/// The deltas are either stored as half floats or as fixed point numbers,
+/// depending on [`ModelFlags::VERT_ANIM_FIXED_POINT_SCALE`](header::ModelFlags::VERT_ANIM_FIXED_POINT_SCALE)
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct VertexAnimationHeader {
+    pub index: u16,
+    pub speed: u8,
+    pub side: u8,
+    pub delta: [u16; 3],
+    pub normal_delta: [u16; 3],
+}
+
+static_assertions::const_assert_eq!(size_of::<VertexAnimationHeader>(), 16);
+
+impl ReadableRelative for VertexAnimationHeader {}
+
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 #[allow(dead_code)]
@@ -122,6 +225,7 @@ pub struct StudioAttachmentHeader {
 }
 
 #[derive(Zeroable, Pod, Copy, Clone, Debug)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct AttachmentFlags(i32);
 
@@ -134,6 +238,15 @@ bitflags! {
 
 static_assertions::const_assert_eq!(size_of::<StudioAttachmentHeader>(), 23 * 4);
 
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct IncludeModelHeader {
+    pub label_index: i32,
+    pub name_index: i32,
+}
+
+static_assertions::const_assert_eq!(size_of::<IncludeModelHeader>(), 8);
+
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 #[allow(dead_code)]
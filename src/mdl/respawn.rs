@@ -0,0 +1,167 @@
+//! Partial support for the models used by Respawn's Titanfall games, versions 52 and 53
+//!
+//! The geometry of these models is stored in external formats, only the header and the skeleton are parsed.
+//! Version 52 shares the start of its header and its bone layout with regular Source models,
+//! version 53 adds a name offset to the header and a scale and extra data to every bone.
+
+use super::{Bone, BoneFlags, BoneHeader, ContentFlags, StudioHeader};
+use crate::{
+    index_range, read_at, read_relative, FixedString, ModelError, Quaternion, RadianEuler,
+    ReadRelative, Readable, Transform3x4, Vector,
+};
+use bytemuck::{Pod, Zeroable};
+use std::any::type_name;
+use std::mem::size_of;
+
+type Result<T> = std::result::Result<T, ModelError>;
+
+/// The start of the version 53 header, up to the bones
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+#[allow(dead_code)]
+pub struct RespawnHeader53 {
+    pub id: i32,
+    pub version: i32,
+    pub checksum: [u8; 4],
+    pub name_index: i32,
+    pub name: [u8; 64],
+    data_length: i32,
+    pub eye_position: Vector,
+    pub illumination_position: Vector,
+    pub bounding_box: [Vector; 2],
+    pub view_bounding_box: [Vector; 2],
+    pub flags: i32,
+    bone_count: i32,
+    bone_offset: i32,
+}
+
+impl RespawnHeader53 {
+    pub fn bone_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.bone_offset,
+            self.bone_count,
+            size_of::<RespawnBoneHeader53>(),
+        )
+    }
+}
+
+/// Version 53 bone, the regular bone layout with a scale for every bone and extra per bone data
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+#[allow(dead_code)]
+pub struct RespawnBoneHeader53 {
+    pub sz_name_index: i32,
+    pub parent: i32,
+    pub bone_controller: [i32; 6],
+    pub pos: Vector,
+    pub quaternion: Quaternion,
+    pub rot: RadianEuler,
+    pub scale: Vector,
+    pub pos_scale: Vector,
+    pub rot_scale: [f32; 3],
+    pub scale_scale: Vector,
+    pub pose_to_bone: Transform3x4,
+    pub q_alignment: Quaternion,
+    pub flags: BoneFlags,
+    pub proc_type: i32,
+    pub proc_index: i32,
+    pub physics_bone: i32,
+    pub surface_prop_idx: i32,
+    pub contents: ContentFlags,
+    surface_prop_lookup: i32,
+    collision_index: i16,
+    collision_count: i16,
+    _unused: [i32; 7],
+}
+
+static_assertions::const_assert_eq!(size_of::<RespawnBoneHeader53>(), 244);
+
+impl From<RespawnBoneHeader53> for BoneHeader {
+    fn from(respawn: RespawnBoneHeader53) -> Self {
+        let mut header = BoneHeader::zeroed();
+        header.sz_name_index = respawn.sz_name_index;
+        header.parent = respawn.parent;
+        header.bone_controller = respawn.bone_controller;
+        header.pos = respawn.pos;
+        header.quaternion = respawn.quaternion;
+        header.rot = respawn.rot;
+        header.pos_scale = respawn.pos_scale;
+        header.rot_scale = respawn.rot_scale;
+        header.pose_to_bone = respawn.pose_to_bone;
+        header.q_alignment = respawn.q_alignment;
+        header.flags = respawn.flags;
+        header.proc_type = respawn.proc_type;
+        header.proc_index = respawn.proc_index;
+        header.physics_bone = respawn.physics_bone;
+        header.surface_prop_idx = respawn.surface_prop_idx;
+        header.contents = respawn.contents;
+        header
+    }
+}
+
+/// The parts of a Respawn model that can be parsed
+#[derive(Debug, Clone)]
+pub struct RespawnMdl {
+    pub version: i32,
+    pub checksum: [u8; 4],
+    pub name: FixedString<64>,
+    pub eye_position: Vector,
+    pub illumination_position: Vector,
+    pub hull_box: [Vector; 2],
+    pub view_box: [Vector; 2],
+    pub flags: i32,
+    pub bones: Vec<Bone>,
+    /// The scale of every bone in its rest pose, version 52 doesn't store bone scales and uses `1` for every bone
+    pub bone_scales: Vec<Vector>,
+}
+
+impl RespawnMdl {
+    pub fn read(data: &[u8]) -> Result<Self> {
+        let version = <[i32; 2] as Readable>::read(data)?[1];
+        match version {
+            52 => {
+                let header = <StudioHeader as Readable>::read(data)?;
+                let bones: Vec<Bone> = read_relative(data, header.bone_indexes())?;
+                Ok(RespawnMdl {
+                    version,
+                    checksum: header.checksum(),
+                    name: header.name.try_into()?,
+                    eye_position: header.eye_position,
+                    illumination_position: header.illumination_position,
+                    hull_box: header.bounding_box,
+                    view_box: header.view_bounding_box,
+                    flags: header.flags.bits() as i32,
+                    bone_scales: vec![Vector::from([1.0; 3]); bones.len()],
+                    bones,
+                })
+            }
+            53 => {
+                let header = <RespawnHeader53 as Readable>::read(data)?;
+                let (bones, bone_scales) = header
+                    .bone_indexes()
+                    .map(|index| {
+                        read_at(data, index, type_name::<Bone>(), |data| {
+                            let bone_header = <RespawnBoneHeader53 as Readable>::read(data)?;
+                            Ok((Bone::read(data, bone_header.into())?, bone_header.scale))
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?
+                    .into_iter()
+                    .unzip();
+                Ok(RespawnMdl {
+                    version,
+                    checksum: header.checksum,
+                    name: header.name.try_into()?,
+                    eye_position: header.eye_position,
+                    illumination_position: header.illumination_position,
+                    hull_box: header.bounding_box,
+                    view_box: header.view_bounding_box,
+                    flags: header.flags,
+                    bones,
+                    bone_scales,
+                })
+            }
+            version => Err(ModelError::UnsupportedVersion(version)),
+        }
+    }
+}
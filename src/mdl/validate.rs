@@ -0,0 +1,123 @@
+use crate::mdl::{BoneId, Mdl, StudioAttachment};
+use thiserror::Error;
+
+/// Inconsistencies in a parsed mdl that don't prevent parsing but will lead to incorrect results
+#[derive(Debug, Clone, Error)]
+pub enum ValidationError {
+    #[error("{track} track for bone {bone} in animation {animation} has {frames} frames, expected {expected}")]
+    TrackLength {
+        animation: String,
+        bone: BoneId,
+        track: &'static str,
+        frames: usize,
+        expected: usize,
+    },
+    #[error("sequence {sequence} has {count} animations for a {width}x{height} blend grid")]
+    BlendGridSize {
+        sequence: String,
+        count: usize,
+        width: usize,
+        height: usize,
+    },
+    #[error("sequence {sequence} references animation {index} but only {count} animations exist")]
+    AnimationOutOfRange {
+        sequence: String,
+        index: i16,
+        count: usize,
+    },
+    #[error("attachment {attachment} is attached to bone {bone} but only {count} bones exist")]
+    AttachmentBoneOutOfRange {
+        attachment: String,
+        bone: i32,
+        count: usize,
+    },
+}
+
+impl Mdl {
+    /// Check the parsed data for internal consistency
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        self.validate_track_lengths(&mut errors);
+        self.validate_blend_grids(&mut errors);
+        self.validate_attachments(&mut errors);
+        errors
+    }
+
+    fn validate_track_lengths(&self, errors: &mut Vec<ValidationError>) {
+        for description in &self.local_animations {
+            for animation in &description.animations {
+                // the decoded tracks are padded to the frame count, so check the frames stored in the file
+                let [rotation_frames, position_frames] = animation.stored_frame_counts();
+                let tracks = [
+                    (
+                        "rotation",
+                        rotation_frames.or(animation.rotation_data().frame_count()),
+                    ),
+                    (
+                        "position",
+                        position_frames.or(animation.position_data().frame_count()),
+                    ),
+                ];
+                for (track, frames) in tracks {
+                    match frames {
+                        Some(frames) if frames != description.frame_count => {
+                            errors.push(ValidationError::TrackLength {
+                                animation: description.name.clone(),
+                                bone: animation.bone,
+                                track,
+                                frames,
+                                expected: description.frame_count,
+                            })
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    fn validate_blend_grids(&self, errors: &mut Vec<ValidationError>) {
+        for sequence in &self.animation_sequences {
+            let [width, height] = sequence.blend_size;
+            // the animation indices are read for the full grid, so check the count stored in the header
+            if sequence.blend_count != sequence.animation_indices.len() {
+                errors.push(ValidationError::BlendGridSize {
+                    sequence: sequence.label.clone(),
+                    count: sequence.blend_count,
+                    width,
+                    height,
+                });
+            }
+            for index in sequence.animation_indices.iter().copied() {
+                let in_range = usize::try_from(index)
+                    .map(|index| index < self.local_animations.len())
+                    .unwrap_or(false);
+                if !in_range {
+                    errors.push(ValidationError::AnimationOutOfRange {
+                        sequence: sequence.label.clone(),
+                        index,
+                        count: self.local_animations.len(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn validate_attachments(&self, errors: &mut Vec<ValidationError>) {
+        for attachment in &self.attachments {
+            if self.attachment_bone(attachment).is_none() {
+                errors.push(ValidationError::AttachmentBoneOutOfRange {
+                    attachment: attachment.name.clone(),
+                    bone: attachment.local_bone,
+                    count: self.bones.len(),
+                });
+            }
+        }
+    }
+
+    /// The bone an attachment is attached to, `None` if the bone doesn't exist
+    pub(crate) fn attachment_bone(&self, attachment: &StudioAttachment) -> Option<BoneId> {
+        let bone = usize::try_from(attachment.local_bone).ok()?;
+        (bone < self.bones.len()).then(|| bone.into())
+    }
+}
@@ -0,0 +1,379 @@
+//! Writing changes to a parsed model back into the `.mdl` data it was read from
+//!
+//! Instead of serializing the full model, the parts that can be edited are written over the original data.
+//! Changed strings and lists are appended to the end of the data and the offsets pointing to them are updated,
+//! everything else stays byte for byte identical.
+
+use super::{
+    Animation, AnimationDescription, AnimationDescriptionHeader, AnimationSequenceHeader,
+    BoneHeader, CompressionOptions, EncodeError, Mdl, MeshTexture, StudioHeader,
+};
+use crate::phy::PhyHeader;
+use crate::vtx::VtxHeader;
+use crate::vvd::VvdHeader;
+use crate::{paths, read_at, read_relative_iter, ModelError, ReadRelative, Readable};
+use bytemuck::Pod;
+use std::mem::size_of;
+use thiserror::Error;
+
+/// Reasons a model can't be written
+#[derive(Debug, Error)]
+pub enum WriteError {
+    #[error(transparent)]
+    Model(#[from] ModelError),
+    #[error("the model has version {model} but the original data has version {original}")]
+    VersionMismatch { model: i32, original: i32 },
+    #[error("models with version {0} can't be written")]
+    UnsupportedVersion(i32),
+    #[error("the model has {model} textures but the original data has {original}, textures can only be renamed")]
+    TextureCount { model: usize, original: usize },
+    #[error("the model has {model} bones but the original data has {original}, bones can't be added or removed")]
+    BoneCount { model: usize, original: usize },
+    #[error("the model has {model} sequences but the original data has {original}, sequences can't be added or removed")]
+    SequenceCount { model: usize, original: usize },
+    #[error("the model has {model} animations but the original data has {original}, animations can't be added or removed")]
+    AnimationCount { model: usize, original: usize },
+    #[error("the string {0:?} contains a null byte and can't be stored")]
+    NullByte(String),
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+}
+
+/// Options for writing a model
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct WriteOptions {
+    /// Replace the checksum with a CRC32 of the written data
+    ///
+    /// The engine only loads a model when the checksum matches the checksum stored in the vtx, vvd and phy files,
+    /// so these need to be updated with the new checksum.
+    /// By default the original checksum is kept, so the model keeps working with the existing files.
+    pub recompute_checksum: bool,
+    /// Re-encode the animation data of all animations with these options
+    ///
+    /// By default only animations that differ from the original data are encoded, with the default
+    /// [`CompressionOptions`]. Animations stored in an animation block of the `.ani` file are never written.
+    pub compression: Option<CompressionOptions>,
+}
+
+/// The data of the files linked to a model by its checksum
+#[derive(Debug, Default)]
+pub struct LinkedFiles<'a> {
+    /// The `.vtx` files of the model, like the `.dx90.vtx` and `.dx80.vtx`
+    pub vtx: Vec<&'a mut [u8]>,
+    pub vvd: Option<&'a mut [u8]>,
+    pub phy: Option<&'a mut [u8]>,
+}
+
+impl Mdl {
+    /// Write the model over the `.mdl` data it was parsed from
+    ///
+    /// The header, surface properties, texture names, texture directories, sequence bounding boxes and changed animations are
+    /// written from the model, all other data is kept from `original`. Texture names and directories are compared in their canonical form, so unchanged names keep their
+    /// original spelling. The search paths of the individual textures are ignored, the [`texture_paths`](Self::texture_paths)
+    /// are written instead.
+    pub fn write(&self, original: &[u8]) -> Result<Vec<u8>, WriteError> {
+        self.write_with_options(original, WriteOptions::default())
+    }
+
+    /// Write the model over the `.mdl` data it was parsed from, see [`write`](Self::write)
+    pub fn write_with_options(
+        &self,
+        original: &[u8],
+        options: WriteOptions,
+    ) -> Result<Vec<u8>, WriteError> {
+        let source = <StudioHeader as Readable>::read(original)?;
+        if source.version != self.header.version {
+            return Err(WriteError::VersionMismatch {
+                model: self.header.version,
+                original: source.version,
+            });
+        }
+        #[cfg(feature = "legacy")]
+        if source.is_legacy() {
+            return Err(WriteError::UnsupportedVersion(source.version));
+        }
+
+        let mut writer = Writer {
+            data: original.to_vec(),
+        };
+        let mut header = self.header;
+        writer.surface_props(&source, &mut header, self)?;
+        writer.textures(&source, self)?;
+        writer.texture_directories(&source, &mut header, &self.texture_paths)?;
+        writer.sequences(&source, self)?;
+        writer.animations(&source, self, options.compression)?;
+        header.data_length = writer.data.len() as i32;
+        if options.recompute_checksum {
+            header.checksum = [0; 4];
+            writer.write(0, &header)?;
+            header.checksum = crc32(&writer.data).to_le_bytes();
+        }
+        writer.write(0, &header)?;
+        Ok(writer.data)
+    }
+
+    /// Write the model over the `.mdl` data it was parsed from and store its checksum in the linked files
+    ///
+    /// The linked files are updated with the checksum of the written model, both when the checksum is
+    /// [recomputed](WriteOptions::recompute_checksum) and when the original checksum is kept.
+    /// The linked files are only changed after the model has been written successfully and the headers of all
+    /// linked files have been read, so none of the files are changed when an error is returned.
+    pub fn write_linked(
+        &self,
+        original: &[u8],
+        options: WriteOptions,
+        linked: LinkedFiles,
+    ) -> Result<Vec<u8>, WriteError> {
+        let data = self.write_with_options(original, options)?;
+        let checksum = <StudioHeader as Readable>::read(&data)?.checksum;
+        let vtx = linked
+            .vtx
+            .into_iter()
+            .map(linked_header::<VtxHeader>)
+            .collect::<Result<Vec<_>, _>>()?;
+        let vvd = linked.vvd.map(linked_header::<VvdHeader>).transpose()?;
+        let phy = linked.phy.map(linked_header::<PhyHeader>).transpose()?;
+
+        for (data, mut header) in vtx {
+            header.checksum = checksum;
+            store_header(data, &header);
+        }
+        if let Some((data, mut header)) = vvd {
+            header.checksum = checksum;
+            store_header(data, &header);
+        }
+        if let Some((data, mut header)) = phy {
+            header.checksum = checksum;
+            store_header(data, &header);
+        }
+        Ok(data)
+    }
+}
+
+/// Read the header of a linked file, keeping the data to store the header in
+fn linked_header<T: Pod>(data: &mut [u8]) -> Result<(&mut [u8], T), ModelError> {
+    let header = <T as Readable>::read(data)?;
+    Ok((data, header))
+}
+
+/// Overwrite the header at the start of data the header has been read from
+fn store_header<T: Pod>(data: &mut [u8], header: &T) {
+    data[..size_of::<T>()].copy_from_slice(bytemuck::bytes_of(header));
+}
+
+struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    fn read<T: Pod>(&self, offset: usize) -> Result<T, ModelError> {
+        read_at(
+            &self.data,
+            offset,
+            std::any::type_name::<T>(),
+            <T as Readable>::read,
+        )
+    }
+
+    fn write<T: Pod>(&mut self, offset: usize, value: &T) -> Result<(), ModelError> {
+        let target =
+            self.data
+                .get_mut(offset..offset + size_of::<T>())
+                .ok_or(ModelError::OutOfBounds {
+                    data: std::any::type_name::<T>(),
+                    offset,
+                })?;
+        target.copy_from_slice(bytemuck::bytes_of(value));
+        Ok(())
+    }
+
+    fn string(&self, offset: usize) -> Result<String, ModelError> {
+        String::read(self.data.get(offset..).unwrap_or_default(), ())
+    }
+
+    /// Append a null terminated string, returning its offset
+    fn append_string(&mut self, value: &str) -> Result<usize, WriteError> {
+        if value.contains('\0') {
+            return Err(WriteError::NullByte(value.into()));
+        }
+        let offset = self.data.len();
+        self.data.extend_from_slice(value.as_bytes());
+        self.data.push(0);
+        Ok(offset)
+    }
+
+    /// Append a list of values aligned to 4 bytes, returning its offset
+    fn append<T: Pod>(&mut self, values: &[T]) -> usize {
+        self.data.resize(self.data.len().next_multiple_of(4), 0);
+        let offset = self.data.len();
+        self.data.extend_from_slice(bytemuck::cast_slice(values));
+        offset
+    }
+
+    fn surface_props(
+        &mut self,
+        source: &StudioHeader,
+        header: &mut StudioHeader,
+        mdl: &Mdl,
+    ) -> Result<(), WriteError> {
+        let count = source.bone_indexes().count();
+        if count != mdl.bones.len() {
+            return Err(WriteError::BoneCount {
+                model: mdl.bones.len(),
+                original: count,
+            });
+        }
+        if self.string(source.surface_prop_index as usize)? != mdl.surface_prop {
+            header.surface_prop_index = self.append_string(&mdl.surface_prop)? as i32;
+        }
+        for (offset, bone) in source.bone_indexes().zip(&mdl.bones) {
+            let mut header: BoneHeader = self.read(offset)?;
+            let current = self.string(offset + header.surface_prop_idx as usize)?;
+            if current != bone.surface_prop {
+                let surface_prop = self.append_string(&bone.surface_prop)?;
+                header.surface_prop_idx = (surface_prop - offset) as i32;
+                self.write(offset, &header)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn textures(&mut self, source: &StudioHeader, mdl: &Mdl) -> Result<(), WriteError> {
+        let count = source.texture_indexes().count();
+        if count != mdl.textures.len() {
+            return Err(WriteError::TextureCount {
+                model: mdl.textures.len(),
+                original: count,
+            });
+        }
+        for (offset, texture) in source.texture_indexes().zip(&mdl.textures) {
+            let header: MeshTexture = self.read(offset)?;
+            let current = self.string(offset + header.name_index as usize)?;
+            if paths::normalize_separators(&current) != texture.name {
+                let name = self.append_string(&texture.name)?;
+                let name_index = (name - offset) as i32;
+                self.write(offset, &name_index)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn sequences(&mut self, source: &StudioHeader, mdl: &Mdl) -> Result<(), WriteError> {
+        let count = source.animation_sequence_indexes().count();
+        if count != mdl.animation_sequences.len() {
+            return Err(WriteError::SequenceCount {
+                model: mdl.animation_sequences.len(),
+                original: count,
+            });
+        }
+        for (offset, sequence) in source
+            .animation_sequence_indexes()
+            .zip(&mdl.animation_sequences)
+        {
+            let mut header: AnimationSequenceHeader = self.read(offset)?;
+            [header.bounding_box_min, header.bounding_box_max] = sequence.bounding_box;
+            self.write(offset, &header)?;
+        }
+        Ok(())
+    }
+
+    fn animations(
+        &mut self,
+        source: &StudioHeader,
+        mdl: &Mdl,
+        compression: Option<CompressionOptions>,
+    ) -> Result<(), WriteError> {
+        let count = source.local_animation_indexes().count();
+        if count != mdl.local_animations.len() {
+            return Err(WriteError::AnimationCount {
+                model: mdl.local_animations.len(),
+                original: count,
+            });
+        }
+        for (offset, description) in source.local_animation_indexes().zip(&mdl.local_animations) {
+            if description.animation_block.is_some() {
+                continue;
+            }
+            let mut header: AnimationDescriptionHeader = self.read(offset)?;
+            if compression.is_none() {
+                let mut original = read_at(&self.data, offset, "animation description", |data| {
+                    AnimationDescription::read(data, header)
+                })?;
+                for animation in &mut original.animations {
+                    if let Some(bone) = mdl.bones.get(usize::from(animation.bone)) {
+                        animation.apply_bone_data(bone);
+                    }
+                }
+                if same_animations(&original, description) {
+                    continue;
+                }
+            }
+
+            let data = description.encode(&mdl.bones, &compression.unwrap_or_default())?;
+            header.animation_index = (self.append(&data) - offset) as i32;
+            header.frame_count = description.frame_count as i32;
+            // the sections and the cached first frames still describe the original data
+            header.section_offset = 0;
+            header.section_frames = 0;
+            header.zero_frame_span = 0;
+            header.zero_frame_count = 0;
+            header.zero_frame_offset = 0;
+            self.write(offset, &header)?;
+        }
+        Ok(())
+    }
+
+    fn texture_directories(
+        &mut self,
+        source: &StudioHeader,
+        header: &mut StudioHeader,
+        directories: &[String],
+    ) -> Result<(), WriteError> {
+        let current = read_relative_iter::<u32, _>(&self.data, source.texture_dir_indexes())
+            .map(|offset| self.string(offset? as usize))
+            .collect::<Result<Vec<_>, _>>()?;
+        if paths::canonical_directories(current) == directories {
+            return Ok(());
+        }
+        let offsets = directories
+            .iter()
+            .map(|directory| Ok(self.append_string(directory)? as u32))
+            .collect::<Result<Vec<u32>, WriteError>>()?;
+        header.texture_dir_offset = self.append(&offsets) as i32;
+        header.texture_dir_count = offsets.len() as i32;
+        Ok(())
+    }
+}
+
+/// Whether the animations decode to the same tracks
+fn same_animations(original: &AnimationDescription, model: &AnimationDescription) -> bool {
+    original.frame_count == model.frame_count
+        && original.animations.len() == model.animations.len()
+        && original
+            .animations
+            .iter()
+            .zip(&model.animations)
+            .all(|(original, track)| same_tracks(original, track, model.frame_count))
+}
+
+fn same_tracks(original: &Animation, model: &Animation, frame_count: usize) -> bool {
+    original.bone == model.bone
+        && original.flags.bits() == model.flags.bits()
+        && original.rotation_data().track_kind() == model.rotation_data().track_kind()
+        && original.position_data().track_kind() == model.position_data().track_kind()
+        && (0..frame_count.max(1)).all(|frame| {
+            bytemuck::bytes_of(&original.rotation(frame))
+                == bytemuck::bytes_of(&model.rotation(frame))
+                && original.position(frame) == model.position(frame)
+        })
+}
+
+/// CRC32 with the polynomial used by the engine
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(u32::MAX, |crc, byte| {
+        (0..8).fold(crc ^ u32::from(*byte), |crc, _| {
+            (crc >> 1) ^ (0xEDB88320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
@@ -0,0 +1,227 @@
+//! Accounting of the heap memory used by a parsed model
+
+use crate::mdl::{PositionData, ProceduralBone, RotationData};
+use crate::{Mdl, Model, Vtx};
+use std::mem::size_of;
+
+/// Heap memory used by a model in bytes, split by the kind of data
+///
+/// Sizes are based on the allocated capacity of the containers, the size of the [`Model`] struct itself is not included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The vertices of the model
+    pub vertices: usize,
+    pub tangents: usize,
+    /// The strips and indices of the meshes
+    pub indices: usize,
+    /// Flex vertex animations
+    pub flexes: usize,
+    /// Animation tracks, animation descriptions and sequences
+    pub animations: usize,
+    /// Names, paths and key values
+    pub strings: usize,
+    /// The collision data of the `.phy` file
+    pub physics: usize,
+    /// Everything else, like bones, textures and hitboxes
+    pub other: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.vertices
+            + self.tangents
+            + self.indices
+            + self.flexes
+            + self.animations
+            + self.strings
+            + self.physics
+            + self.other
+    }
+
+    fn mdl(&mut self, mdl: &Mdl) {
+        self.other += vec(&mdl.bones);
+        for bone in &mdl.bones {
+            self.strings += bone.name.capacity() + bone.surface_prop.capacity();
+            if let Some(ProceduralBone::Unknown { raw, .. }) = &bone.procedural_rules {
+                self.other += vec(raw);
+            }
+        }
+        self.other += vec(&mdl.bone_controllers) + vec(&mdl.body_table_by_name);
+        self.other += vec(&mdl.textures) + vec(&mdl.skin_table);
+        for texture in &mdl.textures {
+            self.strings += texture.name.capacity() + strings(&texture.search_paths);
+        }
+        self.strings += strings(&mdl.texture_paths);
+        self.strings += mdl.surface_prop.capacity() + mdl.animation_block_source.capacity();
+        self.strings += mdl
+            .full_name
+            .as_ref()
+            .map(String::capacity)
+            .unwrap_or_default();
+        self.strings += mdl
+            .key_values
+            .as_ref()
+            .map(String::capacity)
+            .unwrap_or_default();
+
+        self.other += vec(&mdl.body_parts);
+        for part in &mdl.body_parts {
+            self.strings += part.name.capacity();
+            self.other += vec(&part.models);
+            for model in &part.models {
+                self.other += vec(&model.meshes) + vec(&model.eyeballs);
+                self.strings += model
+                    .eyeballs
+                    .iter()
+                    .map(|eyeball| eyeball.name.capacity())
+                    .sum::<usize>();
+                for mesh in &model.meshes {
+                    self.flexes += vec(&mesh.flexes);
+                    self.flexes += mesh
+                        .flexes
+                        .iter()
+                        .map(|flex| vec(&flex.vertices))
+                        .sum::<usize>();
+                }
+            }
+        }
+
+        self.animations += vec(&mdl.local_animations) + vec(&mdl.animation_blocks);
+        for description in &mdl.local_animations {
+            self.strings += description.name.capacity();
+            self.animations += vec(&description.animations);
+            for animation in &description.animations {
+                self.animations += match animation.rotation_data() {
+                    RotationData::Animated(values) => vec(values),
+                    RotationData::Quaternions(values) => vec(values),
+                    _ => 0,
+                };
+                self.animations += match animation.position_data() {
+                    PositionData::PositionValues(values) => vec(values),
+                    _ => 0,
+                };
+            }
+        }
+        self.animations += vec(&mdl.animation_sequences);
+        for sequence in &mdl.animation_sequences {
+            self.strings += sequence.name.capacity() + sequence.label.capacity();
+            self.animations += vec(&sequence.animation_indices)
+                + vec(&sequence.pose_keys[0])
+                + vec(&sequence.pose_keys[1])
+                + vec(&sequence.bone_weights)
+                + vec(&sequence.events)
+                + vec(&sequence.auto_layers);
+            self.strings += sequence
+                .events
+                .iter()
+                .map(|event| event.name.capacity())
+                .sum::<usize>();
+        }
+        self.animations += vec(&mdl.pose_parameters);
+        self.strings += mdl
+            .pose_parameters
+            .iter()
+            .map(|parameter| parameter.name.capacity())
+            .sum::<usize>();
+
+        self.other += vec(&mdl.attachments) + vec(&mdl.hit_boxes);
+        self.strings += mdl
+            .attachments
+            .iter()
+            .map(|attachment| attachment.name.capacity())
+            .sum::<usize>();
+        for set in &mdl.hit_boxes {
+            self.strings += set.name.capacity();
+            self.other += vec(&set.boxes);
+            self.strings += set
+                .boxes
+                .iter()
+                .map(|hit_box| hit_box.name.capacity())
+                .sum::<usize>();
+        }
+        self.other += vec(&mdl.include_models) + vec(&mdl.flex_descriptors);
+        self.strings += mdl
+            .include_models
+            .iter()
+            .map(|include| include.name.capacity() + include.label.capacity())
+            .sum::<usize>();
+        self.strings += mdl
+            .flex_descriptors
+            .iter()
+            .map(|descriptor| descriptor.name.capacity())
+            .sum::<usize>();
+    }
+
+    fn vtx(&mut self, vtx: &Vtx) {
+        self.other += vec(&vtx.body_parts) + vec(&vtx.material_replacements);
+        for list in &vtx.material_replacements {
+            self.other += vec(&list.replacements);
+            self.strings += list
+                .replacements
+                .iter()
+                .map(|replacement| replacement.name.capacity())
+                .sum::<usize>();
+        }
+        for part in &vtx.body_parts {
+            self.other += vec(&part.models);
+            for model in &part.models {
+                self.other += vec(&model.lods);
+                for lod in &model.lods {
+                    self.other += vec(&lod.meshes);
+                    for mesh in &lod.meshes {
+                        self.indices += vec(&mesh.strip_groups);
+                        for group in &mesh.strip_groups {
+                            self.indices +=
+                                vec(&group.indices) + vec(&group.vertices) + vec(&group.strips);
+                            self.other += group
+                                .strips
+                                .iter()
+                                .map(|strip| vec(&strip.bone_state_changes))
+                                .sum::<usize>();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Model {
+    /// The heap memory used by the model, including loaded included models and the `.phy`
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage {
+            vertices: vec(&self.vvd.vertices),
+            tangents: vec(&self.vvd.tangents),
+            other: vec(&self.meshes) + vec(&self.included) + vec(&self.vvd.fixups),
+            ..MemoryUsage::default()
+        };
+        usage.mdl(&self.mdl);
+        for (include, mdl) in &self.included {
+            usage.strings += include.name.capacity() + include.label.capacity();
+            usage.mdl(mdl);
+        }
+        usage.vtx(&self.vtx);
+        if let Some(phy) = &self.phy {
+            usage.physics += vec(&phy.solids) + phy.text.capacity();
+            for solid in &phy.solids {
+                usage.physics += vec(&solid.collision_data);
+                usage.strings += solid.name.capacity()
+                    + solid.surface_prop.capacity()
+                    + solid
+                        .parent
+                        .as_ref()
+                        .map(String::capacity)
+                        .unwrap_or_default();
+            }
+        }
+        usage
+    }
+}
+
+fn vec<T>(values: &Vec<T>) -> usize {
+    values.capacity() * size_of::<T>()
+}
+
+fn strings(values: &Vec<String>) -> usize {
+    vec(values) + values.iter().map(String::capacity).sum::<usize>()
+}
@@ -0,0 +1,101 @@
+//! [`Diagnostic`] support for [`ModelError`], pointing to the data that failed to read
+
+use crate::ModelError;
+use miette::{Diagnostic, LabeledSpan, NamedSource, SourceCode};
+use std::fmt::Display;
+use thiserror::Error;
+
+impl ModelError {
+    fn code_name(&self) -> &'static str {
+        match self {
+            ModelError::IO(_) => "vmdl::io",
+            ModelError::String(_) => "vmdl::string",
+            ModelError::OutOfBounds { .. } => "vmdl::out_of_bounds",
+            ModelError::Eof(_) => "vmdl::eof",
+            ModelError::InvalidIndex { .. } => "vmdl::invalid_index",
+            ModelError::Mismatch { .. } => "vmdl::mismatch",
+            ModelError::UnsupportedVersion(_) => "vmdl::unsupported_version",
+            ModelError::Section { source, .. } => source.code_name(),
+        }
+    }
+
+    /// The file offset and description of the data that was read past the end of the file
+    fn failed_read(&self) -> Option<(usize, &'static str)> {
+        match self {
+            ModelError::OutOfBounds { data, offset } if *offset != usize::MAX => {
+                Some((*offset, data))
+            }
+            ModelError::Eof(offset) => Some((*offset, "data")),
+            ModelError::Section { source, .. } => source.failed_read(),
+            _ => None,
+        }
+    }
+
+    /// Attach the data the error was encountered in, allowing the diagnostic to show the bytes that failed to read
+    pub fn with_source_data(self, name: impl AsRef<str>, data: Vec<u8>) -> SourcedModelError {
+        SourcedModelError {
+            error: self,
+            data: NamedSource::new(name, data),
+        }
+    }
+}
+
+impl Diagnostic for ModelError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new(self.code_name()))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let help = match self {
+            ModelError::OutOfBounds { .. }
+            | ModelError::Eof(_)
+            | ModelError::InvalidIndex { .. } => "the file is truncated or corrupted",
+            ModelError::Mismatch { .. } => {
+                "the .mdl and .vtx files are likely from different versions of the model"
+            }
+            ModelError::UnsupportedVersion(_) => "only source engine models are supported",
+            ModelError::Section { source, .. } => return source.help(),
+            _ => return None,
+        };
+        Some(Box::new(help))
+    }
+}
+
+/// A [`ModelError`] together with the data it was encountered in
+///
+/// Created with [`ModelError::with_source_data`].
+#[derive(Debug, Error)]
+#[error("{error}")]
+pub struct SourcedModelError {
+    pub error: ModelError,
+    data: NamedSource<Vec<u8>>,
+}
+
+impl Diagnostic for SourcedModelError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.code()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.data)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let (offset, data) = self.error.failed_read()?;
+        let len = self.data.inner().len();
+        let label = if offset < len {
+            LabeledSpan::at(offset..len, format!("{data} runs past the end of the file"))
+        } else {
+            // spans past the end can't be shown, point to the last byte instead
+            LabeledSpan::at_offset(
+                len.saturating_sub(1),
+                format!("{data} at offset {offset} is past the end of the file"),
+            )
+        };
+        Some(Box::new(std::iter::once(label)))
+    }
+}
@@ -0,0 +1,68 @@
+//! Conversions between the math types used in the model and [`nalgebra`]
+
+use crate::{Quaternion, RadianEuler, Transform3x4, Vector};
+use nalgebra::{Isometry3, Point3, Translation3, UnitQuaternion, Vector3};
+
+impl From<Vector> for Vector3<f32> {
+    fn from(v: Vector) -> Self {
+        Vector3::new(v.x, v.y, v.z)
+    }
+}
+
+impl From<Vector3<f32>> for Vector {
+    fn from(v: Vector3<f32>) -> Self {
+        Vector {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+}
+
+impl From<Vector> for Point3<f32> {
+    fn from(v: Vector) -> Self {
+        Point3::new(v.x, v.y, v.z)
+    }
+}
+
+impl From<Point3<f32>> for Vector {
+    fn from(p: Point3<f32>) -> Self {
+        Vector {
+            x: p.x,
+            y: p.y,
+            z: p.z,
+        }
+    }
+}
+
+impl From<Quaternion> for UnitQuaternion<f32> {
+    fn from(q: Quaternion) -> Self {
+        UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(q.w, q.x, q.y, q.z))
+    }
+}
+
+impl From<UnitQuaternion<f32>> for Quaternion {
+    fn from(q: UnitQuaternion<f32>) -> Self {
+        Quaternion {
+            x: q.i,
+            y: q.j,
+            z: q.k,
+            w: q.w,
+        }
+    }
+}
+
+impl From<RadianEuler> for UnitQuaternion<f32> {
+    fn from(e: RadianEuler) -> Self {
+        Quaternion::from(e).into()
+    }
+}
+
+impl From<Transform3x4> for Isometry3<f32> {
+    /// Uses the same axis mapping as the conversion into a cgmath [`Matrix4`](cgmath::Matrix4)
+    fn from(t: Transform3x4) -> Self {
+        let rotation = UnitQuaternion::from(t.rotation());
+        let translation = rotation * Vector3::from(t.translate());
+        Isometry3::from_parts(Translation3::from(translation), rotation)
+    }
+}
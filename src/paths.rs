@@ -0,0 +1,52 @@
+//! Normalization of the paths and material names stored in models
+//!
+//! Models compiled on windows often contain backslashes or mixed casing in their paths, all paths are
+//! normalized at parse time so they can be compared and joined directly.
+
+/// Replace all backslashes with forward slashes
+pub fn normalize_separators(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+/// Canonical form of a material name
+///
+/// Uses forward slashes, is lowercase, without leading or trailing slashes and without `.vmt` extension.
+pub fn canonical_material_name(name: &str) -> String {
+    let name = normalize_separators(name).to_ascii_lowercase();
+    let name = name.trim_matches('/');
+    name.strip_suffix(".vmt").unwrap_or(name).into()
+}
+
+/// Canonical form of a material search directory
+///
+/// Uses forward slashes, is lowercase, without leading slash and with a trailing slash unless the directory is empty.
+pub fn canonical_directory(dir: &str) -> String {
+    let dir = normalize_separators(dir).to_ascii_lowercase();
+    let dir = dir.trim_matches('/');
+    if dir.is_empty() {
+        String::new()
+    } else {
+        format!("{dir}/")
+    }
+}
+
+/// Join a canonical directory and material name
+pub fn join(dir: &str, name: &str) -> String {
+    format!(
+        "{}{}",
+        canonical_directory(dir),
+        canonical_material_name(name)
+    )
+}
+
+/// Canonicalize a list of search directories, removing duplicates while keeping the search order
+pub fn canonical_directories<I: IntoIterator<Item = S>, S: AsRef<str>>(dirs: I) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for dir in dirs {
+        let dir = canonical_directory(dir.as_ref());
+        if !result.contains(&dir) {
+            result.push(dir);
+        }
+    }
+    result
+}
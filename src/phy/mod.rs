@@ -0,0 +1,165 @@
+//! Parsing of the `.phy` files containing the collision models and ragdoll setup of a model
+//!
+//! The collision data of every solid is kept in the compact format used by the physics engine,
+//! the properties of the solids are read from the key-value text that follows it.
+
+mod raw;
+
+use crate::{read_at, ModelError, Readable};
+pub use raw::PhyHeader;
+use std::mem::size_of;
+
+type Result<T> = std::result::Result<T, ModelError>;
+
+/// The phy file contains the collision solids of the model
+#[derive(Debug, Clone)]
+pub struct Phy {
+    pub header: PhyHeader,
+    pub solids: Vec<Solid>,
+    /// The full key-value text of the file, including the ragdoll constraints and collision rules
+    pub text: String,
+}
+
+/// A single collision solid
+#[derive(Debug, Clone)]
+pub struct Solid {
+    /// Index of the solid, matches the `physics_bone` of the bones driven by the solid
+    pub index: usize,
+    /// Name of the bone the solid is attached to
+    pub name: String,
+    /// Name of the bone the solid of the parent is attached to
+    pub parent: Option<String>,
+    pub mass: f32,
+    pub surface_prop: String,
+    pub damping: f32,
+    pub rot_damping: f32,
+    pub inertia: f32,
+    pub volume: f32,
+    /// The compact collision surface of the solid, in the format used by the physics engine
+    pub collision_data: Vec<u8>,
+}
+
+impl Phy {
+    pub fn read(data: &[u8]) -> Result<Self> {
+        let header = <PhyHeader as Readable>::read(data)?;
+        let mut offset = header.size.max(size_of::<PhyHeader>() as i32) as usize;
+        // every solid starts with its size, so the count can't exceed the number of sizes that fit
+        let capacity = (header.solid_count.max(0) as usize).min(data.len() / size_of::<i32>());
+        let mut collision_data = Vec::with_capacity(capacity);
+        for _ in 0..header.solid_count.max(0) {
+            let size = read_at(data, offset, "solid size", <i32 as Readable>::read)?;
+            let start = offset + size_of::<i32>();
+            let end = start.saturating_add(size.max(0) as usize);
+            let solid = data.get(start..end).ok_or(ModelError::Eof(start))?;
+            collision_data.push(solid.to_vec());
+            offset = end;
+        }
+
+        let text = data.get(offset..).ok_or(ModelError::OutOfBounds {
+            data: "solid text",
+            offset,
+        })?;
+        let text = &text[..text.iter().position(|c| *c == 0).unwrap_or(text.len())];
+        let text = String::from_utf8_lossy(text).into_owned();
+
+        let solids = key_value_blocks(&text)
+            .filter(|(name, _)| name.eq_ignore_ascii_case("solid"))
+            .map(|(_, values)| Solid::from_key_values(&values, &mut collision_data))
+            .collect();
+
+        Ok(Phy {
+            header,
+            solids,
+            text,
+        })
+    }
+
+    /// The solid with the provided index
+    pub fn solid(&self, index: usize) -> Option<&Solid> {
+        self.solids.iter().find(|solid| solid.index == index)
+    }
+}
+
+impl Solid {
+    fn from_key_values(values: &[(&str, &str)], collision_data: &mut [Vec<u8>]) -> Self {
+        let value = |key: &str| {
+            values
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, value)| *value)
+        };
+        let float = |key: &str| value(key).and_then(|value| value.parse().ok());
+        let index = value("index")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default();
+        Solid {
+            index,
+            name: value("name").unwrap_or_default().into(),
+            parent: value("parent").map(String::from),
+            mass: float("mass").unwrap_or_default(),
+            surface_prop: value("surfaceprop").unwrap_or_default().into(),
+            damping: float("damping").unwrap_or_default(),
+            rot_damping: float("rotdamping").unwrap_or_default(),
+            inertia: float("inertia").unwrap_or(1.0),
+            volume: float("volume").unwrap_or_default(),
+            collision_data: collision_data
+                .get_mut(index)
+                .map(std::mem::take)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Split the key-value text into its top level blocks
+///
+/// Nested blocks are flattened into their parent.
+fn key_value_blocks(text: &str) -> impl Iterator<Item = (&str, Vec<(&str, &str)>)> {
+    let mut tokens = tokens(text).peekable();
+    std::iter::from_fn(move || loop {
+        let name = tokens.next()?;
+        if tokens.next_if_eq(&"{").is_none() {
+            continue;
+        }
+        let mut values = Vec::new();
+        let mut depth = 1;
+        while depth > 0 {
+            match tokens.next()? {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                key => {
+                    if let Some(value) = tokens.next_if(|token| *token != "{" && *token != "}") {
+                        values.push((key, value));
+                    }
+                }
+            }
+        }
+        return Some((name, values));
+    })
+}
+
+fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        rest = rest.trim_start();
+        let token = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            rest = quoted.get(end + 1..).unwrap_or_default();
+            &quoted[..end]
+        } else if rest.starts_with(['{', '}']) {
+            let (token, remaining) = rest.split_at(1);
+            rest = remaining;
+            token
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '{' || c == '}' || c == '"')
+                .unwrap_or(rest.len());
+            if end == 0 {
+                return None;
+            }
+            let (token, remaining) = rest.split_at(end);
+            rest = remaining;
+            token
+        };
+        Some(token)
+    })
+}
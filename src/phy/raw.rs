@@ -0,0 +1,14 @@
+use bytemuck::{Pod, Zeroable};
+use std::mem::size_of;
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct PhyHeader {
+    /// Size of the header, the collision data starts directly after it
+    pub size: i32,
+    pub id: i32,
+    pub solid_count: i32,
+    pub checksum: [u8; 4],
+}
+
+static_assertions::const_assert_eq!(size_of::<PhyHeader>(), 16);
@@ -0,0 +1,61 @@
+//! Linking the bones of a model to the solids of its `.phy`
+
+use crate::mdl::{Bone, BoneId};
+use crate::phy::{Phy, Solid};
+use crate::{Handle, Model};
+
+/// A bone together with the collision solid that drives it
+#[derive(Debug, Clone)]
+pub struct PhysicsBone<'a> {
+    pub bone: Handle<'a, Bone, BoneId>,
+    pub solid: &'a Solid,
+}
+
+impl Model {
+    /// Attach the parsed `.phy` of the model
+    pub fn set_phy(&mut self, phy: Phy) {
+        self.phy = Some(phy);
+    }
+
+    /// The `.phy` of the model, if one has been loaded
+    pub fn phy(&self) -> Option<&Phy> {
+        self.phy.as_ref()
+    }
+
+    /// Every collision solid paired with the bone it is attached to
+    ///
+    /// Solids without a matching bone are skipped.
+    pub fn physics_bones(&self) -> impl Iterator<Item = PhysicsBone<'_>> {
+        self.phy
+            .iter()
+            .flat_map(|phy| phy.solids.iter())
+            .filter_map(|solid| {
+                Some(PhysicsBone {
+                    bone: self.bone_for(solid)?,
+                    solid,
+                })
+            })
+    }
+
+    /// The solid moving a bone
+    ///
+    /// For bones without a solid of their own, this is the solid of the closest simulated parent.
+    pub fn solid_for_bone(&self, bone: BoneId) -> Option<&Solid> {
+        let index = usize::try_from(self.bone(bone)?.physics_bone).ok()?;
+        self.phy.as_ref()?.solid(index)
+    }
+
+    /// The bone a solid is attached to
+    pub fn bone_for_solid(&self, index: usize) -> Option<Handle<'_, Bone, BoneId>> {
+        self.bone_for(self.phy.as_ref()?.solid(index)?)
+    }
+
+    fn bone_for(&self, solid: &Solid) -> Option<Handle<'_, Bone, BoneId>> {
+        self.bones()
+            .find(|bone| bone.name.eq_ignore_ascii_case(&solid.name))
+            .or_else(|| {
+                self.bones()
+                    .find(|bone| usize::try_from(bone.physics_bone) == Ok(solid.index))
+            })
+    }
+}
@@ -0,0 +1,216 @@
+use crate::mdl::{AnimationEvent, AnimationSequence, SampleOptions};
+use crate::{Handle, Pose};
+use std::ops::RangeInclusive;
+
+/// Playback state for a single sequence
+///
+/// Times are in seconds since the start of the playback, which holds the first frame
+/// for the zero frame stall time of the sequence before advancing.
+///
+/// Advancing the playback with [`advance`](Self::advance) or [`advance_to`](Self::advance_to) returns the
+/// [`PlaybackEvent`]s that happened along the way.
+#[derive(Debug, Clone)]
+pub struct SequencePlayer<'a> {
+    sequence: Handle<'a, AnimationSequence, usize>,
+    duration: f32,
+    stall_time: f32,
+    time: f32,
+}
+
+/// Something that happened while advancing the playback of a sequence
+#[derive(Debug, Clone, Copy)]
+pub enum PlaybackEvent<'a> {
+    /// An event of the sequence was triggered
+    Event(&'a AnimationEvent),
+    /// A looping sequence wrapped around, with the number of completed loops
+    Looped(u32),
+    /// A non-looping sequence reached its end
+    Finished,
+}
+
+/// The absolute cycles passed when advancing the playback
+#[derive(Debug, Clone, Copy)]
+struct CycleRange {
+    start: f32,
+    end: f32,
+    from_beginning: bool,
+}
+
+impl CycleRange {
+    fn iterations(&self, looping: bool) -> RangeInclusive<u32> {
+        if looping {
+            self.start.floor() as u32..=self.end.floor() as u32
+        } else {
+            0..=0
+        }
+    }
+
+    fn triggers(&self, iteration: u32, event: &AnimationEvent) -> bool {
+        let cycle = iteration as f32 + event.cycle;
+        (cycle > self.start || (cycle == 0.0 && self.from_beginning)) && cycle <= self.end
+    }
+}
+
+impl<'a> SequencePlayer<'a> {
+    pub fn new(sequence: Handle<'a, AnimationSequence, usize>) -> Self {
+        SequencePlayer {
+            duration: sequence.duration(),
+            stall_time: sequence.zero_frame_stall_time(),
+            sequence,
+            time: 0.0,
+        }
+    }
+
+    pub fn sequence(&self) -> &Handle<'a, AnimationSequence, usize> {
+        &self.sequence
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// Time the first frame is held at the start of the playback
+    pub fn stall_time(&self) -> f32 {
+        self.stall_time
+    }
+
+    /// The cycle of the sequence at a point in time, from `0` to `1`
+    ///
+    /// Looping sequences wrap around while other sequences stop at the end.
+    pub fn cycle_at(&self, time: f32) -> f32 {
+        let cycle = self.absolute_cycle(time);
+        if self.sequence.is_looping() {
+            cycle.rem_euclid(1.0)
+        } else {
+            cycle
+        }
+    }
+
+    /// The number of cycles since the start of the playback, without wrapping for looping sequences
+    fn absolute_cycle(&self, time: f32) -> f32 {
+        let time = (time - self.stall_time).max(0.0);
+        let cycle = if self.duration > 0.0 {
+            time / self.duration
+        } else if time > 0.0 {
+            1.0
+        } else {
+            0.0
+        };
+        if self.sequence.is_looping() {
+            cycle.max(0.0)
+        } else {
+            cycle.clamp(0.0, 1.0)
+        }
+    }
+
+    /// All events triggered when advancing the playback from `start` to `end`
+    ///
+    /// Events are triggered when their cycle is after `start` and at or before `end`, events at the very
+    /// beginning of the sequence are triggered when starting from `0`.
+    /// For looping sequences the events are triggered once for every loop in the interval.
+    pub fn events_between(&self, start: f32, end: f32) -> impl Iterator<Item = &'a AnimationEvent> {
+        let range = self.cycle_range(start, end);
+        let events = &self.sequence.data().events;
+        range
+            .iterations(self.sequence.is_looping())
+            .flat_map(move |iteration| {
+                events
+                    .iter()
+                    .filter(move |event| range.triggers(iteration, event))
+            })
+    }
+
+    fn cycle_range(&self, start: f32, end: f32) -> CycleRange {
+        CycleRange {
+            start: self.absolute_cycle(start),
+            end: self.absolute_cycle(end),
+            from_beginning: start <= 0.0,
+        }
+    }
+
+    /// Blend the pose of the sequence at a point in time onto `pose`, including the auto layers of the sequence
+    ///
+    /// The pose usually starts out as the [rest pose](crate::Model::rest_pose).
+    /// `pose_parameters` are the normalized values driving pose based layers,
+    /// as returned by [`PoseContext::normalized_values`](crate::PoseContext::normalized_values).
+    /// Layers that are applied in the local context are blended after the other layers.
+    pub fn pose_at(&self, time: f32, pose_parameters: &[f32], pose: &mut Pose) {
+        let cycle = self.cycle_at(time);
+        let options = SampleOptions::default();
+        self.sequence
+            .blend_onto(pose, cycle * self.duration, 1.0, options);
+
+        let (local, layers): (Vec<_>, Vec<_>) = self
+            .sequence
+            .auto_layers()
+            .partition(|(layer, _)| layer.is_local());
+        let parameters = &self.sequence.mdl().pose_parameters;
+        for (layer, sequence) in layers.into_iter().chain(local) {
+            if let Some((weight, layer_cycle)) =
+                layer.evaluate(cycle, 1.0, pose_parameters, parameters)
+            {
+                let time = layer_cycle * sequence.duration();
+                sequence.blend_onto(pose, time, weight, options);
+            }
+        }
+    }
+
+    /// The pose of the sequence at a point in time, blended onto the rest pose
+    ///
+    /// See [`pose_at`](Self::pose_at) for blending onto an existing pose.
+    pub fn pose(&self, time: f32, pose_parameters: &[f32]) -> Pose {
+        let mut pose = Pose::rest(self.sequence.mdl());
+        self.pose_at(time, pose_parameters, &mut pose);
+        pose
+    }
+
+    /// The current time of the playback
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Jump to a point in time without triggering any events
+    pub fn seek(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    /// Advance the playback by `delta` seconds, returning what happened along the way
+    pub fn advance(&mut self, delta: f32) -> Vec<PlaybackEvent<'a>> {
+        self.advance_to(self.time + delta)
+    }
+
+    /// Advance the playback to a point in time, returning what happened along the way
+    ///
+    /// Events and loops are returned in the order they happen, moving backwards in time doesn't trigger
+    /// anything.
+    pub fn advance_to(&mut self, time: f32) -> Vec<PlaybackEvent<'a>> {
+        let start = self.time;
+        self.time = time;
+        let mut happened = Vec::new();
+        if time <= start {
+            return happened;
+        }
+
+        let range = self.cycle_range(start, time);
+        let looping = self.sequence.is_looping();
+        let events = &self.sequence.data().events;
+        let iterations = range.iterations(looping);
+        let first = *iterations.start();
+        for iteration in iterations {
+            if iteration > first {
+                happened.push(PlaybackEvent::Looped(iteration));
+            }
+            happened.extend(
+                events
+                    .iter()
+                    .filter(|event| range.triggers(iteration, event))
+                    .map(PlaybackEvent::Event),
+            );
+        }
+
+        if !looping && range.start < 1.0 && range.end >= 1.0 {
+            happened.push(PlaybackEvent::Finished);
+        }
+        happened
+    }
+}
@@ -0,0 +1,262 @@
+use crate::handle::bone_forest;
+use crate::mdl::{AnimationDescription, BoneSample, PoseParameterDescription, SampleOptions};
+use crate::vvd::Vertex;
+use crate::{BoneId, Mdl, Model};
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3, Vector4, Zero};
+use std::sync::OnceLock;
+
+/// The position and rotation of every bone of a model
+///
+/// Samples are stored relative to the parent of the bone, the model space transforms are computed
+/// when first requested and cached until the pose is changed.
+#[derive(Debug, Clone)]
+pub struct Pose {
+    samples: Vec<BoneSample>,
+    parents: Vec<usize>,
+    /// Bone indices with parents before their children
+    order: Vec<usize>,
+    pose_to_bone: Vec<Matrix4<f32>>,
+    cache: OnceLock<Transforms>,
+}
+
+#[derive(Debug, Clone)]
+struct Transforms {
+    world: Vec<Matrix4<f32>>,
+    skinning: Vec<Matrix4<f32>>,
+}
+
+impl Pose {
+    /// The rest pose of every bone in the mdl
+    pub fn rest(mdl: &Mdl) -> Self {
+        let mut order: Vec<usize> = bone_forest(mdl)
+            .map(|bone| usize::from(bone.key()))
+            .collect();
+        // bones in a cyclic hierarchy are treated as roots
+        let mut ordered = vec![false; mdl.bones.len()];
+        order.iter().for_each(|index| ordered[*index] = true);
+        order.extend((0..mdl.bones.len()).filter(|index| !ordered[*index]));
+        Pose {
+            order,
+            samples: mdl
+                .bones
+                .iter()
+                .enumerate()
+                .map(|(index, bone)| BoneSample::rest(index.into(), bone))
+                .collect(),
+            parents: mdl
+                .bones
+                .iter()
+                .map(|bone| usize::from(bone.parent))
+                .collect(),
+            pose_to_bone: mdl
+                .bones
+                .iter()
+                .map(|bone| bone.pose_to_bone.matrix())
+                .collect(),
+            cache: OnceLock::new(),
+        }
+    }
+
+    /// The number of bones in the pose
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// The samples of all bones, indexed by bone id
+    pub fn samples(&self) -> &[BoneSample] {
+        &self.samples
+    }
+
+    pub fn sample(&self, bone: BoneId) -> Option<&BoneSample> {
+        self.samples.get(usize::from(bone))
+    }
+
+    /// Replace the sample of a bone, samples for bones that aren't part of the pose are ignored
+    pub fn set(&mut self, sample: BoneSample) {
+        if let Some(target) = self.samples.get_mut(usize::from(sample.bone)) {
+            *target = sample;
+            self.cache.take();
+        }
+    }
+
+    /// Replace the samples of every bone in `samples`
+    pub fn apply(&mut self, samples: impl IntoIterator<Item = BoneSample>) {
+        for sample in samples {
+            self.set(sample);
+        }
+    }
+
+    /// Interpolate a bone towards a sample by `weight`
+    pub fn blend(&mut self, sample: &BoneSample, weight: f32) {
+        if let Some(target) = self.samples.get_mut(usize::from(sample.bone)) {
+            *target = target.blend(sample, weight);
+            self.cache.take();
+        }
+    }
+
+    /// Whether the model space transforms need to be recomputed since the pose was last changed
+    pub fn is_dirty(&self) -> bool {
+        self.cache.get().is_none()
+    }
+
+    /// The transform of a bone relative to its parent
+    pub fn local_transform(&self, bone: BoneId) -> Option<Matrix4<f32>> {
+        self.sample(bone).map(local_transform)
+    }
+
+    /// The model space transform of every bone, indexed by bone id
+    pub fn world_transforms(&self) -> &[Matrix4<f32>] {
+        &self.transforms().world
+    }
+
+    pub fn world_transform(&self, bone: BoneId) -> Option<Matrix4<f32>> {
+        self.world_transforms().get(usize::from(bone)).copied()
+    }
+
+    /// The transform of every bone from the bind pose to the model space of this pose, indexed by bone id
+    pub fn skinning_transforms(&self) -> &[Matrix4<f32>] {
+        &self.transforms().skinning
+    }
+
+    /// Transform the position and normal of a vertex by the bones it's weighted to
+    pub fn skin_vertex(&self, vertex: &Vertex) -> Vertex {
+        skin_vertex(vertex, self.skinning_transforms())
+    }
+
+    fn transforms(&self) -> &Transforms {
+        self.cache.get_or_init(|| {
+            let mut world = vec![Matrix4::identity(); self.samples.len()];
+            let mut computed = vec![false; self.samples.len()];
+            for &index in &self.order {
+                let local = local_transform(&self.samples[index]);
+                let parent = self.parents[index];
+                world[index] = match computed.get(parent) {
+                    Some(true) => world[parent] * local,
+                    _ => local,
+                };
+                computed[index] = true;
+            }
+            let skinning = world
+                .iter()
+                .zip(&self.pose_to_bone)
+                .map(|(world, pose_to_bone)| world * pose_to_bone)
+                .collect();
+            Transforms { world, skinning }
+        })
+    }
+}
+
+fn local_transform(sample: &BoneSample) -> Matrix4<f32> {
+    Matrix4::from_translation(sample.position.into()) * Matrix4::from(sample.rotation)
+}
+
+/// Transform a vertex by the weighted sum of the skinning transforms of its bones
+///
+/// Vertices without any valid weights use the first transform.
+pub(crate) fn skin_vertex(vertex: &Vertex, skinning: &[Matrix4<f32>]) -> Vertex {
+    let matrix = skinning_matrix(vertex, skinning);
+    let position =
+        matrix * Vector4::new(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
+    let normal = matrix * Vector4::new(vertex.normal.x, vertex.normal.y, vertex.normal.z, 0.0);
+    let normal = Vector3::new(normal.x, normal.y, normal.z);
+    Vertex {
+        position: position.truncate().into(),
+        normal: if normal.magnitude2() > 0.0 {
+            normal.normalize().into()
+        } else {
+            vertex.normal
+        },
+        ..*vertex
+    }
+}
+
+/// The weighted sum of the skinning transforms of the bones of a vertex
+pub(crate) fn skinning_matrix(vertex: &Vertex, skinning: &[Matrix4<f32>]) -> Matrix4<f32> {
+    let total: f32 = vertex
+        .bone_weights
+        .weights()
+        .map(|weight| weight.weight)
+        .sum();
+    let mut matrix = Matrix4::zero();
+    for weight in vertex.bone_weights.weights() {
+        if let Some(transform) = skinning.get(usize::from(weight.bone_id)) {
+            if total > 0.0 {
+                matrix += transform * (weight.weight / total);
+            }
+        }
+    }
+    if matrix == Matrix4::zero() {
+        matrix = skinning.first().copied().unwrap_or_else(Matrix4::identity);
+    }
+    matrix
+}
+
+/// The pose parameter values used when evaluating the pose of a model
+///
+/// Values are stored normalized, like the engine does.
+#[derive(Debug, Clone)]
+pub struct PoseContext<'a> {
+    parameters: &'a [PoseParameterDescription],
+    values: Vec<f32>,
+}
+
+impl<'a> PoseContext<'a> {
+    pub fn new(model: &'a Model) -> Self {
+        let parameters = model.mdl.pose_parameters.as_slice();
+        PoseContext {
+            values: parameters
+                .iter()
+                .map(|parameter| parameter.normalize(0.0))
+                .collect(),
+            parameters,
+        }
+    }
+
+    fn index(&self, name: &str) -> Option<usize> {
+        self.parameters
+            .iter()
+            .position(|parameter| parameter.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Set a pose parameter by name, returning the normalized value or `None` if the model has no such parameter
+    pub fn set_pose_parameter(&mut self, name: &str, value: f32) -> Option<f32> {
+        let index = self.index(name)?;
+        let normalized = self.parameters[index].normalize(value);
+        self.values[index] = normalized;
+        Some(normalized)
+    }
+
+    /// Get the value of a pose parameter by name, in the parameter's range
+    pub fn pose_parameter(&self, name: &str) -> Option<f32> {
+        let index = self.index(name)?;
+        Some(self.parameters[index].denormalize(self.values[index]))
+    }
+
+    /// The normalized values of all pose parameters, in the order of the model's pose parameters
+    pub fn normalized_values(&self) -> &[f32] {
+        &self.values
+    }
+}
+
+impl Model {
+    /// Create a context for evaluating poses of the model with all pose parameters set to `0`
+    pub fn pose_context(&self) -> PoseContext<'_> {
+        PoseContext::new(self)
+    }
+
+    /// The rest pose of every bone
+    pub fn rest_pose(&self) -> Pose {
+        Pose::rest(&self.mdl)
+    }
+
+    /// The pose of an animation at a fractional frame, starting from the rest pose
+    pub fn animation_pose(&self, animation: &AnimationDescription, frame: f32) -> Pose {
+        let mut pose = self.rest_pose();
+        pose.apply(animation.sample_frame(&self.mdl.bones, frame, SampleOptions::default()));
+        pose
+    }
+}
@@ -0,0 +1,82 @@
+//! Listing the external files a model depends on
+
+use crate::mdl::Mdl;
+use crate::{paths, Model};
+
+/// The kind of file referenced by a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    Mdl,
+    Vvd,
+    Vtx,
+    Phy,
+    /// Animation blocks stored outside the `.mdl`
+    Ani,
+    /// A model referenced with `$includemodel`
+    IncludeModel,
+    /// A possible location of a material, only one of the candidates for a texture has to exist
+    Material,
+}
+
+/// A file referenced by a model, with a path relative to the game directory
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReferencedFile {
+    pub kind: FileKind,
+    pub path: String,
+}
+
+impl Model {
+    /// Every external file the model references, including the model files itself
+    ///
+    /// The `.phy` is only listed when it has been loaded and the animation blocks and includes of included models
+    /// only when the included models are loaded. Textures referenced by the materials are not included since
+    /// the materials aren't parsed.
+    pub fn referenced_files(&self) -> Vec<ReferencedFile> {
+        let mut files = Vec::new();
+        let mut push = |kind: FileKind, path: String| {
+            let file = ReferencedFile { kind, path };
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        };
+
+        let name = paths::normalize_separators(self.full_name());
+        let base = format!("models/{}", name.strip_suffix(".mdl").unwrap_or(&name));
+        push(FileKind::Mdl, format!("{base}.mdl"));
+        push(FileKind::Vvd, format!("{base}.vvd"));
+        push(FileKind::Vtx, format!("{base}.dx90.vtx"));
+        if self.phy.is_some() {
+            push(FileKind::Phy, format!("{base}.phy"));
+        }
+
+        for texture in &self.mdl.textures {
+            for dir in &texture.search_paths {
+                push(
+                    FileKind::Material,
+                    format!("materials/{}.vmt", paths::join(dir, &texture.name)),
+                );
+            }
+        }
+
+        let mdls = std::iter::once(&self.mdl).chain(self.included.iter().map(|(_, mdl)| mdl));
+        for mdl in mdls {
+            push_animation_files(mdl, &mut push);
+        }
+        files
+    }
+}
+
+fn push_animation_files(mdl: &Mdl, push: &mut impl FnMut(FileKind, String)) {
+    if !mdl.animation_block_source.is_empty() {
+        push(
+            FileKind::Ani,
+            paths::normalize_separators(&mdl.animation_block_source),
+        );
+    }
+    for include in &mdl.include_models {
+        push(
+            FileKind::IncludeModel,
+            paths::normalize_separators(&include.name),
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Transfer animations between models with different skeletons
+//!
+//! Bones are matched between the skeletons using a [`BoneMapping`], the animated rotations and positions
+//! are then applied relative to the rest pose of the target skeleton, so the proportions of the target
+//! are kept intact.
+
+use crate::biped::BipedBone;
+use crate::mdl::{
+    Animation, AnimationDescription, AnimationFlags, Bone, BoneId, PositionData, RotationData,
+    StudioAnimFlags,
+};
+use crate::{Mdl, Quaternion, Vector};
+use cgmath::One;
+
+/// Mapping from bones in the source skeleton to bones in the target skeleton
+#[derive(Debug, Clone, Default)]
+pub struct BoneMapping {
+    bones: Vec<(BoneId, BoneId)>,
+}
+
+impl BoneMapping {
+    /// Match bones between the skeletons by name, ignoring case
+    pub fn by_name(source: &Mdl, target: &Mdl) -> Self {
+        Self::from_fn(source, target, |source_bone, target_bone| {
+            source_bone.name.eq_ignore_ascii_case(&target_bone.name)
+        })
+    }
+
+    /// Map bones that classify as the same [`BipedBone`], regardless of their name prefix
+    pub fn by_biped(source: &Mdl, target: &Mdl) -> Self {
+        Self::from_fn(source, target, |source_bone, target_bone| {
+            BipedBone::classify(&source_bone.name)
+                .is_some_and(|bone| BipedBone::classify(&target_bone.name) == Some(bone))
+        })
+    }
+
+    /// Match bones between the skeletons using a custom predicate
+    ///
+    /// Every source bone gets mapped to the first target bone for which the predicate returns `true`.
+    pub fn from_fn<F: FnMut(&Bone, &Bone) -> bool>(
+        source: &Mdl,
+        target: &Mdl,
+        mut matches: F,
+    ) -> Self {
+        let bones = source
+            .bones
+            .iter()
+            .enumerate()
+            .filter_map(|(source_index, source_bone)| {
+                let target_index = target
+                    .bones
+                    .iter()
+                    .position(|target_bone| matches(source_bone, target_bone))?;
+                Some((BoneId::from(source_index), BoneId::from(target_index)))
+            })
+            .collect();
+        BoneMapping { bones }
+    }
+
+    /// Add or replace the mapping for a single source bone
+    pub fn insert(&mut self, source: BoneId, target: BoneId) {
+        self.bones.retain(|(bone, _)| *bone != source);
+        self.bones.push((source, target));
+    }
+
+    pub fn get(&self, source: BoneId) -> Option<BoneId> {
+        self.bones
+            .iter()
+            .find(|(bone, _)| *bone == source)
+            .map(|(_, target)| *target)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BoneId, BoneId)> + '_ {
+        self.bones.iter().copied()
+    }
+}
+
+/// Retarget an animation from the `source` skeleton onto the `target` skeleton
+///
+/// Tracks for bones that aren't in the mapping are dropped.
+/// Delta animations are added on top of the pose of the target, so their tracks are copied without rest pose correction.
+pub fn retarget(
+    animation: &AnimationDescription,
+    source: &Mdl,
+    target: &Mdl,
+    mapping: &BoneMapping,
+) -> AnimationDescription {
+    let frames = animation.frame_count.max(1);
+    let delta = animation.flags.contains(StudioAnimFlags::DELTA);
+    let animations = animation
+        .animations
+        .iter()
+        .filter_map(|track| {
+            let target_id = mapping.get(track.bone)?;
+            let source_bone = source.bones.get(usize::from(track.bone))?;
+            let target_bone = target.bones.get(usize::from(target_id))?;
+            Some(retarget_track(
+                track,
+                source_bone,
+                target_bone,
+                target_id,
+                frames,
+                delta,
+            ))
+        })
+        .collect();
+
+    AnimationDescription {
+        animations,
+        ..animation.clone()
+    }
+}
+
+fn retarget_track(
+    track: &Animation,
+    source_bone: &Bone,
+    target_bone: &Bone,
+    target_id: BoneId,
+    frames: usize,
+    delta: bool,
+) -> Animation {
+    let source_rest = cgmath::Quaternion::from(source_bone.rest_rotation());
+    let target_rest = cgmath::Quaternion::from(target_bone.rest_rotation());
+    let correction = target_rest * source_rest.conjugate();
+
+    let rotations = (0..frames)
+        .map(|frame| match track.rotation_data() {
+            RotationData::None if delta => cgmath::Quaternion::one().into(),
+            _ if delta => track.rotation(frame),
+            RotationData::None => target_bone.rest_rotation(),
+            _ => (correction * cgmath::Quaternion::from(track.rotation(frame))).into(),
+        })
+        .collect::<Vec<Quaternion>>();
+    let positions = (0..frames)
+        .map(|frame| match track.position_data() {
+            _ if delta => track.position(frame),
+            PositionData::None => target_bone.rest_position(),
+            _ => track.position(frame) - source_bone.rest_position() + target_bone.rest_position(),
+        })
+        .collect::<Vec<Vector>>();
+
+    Animation::new(
+        target_id,
+        (track.flags & AnimationFlags::STUDIO_ANIM_DELTA)
+            | AnimationFlags::STUDIO_ANIM_ANIMROT
+            | AnimationFlags::STUDIO_ANIM_ANIMPOS,
+        RotationData::Quaternions(rotations),
+        PositionData::PositionValues(positions),
+    )
+}
@@ -0,0 +1,277 @@
+//! Composing multiple models into a single scene
+//!
+//! Models are placed with a transform, bone merged onto the skeleton of another instance
+//! or parented to an attachment of another instance, like a character with its cosmetics and weapon.
+//! The geometry of the scene can be baked into world space for exporting.
+
+use crate::export::common::MeshData;
+use crate::pose::skin_vertex;
+use crate::vvd::Vertex;
+use crate::{Mesh, Model, Pose};
+use cgmath::{Matrix4, SquareMatrix};
+
+/// Identifier of an instance in a [`Scene`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InstanceId(usize);
+
+impl InstanceId {
+    /// Index of the instance in the order the instances were added
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// How an instance is positioned in the scene
+#[derive(Debug, Clone, PartialEq)]
+pub enum Placement {
+    /// Placed with a world space transform
+    Transform(Matrix4<f32>),
+    /// Every bone that also exists in the parent, matched by name ignoring case, follows the bone of the parent
+    ///
+    /// Other bones keep their pose relative to their own parent,
+    /// root bones that don't exist in the parent are placed relative to the placement of the parent.
+    BoneMerge(InstanceId),
+    /// Placed relative to an attachment of the parent
+    Attachment {
+        parent: InstanceId,
+        /// Index into the attachments of the parent model
+        attachment: usize,
+        /// Transform relative to the attachment
+        offset: Matrix4<f32>,
+    },
+}
+
+/// A model placed in a scene
+#[derive(Clone)]
+pub struct Instance<'a> {
+    pub model: &'a Model,
+    pub placement: Placement,
+    /// Index of the skin table used for the materials
+    pub skin: usize,
+    pub pose: Pose,
+}
+
+/// A collection of placed models
+///
+/// Parents are always added before their children, so instances can be resolved in order.
+#[derive(Clone, Default)]
+pub struct Scene<'a> {
+    instances: Vec<Instance<'a>>,
+}
+
+/// A mesh of an instance with its vertices skinned and transformed into world space
+pub struct SceneMesh<'a> {
+    pub instance: InstanceId,
+    pub model: &'a Model,
+    pub mesh: Mesh<'a>,
+    /// Index of the skin table used by the instance
+    pub skin: usize,
+    pub vertices: Vec<Vertex>,
+    /// Triangles indexing into the [`vertices`](Self::vertices) of the mesh
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl<'a> Scene<'a> {
+    pub fn new() -> Self {
+        Scene::default()
+    }
+
+    fn push(&mut self, model: &'a Model, placement: Placement) -> InstanceId {
+        self.instances.push(Instance {
+            model,
+            placement,
+            skin: 0,
+            pose: model.rest_pose(),
+        });
+        InstanceId(self.instances.len() - 1)
+    }
+
+    /// Place a model with a world space transform
+    pub fn add(&mut self, model: &'a Model, transform: Matrix4<f32>) -> InstanceId {
+        self.push(model, Placement::Transform(transform))
+    }
+
+    /// Bone merge a model onto the skeleton of an existing instance
+    pub fn add_bone_merged(&mut self, model: &'a Model, parent: InstanceId) -> InstanceId {
+        self.push(model, Placement::BoneMerge(parent))
+    }
+
+    /// Place a model relative to an attachment of an existing instance, matched by name ignoring case
+    ///
+    /// Returns `None` if the parent doesn't exist or has no attachment with the name.
+    pub fn add_attached(
+        &mut self,
+        model: &'a Model,
+        parent: InstanceId,
+        attachment: &str,
+        offset: Matrix4<f32>,
+    ) -> Option<InstanceId> {
+        let attachment = self
+            .instance(parent)?
+            .model
+            .mdl
+            .attachments
+            .iter()
+            .position(|existing| existing.name.eq_ignore_ascii_case(attachment))?;
+        Some(self.push(
+            model,
+            Placement::Attachment {
+                parent,
+                attachment,
+                offset,
+            },
+        ))
+    }
+
+    pub fn instance(&self, id: InstanceId) -> Option<&Instance<'a>> {
+        self.instances.get(id.0)
+    }
+
+    /// Get an instance to change its skin or pose
+    pub fn instance_mut(&mut self, id: InstanceId) -> Option<&mut Instance<'a>> {
+        self.instances.get_mut(id.0)
+    }
+
+    pub fn instances(&self) -> impl Iterator<Item = (InstanceId, &Instance<'a>)> {
+        self.instances
+            .iter()
+            .enumerate()
+            .map(|(i, instance)| (InstanceId(i), instance))
+    }
+
+    /// The world space transform of every bone, for every instance
+    ///
+    /// Instances referencing a missing parent or attachment are placed at the origin.
+    pub fn bone_transforms(&self) -> Vec<Vec<Matrix4<f32>>> {
+        let mut transforms: Vec<Vec<Matrix4<f32>>> = Vec::with_capacity(self.instances.len());
+        // the transform the bones of every instance are placed relative to
+        let mut roots: Vec<Matrix4<f32>> = Vec::with_capacity(self.instances.len());
+        for instance in &self.instances {
+            let model = instance.model;
+            let (root, bone_transforms) = match &instance.placement {
+                Placement::Transform(transform) => (
+                    *transform,
+                    instance
+                        .pose
+                        .world_transforms()
+                        .iter()
+                        .map(|bone| transform * bone)
+                        .collect(),
+                ),
+                Placement::Attachment {
+                    parent,
+                    attachment,
+                    offset,
+                } => {
+                    let root = self
+                        .instance(*parent)
+                        .and_then(|parent_instance| {
+                            let attachment =
+                                parent_instance.model.mdl.attachments.get(*attachment)?;
+                            let bone = usize::try_from(attachment.local_bone).ok()?;
+                            Some(transforms.get(parent.0)?.get(bone)? * attachment.local.matrix())
+                        })
+                        .unwrap_or_else(Matrix4::identity)
+                        * offset;
+                    (
+                        root,
+                        instance
+                            .pose
+                            .world_transforms()
+                            .iter()
+                            .map(|bone| root * bone)
+                            .collect(),
+                    )
+                }
+                Placement::BoneMerge(parent) => {
+                    let parent_model = self.instance(*parent).map(|parent| parent.model);
+                    let parent_transforms = transforms.get(parent.0);
+                    let root = roots
+                        .get(parent.0)
+                        .copied()
+                        .unwrap_or_else(Matrix4::identity);
+                    let mut merged: Vec<Option<Matrix4<f32>>> = vec![None; instance.pose.len()];
+                    for bone in model.bone_tree() {
+                        let local = instance
+                            .pose
+                            .local_transform(bone.key())
+                            .unwrap_or_else(Matrix4::identity);
+                        let target = parent_model
+                            .and_then(|parent_model| parent_model.bone_by_name(&bone.name))
+                            .and_then(|target| parent_transforms?.get(usize::from(target.key())));
+                        let parent = bone
+                            .parent()
+                            .and_then(|parent| *merged.get(usize::from(parent.key()))?);
+                        merged[usize::from(bone.key())] = Some(match (target, parent) {
+                            (Some(target), _) => *target,
+                            (None, Some(parent)) => parent * local,
+                            (None, None) => root * local,
+                        });
+                    }
+                    let bone_transforms = merged
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, transform)| {
+                            // bones in a cyclic hierarchy aren't part of the tree
+                            transform.unwrap_or_else(|| {
+                                root * instance
+                                    .pose
+                                    .local_transform(index.into())
+                                    .unwrap_or_else(Matrix4::identity)
+                            })
+                        })
+                        .collect();
+                    (root, bone_transforms)
+                }
+            };
+            roots.push(root);
+            transforms.push(bone_transforms);
+        }
+        transforms
+    }
+
+    /// The meshes of every instance, skinned with the pose of the instance and transformed into world space
+    pub fn meshes(&self) -> Vec<SceneMesh<'a>> {
+        let bone_transforms = self.bone_transforms();
+        self.instances
+            .iter()
+            .zip(bone_transforms)
+            .enumerate()
+            .flat_map(|(index, (instance, bone_transforms))| {
+                let model = instance.model;
+                let skinning: Vec<Matrix4<f32>> = model
+                    .bones()
+                    .zip(bone_transforms)
+                    .map(|(bone, transform)| transform * bone.pose_to_bone.matrix())
+                    .collect();
+                model
+                    .meshes()
+                    .map(|mesh| {
+                        let (vertices, triangles) = skinned_mesh(&mesh, &skinning);
+                        SceneMesh {
+                            instance: InstanceId(index),
+                            model,
+                            skin: instance.skin,
+                            mesh,
+                            vertices,
+                            triangles,
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// Deduplicate the vertices of a mesh and apply the skinning transforms
+fn skinned_mesh(mesh: &Mesh, skinning: &[Matrix4<f32>]) -> (Vec<Vertex>, Vec<[usize; 3]>) {
+    let MeshData {
+        vertices,
+        triangles,
+    } = MeshData::new(mesh);
+    let vertices = vertices
+        .into_iter()
+        .map(|vertex| skin_vertex(vertex, skinning))
+        .collect();
+    (vertices, triangles)
+}
@@ -5,9 +5,21 @@ use cgmath::{Angle, Deg, Euler, InnerSpace, Matrix3, Matrix4, Rad, Rotation3, Tr
 use std::f32::consts::PI;
 use std::fmt;
 use std::fmt::{Display, Formatter};
-use std::ops::{Add, Mul};
+use std::ops::{Add, Mul, Sub};
+
+/// Scalar type used when accumulating values over many vertices or frames
+///
+/// This is `f64` when the `f64` feature is enabled, the data stored in the model files always uses `f32`.
+#[cfg(not(feature = "f64"))]
+pub type Scalar = f32;
+/// Scalar type used when accumulating values over many vertices or frames
+///
+/// This is `f64` when the `f64` feature is enabled, the data stored in the model files always uses `f32`.
+#[cfg(feature = "f64")]
+pub type Scalar = f64;
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod, PartialEq, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct Vector {
     pub x: f32,
@@ -40,10 +52,47 @@ impl Vector {
         [self.x, self.y, self.z].into_iter()
     }
 
+    pub fn length(&self) -> f32 {
+        Vector3::from(*self).magnitude()
+    }
+
+    /// Component-wise minimum
+    pub fn min(&self, other: Vector) -> Vector {
+        Vector {
+            x: self.x.min(other.x),
+            y: self.y.min(other.y),
+            z: self.z.min(other.z),
+        }
+    }
+
+    /// Component-wise maximum
+    pub fn max(&self, other: Vector) -> Vector {
+        Vector {
+            x: self.x.max(other.x),
+            y: self.y.max(other.y),
+            z: self.z.max(other.z),
+        }
+    }
+
     pub fn transformed<T: Into<Matrix4<f32>>>(&self, transform: T) -> Vector {
         let transform = transform.into();
         transform.transform_vector((*self).into()).into()
     }
+
+    /// Convert to the [`Scalar`] type used for accumulation
+    pub fn to_scalar(self) -> Vector3<Scalar> {
+        Vector3::new(self.x as Scalar, self.y as Scalar, self.z as Scalar)
+    }
+
+    /// Convert back from the [`Scalar`] type used for accumulation
+    #[allow(clippy::unnecessary_cast)] // only a no-op without the f64 feature
+    pub fn from_scalar(vector: Vector3<Scalar>) -> Vector {
+        Vector {
+            x: vector.x as f32,
+            y: vector.y as f32,
+            z: vector.z as f32,
+        }
+    }
 }
 
 impl From<Vector> for [f32; 3] {
@@ -80,6 +129,18 @@ impl Add<Vector> for Vector {
     }
 }
 
+impl Sub<Vector> for Vector {
+    type Output = Vector;
+
+    fn sub(self, rhs: Vector) -> Self::Output {
+        Vector {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
 impl Mul<f32> for Vector {
     type Output = Vector;
 
@@ -93,6 +154,7 @@ impl Mul<f32> for Vector {
 }
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct Quaternion {
     pub x: f32,
@@ -112,6 +174,28 @@ impl Default for Quaternion {
     }
 }
 
+impl Quaternion {
+    /// Flip the sign of the quaternion if needed so it's in the same hemisphere as `reference`
+    ///
+    /// Both quaternions describe the same rotation, but aligned quaternions interpolate along the shortest path.
+    pub fn aligned(self, reference: Quaternion) -> Quaternion {
+        let dot = self.x * reference.x
+            + self.y * reference.y
+            + self.z * reference.z
+            + self.w * reference.w;
+        if dot < 0.0 {
+            Quaternion {
+                x: -self.x,
+                y: -self.y,
+                z: -self.z,
+                w: -self.w,
+            }
+        } else {
+            self
+        }
+    }
+}
+
 impl From<Quaternion> for cgmath::Quaternion<f32> {
     fn from(q: Quaternion) -> Self {
         [q.x, q.y, q.z, q.w].into()
@@ -159,6 +243,7 @@ impl Mul<RadianEuler> for Quaternion {
 }
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct RadianEuler {
     /// Roll
@@ -234,6 +319,26 @@ impl From<RadianEuler> for Quaternion {
     }
 }
 
+impl From<Quaternion> for RadianEuler {
+    fn from(q: Quaternion) -> Self {
+        let roll = f32::atan2(
+            2.0 * (q.w * q.x + q.y * q.z),
+            1.0 - 2.0 * (q.x * q.x + q.y * q.y),
+        );
+        let pitch = (2.0 * (q.w * q.y - q.z * q.x)).clamp(-1.0, 1.0).asin();
+        let yaw = f32::atan2(
+            2.0 * (q.w * q.z + q.x * q.y),
+            1.0 - 2.0 * (q.y * q.y + q.z * q.z),
+        );
+        // roll is inverted when converting from euler angles
+        RadianEuler {
+            x: -roll,
+            y: pitch,
+            z: yaw,
+        }
+    }
+}
+
 impl From<RadianEuler> for Matrix4<f32> {
     fn from(value: RadianEuler) -> Self {
         cgmath::Quaternion::from(value).into()
@@ -292,6 +397,7 @@ impl<const LEN: usize> Display for FixedString<LEN> {
 }
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct Transform3x4 {
     transform: [[f32; 4]; 3],
@@ -320,6 +426,27 @@ impl Transform3x4 {
         }
     }
 
+    /// The transform as a matrix in the model's own coordinate space
+    ///
+    /// Unlike the [`From`] conversion into [`Matrix4`] this doesn't do any axis remapping.
+    pub fn matrix(&self) -> Matrix4<f32> {
+        let [x, y, z] = self.transform;
+        Matrix4::new(
+            x[0], y[0], z[0], 0.0, //
+            x[1], y[1], z[1], 0.0, //
+            x[2], y[2], z[2], 0.0, //
+            x[3], y[3], z[3], 1.0,
+        )
+    }
+
+    /// Create a transform from a matrix in the model's own coordinate space, the inverse of [`matrix`](Self::matrix)
+    pub fn from_matrix(matrix: Matrix4<f32>) -> Self {
+        let row = |i: usize| [matrix.x[i], matrix.y[i], matrix.z[i], matrix.w[i]];
+        Transform3x4 {
+            transform: [row(0), row(1), row(2)],
+        }
+    }
+
     pub fn rotation_matrix(&self) -> Matrix3<f32> {
         let mat = Matrix3 {
             x: self.x(),
@@ -348,6 +475,13 @@ impl Transform3x4 {
         cgmath::Quaternion::from(self.rotation_matrix()).into()
     }
 
+    /// Scale the translation of the transform, leaving the rotation intact
+    pub fn scale_translation(&mut self, factor: f32) {
+        for row in self.transform.iter_mut() {
+            row[3] *= factor;
+        }
+    }
+
     pub fn translate(&self) -> Vector {
         [
             self.transform[0][3],
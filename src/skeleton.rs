@@ -0,0 +1,193 @@
+//! Queries over the skeleton of a model
+
+use crate::mdl::{Bone, BoneId};
+use crate::{Handle, Model};
+use thiserror::Error;
+
+/// Maximum differences in the bind pose for bones to be considered equal
+#[derive(Debug, Clone, Copy)]
+pub struct SkeletonTolerance {
+    /// Maximum distance between bone positions
+    pub position: f32,
+    /// Maximum angle between bone rotations in radians
+    pub rotation: f32,
+}
+
+impl Default for SkeletonTolerance {
+    fn default() -> Self {
+        SkeletonTolerance {
+            position: 0.01,
+            rotation: 0.001,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoneDifference {
+    /// The bone only exists in the first skeleton
+    Missing { name: String },
+    /// The bone only exists in the second skeleton
+    Extra { name: String },
+    /// The bone has a different parent
+    Parent {
+        name: String,
+        parent: Option<String>,
+        other_parent: Option<String>,
+    },
+    /// The bind position of the bone differs by more than the tolerance
+    Position { name: String, distance: f32 },
+    /// The bind rotation of the bone differs by more than the tolerance
+    Rotation { name: String, angle: f32 },
+}
+
+/// Result of comparing two skeletons
+#[derive(Debug, Clone, Default)]
+pub struct SkeletonComparison {
+    pub differences: Vec<BoneDifference>,
+}
+
+impl SkeletonComparison {
+    /// Whether animations can be shared between the skeletons
+    ///
+    /// Extra bones in the second skeleton don't prevent sharing animations.
+    pub fn is_compatible(&self) -> bool {
+        self.differences
+            .iter()
+            .all(|difference| matches!(difference, BoneDifference::Extra { .. }))
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+/// Reasons a bone chain can't be extracted
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum BoneChainError {
+    #[error("no bone named {0}")]
+    UnknownBone(String),
+    #[error("chain from {bone} only has {available} bones, expected {length}")]
+    TooShort {
+        bone: String,
+        length: usize,
+        available: usize,
+    },
+    #[error("bone {bone} is its own ancestor")]
+    Cyclic { bone: String },
+}
+
+impl Model {
+    /// The chain of `length` bones from an end effector up through its ancestors, like foot, calf and thigh
+    ///
+    /// The end effector is found by name, ignoring case, and is the first bone of the chain.
+    /// Every following bone is the parent of the bone before it.
+    pub fn bone_chain(
+        &self,
+        end_effector: &str,
+        length: usize,
+    ) -> Result<Vec<Handle<'_, Bone, BoneId>>, BoneChainError> {
+        let end = self
+            .bone_by_name(end_effector)
+            .ok_or_else(|| BoneChainError::UnknownBone(end_effector.into()))?;
+        let mut chain = Vec::with_capacity(length.min(self.mdl.bones.len()));
+        for bone in std::iter::once(end.clone())
+            .chain(end.ancestors())
+            .take(length)
+        {
+            if chain.contains(&bone) {
+                return Err(BoneChainError::Cyclic {
+                    bone: bone.name.clone(),
+                });
+            }
+            chain.push(bone);
+        }
+        if chain.len() < length {
+            return Err(BoneChainError::TooShort {
+                bone: end.name.clone(),
+                length,
+                available: chain.len(),
+            });
+        }
+        Ok(chain)
+    }
+
+    /// Compare the skeleton of the model with another model using the default tolerances
+    pub fn skeleton_compatible_with(&self, other: &Model) -> SkeletonComparison {
+        self.skeleton_compatible_with_tolerance(other, SkeletonTolerance::default())
+    }
+
+    /// Compare the bone names, hierarchy and bind pose of the skeleton with another model
+    ///
+    /// Bones are matched by name, ignoring case.
+    pub fn skeleton_compatible_with_tolerance(
+        &self,
+        other: &Model,
+        tolerance: SkeletonTolerance,
+    ) -> SkeletonComparison {
+        let mut differences = Vec::new();
+        for bone in self.bones() {
+            let Some(other_bone) = other.bone_by_name(&bone.name) else {
+                differences.push(BoneDifference::Missing {
+                    name: bone.name.clone(),
+                });
+                continue;
+            };
+
+            let parent = bone.parent().map(|parent| parent.name.clone());
+            let other_parent = other_bone.parent().map(|parent| parent.name.clone());
+            let same_parent = match (&parent, &other_parent) {
+                (Some(parent), Some(other_parent)) => parent.eq_ignore_ascii_case(other_parent),
+                (None, None) => true,
+                _ => false,
+            };
+            if !same_parent {
+                differences.push(BoneDifference::Parent {
+                    name: bone.name.clone(),
+                    parent,
+                    other_parent,
+                });
+            }
+
+            let distance = (bone.rest_position() - other_bone.rest_position()).length();
+            if distance > tolerance.position {
+                differences.push(BoneDifference::Position {
+                    name: bone.name.clone(),
+                    distance,
+                });
+            }
+
+            let angle = rotation_difference(&bone, &other_bone);
+            if angle > tolerance.rotation {
+                differences.push(BoneDifference::Rotation {
+                    name: bone.name.clone(),
+                    angle,
+                });
+            }
+        }
+
+        differences.extend(
+            other
+                .bones()
+                .filter(|other_bone| self.bone_by_name(&other_bone.name).is_none())
+                .map(|other_bone| BoneDifference::Extra {
+                    name: other_bone.name.clone(),
+                }),
+        );
+
+        SkeletonComparison { differences }
+    }
+
+    /// Find a bone by name, ignoring case
+    pub fn bone_by_name(&self, name: &str) -> Option<Handle<'_, Bone, BoneId>> {
+        self.bones()
+            .find(|bone| bone.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Angle between the rest rotations of two bones in radians
+fn rotation_difference(a: &Bone, b: &Bone) -> f32 {
+    let a = a.rest_rotation();
+    let b = b.rest_rotation();
+    let dot = (a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w).abs();
+    2.0 * dot.min(1.0).acos()
+}
@@ -0,0 +1,118 @@
+use crate::vtx::StripFlags;
+use crate::{Mesh, ModelError};
+use itertools::Either;
+use std::iter::once;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveType {
+    TriangleList,
+    TriangleStrip,
+}
+
+/// A range of indices drawn with a single primitive type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StripRange {
+    pub primitive: PrimitiveType,
+    pub range: Range<usize>,
+}
+
+/// The indices of a mesh, grouped by the strips they are drawn with
+#[derive(Debug, Clone, Default)]
+pub struct MeshStrips {
+    /// Indices into the model's vertex list
+    pub indices: Vec<usize>,
+    pub strips: Vec<StripRange>,
+}
+
+impl MeshStrips {
+    /// Concatenate all strips into a single triangle strip, separated by a `0xFFFF` primitive restart index
+    ///
+    /// Triangle lists are split into a strip per triangle. Returns `None` if any index doesn't fit in a `u16`.
+    pub fn primitive_restart_u16(&self) -> Option<Vec<u16>> {
+        self.primitive_restart()
+            .map(|index| match index {
+                Some(index) => u16::try_from(index).ok().filter(|index| *index != u16::MAX),
+                None => Some(u16::MAX),
+            })
+            .collect()
+    }
+
+    /// Concatenate all strips into a single triangle strip, separated by a `0xFFFFFFFF` primitive restart index
+    ///
+    /// Triangle lists are split into a strip per triangle.
+    pub fn primitive_restart_u32(&self) -> Vec<u32> {
+        self.primitive_restart()
+            .map(|index| index.map_or(u32::MAX, |index| index as u32))
+            .collect()
+    }
+
+    /// All indices with `None` marking the restart between strips
+    fn primitive_restart(&self) -> impl Iterator<Item = Option<usize>> + '_ {
+        self.strips
+            .iter()
+            .flat_map(|strip| {
+                let indices = &self.indices[strip.range.clone()];
+                match strip.primitive {
+                    PrimitiveType::TriangleStrip => Either::Left(once(indices)),
+                    PrimitiveType::TriangleList => Either::Right(indices.chunks_exact(3)),
+                }
+            })
+            .filter(|strip| !strip.is_empty())
+            .enumerate()
+            .flat_map(|(i, strip)| {
+                let restart = (i > 0).then_some(None);
+                restart.into_iter().chain(strip.iter().copied().map(Some))
+            })
+    }
+}
+
+impl Mesh<'_> {
+    /// The indices of the mesh with the triangle strips kept intact
+    ///
+    /// Triangles have the same counter-clockwise winding as [`vertex_strip_indices`](Self::vertex_strip_indices).
+    /// To flip the winding of a triangle strip, its first index is repeated, adding a single degenerate triangle.
+    pub fn strips(&self) -> Result<MeshStrips, ModelError> {
+        let mdl_offset = self.mdl.vertex_offset as usize + self.model_vertex_offset;
+        let mut strips = MeshStrips::default();
+        for strip_group in self.vtx.strip_groups.iter() {
+            let vertex_index = |index: &u16| -> Result<usize, ModelError> {
+                let index = *index as usize;
+                let vertex = strip_group
+                    .vertices
+                    .get(index)
+                    .ok_or(ModelError::InvalidIndex {
+                        data: "strip group vertex",
+                        index,
+                    })?;
+                Ok(vertex.original_mesh_vertex_id as usize + mdl_offset)
+            };
+            for strip in strip_group.strips.iter() {
+                let source = strip_group
+                    .indices
+                    .get(strip.index_range())
+                    .unwrap_or_default();
+                let start = strips.indices.len();
+                let primitive = if strip.flags.contains(StripFlags::IS_TRI_STRIP) {
+                    if let Some(first) = source.first() {
+                        strips.indices.push(vertex_index(first)?);
+                    }
+                    for index in source {
+                        strips.indices.push(vertex_index(index)?);
+                    }
+                    PrimitiveType::TriangleStrip
+                } else {
+                    for index in source.iter().rev() {
+                        strips.indices.push(vertex_index(index)?);
+                    }
+                    PrimitiveType::TriangleList
+                };
+                strips.strips.push(StripRange {
+                    primitive,
+                    range: start..strips.indices.len(),
+                });
+            }
+        }
+        Ok(strips)
+    }
+}
@@ -0,0 +1,172 @@
+//! Validation and repair of the tangents stored in the vertex data
+
+use crate::vvd::{Tangent, Vertex};
+use crate::{Model, Scalar, Vector};
+use cgmath::{InnerSpace, Vector3};
+
+/// Maximum deviation from unit length and orthogonality before a tangent is considered broken
+const TOLERANCE: f32 = 0.1;
+
+/// Statistics about the tangents of a model
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TangentReport {
+    /// Number of tangents that were checked
+    pub checked: usize,
+    /// Tangents that aren't unit length or contain non-finite values
+    pub invalid_length: usize,
+    /// Tangents that aren't perpendicular to the vertex normal
+    pub not_orthogonal: usize,
+    /// Tangents where the handedness doesn't match the winding of the uv coordinates
+    pub wrong_handedness: usize,
+    /// Tangents that were regenerated
+    pub repaired: usize,
+}
+
+impl TangentReport {
+    pub fn is_valid(&self) -> bool {
+        self.invalid_length == 0 && self.not_orthogonal == 0 && self.wrong_handedness == 0
+    }
+}
+
+impl Model {
+    /// Check the stored tangents against the normals and uv layout of the model
+    pub fn validate_tangents(&self) -> TangentReport {
+        self.check_tangents().0
+    }
+
+    /// Check the stored tangents and regenerate any broken ones from the normals and uv layout of the model
+    pub fn repair_tangents(&mut self) -> TangentReport {
+        let (mut report, repairs) = self.check_tangents();
+        report.repaired = repairs.len();
+        for (index, tangent) in repairs {
+            if let Some(stored) = self.vvd.tangents.get_mut(index) {
+                *stored = tangent;
+            }
+        }
+        report
+    }
+
+    /// Check all tangents, returning the statistics and the regenerated tangents for all broken ones
+    fn check_tangents(&self) -> (TangentReport, Vec<(usize, Tangent)>) {
+        let computed = self.computed_tangents();
+        let mut report = TangentReport::default();
+        let mut repairs = Vec::new();
+
+        for (index, computed) in computed.into_iter().enumerate() {
+            // vertices without a stored tangent can't be checked or repaired
+            let (Some((tangent, bitangent)), Some(vertex), Some(stored)) = (
+                computed,
+                self.vvd.vertices.get(index),
+                self.vvd.tangents.get(index).copied(),
+            ) else {
+                continue;
+            };
+            let normal: Vector3<f32> = vertex.normal.into();
+            let direction = Vector3::from(stored.direction());
+            report.checked += 1;
+
+            let length = direction.magnitude();
+            let valid_length = <[f32; 4]>::from(stored)
+                .iter()
+                .all(|value| value.is_finite())
+                && (length - 1.0).abs() < TOLERANCE;
+            let orthogonal = valid_length && direction.dot(normal).abs() < TOLERANCE;
+            let handedness = handedness(normal, tangent, bitangent);
+            let correct_handedness = stored.w == handedness;
+
+            if !valid_length {
+                report.invalid_length += 1;
+            } else if !orthogonal {
+                report.not_orthogonal += 1;
+            }
+            if !correct_handedness {
+                report.wrong_handedness += 1;
+            }
+
+            if !(valid_length && orthogonal && correct_handedness) {
+                let tangent = orthogonalize(normal, tangent);
+                repairs.push((index, Tangent::new(tangent.into(), handedness)));
+            }
+        }
+
+        (report, repairs)
+    }
+
+    /// Calculate the tangent and bitangent direction for each vertex from the triangles using it
+    ///
+    /// Vertices that aren't used by any triangle have no computed tangent,
+    /// triangles with a vertex that doesn't exist are skipped.
+    /// The directions of all triangles are accumulated with [`Scalar`] precision.
+    fn computed_tangents(&self) -> Vec<Option<(Vector3<f32>, Vector3<f32>)>> {
+        let zero = Vector3::<Scalar>::new(0.0, 0.0, 0.0);
+        let mut tangents = vec![None; self.vvd.vertices.len()];
+        for mesh in self.meshes() {
+            for triangle in mesh.triangles() {
+                let lookup = |index: usize| self.vvd.vertices.get(index);
+                let (Some(a), Some(b), Some(c)) = (
+                    lookup(triangle[0]),
+                    lookup(triangle[1]),
+                    lookup(triangle[2]),
+                ) else {
+                    continue;
+                };
+                let edge1 = b.position.to_scalar() - a.position.to_scalar();
+                let edge2 = c.position.to_scalar() - a.position.to_scalar();
+                let uv = |vertex: &Vertex, axis: usize| vertex.texture_coordinates[axis] as Scalar;
+                let (du1, dv1) = (uv(b, 0) - uv(a, 0), uv(b, 1) - uv(a, 1));
+                let (du2, dv2) = (uv(c, 0) - uv(a, 0), uv(c, 1) - uv(a, 1));
+                let determinant = du1 * dv2 - du2 * dv1;
+                let (tangent, bitangent) = if determinant.abs() > Scalar::EPSILON {
+                    let r = 1.0 / determinant;
+                    (
+                        (edge1 * dv2 - edge2 * dv1) * r,
+                        (edge2 * du1 - edge1 * du2) * r,
+                    )
+                } else {
+                    (zero, zero)
+                };
+                for index in triangle {
+                    if let Some(sums) = tangents.get_mut(index) {
+                        let (sum_tangent, sum_bitangent) = sums.get_or_insert((zero, zero));
+                        *sum_tangent += tangent;
+                        *sum_bitangent += bitangent;
+                    }
+                }
+            }
+        }
+        tangents
+            .into_iter()
+            .map(|sums| {
+                sums.map(|(tangent, bitangent)| {
+                    (
+                        Vector::from_scalar(tangent).into(),
+                        Vector::from_scalar(bitangent).into(),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// The handedness of the tangent space, `-1.0` if the uv coordinates are mirrored
+fn handedness(normal: Vector3<f32>, tangent: Vector3<f32>, bitangent: Vector3<f32>) -> f32 {
+    if normal.cross(tangent).dot(bitangent) < 0.0 {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+/// Make the tangent perpendicular to the normal, picking an arbitrary perpendicular direction for degenerate tangents
+fn orthogonalize(normal: Vector3<f32>, tangent: Vector3<f32>) -> Vector3<f32> {
+    let tangent = tangent - normal * normal.dot(tangent);
+    if tangent.magnitude2() > f32::EPSILON {
+        return tangent.normalize();
+    }
+    let axis = if normal.x.abs() < 0.9 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+    normal.cross(axis).normalize()
+}
@@ -0,0 +1,482 @@
+//! Baking transforms into the model data
+
+use crate::mdl::{
+    Animation, AnimationFlags, BoneSample, PositionData, ProceduralBone, RotationData,
+    StudioAnimFlags,
+};
+use crate::pose::{skin_vertex, skinning_matrix};
+use crate::vvd::Tangent;
+use crate::{Model, Pose, Quaternion, Transform3x4, Vector};
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, Point3, SquareMatrix, Transform, Zero};
+use std::collections::BTreeSet;
+
+impl Model {
+    /// Create a copy of the model with all positions scaled uniformly
+    ///
+    /// This scales the vertices, the bones' rest pose, hitboxes, attachments and the positions in the animations,
+    /// similar to what `$scale` does when compiling the model.
+    pub fn scaled(&self, factor: f32) -> Model {
+        let mut model = self.clone();
+        model.mdl.scale(factor);
+        model.vvd.scale(factor);
+        model
+    }
+
+    /// Create a copy of the model with a transform baked into the model data
+    ///
+    /// The full transform is applied to the vertices, with normals transformed by the normal matrix.
+    /// Since bones can only hold a rotation and translation, the skeleton and animations only receive the rotation,
+    /// translation and uniform part of the scale from the transform.
+    ///
+    /// Hitboxes and attachments are relative to their bone and follow the transformed skeleton.
+    ///
+    /// The triangle winding is left as is, so transforms that mirror the model will turn the faces inside out,
+    /// use [`mirrored`](Self::mirrored) for those instead.
+    pub fn transformed(&self, transform: Matrix4<f32>) -> Model {
+        let mut model = self.clone();
+
+        let linear = Matrix3::from_cols(
+            transform.x.truncate(),
+            transform.y.truncate(),
+            transform.z.truncate(),
+        );
+        let determinant = linear.determinant();
+        let scale = determinant.abs().cbrt();
+        let rotation = cgmath::Quaternion::from(linear * (1.0 / scale)).normalize();
+        let rigid = Matrix4::from_translation(transform.w.truncate()) * Matrix4::from(rotation);
+        let normal_matrix = linear.invert().unwrap_or(linear).transpose();
+        let handedness = determinant.signum();
+
+        for vertex in model.vvd.vertices.iter_mut() {
+            vertex.position = transform_point(transform, vertex.position);
+            vertex.normal = (normal_matrix * cgmath::Vector3::from(vertex.normal))
+                .normalize()
+                .into();
+        }
+        for tangent in model.vvd.tangents.iter_mut() {
+            let direction = (linear * cgmath::Vector3::from(tangent.direction())).normalize();
+            *tangent = Tangent::new(direction.into(), tangent.w * handedness);
+        }
+
+        let mdl = &mut model.mdl;
+        if (scale - 1.0).abs() > f32::EPSILON {
+            mdl.scale(scale);
+        }
+
+        let header = &mut mdl.header;
+        header.eye_position = transform_point(rigid, header.eye_position);
+        header.illumination_position = transform_point(rigid, header.illumination_position);
+        header.bounding_box = transform_box(rigid, header.bounding_box);
+        header.view_bounding_box = transform_box(rigid, header.view_bounding_box);
+
+        for mesh in mdl
+            .body_parts
+            .iter_mut()
+            .flat_map(|part| part.models.iter_mut())
+            .flat_map(|model| model.meshes.iter_mut())
+        {
+            mesh.center = transform_point(rigid, mesh.center);
+        }
+
+        let inverse = rigid.invert().unwrap_or(rigid);
+        let bone_count = mdl.bones.len();
+        let is_root = |parent| usize::from(parent) >= bone_count;
+        for bone in mdl.bones.iter_mut() {
+            if is_root(bone.parent) {
+                bone.set_rest_position(transform_point(rigid, bone.rest_position()));
+                bone.set_rest_rotation(
+                    (rotation * cgmath::Quaternion::from(bone.rest_rotation())).into(),
+                );
+            }
+            bone.pose_to_bone = Transform3x4::from_matrix(bone.pose_to_bone.matrix() * inverse);
+        }
+
+        let bones = &mdl.bones;
+        for animation in mdl.local_animations.iter_mut() {
+            for track in animation.animations.iter_mut() {
+                let root = bones
+                    .get(usize::from(track.bone))
+                    .is_some_and(|bone| is_root(bone.parent));
+                if root && !track.flags.contains(AnimationFlags::STUDIO_ANIM_DELTA) {
+                    *track = map_track(
+                        track,
+                        |q| (rotation * cgmath::Quaternion::from(q)).into(),
+                        |position| transform_point(rigid, position),
+                    );
+                }
+            }
+        }
+
+        model
+    }
+
+    /// Create a copy of the model with a pose baked in as the new bind pose
+    ///
+    /// The vertices are skinned into the pose, the rest pose of the bones is replaced by the pose and their
+    /// `pose_to_bone` transforms are recomputed. This allows creating A-pose or T-pose variants of a model.
+    ///
+    /// The flex deltas are rotated with the skinning matrix of their vertex and the mesh centers are moved with the
+    /// average skinning matrix of the vertices of the mesh. Eyeballs are stored relative to their bone and follow the new
+    /// rest pose of the bone.
+    ///
+    /// Animations keep posing the model the same way, bones that used their rest pose in an animation get a track
+    /// holding their original rest pose. Animations stored in an animation block and the bounding boxes are left as is.
+    pub fn rebind(&self, pose: &Pose) -> Model {
+        let mut model = self.clone();
+
+        let skinning = pose.skinning_transforms();
+        let matrices: Vec<Matrix4<f32>> = self
+            .vvd
+            .vertices
+            .iter()
+            .map(|vertex| skinning_matrix(vertex, skinning))
+            .collect();
+        for (index, vertex) in model.vvd.vertices.iter_mut().enumerate() {
+            if let Some(tangent) = model.vvd.tangents.get_mut(index) {
+                let direction = (matrices[index]
+                    * cgmath::Vector3::from(tangent.direction()).extend(0.0))
+                .truncate();
+                if direction.magnitude2() > 0.0 {
+                    *tangent = Tangent::new(direction.normalize().into(), tangent.w);
+                }
+            }
+            *vertex = skin_vertex(vertex, skinning);
+        }
+
+        let centers: Vec<_> = self
+            .meshes()
+            .map(|mesh| {
+                let vertices: BTreeSet<usize> = mesh.vertex_strip_indices().flatten().collect();
+                let matrix = vertices
+                    .iter()
+                    .filter_map(|index| matrices.get(*index))
+                    .fold(Matrix4::zero(), |sum, matrix| sum + matrix);
+                let matrix = match vertices.len() {
+                    0 => Matrix4::identity(),
+                    count => matrix / count as f32,
+                };
+                let index = (mesh.body_part_index, mesh.model_index, mesh.mesh_index);
+                (index, transform_point(matrix, mesh.mdl.center))
+            })
+            .collect();
+
+        let mdl = &mut model.mdl;
+        for ((body_part, model, mesh), center) in centers {
+            mdl.body_parts[body_part].models[model].meshes[mesh].center = center;
+        }
+        for model in mdl
+            .body_parts
+            .iter_mut()
+            .flat_map(|part| part.models.iter_mut())
+        {
+            let model_offset = model.vertex_offset.max(0) as usize;
+            for mesh in model.meshes.iter_mut() {
+                let offset = model_offset + mesh.vertex_offset.max(0) as usize;
+                for vertex in mesh
+                    .flexes
+                    .iter_mut()
+                    .flat_map(|flex| flex.vertices.iter_mut())
+                {
+                    if let Some(matrix) = matrices.get(offset + usize::from(vertex.index)) {
+                        let rotate = |delta: Vector| {
+                            (matrix * cgmath::Vector3::from(delta).extend(0.0))
+                                .truncate()
+                                .into()
+                        };
+                        vertex.delta = rotate(vertex.delta);
+                        vertex.normal_delta = rotate(vertex.normal_delta);
+                    }
+                }
+            }
+        }
+
+        let rest: Vec<BoneSample> = mdl
+            .bones
+            .iter()
+            .enumerate()
+            .map(|(index, bone)| BoneSample::rest(index.into(), bone))
+            .collect();
+        for ((bone, sample), world) in mdl
+            .bones
+            .iter_mut()
+            .zip(pose.samples())
+            .zip(pose.world_transforms())
+        {
+            bone.set_rest_position(sample.position);
+            bone.set_rest_rotation(sample.rotation);
+            bone.pose_to_bone = Transform3x4::from_matrix(world.invert().unwrap_or(*world));
+        }
+
+        let changed: Vec<&BoneSample> = rest
+            .iter()
+            .zip(&mdl.bones)
+            .filter(|(rest, bone)| {
+                rest.position != bone.rest_position()
+                    || cgmath::Quaternion::from(rest.rotation)
+                        != cgmath::Quaternion::from(bone.rest_rotation())
+            })
+            .map(|(rest, _)| rest)
+            .collect();
+        for animation in mdl
+            .local_animations
+            .iter_mut()
+            .filter(|animation| animation.animation_block.is_none())
+            .filter(|animation| !animation.flags.contains(StudioAnimFlags::DELTA))
+        {
+            let frames = animation.frame_count.max(1);
+            for rest in &changed {
+                let track = animation
+                    .animations
+                    .iter_mut()
+                    .find(|track| track.bone == rest.bone);
+                match track {
+                    Some(track) if !track.flags.contains(AnimationFlags::STUDIO_ANIM_DELTA) => {
+                        *track = rest_track(track, rest, frames)
+                    }
+                    Some(_) => {}
+                    None => animation.animations.push(rest_track(
+                        &Animation::new(
+                            rest.bone,
+                            AnimationFlags::empty(),
+                            RotationData::None,
+                            PositionData::None,
+                        ),
+                        rest,
+                        frames,
+                    )),
+                }
+            }
+        }
+
+        model
+    }
+
+    /// Create a copy of the model mirrored along an axis
+    ///
+    /// Besides flipping the geometry, this reverses the triangle winding and tangent handedness, and mirrors the skeleton
+    /// and animations so the mirrored model can be animated like the original.
+    pub fn mirrored(&self, axis: Axis) -> Model {
+        let mut model = self.clone();
+
+        for vertex in model.vvd.vertices.iter_mut() {
+            vertex.position = axis.mirror(vertex.position);
+            vertex.normal = axis.mirror(vertex.normal);
+        }
+        for tangent in model.vvd.tangents.iter_mut() {
+            *tangent = Tangent::new(axis.mirror(tangent.direction()), -tangent.w);
+        }
+        model.vtx.reverse_winding();
+
+        let mdl = &mut model.mdl;
+        let header = &mut mdl.header;
+        header.eye_position = axis.mirror(header.eye_position);
+        header.illumination_position = axis.mirror(header.illumination_position);
+        header.bounding_box = axis.mirror_box(header.bounding_box);
+        header.view_bounding_box = axis.mirror_box(header.view_bounding_box);
+
+        for model in mdl
+            .body_parts
+            .iter_mut()
+            .flat_map(|part| part.models.iter_mut())
+        {
+            for mesh in model.meshes.iter_mut() {
+                mesh.center = axis.mirror(mesh.center);
+            }
+            for eyeball in model.eyeballs.iter_mut() {
+                eyeball.origin = axis.mirror(eyeball.origin);
+                eyeball.up = axis.mirror(eyeball.up);
+                eyeball.forward = axis.mirror(eyeball.forward);
+            }
+        }
+
+        // mirroring the bones as `F * B * F` keeps them as proper rotations
+        // while still mapping the mirrored vertices onto the mirrored pose
+        for bone in mdl.bones.iter_mut() {
+            bone.set_rest_position(axis.mirror(bone.rest_position()));
+            bone.set_rest_rotation(axis.mirror_rotation(bone.rest_rotation()));
+            bone.pose_to_bone = axis.mirror_transform(bone.pose_to_bone);
+            match bone.procedural_rules.as_mut() {
+                Some(ProceduralBone::AxisInterp(rules)) => {
+                    rules
+                        .position
+                        .iter_mut()
+                        .for_each(|pos| *pos = axis.mirror(*pos));
+                    rules
+                        .quaternion
+                        .iter_mut()
+                        .for_each(|q| *q = axis.mirror_rotation(*q));
+                }
+                Some(ProceduralBone::QuaternionInterp(rules)) => {
+                    rules.trigger = axis.mirror_rotation(rules.trigger);
+                    rules.position = axis.mirror(rules.position);
+                    rules.quaternion = axis.mirror_rotation(rules.quaternion);
+                }
+                Some(ProceduralBone::AiMatBone(rules) | ProceduralBone::AiMatAttach(rules)) => {
+                    rules.aim_vector = axis.mirror(rules.aim_vector);
+                    rules.up_vector = axis.mirror(rules.up_vector);
+                    rules.base_position = axis.mirror(rules.base_position);
+                }
+                _ => {}
+            }
+        }
+        for attachment in mdl.attachments.iter_mut() {
+            attachment.local = axis.mirror_transform(attachment.local);
+        }
+        for hitbox in mdl
+            .hit_boxes
+            .iter_mut()
+            .flat_map(|set| set.boxes.iter_mut())
+        {
+            [hitbox.min, hitbox.max] = axis.mirror_box([hitbox.min, hitbox.max]);
+        }
+        for track in mdl
+            .local_animations
+            .iter_mut()
+            .flat_map(|animation| animation.animations.iter_mut())
+        {
+            *track = map_track(
+                track,
+                |q| axis.mirror_rotation(q),
+                |position| axis.mirror(position),
+            );
+        }
+
+        model
+    }
+}
+
+/// Axis to mirror a model along
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    fn mirror(&self, vector: Vector) -> Vector {
+        match self {
+            Axis::X => Vector {
+                x: -vector.x,
+                ..vector
+            },
+            Axis::Y => Vector {
+                y: -vector.y,
+                ..vector
+            },
+            Axis::Z => Vector {
+                z: -vector.z,
+                ..vector
+            },
+        }
+    }
+
+    fn mirror_box(&self, [min, max]: [Vector; 2]) -> [Vector; 2] {
+        let (a, b) = (self.mirror(min), self.mirror(max));
+        [a.min(b), a.max(b)]
+    }
+
+    /// Mirror a rotation, the rotation around the mirror axis is kept while the others are reversed
+    fn mirror_rotation(&self, q: Quaternion) -> Quaternion {
+        match self {
+            Axis::X => Quaternion {
+                x: q.x,
+                y: -q.y,
+                z: -q.z,
+                w: q.w,
+            },
+            Axis::Y => Quaternion {
+                x: -q.x,
+                y: q.y,
+                z: -q.z,
+                w: q.w,
+            },
+            Axis::Z => Quaternion {
+                x: -q.x,
+                y: -q.y,
+                z: q.z,
+                w: q.w,
+            },
+        }
+    }
+
+    fn mirror_transform(&self, transform: Transform3x4) -> Transform3x4 {
+        let scale = self.mirror(Vector::from([1.0; 3]));
+        let mirror = Matrix4::from_nonuniform_scale(scale.x, scale.y, scale.z);
+        Transform3x4::from_matrix(mirror * transform.matrix() * mirror)
+    }
+}
+
+fn transform_point(transform: Matrix4<f32>, point: Vector) -> Vector {
+    let point = transform.transform_point(Point3::new(point.x, point.y, point.z));
+    Vector {
+        x: point.x,
+        y: point.y,
+        z: point.z,
+    }
+}
+
+fn transform_box(transform: Matrix4<f32>, [min, max]: [Vector; 2]) -> [Vector; 2] {
+    (0..8)
+        .map(|corner| Vector {
+            x: if corner & 1 == 0 { min.x } else { max.x },
+            y: if corner & 2 == 0 { min.y } else { max.y },
+            z: if corner & 4 == 0 { min.z } else { max.z },
+        })
+        .map(|corner| transform_point(transform, corner))
+        .fold(
+            [Vector::from([f32::MAX; 3]), Vector::from([f32::MIN; 3])],
+            |[min, max], corner| [min.min(corner), max.max(corner)],
+        )
+}
+
+/// Create a copy of an animation track with the missing rotation and position filled in from the rest pose of the bone
+fn rest_track(track: &Animation, rest: &BoneSample, frames: usize) -> Animation {
+    let mut flags = track.flags;
+    let rotation_data = match track.rotation_data() {
+        RotationData::None => {
+            flags |= AnimationFlags::STUDIO_ANIM_ANIMROT;
+            // the last rotation is used for all following frames
+            RotationData::Quaternions(vec![rest.rotation])
+        }
+        data => data.clone(),
+    };
+    let position_data = match track.position_data() {
+        PositionData::None => {
+            flags |= AnimationFlags::STUDIO_ANIM_ANIMPOS;
+            PositionData::PositionValues(vec![rest.position; frames])
+        }
+        data => data.clone(),
+    };
+    Animation::new(track.bone, flags, rotation_data, position_data)
+}
+
+/// Create a copy of an animation track with the rotations and positions mapped
+fn map_track(
+    track: &Animation,
+    rotate: impl Fn(Quaternion) -> Quaternion,
+    translate: impl Fn(Vector) -> Vector,
+) -> Animation {
+    let rotation_data = match track.rotation_data() {
+        RotationData::None => RotationData::None,
+        RotationData::Quaternion48(q) | RotationData::Quaternion64(q) => {
+            RotationData::Quaternions(vec![rotate(*q)])
+        }
+        RotationData::Animated(values) => {
+            RotationData::Quaternions(values.iter().map(|euler| rotate((*euler).into())).collect())
+        }
+        RotationData::Quaternions(values) => {
+            RotationData::Quaternions(values.iter().copied().map(rotate).collect())
+        }
+    };
+    let position_data = match track.position_data() {
+        PositionData::None => PositionData::None,
+        PositionData::Vector48(vector) => {
+            PositionData::Vector48(translate((*vector).into()).into())
+        }
+        PositionData::PositionValues(values) => {
+            PositionData::PositionValues(values.iter().copied().map(translate).collect())
+        }
+    };
+    Animation::new(track.bone, track.flags, rotation_data, position_data)
+}
@@ -0,0 +1,38 @@
+//! Volume calculations over the triangles of a model
+
+use crate::{Model, Scalar, Vector};
+use cgmath::{InnerSpace, Vector3};
+
+impl Model {
+    /// Volume enclosed by the triangles of the model
+    ///
+    /// The result is only meaningful for closed meshes. The volume of all triangles is accumulated
+    /// with [`Scalar`] precision.
+    pub fn volume(&self) -> Scalar {
+        self.volume_moments().0
+    }
+
+    /// Center of the volume enclosed by the triangles of the model
+    ///
+    /// Returns `None` if the model doesn't enclose any volume.
+    pub fn center_of_volume(&self) -> Option<Vector> {
+        let (volume, moment) = self.volume_moments();
+        (volume.abs() > Scalar::EPSILON).then(|| Vector::from_scalar(moment / volume))
+    }
+
+    /// The enclosed volume and its first moment, summed over the tetrahedrons formed by every triangle and the origin
+    fn volume_moments(&self) -> (Scalar, Vector3<Scalar>) {
+        let vertices = self.vertices();
+        let mut volume = 0.0;
+        let mut moment = Vector3::new(0.0, 0.0, 0.0);
+        for mesh in self.meshes() {
+            for triangle in mesh.triangles() {
+                let [a, b, c] = triangle.map(|index| vertices[index].position.to_scalar());
+                let tetrahedron = a.dot(b.cross(c)) / 6.0;
+                volume += tetrahedron;
+                moment += (a + b + c) * (tetrahedron / 4.0);
+            }
+        }
+        (volume, moment)
+    }
+}
@@ -1,9 +1,10 @@
 mod raw;
 
-use crate::{read_relative, ModelError, ReadRelative, Readable};
+use crate::{paths, read_relative, read_single, ModelError, ReadRelative, Readable};
 use itertools::Either;
+pub(crate) use raw::VtxHeader;
 use raw::*;
-pub use raw::{MeshFlags, StripFlags, StripGroupFlags, Vertex};
+pub use raw::{BoneStateChange, MeshFlags, StripFlags, StripGroupFlags, Vertex};
 use std::ops::Range;
 
 pub const MDL_VERSION: i32 = 7;
@@ -15,6 +16,8 @@ type Result<T> = std::result::Result<T, ModelError>;
 pub struct Vtx {
     pub header: VtxHeader,
     pub body_parts: Vec<BodyPart>,
+    /// Material replacements for every level of detail
+    pub material_replacements: Vec<MaterialReplacementList>,
 }
 
 impl Vtx {
@@ -22,9 +25,66 @@ impl Vtx {
         let header = <VtxHeader as Readable>::read(data)?;
         Ok(Vtx {
             body_parts: read_relative(data, header.body_indexes())?,
+            material_replacements: read_relative(data, header.material_replacement_list_indexes())?,
             header,
         })
     }
+
+    /// The name of the material replacing `material` at a level of detail
+    pub fn material_replacement(&self, lod: usize, material: i32) -> Option<&str> {
+        self.material_replacements
+            .get(lod)?
+            .replacements
+            .iter()
+            .find(|replacement| replacement.material as i32 == material)
+            .map(|replacement| replacement.name.as_str())
+    }
+
+    /// Reverse the winding order of all triangles in all meshes
+    pub fn reverse_winding(&mut self) {
+        self.body_parts
+            .iter_mut()
+            .flat_map(|part| part.models.iter_mut())
+            .flat_map(|model| model.lods.iter_mut())
+            .flat_map(|lod| lod.meshes.iter_mut())
+            .flat_map(|mesh| mesh.strip_groups.iter_mut())
+            .for_each(StripGroup::reverse_winding);
+    }
+}
+
+/// Materials that are replaced at a level of detail
+#[derive(Debug, Clone)]
+pub struct MaterialReplacementList {
+    pub replacements: Vec<MaterialReplacement>,
+}
+
+impl ReadRelative for MaterialReplacementList {
+    type Header = MaterialReplacementListHeader;
+
+    fn read(data: &[u8], header: Self::Header) -> Result<Self> {
+        Ok(MaterialReplacementList {
+            replacements: read_relative(data, header.replacement_indexes())?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MaterialReplacement {
+    /// Index of the material being replaced
+    pub material: i16,
+    /// Name of the replacement material
+    pub name: String,
+}
+
+impl ReadRelative for MaterialReplacement {
+    type Header = MaterialReplacementHeader;
+
+    fn read(data: &[u8], header: Self::Header) -> Result<Self> {
+        Ok(MaterialReplacement {
+            material: header.material_id,
+            name: paths::normalize_separators(&read_single::<String, _>(data, header.name_offset)?),
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -113,39 +173,100 @@ impl ReadRelative for StripGroup {
     }
 }
 
+impl StripGroup {
+    /// Reverse the winding order of all triangles in the strip group
+    ///
+    /// Triangle strips can't have their winding reversed in place, so they are converted into triangle lists.
+    pub fn reverse_winding(&mut self) {
+        let mut indices = Vec::with_capacity(self.indices.len());
+        for strip in self.strips.iter_mut() {
+            let source = self.indices.get(strip.indices.clone()).unwrap_or_default();
+            let start = indices.len();
+            if strip.flags.contains(StripFlags::IS_TRI_STRIP) {
+                for i in 0..source.len().saturating_sub(2) {
+                    let [a, b, c] = if i % 2 == 0 {
+                        [source[i], source[i + 1], source[i + 2]]
+                    } else {
+                        [source[i + 1], source[i], source[i + 2]]
+                    };
+                    if a != b && b != c && a != c {
+                        indices.extend([a, c, b]);
+                    }
+                }
+                strip.flags.remove(StripFlags::IS_TRI_STRIP);
+                strip.flags.insert(StripFlags::IS_TRI_LIST);
+            } else {
+                for triangle in source.chunks_exact(3) {
+                    indices.extend([triangle[0], triangle[2], triangle[1]]);
+                }
+            }
+            strip.indices = start..indices.len();
+        }
+        self.indices = indices;
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Strip {
-    // todo bone state changes
     vertices: Range<usize>,
     pub flags: StripFlags,
     indices: Range<usize>,
+    /// The maximum number of bones referenced by a single vertex in the strip
+    pub bone_count: u16,
+    pub bone_state_changes: Vec<BoneStateChange>,
 }
 
 impl ReadRelative for Strip {
     type Header = StripHeader;
 
-    fn read(_data: &[u8], header: Self::Header) -> Result<Self> {
+    fn read(data: &[u8], header: Self::Header) -> Result<Self> {
         Ok(Strip {
             vertices: header.vertex_indexes(),
             indices: header.index_indexes(),
             flags: header.flags,
+            bone_count: header.bone_count,
+            bone_state_changes: read_relative(data, header.bone_state_change_indexes())?,
         })
     }
 }
 
 impl Strip {
+    /// Number of (possibly degenerate) triangles in the strip
+    pub fn triangle_count(&self) -> usize {
+        if self.flags.contains(StripFlags::IS_TRI_STRIP) {
+            self.indices.len().saturating_sub(2)
+        } else {
+            self.indices.len() / 3
+        }
+    }
+
     pub fn vertices(&self) -> impl Iterator<Item = usize> + 'static {
         self.vertices.clone()
     }
 
+    /// Range of the strip in the indices of its strip group, in the original strip or list order
+    pub fn index_range(&self) -> Range<usize> {
+        self.indices.clone()
+    }
+
+    /// The triangles of the strip as positions in the indices of its strip group, in counter-clockwise winding
+    ///
+    /// A triangle strip of `n` indices forms `n - 2` triangles, each sharing an edge with the previous one.
     pub fn indices(&self) -> impl Iterator<Item = usize> + 'static {
         if self.flags.contains(StripFlags::IS_TRI_STRIP) {
             let offset = self.indices.start;
-            Either::Left((0..self.indices.len()).flat_map(move |i| {
-                let cw = i & 1;
-                let idx = offset + i;
-                [idx, idx + 1 - cw, idx + 2 - cw].into_iter().rev()
-            }))
+            Either::Left(
+                (0..self.indices.len().saturating_sub(2)).flat_map(move |i| {
+                    let idx = offset + i;
+                    // every other triangle in a strip has its first two vertices swapped to keep the winding consistent
+                    let triangle = if i & 1 == 0 {
+                        [idx, idx + 1, idx + 2]
+                    } else {
+                        [idx + 1, idx, idx + 2]
+                    };
+                    triangle.into_iter().rev()
+                }),
+            )
         } else {
             Either::Right(self.indices.clone().rev())
         }
@@ -29,8 +29,49 @@ impl VtxHeader {
             size_of::<BodyPartHeader>(),
         )
     }
+
+    pub fn material_replacement_list_indexes(&self) -> impl Iterator<Item = usize> {
+        let count = if self.material_replacement_list > 0 {
+            self.lod_count
+        } else {
+            0
+        };
+        index_range(
+            self.material_replacement_list,
+            count,
+            size_of::<MaterialReplacementListHeader>(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct MaterialReplacementListHeader {
+    replacement_count: i32,
+    replacement_offset: i32,
+}
+
+static_assertions::const_assert_eq!(size_of::<MaterialReplacementListHeader>(), 8);
+
+impl MaterialReplacementListHeader {
+    pub fn replacement_indexes(&self) -> impl Iterator<Item = usize> {
+        index_range(
+            self.replacement_offset,
+            self.replacement_count,
+            size_of::<MaterialReplacementHeader>(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C, packed)]
+pub struct MaterialReplacementHeader {
+    pub material_id: i16,
+    pub name_offset: i32,
 }
 
+static_assertions::const_assert_eq!(size_of::<MaterialReplacementHeader>(), 6);
+
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 pub struct BodyPartHeader {
@@ -201,16 +242,29 @@ impl StripHeader {
         self.index_offset as usize..(self.index_offset.saturating_add(self.index_count)) as usize
     }
 
-    #[allow(dead_code)]
     pub fn bone_state_change_indexes(&self) -> impl Iterator<Item = usize> {
         index_range(
             self.bone_state_change_offset,
             self.bone_state_change_count,
-            1,
+            size_of::<BoneStateChange>(),
         )
     }
 }
 
+/// Assigns a model bone to a hardware bone index used by the vertices of a strip
+///
+/// The changes of a strip are applied on top of the hardware bones of the previous strips in the strip group.
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct BoneStateChange {
+    pub hardware_id: i32,
+    pub new_bone_id: i32,
+}
+
+impl ReadableRelative for BoneStateChange {}
+
+static_assertions::const_assert_eq!(size_of::<BoneStateChange>(), 8);
+
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 #[repr(packed)]
@@ -1,8 +1,9 @@
 mod raw;
 
-use crate::vvd::raw::{VertexFileFixup, VvdHeader};
-use crate::{read_relative, read_relative_iter, ModelError, Readable};
-pub use raw::{BoneWeights, Tangent, Vertex};
+use crate::{read_relative, ModelError, Readable};
+pub(crate) use raw::VvdHeader;
+pub use raw::{BoneWeights, Tangent, Vertex, VertexFileFixup};
+use std::ops::Range;
 
 type Result<T> = std::result::Result<T, ModelError>;
 
@@ -11,7 +12,9 @@ type Result<T> = std::result::Result<T, ModelError>;
 pub struct Vvd {
     pub header: VvdHeader,
     pub vertices: Vec<Vertex>,
-    pub tangents: Vec<[f32; 4]>,
+    pub tangents: Vec<Tangent>,
+    /// The fixups applied to the source vertices of the file, empty if the vertices are stored without fixups
+    pub fixups: Vec<VertexFileFixup>,
 }
 
 impl Vvd {
@@ -19,38 +22,41 @@ impl Vvd {
         let header = <VvdHeader as Readable>::read(data)?;
         let source_vertices = read_relative(
             data,
-            header.vertex_indexes(0).ok_or(ModelError::OutOfBounds {
+            header.vertex_indexes(0).ok_or(ModelError::InvalidIndex {
                 data: "model_lod",
-                offset: 0,
+                index: 0,
             })?,
         )?;
         let source_tangents = read_relative(
             data,
-            header.tangent_indexes(0).ok_or(ModelError::OutOfBounds {
+            header.tangent_indexes(0).ok_or(ModelError::InvalidIndex {
                 data: "model_lod",
-                offset: 0,
+                index: 0,
             })?,
         )?;
-        let (tangents, vertices) = if !header.has_fixups() {
+        let fixups: Vec<VertexFileFixup> = if header.has_fixups() {
+            read_relative(data, header.fixup_indexes())?
+        } else {
+            Vec::new()
+        };
+        let (tangents, vertices) = if fixups.is_empty() {
             (source_tangents, source_vertices)
         } else {
             let mut vertices = Vec::new();
             let mut tangents = Vec::new();
-            for fixup in read_relative_iter::<'_, VertexFileFixup, _>(data, header.fixup_indexes())
-            {
-                let fixup = fixup?;
+            for fixup in &fixups {
                 let from = fixup.source_vertex_id as usize;
                 let to = (fixup.source_vertex_id.saturating_add(fixup.vertex_count)) as usize;
                 vertices.extend_from_slice(source_vertices.get(from..to).ok_or({
-                    ModelError::OutOfBounds {
+                    ModelError::InvalidIndex {
                         data: "source_vertices",
-                        offset: to,
+                        index: to,
                     }
                 })?);
                 tangents.extend_from_slice(source_tangents.get(from..to).ok_or({
-                    ModelError::OutOfBounds {
+                    ModelError::InvalidIndex {
                         data: "source_tangents",
-                        offset: to,
+                        index: to,
                     }
                 })?);
             }
@@ -63,6 +69,53 @@ impl Vvd {
             vertices,
             header,
             tangents,
+            fixups,
         })
     }
+
+    /// The index into the vertices as stored in the file for a vertex, undoing the fixups
+    ///
+    /// Returns `None` if the vertex doesn't exist.
+    pub fn source_index(&self, index: usize) -> Option<usize> {
+        if index >= self.vertices.len() {
+            return None;
+        }
+        if self.fixups.is_empty() {
+            return Some(index);
+        }
+        self.fixup_ranges()
+            .find(|(target, _)| target.contains(&index))
+            .map(|(target, source)| source + (index - target.start))
+    }
+
+    /// All indices into the vertices for a vertex as stored in the file
+    ///
+    /// With fixups a source vertex can be copied into the vertices multiple times, once for each
+    /// level of detail using it.
+    pub fn fixed_indices(&self, source: usize) -> impl Iterator<Item = usize> + '_ {
+        let identity = (self.fixups.is_empty() && source < self.vertices.len()).then_some(source);
+        self.fixup_ranges()
+            .filter_map(move |(target, source_start)| {
+                let offset = source.checked_sub(source_start)?;
+                (offset < target.len()).then_some(target.start + offset)
+            })
+            .chain(identity)
+    }
+
+    /// The range in the vertices and the first source vertex of every fixup
+    fn fixup_ranges(&self) -> impl Iterator<Item = (Range<usize>, usize)> + '_ {
+        self.fixups.iter().scan(0, |start, fixup| {
+            let count = fixup.vertex_count.max(0) as usize;
+            let target = *start..*start + count;
+            *start += count;
+            Some((target, fixup.source_vertex_id.max(0) as usize))
+        })
+    }
+
+    /// Scale the position of all vertices
+    pub fn scale(&mut self, factor: f32) {
+        self.vertices
+            .iter_mut()
+            .for_each(|vertex| vertex.position = vertex.position * factor);
+    }
 }
@@ -31,6 +31,15 @@ impl VvdHeader {
         self.fixup_count > 0
     }
 
+    /// Number of vertices used by a level of detail
+    pub fn lod_vertex_count(&self, lod: usize) -> Option<usize> {
+        if lod < self.lod_count as usize {
+            Some(*self.lod_vertex_count.get(lod)? as usize)
+        } else {
+            None
+        }
+    }
+
     pub fn vertex_indexes(&self, lod: i32) -> Option<impl Iterator<Item = usize>> {
         if lod < self.lod_count {
             Some(index_range(
@@ -56,6 +65,7 @@ impl VvdHeader {
     }
 }
 
+/// A range of the source vertices copied into the vertices of a level of detail
 #[derive(Debug, Clone, Zeroable, Pod, Copy)]
 #[repr(C)]
 pub struct VertexFileFixup {
@@ -88,6 +98,15 @@ pub struct BoneWeights {
 }
 
 impl BoneWeights {
+    /// Weights for a vertex that is fully attached to a single bone
+    pub(crate) fn single(bone: BoneId) -> Self {
+        BoneWeights {
+            weight: [1.0, 0.0, 0.0],
+            bone: [bone, BoneId::default(), BoneId::default()],
+            bone_count: 1,
+        }
+    }
+
     pub fn weights(&self) -> impl Iterator<Item = BoneWeight> + '_ {
         self.bone
             .into_iter()
@@ -115,7 +134,12 @@ pub struct BoneWeight {
 
 static_assertions::const_assert_eq!(size_of::<BoneWeights>(), 16);
 
-#[derive(Debug, Clone, Zeroable, Pod, Copy)]
+/// The tangent of a vertex
+///
+/// `x`, `y` and `z` are the direction of the tangent, `w` is the handedness of the tangent space:
+/// `1.0` normally and `-1.0` when the uv coordinates are mirrored.
+/// The bitangent is the cross product of the normal and tangent, multiplied by `w`.
+#[derive(Debug, Clone, Zeroable, Pod, Copy, PartialEq, Default)]
 #[repr(C)]
 pub struct Tangent {
     pub x: f32,
@@ -123,3 +147,54 @@ pub struct Tangent {
     pub z: f32,
     pub w: f32,
 }
+
+impl ReadableRelative for Tangent {}
+
+static_assertions::const_assert_eq!(size_of::<Tangent>(), 16);
+
+impl Tangent {
+    pub fn new(direction: Vector, handedness: f32) -> Self {
+        Tangent {
+            x: direction.x,
+            y: direction.y,
+            z: direction.z,
+            w: handedness,
+        }
+    }
+
+    pub fn direction(&self) -> Vector {
+        Vector {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+        }
+    }
+
+    /// `-1.0` if the uv coordinates are mirrored, `1.0` otherwise
+    pub fn handedness(&self) -> f32 {
+        if self.w < 0.0 {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+
+    /// The bitangent for a vertex with the provided normal
+    pub fn bitangent(&self, normal: Vector) -> Vector {
+        let normal = cgmath::Vector3::from(normal);
+        let direction = cgmath::Vector3::from(self.direction());
+        (normal.cross(direction) * self.handedness()).into()
+    }
+}
+
+impl From<[f32; 4]> for Tangent {
+    fn from([x, y, z, w]: [f32; 4]) -> Self {
+        Tangent { x, y, z, w }
+    }
+}
+
+impl From<Tangent> for [f32; 4] {
+    fn from(tangent: Tangent) -> Self {
+        [tangent.x, tangent.y, tangent.z, tangent.w]
+    }
+}
@@ -0,0 +1,727 @@
+use cgmath::{InnerSpace, One};
+use std::fs::read;
+use vmdl::mdl::{
+    Animation, AnimationBlock, AnimationBlockLocation, AnimationDescription, AnimationEvent,
+    AnimationFlags, AnimationSequence, AutoLayer, AutoLayerFlags, Bone, BoneId, BoneSample,
+    CompressionOptions, ConcatError, EncodeError, Interpolation, Mdl, PoseParameterDescription,
+    PositionData, RotationData, SampleOptions, StudioAnimFlags, TrackKind,
+};
+use vmdl::retarget::{retarget, BoneMapping};
+use vmdl::{Handle, PlaybackEvent, Pose, Quaternion, RadianEuler, SequencePlayer, Vector};
+
+fn barrel() -> Mdl {
+    Mdl::read(&read("data/barrel01.mdl").unwrap()).unwrap()
+}
+
+#[test]
+fn retarget_onto_same_skeleton() {
+    let mdl = barrel();
+    let mapping = BoneMapping::by_name(&mdl, &mdl);
+    assert_eq!(mapping.get(BoneId::from(0u8)), Some(BoneId::from(0u8)));
+
+    let animation = walk();
+    let retargeted = retarget(&animation, &mdl, &mdl, &mapping);
+    assert_eq!(retargeted.frame_count, animation.frame_count);
+    assert_eq!(retargeted.animations.len(), 1);
+    let track = &retargeted.animations[0];
+    assert_eq!(track.bone, BoneId::from(0u8));
+    let rest = cgmath::Quaternion::from(mdl.bones[0].rest_rotation());
+    for frame in 0..animation.frame_count {
+        // onto the same skeleton the positions are unchanged and the missing rotations use the rest pose
+        let expected = animation.animations[0].position(frame);
+        assert!((track.position(frame) - expected).length() < 0.001);
+        assert_eq!(cgmath::Quaternion::from(track.rotation(frame)), rest);
+    }
+    assert_eq!(track.position(3).x.round(), 30.0);
+
+    // the only track in the barrel animation is for a placeholder bone that doesn't exist
+    let placeholder = retarget(&mdl.local_animations[0], &mdl, &mdl, &mapping);
+    assert!(placeholder.animations.is_empty());
+}
+
+#[test]
+fn retarget_delta_animation() {
+    let source = barrel();
+    let mut target = barrel();
+    target.bones[0].pos = Vector {
+        x: 5.0,
+        y: 6.0,
+        z: 7.0,
+    };
+    target.bones[0].quaternion = Quaternion {
+        x: 0.0,
+        y: 0.0,
+        z: std::f32::consts::FRAC_1_SQRT_2,
+        w: std::f32::consts::FRAC_1_SQRT_2,
+    };
+    let mapping = BoneMapping::by_name(&source, &target);
+    let offset = target.bones[0].rest_position() - source.bones[0].rest_position();
+
+    // absolute animations are corrected for the different rest pose
+    let animation = walk();
+    let track = &retarget(&animation, &source, &target, &mapping).animations[0];
+    let expected = animation.animations[0].position(2) + offset;
+    assert!((track.position(2) - expected).length() < 0.001);
+    assert_eq!(
+        cgmath::Quaternion::from(track.rotation(2)),
+        cgmath::Quaternion::from(target.bones[0].rest_rotation())
+    );
+
+    // delta animations are applied on top of the pose, the rest pose doesn't matter
+    let animation = AnimationDescription {
+        flags: StudioAnimFlags::DELTA,
+        ..walk()
+    };
+    let track = &retarget(&animation, &source, &target, &mapping).animations[0];
+    for frame in 0..animation.frame_count {
+        let expected = animation.animations[0].position(frame);
+        assert!((track.position(frame) - expected).length() < 0.001);
+        assert_eq!(
+            cgmath::Quaternion::from(track.rotation(frame)),
+            cgmath::Quaternion::one()
+        );
+    }
+}
+
+#[test]
+fn sequence_animation_blocks() {
+    let mut mdl = barrel();
+    assert!(mdl.local_animations[0].animation_block.is_none());
+    let sequence = mdl.animation_sequences[0].clone();
+    assert_eq!(
+        Handle::new(&mdl, &sequence, 0).animation_blocks().count(),
+        0
+    );
+
+    mdl.animation_blocks = vec![
+        AnimationBlock { start: 0, end: 0 },
+        AnimationBlock {
+            start: 512,
+            end: 2048,
+        },
+    ];
+    mdl.local_animations[0].animation_block = Some(AnimationBlockLocation {
+        block: 1,
+        offset: 16,
+    });
+    let handle = Handle::new(&mdl, &sequence, 0);
+    let block = handle.animation_blocks().next().unwrap();
+    assert_eq!(block.range(), 512..2048);
+    assert_eq!(handle.animation_blocks().count(), 1);
+}
+
+fn event(cycle: f32, name: &str) -> AnimationEvent {
+    AnimationEvent {
+        cycle,
+        event: 0,
+        ty: 0,
+        options: Default::default(),
+        name: name.into(),
+    }
+}
+
+#[test]
+fn events_between() {
+    let mut mdl = barrel();
+    // make the animation last exactly one second
+    mdl.local_animations[0].fps = 30.0;
+    mdl.local_animations[0].frame_count = 31;
+    let mut sequence = mdl.animation_sequences[0].clone();
+    sequence.events = vec![event(0.0, "start"), event(0.5, "middle")];
+    sequence.flags = StudioAnimFlags::LOOPING;
+
+    let player = SequencePlayer::new(Handle::new(&mdl, &sequence, 0));
+    let names = |start: f32, end: f32| {
+        player
+            .events_between(start, end)
+            .map(|event| event.name.as_str())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(0.0, 0.25), vec!["start"]);
+    assert_eq!(names(0.25, 0.5), vec!["middle"]);
+    assert_eq!(names(0.5, 0.75), Vec::<&str>::new());
+    assert_eq!(names(0.75, 1.6), vec!["start", "middle"]);
+    assert_eq!(
+        names(0.25, 2.25),
+        vec!["middle", "start", "middle", "start"]
+    );
+
+    sequence.flags = StudioAnimFlags::empty();
+    let player = SequencePlayer::new(Handle::new(&mdl, &sequence, 0));
+    assert_eq!(player.events_between(0.75, 5.0).count(), 0);
+    assert_eq!(player.cycle_at(5.0), 1.0);
+}
+
+#[test]
+fn zero_frame_stall() {
+    let mut mdl = barrel();
+    mdl.local_animations[0].fps = 30.0;
+    mdl.local_animations[0].frame_count = 31;
+    mdl.local_animations[0].zero_frame_stall_time = 0.5;
+    let mut sequence = mdl.animation_sequences[0].clone();
+    sequence.events = vec![event(0.0, "start"), event(0.5, "middle")];
+
+    let player = SequencePlayer::new(Handle::new(&mdl, &sequence, 0));
+    assert_eq!(player.stall_time(), 0.5);
+    assert_eq!(player.cycle_at(0.25), 0.0);
+    assert_eq!(player.cycle_at(1.0), 0.5);
+    assert_eq!(player.events_between(0.0, 0.25).count(), 1);
+    assert_eq!(player.events_between(0.25, 0.5).count(), 0);
+    assert_eq!(player.events_between(0.5, 1.0).count(), 1);
+}
+
+#[test]
+fn player_callbacks() {
+    let mut mdl = barrel();
+    mdl.local_animations[0].fps = 30.0;
+    mdl.local_animations[0].frame_count = 31;
+    let mut sequence = mdl.animation_sequences[0].clone();
+    sequence.events = vec![event(0.0, "start"), event(0.5, "middle")];
+    sequence.flags = StudioAnimFlags::LOOPING;
+
+    let name = |happened: Vec<PlaybackEvent>| -> Vec<String> {
+        happened
+            .into_iter()
+            .map(|happened| match happened {
+                PlaybackEvent::Event(event) => event.name.clone(),
+                PlaybackEvent::Looped(count) => format!("loop {count}"),
+                PlaybackEvent::Finished => "finished".into(),
+            })
+            .collect()
+    };
+    let mut player = SequencePlayer::new(Handle::new(&mdl, &sequence, 0));
+    assert_eq!(name(player.advance(0.25)), ["start"]);
+    assert_eq!(name(player.advance(1.0)), ["middle", "loop 1", "start"]);
+    assert_eq!(player.time(), 1.25);
+    assert!(player.advance_to(1.0).is_empty());
+
+    sequence.flags = StudioAnimFlags::empty();
+    let mut player = SequencePlayer::new(Handle::new(&mdl, &sequence, 0));
+    assert_eq!(name(player.advance(0.75)), ["start", "middle"]);
+    assert_eq!(name(player.advance(0.5)), ["finished"]);
+    assert!(player.advance(0.5).is_empty());
+}
+
+#[test]
+fn pose_parameter_mapping() {
+    let yaw = PoseParameterDescription {
+        name: "body_yaw".into(),
+        flags: 0,
+        start: -180.0,
+        end: 180.0,
+        loop_range: 360.0,
+    };
+    assert_eq!(yaw.normalize(0.0), 0.5);
+    assert_eq!(yaw.normalize(-180.0), 0.0);
+    assert_eq!(yaw.normalize(270.0), 0.25);
+    assert_eq!(yaw.denormalize(0.25), -90.0);
+
+    let aim = PoseParameterDescription {
+        name: "aim_pitch".into(),
+        flags: 0,
+        start: -45.0,
+        end: 90.0,
+        loop_range: 0.0,
+    };
+    assert_eq!(aim.normalize(-90.0), 0.0);
+    assert_eq!(aim.normalize(200.0), 1.0);
+    assert_eq!(aim.denormalize(aim.normalize(45.0)), 45.0);
+}
+
+fn walk() -> AnimationDescription {
+    let positions = (0..4)
+        .map(|frame| Vector {
+            x: frame as f32 * 10.0,
+            y: 0.0,
+            z: 0.0,
+        })
+        .collect();
+    AnimationDescription {
+        name: "walk".into(),
+        fps: 4.0,
+        flags: StudioAnimFlags::LOOPING,
+        frame_count: 4,
+        zero_frame_stall_time: 0.0,
+        animation_block: None,
+        animations: vec![Animation::new(
+            BoneId::from(0u8),
+            AnimationFlags::STUDIO_ANIM_ANIMPOS,
+            RotationData::None,
+            PositionData::PositionValues(positions),
+        )],
+    }
+}
+
+#[test]
+fn sample_looping() {
+    let animation = walk();
+    let position = |frame: f32, options: SampleOptions| {
+        animation
+            .sample_frame(&[], frame, options)
+            .next()
+            .unwrap()
+            .position
+            .x
+    };
+
+    assert_eq!(position(1.5, SampleOptions::default()), 15.0);
+    // the last frame matches the first, so the animation wraps around after the duration
+    assert_eq!(position(3.5, SampleOptions::default()), 5.0);
+    assert_eq!(position(5.0, SampleOptions::default()), 20.0);
+    assert_eq!(animation.duration() * animation.fps, 3.0);
+    assert_eq!(
+        animation
+            .sample(&[], 0.25, SampleOptions::default())
+            .next()
+            .unwrap()
+            .position
+            .x,
+        10.0
+    );
+
+    let clamped = SampleOptions {
+        looping: Some(false),
+        ..SampleOptions::default()
+    };
+    assert_eq!(position(3.5, clamped), 30.0);
+    assert_eq!(position(-1.0, clamped), 0.0);
+
+    let in_place = SampleOptions {
+        remove_loop_motion: true,
+        ..SampleOptions::default()
+    };
+    assert_eq!(position(1.0, in_place), 0.0);
+    assert_eq!(position(3.5, in_place), 0.0);
+}
+
+#[test]
+fn sample_rest_fallback() {
+    let mdl = barrel();
+    let mut animation = walk();
+    let rest = cgmath::Quaternion::from(mdl.bones[0].rest_rotation());
+    let rotation = |animation: &AnimationDescription, bones: &[Bone]| {
+        cgmath::Quaternion::from(
+            animation
+                .sample_frame(bones, 1.0, SampleOptions::default())
+                .next()
+                .unwrap()
+                .rotation,
+        )
+    };
+    assert_eq!(rotation(&animation, &mdl.bones), rest);
+    assert_eq!(rotation(&animation, &[]), cgmath::Quaternion::one());
+
+    animation.flags |= StudioAnimFlags::DELTA;
+    assert_eq!(rotation(&animation, &mdl.bones), cgmath::Quaternion::one());
+}
+
+#[test]
+fn sample_in_place() {
+    let mut animation = walk();
+    animation.animations.push(Animation::new(
+        BoneId::from(1u8),
+        AnimationFlags::STUDIO_ANIM_ANIMPOS,
+        RotationData::None,
+        PositionData::PositionValues(vec![
+            Vector {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0
+            };
+            4
+        ]),
+    ));
+    let options = SampleOptions {
+        remove_root_motion: Some(BoneId::from(0u8)),
+        ..SampleOptions::default()
+    };
+    let samples = animation
+        .sample_frame(&[], 2.5, options)
+        .collect::<Vec<_>>();
+    assert_eq!(samples[0].position.x, 0.0);
+    assert_eq!(samples[1].position.x, 1.0);
+}
+
+#[test]
+fn animated_bones() {
+    let mut animation = walk();
+    animation.animations.push(Animation::new(
+        BoneId::from(1u8),
+        AnimationFlags::STUDIO_ANIM_ANIMPOS,
+        RotationData::None,
+        PositionData::PositionValues(vec![Vector::default(); 4]),
+    ));
+    let coverage = animation.animated_bones().collect::<Vec<_>>();
+    assert_eq!(coverage.len(), 2);
+    assert_eq!(coverage[0].rotation, TrackKind::None);
+    assert_eq!(coverage[0].position, TrackKind::Animated);
+    assert!(coverage[0].is_animated());
+    assert_eq!(coverage[1].bone, BoneId::from(1u8));
+    assert_eq!(coverage[1].position, TrackKind::Constant);
+    assert!(!coverage[1].is_animated());
+    assert!(coverage[1].is_touched());
+}
+
+#[test]
+fn sequence_bone_weights() {
+    let mut mdl = barrel();
+    assert_eq!(
+        mdl.animation_sequences[0].bone_weights.len(),
+        mdl.bones.len()
+    );
+    mdl.local_animations[0] = walk();
+    let mut sequence = mdl.animation_sequences[0].clone();
+    sequence.bone_weights = vec![0.5];
+    let rest = BoneSample::rest(BoneId::from(0u8), &mdl.bones[0]);
+
+    let mut pose = Pose::rest(&mdl);
+    Handle::new(&mdl, &sequence, 0).blend_onto(&mut pose, 0.25, 1.0, SampleOptions::default());
+    assert_eq!(
+        pose.samples()[0].position.x,
+        rest.position.x + (10.0 - rest.position.x) * 0.5
+    );
+
+    sequence.bone_weights = vec![0.0];
+    let mut pose = Pose::rest(&mdl);
+    Handle::new(&mdl, &sequence, 0).blend_onto(&mut pose, 0.25, 1.0, SampleOptions::default());
+    assert_eq!(pose.samples()[0].position.x, rest.position.x);
+}
+
+#[test]
+fn auto_layers() {
+    let mut mdl = barrel();
+    mdl.local_animations[0].fps = 30.0;
+    mdl.local_animations[0].frame_count = 31;
+    mdl.local_animations.push(walk());
+    let mut layer = mdl.animation_sequences[0].clone();
+    layer.animation_indices = vec![1];
+    mdl.animation_sequences.push(layer);
+    let ramp = AutoLayer {
+        sequence: 1,
+        pose: 0,
+        flags: AutoLayerFlags::empty(),
+        start: 0.0,
+        peak: 0.5,
+        tail: 0.5,
+        end: 1.0,
+    };
+    assert_eq!(ramp.evaluate(0.25, 1.0, &[], &[]), Some((0.5, 0.25)));
+    assert_eq!(ramp.evaluate(1.0, 1.0, &[], &[]), None);
+    let pose_ramp = AutoLayer {
+        pose: 1,
+        flags: AutoLayerFlags::POSE,
+        ..ramp.clone()
+    };
+    // the pose parameter drives the weight and is used as the cycle as is
+    assert_eq!(
+        pose_ramp.evaluate(0.9, 1.0, &[0.0, 0.25], &[]),
+        Some((0.5, 0.25))
+    );
+    assert_eq!(pose_ramp.evaluate(0.25, 1.0, &[0.25, 1.0], &[]), None);
+    // the ramp of the layer is in the range of the pose parameter
+    let aim = PoseParameterDescription {
+        name: "aim".into(),
+        flags: 0,
+        start: -90.0,
+        end: 90.0,
+        loop_range: 0.0,
+    };
+    let aim_ramp = AutoLayer {
+        pose: 0,
+        start: -45.0,
+        peak: 0.0,
+        tail: 0.0,
+        end: 45.0,
+        ..pose_ramp.clone()
+    };
+    let parameters = [aim];
+    assert_eq!(
+        aim_ramp.evaluate(0.9, 1.0, &[0.375], &parameters),
+        Some((0.5, 0.375))
+    );
+    assert_eq!(
+        aim_ramp.evaluate(0.9, 1.0, &[0.5], &parameters),
+        Some((1.0, 0.5))
+    );
+    assert_eq!(aim_ramp.evaluate(0.9, 1.0, &[0.75], &parameters), None);
+    mdl.animation_sequences[0].auto_layers = vec![ramp];
+
+    let rest = Pose::rest(&mdl);
+    let base = Handle::new(&mdl, &mdl.animation_sequences[0], 0);
+    let mut expected = rest.clone();
+    base.blend_onto(&mut expected, 0.25, 1.0, SampleOptions::default());
+
+    let player = SequencePlayer::new(base);
+    let mut pose = rest.clone();
+    player.pose_at(0.25, &[], &mut pose);
+    // the layer is at half weight and a quarter of the way through the walk animation
+    let x = expected.samples()[0].position.x;
+    assert_eq!(pose.samples()[0].position.x, x + (7.5 - x) * 0.5);
+    assert_eq!(
+        player.pose(0.25, &[]).samples()[0].position,
+        pose.samples()[0].position
+    );
+}
+
+#[test]
+fn sample_interpolation() {
+    let mut animation = walk();
+    animation.animations[0] = Animation::new(
+        BoneId::from(0u8),
+        AnimationFlags::STUDIO_ANIM_ANIMPOS,
+        RotationData::None,
+        PositionData::PositionValues(
+            (0..4)
+                .map(|frame| Vector {
+                    x: (frame * frame) as f32,
+                    y: 0.0,
+                    z: 0.0,
+                })
+                .collect(),
+        ),
+    );
+    let position = |frame: f32, interpolation: Interpolation| {
+        let options = SampleOptions {
+            looping: Some(false),
+            interpolation,
+            ..SampleOptions::default()
+        };
+        animation
+            .sample_frame(&[], frame, options)
+            .next()
+            .unwrap()
+            .position
+            .x
+    };
+    assert_eq!(position(1.4, Interpolation::Nearest), 1.0);
+    assert_eq!(position(1.6, Interpolation::Nearest), 4.0);
+    assert_eq!(position(1.5, Interpolation::Linear), 2.5);
+    assert_eq!(position(1.5, Interpolation::CatmullRom), 2.25);
+    assert_eq!(position(2.0, Interpolation::CatmullRom), 4.0);
+}
+
+#[test]
+fn encode_animation() {
+    let mut bone = barrel().bones[0].clone();
+    bone.pos_scale = Vector {
+        x: 1.0 / 32.0,
+        y: 1.0 / 32.0,
+        z: 1.0 / 32.0,
+    };
+    let position = |x: f32| Vector { x, y: 0.0, z: 2.0 };
+    let positions = vec![
+        position(0.0),
+        position(0.0625),
+        position(0.03125),
+        position(10.0),
+        position(10.0),
+    ];
+    let rotation = RadianEuler {
+        x: 0.5,
+        y: 0.0,
+        z: 0.0,
+    };
+    let animation = Animation::new(
+        BoneId::from(0u8),
+        AnimationFlags::empty(),
+        RotationData::Animated(vec![rotation; 5]),
+        PositionData::PositionValues(positions.clone()),
+    );
+
+    let exact = animation
+        .encode(&bone, &CompressionOptions::default())
+        .unwrap();
+    let decoded = Animation::decode(&exact, &bone, 5).unwrap();
+    assert!(matches!(
+        decoded.rotation_data(),
+        RotationData::Quaternion48(_)
+    ));
+    let expected = cgmath::Quaternion::from(Quaternion::from(rotation));
+    assert!(cgmath::Quaternion::from(decoded.rotation(2)).dot(expected) > 0.9999);
+    for (frame, position) in positions.iter().enumerate() {
+        assert_eq!(decoded.position(frame), *position);
+    }
+
+    let options = CompressionOptions {
+        position_tolerance: 0.1,
+        ..CompressionOptions::default()
+    };
+    let compressed = animation.encode(&bone, &options).unwrap();
+    assert!(compressed.len() < exact.len());
+    let decoded = Animation::decode(&compressed, &bone, 5).unwrap();
+    assert_eq!(decoded.position(1).x, 0.0);
+    assert_eq!(decoded.position(2).x, 0.0);
+    assert_eq!(decoded.position(4).x, 10.0);
+}
+
+#[test]
+fn encode_value_offset_overflow() {
+    let mut bone = barrel().bones[0].clone();
+    bone.pos_scale = Vector {
+        x: 1.0 / 32.0,
+        y: 1.0 / 32.0,
+        z: 1.0 / 32.0,
+    };
+    // every frame has a different value, so the values of the first axis don't fit in 16 bit offsets
+    let positions = (0..40_000)
+        .map(|frame| Vector {
+            x: (frame % 1000) as f32,
+            y: 1.0,
+            z: (frame % 7) as f32,
+        })
+        .collect();
+    let animation = Animation::new(
+        BoneId::from(0u8),
+        AnimationFlags::empty(),
+        RotationData::None,
+        PositionData::PositionValues(positions),
+    );
+    assert!(matches!(
+        animation.encode(&bone, &CompressionOptions::default()),
+        Err(EncodeError::ValueOffset { offset, .. }) if offset > usize::from(u16::MAX)
+    ));
+}
+
+#[test]
+fn quaternion_alignment() {
+    let reference = Quaternion {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+        w: 1.0,
+    };
+    let flipped = Quaternion {
+        x: 0.0,
+        y: 0.6,
+        z: 0.0,
+        w: -0.8,
+    };
+    let aligned = flipped.aligned(reference);
+    assert_eq!((aligned.y, aligned.w), (-0.6, 0.8));
+    assert_eq!(aligned.aligned(reference).w, 0.8);
+}
+
+#[test]
+fn transition_weights() {
+    let mdl = barrel();
+    let mut sequence = mdl.animation_sequences[0].clone();
+    assert!(!sequence.is_transition());
+
+    sequence.fade_in_time = 0.2;
+    assert_eq!(sequence.transition_weights(0.0), (1.0, 0.0));
+    assert_eq!(sequence.transition_weights(0.1), (0.5, 0.5));
+    let (previous, next) = sequence.transition_weights(0.05);
+    assert!(next < 0.25);
+    assert!((previous + next - 1.0).abs() < 0.0001);
+    assert_eq!(sequence.transition_weights(1.0), (0.0, 1.0));
+
+    sequence.fade_in_time = 0.0;
+    assert_eq!(sequence.transition_weights(0.0), (0.0, 1.0));
+}
+
+#[test]
+fn retime_events() {
+    let mut sequence = barrel().animation_sequences[0].clone();
+    let events = vec![
+        event(0.0, "start"),
+        event(0.5, "middle"),
+        event(0.75, "late"),
+        event(1.0, "end"),
+    ];
+    let names = |sequence: &AnimationSequence| {
+        sequence
+            .events
+            .iter()
+            .map(|event| (event.name.clone(), event.cycle))
+            .collect::<Vec<_>>()
+    };
+
+    sequence.events = events.clone();
+    sequence.retime_events(0.5, 0.5);
+    assert_eq!(
+        names(&sequence),
+        [
+            ("start".into(), 0.5),
+            ("middle".into(), 0.75),
+            ("late".into(), 0.875),
+            ("end".into(), 1.0)
+        ]
+    );
+
+    // keep frames 20 to 30 out of 41
+    sequence.events = events.clone();
+    sequence.trim_events(41, 20..31);
+    assert_eq!(
+        names(&sequence),
+        [("middle".into(), 0.0), ("late".into(), 1.0)]
+    );
+
+    sequence.events = events;
+    sequence.trim_events(41, 30..31);
+    assert_eq!(names(&sequence), [("late".into(), 0.0)]);
+}
+
+#[test]
+fn slice_animation() {
+    let animation = walk();
+    let clamped = SampleOptions {
+        looping: Some(false),
+        ..SampleOptions::default()
+    };
+    let position = |animation: &AnimationDescription, frame: f32| {
+        animation
+            .sample_frame(&[], frame, clamped)
+            .next()
+            .unwrap()
+            .position
+            .x
+    };
+
+    let clip = animation.slice(1..3);
+    assert_eq!(clip.frame_count, 2);
+    assert_eq!(clip.name, "walk");
+    assert_eq!(position(&clip, 0.0), 10.0);
+    assert_eq!(position(&clip, 1.0), 20.0);
+    assert_eq!(position(&clip, 5.0), 20.0);
+
+    let tail = animation.slice(2..10);
+    assert_eq!(tail.frame_count, 2);
+    assert_eq!(position(&tail, 1.0), 30.0);
+
+    assert_eq!(animation.slice(6..8).frame_count, 0);
+}
+
+#[test]
+fn concat_animations() {
+    let animation = walk();
+    let combined = animation.concat(&walk(), 1).unwrap();
+    assert_eq!(combined.frame_count, 9);
+    let positions = (0..9)
+        .map(|frame| combined.animations[0].position(frame).x)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        positions,
+        [0.0, 10.0, 20.0, 30.0, 15.0, 0.0, 10.0, 20.0, 30.0]
+    );
+    assert_eq!(
+        combined.animations[0].rotation_data().track_kind(),
+        TrackKind::None
+    );
+
+    let mut other = walk();
+    other.fps = 30.0;
+    assert_eq!(
+        animation.concat(&other, 0).unwrap_err(),
+        ConcatError::FrameRate {
+            fps: 4.0,
+            other: 30.0
+        }
+    );
+
+    let mut other = walk();
+    other.animations[0].bone = BoneId::from(1u8);
+    assert_eq!(
+        animation.concat(&other, 0).unwrap_err(),
+        ConcatError::MissingBone(BoneId::from(1u8))
+    );
+}
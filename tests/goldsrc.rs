@@ -0,0 +1,215 @@
+use vmdl::goldsrc::{Model, TextureFlags};
+use vmdl::ModelError;
+
+/// Little endian writer for building a minimal model in memory
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn bytes(&mut self, offset: usize, bytes: &[u8]) {
+        if self.0.len() < offset + bytes.len() {
+            self.0.resize(offset + bytes.len(), 0);
+        }
+        self.0[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn i32(&mut self, offset: usize, values: &[i32]) {
+        for (i, value) in values.iter().enumerate() {
+            self.bytes(offset + i * 4, &value.to_le_bytes());
+        }
+    }
+
+    fn f32(&mut self, offset: usize, values: &[f32]) {
+        for (i, value) in values.iter().enumerate() {
+            self.bytes(offset + i * 4, &value.to_le_bytes());
+        }
+    }
+
+    fn i16(&mut self, offset: usize, values: &[i16]) {
+        for (i, value) in values.iter().enumerate() {
+            self.bytes(offset + i * 2, &value.to_le_bytes());
+        }
+    }
+}
+
+/// A model with two bones, a single textured triangle and a two frame sequence
+fn triangle_model() -> Vec<u8> {
+    let mut w = Writer(Vec::new());
+
+    // header
+    w.bytes(0, b"IDST");
+    w.i32(4, &[10]);
+    w.bytes(8, b"triangle.mdl");
+    #[rustfmt::skip]
+    w.i32(136, &[
+        0,    // flags
+        2, 244, // bones
+        0, 0, // bone controllers
+        0, 0, // hitboxes
+        1, 1616, // sequences
+        1, 1900, // sequence groups
+        1, 468, 468, // textures
+        1, 1, 1320, // skins
+        1, 1324, // body parts
+        0, 0, // attachments
+    ]);
+
+    // bones
+    w.bytes(244, b"root");
+    w.i32(244 + 32, &[-1, 0, -1, -1, -1, -1, -1, -1]);
+    w.f32(244 + 64, &[0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    w.f32(244 + 88, &[0.5, 0.5, 0.5, 0.01, 0.01, 0.01]);
+    w.bytes(356, b"child");
+    w.i32(356 + 32, &[0, 0, -1, -1, -1, -1, -1, -1]);
+    w.f32(356 + 64, &[10.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    w.f32(356 + 88, &[0.5, 0.5, 0.5, 0.01, 0.01, 0.01]);
+
+    // texture, 2x2 pixels followed by the palette
+    w.bytes(468, b"skin.bmp");
+    w.i32(468 + 64, &[0, 2, 2, 548]);
+    w.bytes(548, &[0, 1, 2, 255]);
+    w.bytes(552, &[10, 20, 30, 40, 50, 60]);
+    w.bytes(552 + 255 * 3, &[1, 2, 3]);
+
+    // skin table
+    w.i16(1320, &[0]);
+
+    // body part and model
+    w.bytes(1324, b"body");
+    w.i32(1324 + 64, &[1, 1, 1400]);
+    w.bytes(1400, b"triangle");
+    w.i32(1400 + 64, &[0]);
+    w.f32(1400 + 68, &[10.0]);
+    #[rustfmt::skip]
+    w.i32(1400 + 72, &[
+        1, 1568, // meshes
+        3, 1548, 1512, // vertices
+        1, 1564, 1552, // normals
+        0, 0, // groups
+    ]);
+    w.f32(1512, &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+    w.bytes(1548, &[0, 1, 1]);
+    w.f32(1552, &[0.0, 0.0, 1.0]);
+    w.bytes(1564, &[0]);
+
+    // mesh with a single triangle strip
+    w.i32(1568, &[1, 1588, 0, 1, 1552]);
+    w.i16(1588, &[3, 0, 0, 0, 0, 1, 0, 2, 0, 2, 0, 0, 2, 0]);
+
+    // sequence
+    w.bytes(1616, b"idle");
+    w.f32(1616 + 32, &[10.0]);
+    w.i32(1616 + 36, &[1, 0, 1, 1, 1824, 2]);
+    w.i32(1616 + 120, &[1, 1792]);
+
+    // animation, the x position of the root moves over 2 frames
+    w.i16(1792, &[24, 0, 0, 0, 0, 0]);
+    w.i16(1816, &[i16::from_le_bytes([2, 2]), 0, 4]);
+
+    // event
+    w.i32(1824, &[1, 5004, 0]);
+    w.bytes(1836, b"sound.wav");
+
+    // sequence group
+    w.bytes(1900, b"default");
+    w.bytes(2004, &[0; 4]);
+
+    w.0
+}
+
+#[test]
+fn goldsrc_model() {
+    let model = Model::read(&triangle_model()).unwrap();
+    assert_eq!(model.name(), "triangle.mdl");
+
+    let bones: Vec<_> = model.bones().map(|(_, bone)| bone.name.as_str()).collect();
+    assert_eq!(bones, ["root", "child"]);
+    assert_eq!(model.bones[1].parent, Some(0u8.into()));
+
+    let texture = model.texture_for_skin_reference(0, 0).unwrap();
+    assert_eq!(texture.name, "skin.bmp");
+    assert!(texture.is_embedded());
+    let rgba = texture.rgba();
+    assert_eq!(&rgba[..8], &[10, 20, 30, 255, 40, 50, 60, 255]);
+    assert_eq!(&rgba[12..], &[1, 2, 3, 255]);
+}
+
+#[test]
+fn goldsrc_masked_texture() {
+    let mut data = triangle_model();
+    data[468 + 64..468 + 68].copy_from_slice(&TextureFlags::MASKED.bits().to_le_bytes());
+    let model = Model::read(&data).unwrap();
+    assert_eq!(&model.textures()[0].rgba()[12..], &[1, 2, 3, 0]);
+}
+
+#[test]
+fn goldsrc_geometry() {
+    let model = Model::read(&triangle_model()).unwrap();
+    assert_eq!(model.vertices().len(), 3);
+
+    let mesh = model.meshes().next().unwrap();
+    let triangles: Vec<_> = mesh.triangle_indices().collect();
+    assert_eq!(triangles, [[0, 1, 2]]);
+
+    // vertices are stored relative to their bone
+    let positions: Vec<[f32; 3]> = mesh
+        .vertices()
+        .map(|vertex| vertex.position.into())
+        .collect();
+    assert_eq!(positions[0], [0.0, 0.0, 0.0]);
+    assert_eq!(positions[1], [11.0, 0.0, 0.0]);
+    assert_eq!(positions[2], [10.0, 1.0, 0.0]);
+    assert_eq!(model.vertices()[1].texture_coordinates, [1.0, 0.0]);
+    assert_eq!(model.vertices()[2].texture_coordinates, [0.0, 1.0]);
+
+    let (min, max) = model.computed_bounding_box().unwrap();
+    assert_eq!(min.x, 0.0);
+    assert_eq!(max.x, 11.0);
+}
+
+#[test]
+fn goldsrc_sequences() {
+    let model = Model::read(&triangle_model()).unwrap();
+    let sequence = &model.sequences()[0];
+    assert_eq!(sequence.label, "idle");
+    assert_eq!(sequence.frame_count, 2);
+    assert!(sequence.is_looping());
+    assert_eq!(sequence.events[0].options, "sound.wav");
+
+    let root = &sequence.blends[0][0];
+    assert_eq!(root.positions[0].x, 0.0);
+    assert_eq!(root.positions[1].x, 2.0);
+    let child = &sequence.blends[0][1];
+    assert_eq!(child.positions[1].x, 10.0);
+
+    let pose = sequence.sample_frame(0.5, 0);
+    assert_eq!(pose[0].position.x, 1.0);
+    let transforms = model.bone_transforms(&pose);
+    assert_eq!(transforms[1].w.x, 11.0);
+}
+
+#[test]
+fn goldsrc_version() {
+    let mut data = triangle_model();
+    data[4..8].copy_from_slice(&44i32.to_le_bytes());
+    assert!(matches!(
+        Model::read(&data),
+        Err(ModelError::UnsupportedVersion(44))
+    ));
+}
+
+#[test]
+fn goldsrc_untrusted_counts() {
+    // frame count
+    let mut data = triangle_model();
+    data[1616 + 56..1616 + 60].copy_from_slice(&i32::MAX.to_le_bytes());
+    assert!(matches!(
+        Model::read(&data),
+        Err(ModelError::OutOfBounds { offset: 1792, .. })
+    ));
+
+    // triangle count
+    let mut data = triangle_model();
+    data[1568..1572].copy_from_slice(&i32::MAX.to_le_bytes());
+    let model = Model::read(&data).unwrap();
+    assert_eq!(model.meshes().next().unwrap().triangle_indices().count(), 1);
+}
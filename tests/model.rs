@@ -0,0 +1,2008 @@
+use cgmath::{Deg, InnerSpace, Matrix4, SquareMatrix, Vector3};
+use std::fs;
+use vmdl::biped::{BipedBone, Side};
+use vmdl::collision::{convex_hull, HullOptions};
+use vmdl::gpu::{
+    build_vertex_buffer, octahedral_decode, octahedral_encode, BoneIndexMode, BonePalette,
+    MeshletOptions, VertexAttribute, VertexFormat, VertexLayout, VertexPrecision,
+};
+use vmdl::mdl::{
+    BoneId, ContentFlags, Eyeball, IncludeModel, ParseOptions, TextureInfo, ValidationError,
+};
+use vmdl::scene::Scene;
+use vmdl::skeleton::BoneChainError;
+use vmdl::vtx::{BoneStateChange, MaterialReplacement, StripFlags};
+use vmdl::vvd::{Tangent, VertexFileFixup};
+use vmdl::{
+    debug_geometry, AnimationSource, Axis, FileKind, Mdl, MeshStrips, Model, ModelError, Phy, Pose,
+    PrimitiveType, StripRange, Vector, Vtx, Vvd,
+};
+
+fn barrel() -> Model {
+    Model::from_path("data/barrel01.mdl").unwrap()
+}
+
+#[test]
+fn sequence_animations() {
+    let model = barrel();
+    let sequence = model.sequences().next().unwrap();
+    assert_eq!(sequence.label, "idle");
+    let animation = sequence.animations().next().unwrap();
+    assert_eq!(animation.name, "@idle");
+    assert_eq!(animation.frame_count, 1);
+    assert_eq!(sequence.duration(), 0.0);
+}
+
+#[test]
+fn texture_by_name() {
+    let model = barrel();
+    assert_eq!(model.texture_by_name("Barrel01").unwrap().0, 0);
+    assert_eq!(
+        model
+            .texture_by_name("models\\Props_Badlands\\barrel01.vmt")
+            .unwrap()
+            .0,
+        0
+    );
+    assert!(model.texture_by_name("barrel02").is_none());
+}
+
+#[test]
+fn materials_for_skin() {
+    let model = barrel();
+    let materials = model.materials_for_skin(0);
+    assert_eq!(materials.len(), 1);
+    assert_eq!(materials[0].0, 0);
+    assert!(materials[0].1.name.eq_ignore_ascii_case("barrel01"));
+    assert!(model
+        .materials_for_skin(model.skin_tables().count())
+        .is_empty());
+}
+
+#[test]
+fn triangles_by_material() {
+    let model = barrel();
+    let groups = model.triangles_by_material(0, 0);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].texture_index, Some(0));
+    assert_eq!(groups[0].texture.unwrap().name, model.textures()[0].name);
+    let indices: Vec<usize> = model
+        .meshes()
+        .flat_map(|mesh| mesh.triangles().flatten().collect::<Vec<_>>())
+        .collect();
+    assert_eq!(groups[0].indices, indices);
+    assert_eq!(groups[0].indices.len() % 3, 0);
+    assert!(model.triangles_by_material(99, 0).is_empty());
+    assert!(model.triangles_by_material(0, 99).is_empty());
+}
+
+#[test]
+fn mesh_bounding_sphere() {
+    let model = barrel();
+    let mesh = model.meshes().next().unwrap();
+    let (center, radius) = mesh.computed_bounding_sphere();
+    assert!(center.z > 35.0 && center.z < 45.0);
+    assert!(radius > 40.0 && radius < 60.0);
+
+    // the barrel has no stored radius, so the sphere is computed
+    assert_eq!(mesh.model_bounding_radius(), 0.0);
+    assert_eq!(mesh.bounding_sphere(), (center, radius));
+
+    // the stored center and radius are used when available
+    let mut mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    mdl.body_parts[0].models[0].bounding_radius = 64.0;
+    mdl.body_parts[0].models[0].meshes[0].center = Vector::from([0.0, 0.0, 32.0]);
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let model = Model::from_parts(mdl, vtx, vvd);
+    let mesh = model.meshes().next().unwrap();
+    assert_eq!(
+        mesh.bounding_sphere(),
+        (Vector::from([0.0, 0.0, 32.0]), 64.0)
+    );
+    assert_eq!(mesh.computed_bounding_sphere(), (center, radius));
+}
+
+#[test]
+fn mesh_bounding_sphere_off_center() {
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let mut vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let offset = Vector::from([100.0, -50.0, 20.0]);
+    for vertex in &mut vvd.vertices {
+        vertex.position = vertex.position + offset;
+    }
+    let model = Model::from_parts(mdl, vtx, vvd);
+    let mesh = model.meshes().next().unwrap();
+
+    let (min, max) = mesh.computed_bounding_box().unwrap();
+    let expected_center = (min + max) * 0.5;
+    let expected_radius = mesh
+        .vertices()
+        .map(|vertex| (vertex.position - expected_center).length())
+        .fold(0.0, f32::max);
+    let (center, radius) = mesh.computed_bounding_sphere();
+    assert_eq!(center, expected_center);
+    assert_eq!(radius, expected_radius);
+
+    // the sphere moves with the vertices, not the center stored in the model
+    let original = barrel();
+    let (original_center, original_radius) =
+        original.meshes().next().unwrap().computed_bounding_sphere();
+    assert!((center - original_center - offset).length() < 0.001);
+    assert!((radius - original_radius).abs() < 0.001);
+    assert!((center - mesh.center()).length() > 100.0);
+}
+
+#[test]
+fn computed_bounding_box() {
+    let model = barrel();
+    let (min, max) = model.computed_bounding_box().unwrap();
+    let (header_min, header_max) = model.view_box();
+    assert!(min.x >= header_min.x - 0.01 && min.z >= header_min.z - 0.01);
+    assert!(max.x <= header_max.x + 0.01 && max.z <= header_max.z + 0.01);
+    assert_eq!(
+        model.computed_bounding_box_for_body_groups(&[0]),
+        Some((min, max))
+    );
+    assert_eq!(model.computed_bounding_box_for_body_groups(&[1]), None);
+}
+
+#[test]
+fn skeleton_compatible_with_self() {
+    let model = barrel();
+    assert!(model.skeleton_compatible_with(&model).is_identical());
+}
+
+#[test]
+fn classify_prefixes() {
+    for name in [
+        "ValveBiped.Bip01_L_UpperArm",
+        "Bip01 L UpperArm",
+        "bip01_l_upperarm",
+    ] {
+        assert_eq!(
+            BipedBone::classify(name),
+            Some(BipedBone::UpperArm(Side::Left))
+        );
+    }
+    assert_eq!(
+        BipedBone::classify("ValveBiped.Bip01_R_Finger42"),
+        Some(BipedBone::Finger {
+            side: Side::Right,
+            finger: 4,
+            segment: 2
+        })
+    );
+    assert_eq!(BipedBone::classify("static_prop"), None);
+}
+
+#[test]
+fn canonical_name_round_trip() {
+    for bone in [
+        BipedBone::Spine(0),
+        BipedBone::Spine(2),
+        BipedBone::Head,
+        BipedBone::Toe(Side::Right),
+        BipedBone::Finger {
+            side: Side::Left,
+            finger: 1,
+            segment: 0,
+        },
+    ] {
+        assert_eq!(BipedBone::classify(&bone.to_string()), Some(bone));
+    }
+}
+
+#[test]
+fn scaled() {
+    let model = barrel();
+    let scaled = model.scaled(2.0);
+    let (min, max) = model.computed_bounding_box().unwrap();
+    let (scaled_min, scaled_max) = scaled.computed_bounding_box().unwrap();
+    assert_eq!(scaled_min, min * 2.0);
+    assert_eq!(scaled_max, max * 2.0);
+    assert_eq!(scaled.bones().count(), model.bones().count());
+}
+
+#[test]
+fn transformed() {
+    let model = barrel();
+    let offset = Vector3::new(0.0, 0.0, 10.0);
+    let transformed =
+        model.transformed(Matrix4::from_translation(offset) * Matrix4::from_angle_z(Deg(90.0)));
+    let (min, max) = model.computed_bounding_box().unwrap();
+    let (transformed_min, transformed_max) = transformed.computed_bounding_box().unwrap();
+    assert!((transformed_min.x + max.y).abs() < 0.001);
+    assert!((transformed_max.y - max.x).abs() < 0.001);
+    assert!((transformed_min.z - min.z - 10.0).abs() < 0.001);
+
+    let bone = model.bones().next().unwrap();
+    let transformed_bone = transformed.bones().next().unwrap();
+    let position = transformed_bone.rest_position() - bone.rest_position();
+    assert!((position.z - 10.0).abs() < 0.001);
+
+    // the bind pose still maps the transformed bone back to the origin
+    let world = Matrix4::from_translation(transformed_bone.rest_position().into())
+        * Matrix4::from(cgmath::Quaternion::from(transformed_bone.rest_rotation()));
+    let identity = transformed_bone.pose_to_bone.matrix() * world;
+    assert!((identity.w.truncate().magnitude()) < 0.001);
+}
+
+#[test]
+fn mirrored() {
+    let model = barrel();
+    let mirrored = model.mirrored(Axis::X);
+    let (min, max) = model.computed_bounding_box().unwrap();
+    let (mirrored_min, mirrored_max) = mirrored.computed_bounding_box().unwrap();
+    assert_eq!(mirrored_min.x, -max.x);
+    assert_eq!(mirrored_max.x, -min.x);
+
+    // the faces keep pointing in the same direction as the vertex normals
+    let facing = |model: &Model| {
+        let mesh = model.meshes().next().unwrap();
+        let [a, b, c]: [Vector3<f32>; 3] = mesh
+            .vertices()
+            .take(3)
+            .map(|vertex| vertex.position.into())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let normal: Vector3<f32> = mesh.vertices().next().unwrap().normal.into();
+        (b - a).cross(c - a).dot(normal) > 0.0
+    };
+    assert_eq!(facing(&model), facing(&mirrored));
+}
+
+#[test]
+fn meshlets() {
+    let model = barrel();
+    let mesh = model.meshes().next().unwrap();
+    let options = MeshletOptions {
+        max_vertices: 32,
+        max_triangles: 40,
+    };
+    let meshlets = vmdl::gpu::meshlets(&mesh, options);
+    let triangle_count: usize = meshlets.iter().map(|meshlet| meshlet.triangles.len()).sum();
+    assert_eq!(triangle_count * 3, mesh.vertices().count());
+    for meshlet in meshlets {
+        assert!(meshlet.vertices.len() <= 32);
+        assert!(meshlet.triangles.len() <= 40);
+        assert!(meshlet
+            .triangles
+            .iter()
+            .flatten()
+            .all(|index| (*index as usize) < meshlet.vertices.len()));
+    }
+}
+
+#[test]
+fn meshlets_mismatched_vertices() {
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let mut vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let count = vvd.vertices.len();
+    vvd.vertices.truncate(count / 2);
+    let model = Model::from_parts(mdl, vtx, vvd);
+    let mesh = model.meshes().next().unwrap();
+
+    let meshlets = vmdl::gpu::meshlets(&mesh, MeshletOptions::default());
+    let triangle_count: usize = meshlets.iter().map(|meshlet| meshlet.triangles.len()).sum();
+    assert!(triangle_count > 0);
+    assert_eq!(triangle_count * 3, mesh.vertices().count());
+    assert!(meshlets
+        .iter()
+        .flat_map(|meshlet| &meshlet.vertices)
+        .all(|index| *index < count / 2));
+}
+
+#[test]
+fn quantized_vertex_buffer() {
+    let model = barrel();
+    let mesh = model.meshes().next().unwrap();
+    let full = build_vertex_buffer(&mesh, VertexPrecision::Full);
+    let quantized = build_vertex_buffer(&mesh, VertexPrecision::Quantized);
+    assert_eq!(full.indices, quantized.indices);
+    assert_eq!(full.data.len(), full.vertex_indices.len() * 32);
+    assert_eq!(quantized.data.len() * 2, full.data.len());
+
+    for vertex in mesh.vertices() {
+        let decoded = octahedral_decode(octahedral_encode(vertex.normal));
+        assert!((decoded - vertex.normal).length() < 0.001);
+    }
+}
+
+#[test]
+fn custom_vertex_layout() {
+    let model = barrel();
+    let mesh = model.meshes().next().unwrap();
+    let layout = VertexLayout::new()
+        .attribute(VertexAttribute::TextureCoordinates, VertexFormat::Float32x2)
+        .padding(4)
+        .attribute(VertexAttribute::BoneIndices, VertexFormat::Uint8x4)
+        .attribute(VertexAttribute::BoneWeights, VertexFormat::Unorm8x4)
+        .aligned(16);
+    assert_eq!(layout.stride(), 32);
+
+    let buffer = mesh.build_vertex_buffer(&layout);
+    assert_eq!(buffer.data.len(), buffer.vertex_indices.len() * 32);
+    let first = mesh.vertices().next().unwrap();
+    let index = buffer.indices[0] as usize * 32;
+    let u = f32::from_le_bytes(buffer.data[index..index + 4].try_into().unwrap());
+    assert_eq!(u, first.texture_coordinates[0]);
+    assert_eq!(buffer.data[index + 16], 255);
+}
+
+#[test]
+fn bones_for_lod() {
+    let model = barrel();
+    let bones: Vec<_> = model.bones_for_lod(0).map(|bone| bone.key()).collect();
+    assert_eq!(bones, [0u8.into()]);
+    assert_eq!(model.bones_for_lod(8).count(), 0);
+
+    let palette = BonePalette::for_lod(&model, 0);
+    assert_eq!(palette.bones(), bones.as_slice());
+    assert_eq!(palette.index(0u8.into()), Some(0));
+
+    let mesh = model.meshes().next().unwrap();
+    let layout = VertexPrecision::Full
+        .layout()
+        .attribute(VertexAttribute::BoneIndices, VertexFormat::Uint8x4);
+    let buffer = mesh.build_vertex_buffer_with_palette(&layout, &palette);
+    assert_eq!(buffer.data, mesh.build_vertex_buffer(&layout).data);
+}
+
+#[test]
+fn hardware_bone_indices() {
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let mut vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    // put the only bone in the third hardware slot of the first strip
+    let group = &mut vtx.body_parts[0].models[0].lods[0].meshes[0].strip_groups[0];
+    group.strips[0].bone_state_changes = vec![BoneStateChange {
+        hardware_id: 2,
+        new_bone_id: 0,
+    }];
+    for vertex in group.vertices.iter_mut() {
+        vertex.bone_count = 1;
+        vertex.bone_id = [2, 0, 0];
+    }
+    let model = Model::from_parts(mdl, vtx, vvd);
+    let mesh = model.meshes().next().unwrap();
+    let layout = VertexLayout::new()
+        .attribute(VertexAttribute::Position, VertexFormat::Float32x3)
+        .attribute(VertexAttribute::BoneIndices, VertexFormat::Uint8x4);
+
+    let buffer = mesh.build_vertex_buffer_with_bones(&layout, BoneIndexMode::Hardware);
+    let model_buffer = mesh.build_vertex_buffer_with_bones(&layout, BoneIndexMode::Model);
+    assert!(model_buffer.draws.is_empty());
+    assert_eq!(buffer.indices.len(), model_buffer.indices.len());
+    assert_eq!(buffer.draws.len(), 1);
+    assert_eq!(buffer.draws[0].indices, 0..buffer.indices.len());
+    assert_eq!(buffer.draws[0].bones, [0u8.into(); 3]);
+    for vertex in buffer.data.chunks_exact(buffer.stride) {
+        assert_eq!(vertex[12..16], [2, 0, 0, 0]);
+    }
+}
+
+#[test]
+fn vertex_buffer_mismatched_vertices() {
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let mut vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let count = vvd.vertices.len();
+    vvd.vertices.truncate(count / 2);
+    vvd.tangents.truncate(count / 2);
+    let model = Model::from_parts(mdl, vtx, vvd);
+    let mesh = model.meshes().next().unwrap();
+    let palette = BonePalette::for_lod(&model, 0);
+    let layout = VertexPrecision::Full
+        .layout()
+        .attribute(VertexAttribute::BoneIndices, VertexFormat::Uint8x4);
+
+    let expected = mesh.vertices().count();
+    assert!(expected > 0);
+    for mode in [
+        BoneIndexMode::Model,
+        BoneIndexMode::Palette(&palette),
+        BoneIndexMode::Hardware,
+    ] {
+        let buffer = mesh.build_vertex_buffer_with_bones(&layout, mode);
+        assert_eq!(buffer.indices.len(), expected);
+        assert!(buffer.vertex_indices.iter().all(|index| *index < count / 2));
+    }
+}
+
+#[test]
+fn vertex_fixups() {
+    let model = barrel();
+    assert_eq!(model.source_vertex_index(10), Some(10));
+    assert_eq!(model.fixed_vertex_indices(10).collect::<Vec<_>>(), [10]);
+    assert_eq!(model.source_vertex_index(model.vertices().len()), None);
+
+    let mut vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    vvd.vertices.truncate(5);
+    vvd.fixups = vec![
+        VertexFileFixup {
+            lod: 0,
+            source_vertex_id: 0,
+            vertex_count: 3,
+        },
+        VertexFileFixup {
+            lod: 1,
+            source_vertex_id: 1,
+            vertex_count: 2,
+        },
+    ];
+    assert_eq!(vvd.source_index(2), Some(2));
+    assert_eq!(vvd.source_index(3), Some(1));
+    assert_eq!(vvd.source_index(4), Some(2));
+    assert_eq!(vvd.source_index(5), None);
+    assert_eq!(vvd.fixed_indices(0).collect::<Vec<_>>(), [0]);
+    assert_eq!(vvd.fixed_indices(2).collect::<Vec<_>>(), [2, 4]);
+    assert_eq!(vvd.fixed_indices(3).count(), 0);
+}
+
+#[test]
+fn mismatched_vertices() {
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let mut vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let count = vvd.vertices.len();
+    vvd.vertices.truncate(count / 2);
+    vvd.tangents.truncate(count / 2);
+    let model = Model::from_parts(mdl, vtx, vvd);
+
+    let full = barrel();
+    let full_mesh = full.meshes().next().unwrap();
+    let mesh = model.meshes().next().unwrap();
+    assert_eq!(
+        full_mesh.try_vertices().unwrap().len(),
+        full_mesh.vertices().count()
+    );
+    assert!(matches!(
+        mesh.try_vertices(),
+        Err(ModelError::InvalidIndex { data: "vertex", .. })
+    ));
+    assert!(mesh.try_tangents().is_err());
+    assert!(mesh.vertices().count() < full_mesh.vertices().count());
+    assert_eq!(mesh.tangents().count(), mesh.vertices().count());
+}
+
+#[test]
+fn invalid_index_skips_triangle() {
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let mut vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let group = &mut vtx.body_parts[0].models[0].lods[0].meshes[0].strip_groups[0];
+    group.indices[4] = u16::MAX;
+    // the triangles of the first strip using the broken index
+    let broken: Vec<usize> = group.strips[0]
+        .indices()
+        .enumerate()
+        .filter(|(_, index)| *index == 4)
+        .map(|(position, _)| position / 3)
+        .collect();
+    assert!(!broken.is_empty());
+    fn without_triangles<T>(values: Vec<T>, triangles: &[usize]) -> Vec<T> {
+        values
+            .into_iter()
+            .enumerate()
+            .filter(|(position, _)| !triangles.contains(&(position / 3)))
+            .map(|(_, value)| value)
+            .collect()
+    }
+    let model = Model::from_parts(mdl, vtx, vvd);
+
+    let full = barrel();
+    let full_mesh = full.meshes().next().unwrap();
+    let mesh = model.meshes().next().unwrap();
+    let positions = |vertices: Vec<&vmdl::vvd::Vertex>| {
+        vertices
+            .into_iter()
+            .map(|vertex| vertex.position)
+            .collect::<Vec<_>>()
+    };
+    let expected = without_triangles(positions(full_mesh.vertices().collect()), &broken);
+    assert!(expected.len() < full_mesh.vertices().count());
+    assert_eq!(positions(mesh.vertices().collect()), expected);
+    assert_eq!(mesh.tangents().count(), expected.len());
+    assert_eq!(mesh.tangent_vectors().count(), expected.len());
+
+    let expected_indices = without_triangles(
+        full_mesh.vertex_strip_indices().flatten().collect(),
+        &broken,
+    );
+    let indices: Vec<usize> = mesh.vertex_strip_indices().flatten().collect();
+    assert_eq!(indices, expected_indices);
+    assert!(mesh.try_vertices().is_err());
+}
+
+#[test]
+fn tangent_vectors() {
+    let model = barrel();
+    let mesh = model.meshes().next().unwrap();
+    let vectors: Vec<_> = mesh.tangent_vectors().collect();
+    assert_eq!(vectors.len(), mesh.vertices().count());
+    for ((tangent, bitangent), vertex) in vectors.into_iter().zip(mesh.vertices()) {
+        let normal = Vector3::from(vertex.normal);
+        assert!(Vector3::from(bitangent).dot(normal).abs() < 0.01);
+        assert!(Vector3::from(bitangent).dot(Vector3::from(tangent)).abs() < 0.01);
+    }
+
+    let tangent = Tangent::from([1.0, 0.0, 0.0, -1.0]);
+    assert_eq!(tangent.handedness(), -1.0);
+    let bitangent = tangent.bitangent(Vector3::unit_z().into());
+    assert_eq!(Vector3::from(bitangent), -Vector3::unit_y());
+}
+
+#[test]
+fn repair_tangents() {
+    let model = barrel();
+    assert!(model.validate_tangents().is_valid());
+
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let mut vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    vvd.tangents[0] = Tangent::default();
+    vvd.tangents[1].w = -vvd.tangents[1].w;
+    let mut broken = Model::from_parts(mdl, vtx, vvd);
+
+    let report = broken.repair_tangents();
+    assert_eq!(report.invalid_length, 1);
+    assert_eq!(report.wrong_handedness, 2);
+    assert_eq!(report.repaired, 2);
+    assert!(broken.validate_tangents().is_valid());
+}
+
+#[test]
+fn repair_tangents_mismatched_vertices() {
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let mut vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let count = vvd.vertices.len();
+    vvd.vertices.truncate(count / 2);
+    vvd.tangents.truncate(count / 4);
+    let mut model = Model::from_parts(mdl, vtx, vvd);
+
+    let report = model.repair_tangents();
+    assert!(report.checked > 0);
+    assert!(report.checked <= count / 4);
+    assert_eq!(report.repaired, 0);
+}
+
+#[test]
+fn lods() {
+    let model = barrel();
+    let lods = model.lods().collect::<Vec<_>>();
+    assert_eq!(lods.len(), 2);
+    assert_eq!(lods[0].vertex_count, 698);
+    assert_eq!(lods[1].vertex_count, 272);
+    assert_eq!(lods[1].switch_point, 60.0);
+    assert_eq!(
+        lods[0].triangle_count * 3,
+        model
+            .meshes()
+            .map(|mesh| mesh.vertices().count())
+            .sum::<usize>()
+    );
+    assert!(lods[1].triangle_count < lods[0].triangle_count);
+}
+
+#[test]
+fn meshes_for_lod() {
+    let model = barrel();
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    assert_eq!(vtx.material_replacements.len(), 2);
+    let lod1 = model.meshes_for_lod(1).collect::<Vec<_>>();
+    assert_eq!(lod1.len(), model.meshes().count());
+    assert_eq!(
+        lod1.iter()
+            .map(|mesh| mesh.vertices().count())
+            .sum::<usize>(),
+        model.lods().nth(1).unwrap().triangle_count * 3
+    );
+    assert_eq!(model.meshes_for_lod(2).count(), 0);
+}
+
+#[test]
+fn lod_material_replacement() {
+    let with_replacement = |replacement: &str| {
+        // move the skin table to the end of the file, with a second skin reference for the lod texture
+        let mut data = fs::read("data/barrel01.mdl").unwrap();
+        let skin_offset = data.len() as i32;
+        data.extend_from_slice(&[0, 0, 1, 0]);
+        data[220..224].copy_from_slice(&2i32.to_le_bytes());
+        data[228..232].copy_from_slice(&skin_offset.to_le_bytes());
+        let mut mdl = Mdl::read(&data).unwrap();
+        mdl.textures.push(TextureInfo {
+            name: "barrel01_lod".into(),
+            ..mdl.textures[0].clone()
+        });
+        let mut vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+        vtx.material_replacements[1]
+            .replacements
+            .push(MaterialReplacement {
+                material: 0,
+                name: replacement.into(),
+            });
+        let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+        Model::from_parts(mdl, vtx, vvd)
+    };
+
+    let model = with_replacement("Models\\Props_Badlands\\Barrel01_LOD.vmt");
+    let skin = model.skin_tables().next().unwrap();
+    let lod0 = model.meshes_for_lod(0).next().unwrap();
+    assert_eq!(skin.texture(lod0.material_index()), Some("barrel01"));
+    let lod1 = model.meshes_for_lod(1).next().unwrap();
+    assert_eq!(lod1.material_index(), 1);
+    assert_eq!(skin.texture(lod1.material_index()), Some("barrel01_lod"));
+
+    // replacements that aren't a texture of the model are ignored
+    let model = with_replacement("missing");
+    assert_eq!(model.meshes_for_lod(1).next().unwrap().material_index(), 0);
+}
+
+#[test]
+fn canonical_paths() {
+    use vmdl::paths;
+
+    assert_eq!(
+        paths::canonical_material_name("\\Models\\Props\\Barrel01.VMT"),
+        "models/props/barrel01"
+    );
+    assert_eq!(paths::canonical_directory("Models\\Props"), "models/props/");
+    assert_eq!(paths::canonical_directory("/"), "");
+    assert_eq!(
+        paths::canonical_directories(["models/props/", "Models\\Props", "other"]),
+        vec!["models/props/", "other/"]
+    );
+    assert_eq!(
+        barrel().texture_directories(),
+        &["models/props_badlands/".to_string()]
+    );
+
+    // texture names keep their casing, the canonical name is available separately
+    let mut mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let name = mdl.textures[0].name.to_ascii_uppercase();
+    assert!(mdl.rename_texture(&name, "Props\\Barrel_Red.vmt"));
+    assert_eq!(mdl.textures[0].name, "Props/Barrel_Red.vmt");
+    assert_eq!(mdl.textures[0].canonical_name(), "props/barrel_red");
+}
+
+#[test]
+fn model_flags() {
+    let model = barrel();
+    assert!(model.is_static_prop());
+    assert!(model.casts_shadows());
+    assert!(!model.is_translucent_twopass());
+    assert!(!model.uses_bumpmapping());
+    assert!(!model.has_shadow_lod());
+}
+
+#[test]
+fn bone_contents() {
+    let model = barrel();
+    let contents = model.contents();
+    let bone = model.bones().next().unwrap();
+    assert_eq!(contents, bone.contents());
+    assert_eq!(
+        model.bones_with_contents(ContentFlags::all()).count(),
+        usize::from(!contents.is_empty())
+    );
+    assert_eq!(model.bones_with_contents(ContentFlags::empty()).count(), 0);
+}
+
+#[test]
+fn lighting_origin() {
+    let model = barrel();
+    assert!(model.illumination_attachment().is_none());
+    assert_eq!(model.lighting_origin(), model.illumination_position());
+}
+
+#[test]
+fn model_info() {
+    let model = barrel();
+    let info = model.info();
+    assert_eq!(info.name, model.name());
+    assert_eq!(model.full_name(), model.name());
+    assert_eq!(info.version, 46);
+    assert_eq!(info.surface_prop, model.surface_prop());
+    assert_eq!(info.hull_box, model.hull_box());
+    assert!(info.illumination_attachment.is_none());
+
+    // the maximum eye deflection is stored in the secondary header
+    let mut data = fs::read("data/barrel01.mdl").unwrap();
+    let header2 = i32::from_le_bytes(data[400..404].try_into().unwrap()) as usize;
+    data[header2 + 12..header2 + 16].copy_from_slice(&0.5f32.to_le_bytes());
+    let mdl = Mdl::read(&data).unwrap();
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let model = Model::from_parts(mdl, vtx, vvd);
+    assert_eq!(model.info().max_eye_deflection, 0.5);
+}
+
+#[test]
+fn attachment_bones() {
+    // add two attachments, one to the root bone and one to a bone that doesn't exist
+    let mut data = fs::read("data/barrel01.mdl").unwrap();
+    let offset = data.len() as i32;
+    data[240..248].copy_from_slice(&[2i32.to_le_bytes(), offset.to_le_bytes()].concat());
+    for (i, bone) in [0i32, 3].into_iter().enumerate() {
+        let name_offset = (2 - i as i32) * 92 + i as i32 * 8;
+        let mut attachment = vec![0; 92];
+        attachment[0..4].copy_from_slice(&name_offset.to_le_bytes());
+        attachment[8..12].copy_from_slice(&bone.to_le_bytes());
+        data.extend_from_slice(&attachment);
+    }
+    data.extend_from_slice(b"root\0\0\0\0broken\0");
+
+    let mdl = Mdl::read(&data).unwrap();
+    assert!(matches!(
+        mdl.validate().as_slice(),
+        [ValidationError::AttachmentBoneOutOfRange {
+            bone: 3,
+            count: 1,
+            ..
+        }]
+    ));
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let model = Model::from_parts(mdl, vtx, vvd);
+    let attachments: Vec<_> = model.attachments().collect();
+    assert_eq!(attachments.len(), 2);
+    assert_eq!(attachments[0].name, "root");
+    assert_eq!(
+        attachments[0].bone().unwrap().key(),
+        model.bones().next().unwrap().key()
+    );
+    assert_eq!(attachments[1].name, "broken");
+    assert!(attachments[1].bone().is_err());
+}
+
+#[test]
+fn select_sequence() {
+    let model = barrel();
+    let sequence = model.sequences().next().unwrap();
+    let selected = model.select_sequence(&sequence.name, |_| 0).unwrap();
+    assert_eq!(selected.key(), 0);
+    assert!(model.select_sequence("ACT_NONEXISTENT", |_| 0).is_none());
+}
+
+#[test]
+fn pose_context() {
+    let model = barrel();
+    let mut context = model.pose_context();
+    assert_eq!(context.normalized_values().len(), model.poses().count());
+    assert_eq!(context.set_pose_parameter("missing", 1.0), None);
+}
+
+#[test]
+fn include_models() {
+    assert_eq!(barrel().sequences_with_includes().count(), 1);
+
+    let mut mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    mdl.include_models.push(IncludeModel {
+        label: String::new(),
+        name: "models/shared_anims.mdl".into(),
+    });
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let mut model = Model::from_parts(mdl, vtx, vvd);
+    let mut loaded = Vec::new();
+    model
+        .resolve_include_models(|name| {
+            loaded.push(name.to_string());
+            Mdl::read(&fs::read("data/barrel01.mdl").unwrap())
+        })
+        .unwrap();
+    assert_eq!(loaded, vec!["models/shared_anims.mdl"]);
+
+    let sequences = model.sequences_with_includes().collect::<Vec<_>>();
+    assert_eq!(sequences.len(), 2);
+    assert!(matches!(sequences[0].0, AnimationSource::Local));
+    assert!(
+        matches!(sequences[1].0, AnimationSource::Included(include) if include.name == "models/shared_anims.mdl")
+    );
+    assert_eq!(model.animations_with_includes().count(), 2);
+}
+
+#[test]
+fn hull_and_view_box() {
+    let model = barrel();
+    let (hull_min, hull_max) = model.hull_box();
+    let (view_min, view_max) = model.view_box();
+    assert!(hull_min.z < hull_max.z);
+    assert!(view_min.z < view_max.z);
+}
+
+#[test]
+fn mesh_count() {
+    let model = barrel();
+    assert!(model.mesh_count() > 0);
+    assert_eq!(model.mesh_count(), model.meshes().count());
+    assert_eq!(model.mesh_count(), model.meshes_for_lod(1).count());
+}
+
+#[test]
+fn mismatched_parts() {
+    let read_parts = || {
+        (
+            Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap(),
+            Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap(),
+            Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap(),
+        )
+    };
+    let (mdl, vtx, vvd) = read_parts();
+    assert!(Model::try_from_parts(mdl, vtx, vvd).is_ok());
+
+    let (mut mdl, vtx, vvd) = read_parts();
+    mdl.body_parts[0].models[0].meshes.clear();
+    let error = Model::try_from_parts(mdl.clone(), vtx.clone(), vvd.clone())
+        .err()
+        .unwrap();
+    assert!(matches!(
+        error,
+        ModelError::Mismatch {
+            data: "meshes",
+            mdl: 0,
+            ..
+        }
+    ));
+    assert_eq!(Model::from_parts(mdl, vtx, vvd).mesh_count(), 0);
+}
+
+/// Expand strips to triangles, rotated so the smallest index is first
+fn strip_triangles(strips: &MeshStrips) -> Vec<[usize; 3]> {
+    let mut triangles = Vec::new();
+    for strip in strips.strips.iter() {
+        let indices = &strips.indices[strip.range.clone()];
+        match strip.primitive {
+            PrimitiveType::TriangleList => {
+                triangles.extend(indices.chunks(3).map(|t| [t[0], t[1], t[2]]))
+            }
+            PrimitiveType::TriangleStrip => {
+                triangles.extend(indices.windows(3).enumerate().map(|(i, t)| {
+                    if i % 2 == 0 {
+                        [t[0], t[1], t[2]]
+                    } else {
+                        [t[1], t[0], t[2]]
+                    }
+                }))
+            }
+        }
+    }
+    triangles
+        .into_iter()
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+        .map(|mut triangle| {
+            let min = (0..3).min_by_key(|i| triangle[*i]).unwrap();
+            triangle.rotate_left(min);
+            triangle
+        })
+        .collect()
+}
+
+#[test]
+fn strips() {
+    let model = barrel();
+    for mesh in model.meshes() {
+        let strips = mesh.strips().unwrap();
+        let mut triangles = strip_triangles(&strips);
+        let mut expected = strip_triangles(&MeshStrips {
+            indices: mesh.vertex_strip_indices().flatten().collect(),
+            strips: vec![StripRange {
+                primitive: PrimitiveType::TriangleList,
+                range: 0..mesh.vertex_strip_indices().flatten().count(),
+            }],
+        });
+        triangles.sort();
+        expected.sort();
+        assert_eq!(triangles, expected);
+    }
+}
+
+#[test]
+fn triangle_strip_winding() {
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let mut vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let strip_group = &mut vtx.body_parts[0].models[0].lods[0].meshes[0].strip_groups[0];
+    strip_group.strips[0].flags = StripFlags::IS_TRI_STRIP;
+
+    // a strip of n indices has n - 2 triangles, every other triangle flipped to keep the winding
+    let strip = &strip_group.strips[0];
+    let start = strip.index_range().start;
+    let indices: Vec<usize> = strip.indices().collect();
+    assert_eq!(indices.len(), strip.triangle_count() * 3);
+    assert!(indices
+        .iter()
+        .all(|index| strip.index_range().contains(index)));
+    let triangles: Vec<[usize; 3]> = indices
+        .chunks(3)
+        .take(4)
+        .map(|t| [t[0] - start, t[1] - start, t[2] - start])
+        .collect();
+    assert_eq!(triangles, [[2, 1, 0], [3, 1, 2], [4, 3, 2], [5, 3, 4]]);
+    // neighbouring triangles share an edge in opposite directions
+    for pair in triangles.windows(2) {
+        let edges = |t: [usize; 3]| [[t[0], t[1]], [t[1], t[2]], [t[2], t[0]]];
+        assert!(edges(pair[0])
+            .iter()
+            .any(|[a, b]| edges(pair[1]).contains(&[*b, *a])));
+    }
+
+    // the strips and the expanded triangles have the same winding
+    let model = Model::from_parts(mdl, vtx, vvd);
+    let mesh = model.meshes().next().unwrap();
+    let strips = mesh.strips().unwrap();
+    assert_eq!(strips.strips[0].primitive, PrimitiveType::TriangleStrip);
+    let mut triangles = strip_triangles(&strips);
+    let mut expected = strip_triangles(&MeshStrips {
+        indices: mesh.vertex_strip_indices().flatten().collect(),
+        strips: vec![StripRange {
+            primitive: PrimitiveType::TriangleList,
+            range: 0..mesh.vertex_strip_indices().flatten().count(),
+        }],
+    });
+    triangles.sort();
+    expected.sort();
+    assert_eq!(triangles, expected);
+}
+
+#[test]
+fn strips_invalid_index() {
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let mut vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    vtx.body_parts[0].models[0].lods[0].meshes[0].strip_groups[0].indices[4] = u16::MAX;
+    let model = Model::from_parts(mdl, vtx, vvd);
+    assert!(matches!(
+        model.meshes().next().unwrap().strips(),
+        Err(ModelError::InvalidIndex {
+            data: "strip group vertex",
+            index: 65535
+        })
+    ));
+}
+
+#[test]
+fn primitive_restart() {
+    let strips = MeshStrips {
+        indices: vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+        strips: vec![
+            StripRange {
+                primitive: PrimitiveType::TriangleStrip,
+                range: 0..4,
+            },
+            StripRange {
+                primitive: PrimitiveType::TriangleList,
+                range: 4..10,
+            },
+        ],
+    };
+    assert_eq!(
+        strips.primitive_restart_u16().unwrap(),
+        vec![0, 1, 2, 3, 0xFFFF, 4, 5, 6, 0xFFFF, 7, 8, 9]
+    );
+    assert_eq!(strips.primitive_restart_u32()[4], u32::MAX);
+
+    let large = MeshStrips {
+        indices: vec![0, 1, 0xFFFF],
+        strips: vec![StripRange {
+            primitive: PrimitiveType::TriangleStrip,
+            range: 0..3,
+        }],
+    };
+    assert_eq!(large.primitive_restart_u16(), None);
+    assert_eq!(large.primitive_restart_u32(), vec![0, 1, 0xFFFF]);
+}
+
+#[cfg(feature = "usd")]
+#[test]
+fn usd_export() {
+    let model = barrel();
+    let usda = vmdl::export::usd::to_usda(&model, 0);
+    assert!(usda.starts_with("#usda 1.0"));
+    assert!(usda.contains("def SkelRoot \"barrel01\""));
+    assert!(usda.contains("uniform token[] joints = [\"static_prop\"]"));
+    assert!(usda.contains("@models/props_badlands/barrel01.png@"));
+    assert_eq!(usda.matches("def Mesh").count(), model.mesh_count());
+}
+
+#[cfg(feature = "obj")]
+#[test]
+fn obj_export() {
+    let model = barrel();
+    let obj = vmdl::export::obj::to_obj(&model, 0);
+    assert!(obj.starts_with("o barrel01_0"));
+    assert!(obj.contains("usemtl models/props_badlands/barrel01"));
+    assert_eq!(obj.matches("\ng ").count(), model.mesh_count());
+    let indices: usize = model
+        .meshes()
+        .map(|mesh| mesh.vertex_strip_indices().flatten().count())
+        .sum();
+    assert_eq!(obj.matches("\nf ").count(), indices / 3);
+}
+
+#[cfg(feature = "obj")]
+#[test]
+fn obj_export_invalid_index() {
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let mut vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    vtx.body_parts[0].models[0].lods[0].meshes[0].strip_groups[0].indices[4] = u16::MAX;
+    let model = Model::from_parts(mdl, vtx, vvd);
+
+    let obj = vmdl::export::obj::to_obj(&model, 0);
+    let full = vmdl::export::obj::to_obj(&barrel(), 0);
+    assert!(obj.matches("\nf ").count() < full.matches("\nf ").count());
+    let indices: usize = model
+        .meshes()
+        .map(|mesh| mesh.vertex_strip_indices().flatten().count())
+        .sum();
+    assert_eq!(obj.matches("\nf ").count(), indices / 3);
+}
+
+#[cfg(feature = "dae")]
+#[test]
+fn dae_export() {
+    let model = barrel();
+    let dae = vmdl::export::dae::to_dae(&model, 0);
+    assert!(dae.starts_with("<?xml"));
+    assert!(dae.trim_end().ends_with("</COLLADA>"));
+    assert_eq!(dae.matches("<geometry ").count(), model.mesh_count());
+    assert_eq!(dae.matches("<controller ").count(), model.mesh_count());
+    assert!(dae.contains(r#"name="static_prop" type="JOINT""#));
+    assert!(dae.contains(r#"<animation_clip id="clip_0" name="@idle""#));
+    assert!(
+        dae.contains(r##"<accessor source="#geometry_0_positions_array" count="674" stride="3">"##)
+    );
+}
+
+#[cfg(feature = "vta")]
+#[test]
+fn vta_export() {
+    let model = barrel();
+    assert!(model.flex_descriptors().is_empty());
+    let vta = vmdl::export::vta::to_vta(&model);
+    assert!(vta.starts_with("version 1\nnodes\n0 \"static_prop\" -1\nend\n"));
+    assert!(vta.contains("skeleton\ntime 0\nend\n"));
+    assert!(vta.contains("vertexanimation\ntime 0 # basis\n"));
+    let base_vertices = vta
+        .lines()
+        .skip_while(|line| !line.starts_with("time 0 # basis"))
+        .skip(1)
+        .take_while(|line| *line != "end")
+        .count();
+    assert_eq!(base_vertices, model.vertices().len());
+
+    let model = flex_barrel(|_| {});
+    let vta = vmdl::export::vta::to_vta(&model);
+    assert!(vta.contains("skeleton\ntime 0\ntime 1\nend\n"));
+    let frame: Vec<&str> = vta
+        .lines()
+        .skip_while(|line| !line.starts_with("time 1 # dent"))
+        .skip(1)
+        .take_while(|line| *line != "end")
+        .collect();
+    assert_eq!(frame.len(), 3);
+    let deltas = [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 1.0]];
+    let normal_deltas = [[0.0; 3], [1.0, 0.0, 0.0], [0.0; 3]];
+    for (index, line) in frame.iter().enumerate() {
+        let vertex = &model.vertices()[index];
+        let position = vertex.position + Vector::from(deltas[index]);
+        let normal = vertex.normal + Vector::from(normal_deltas[index]);
+        let expected = format!(
+            "{index} {:.6} {:.6} {:.6} {:.6} {:.6} {:.6}",
+            position.x, position.y, position.z, normal.x, normal.y, normal.z
+        );
+        assert_eq!(*line, expected);
+    }
+}
+
+#[test]
+fn volume() {
+    let model = barrel();
+    let (min, max) = model.computed_bounding_box().unwrap();
+    let size = max - min;
+    let box_volume = (size.x * size.y * size.z) as vmdl::Scalar;
+    let volume = model.volume();
+    assert!(volume > box_volume * 0.5 && volume < box_volume);
+    let center = model.center_of_volume().unwrap();
+    assert!(center.x > min.x && center.x < max.x);
+    assert!(center.z > min.z && center.z < max.z);
+}
+
+#[test]
+fn triangles_with_missing_vertices() {
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let mut vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    vvd.vertices.truncate(vvd.vertices.len() / 2);
+    vvd.tangents.truncate(vvd.tangents.len() / 2);
+    let model = Model::from_parts(mdl, vtx, vvd);
+
+    let triangles: usize = model.meshes().map(|mesh| mesh.triangles().count()).sum();
+    assert!(triangles > 0);
+    assert!(model.volume().is_finite());
+    let groups = model.triangles_by_material(0, 0);
+    assert_eq!(groups[0].indices.len(), triangles * 3);
+    assert!(groups[0]
+        .indices
+        .iter()
+        .all(|index| *index < model.vertices().len()));
+}
+
+#[test]
+fn content_hash() {
+    let model = barrel();
+    let hash = model.content_hash();
+    assert_eq!(barrel().content_hash(), hash);
+    assert_ne!(model.scaled(2.0).content_hash(), hash);
+
+    let mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    assert_eq!(
+        Mdl::read(&fs::read("data/barrel01.mdl").unwrap())
+            .unwrap()
+            .content_hash(),
+        mdl.content_hash()
+    );
+    assert_ne!(mdl.content_hash(), hash);
+    // the hash doesn't depend on the platform
+    assert_eq!(mdl.content_hash(), 0xbe56_d5b8_9529_da58);
+}
+
+#[test]
+fn surface_props() {
+    let model = barrel();
+    let surface_props = model.surface_props();
+    assert_eq!(surface_props.len(), model.bones().count());
+    for (bone, surface_prop) in &surface_props {
+        let bone = model.bone(*bone).unwrap();
+        if bone.surface_prop.is_empty() {
+            assert_eq!(*surface_prop, model.surface_prop());
+        } else {
+            assert_eq!(*surface_prop, bone.surface_prop);
+        }
+        assert!(!surface_prop.is_empty());
+    }
+}
+
+#[test]
+fn eye_render_info() {
+    let model = barrel();
+    assert_eq!(model.eyeballs().count(), 0);
+    assert!(model.eye_render_info(&model.rest_pose()).is_empty());
+    assert!((model.max_eye_deflection() - 30.0f32.to_radians().cos()).abs() < 0.0001);
+
+    let model = flex_barrel(|mdl| {
+        let eyeballs = &mut mdl.body_parts[0].models[0].eyeballs;
+        eyeballs.push(eyeball(0));
+        eyeballs.push(Eyeball {
+            name: "eye_left".into(),
+            ..eyeball(-1)
+        });
+    });
+    assert_eq!(model.eyeballs().count(), 2);
+    let close = |a: Vector, b: [f32; 3]| (a - Vector::from(b)).length() < 0.001;
+
+    // the rest pose of the barrel is the identity, the eyeball stays where it is
+    let info = model.eye_render_info(&model.rest_pose());
+    assert_eq!(info[0].eyeball.name, "eye_right");
+    assert!(close(info[0].origin, [2.0, -1.0, 60.0]));
+    assert!(close(info[0].forward, [1.0, 0.0, 0.0]));
+    assert!(close(info[0].up, [0.0, 0.0, 1.0]));
+    assert_eq!(info[0].radius, 0.6);
+    assert_eq!(info[0].iris_scale, 2.5);
+    assert_eq!(info[0].max_deflection, model.max_eye_deflection());
+
+    // the eyeball follows its bone, raised by 10 units and turned 90 degrees around z
+    let info = model.eye_render_info(&turned_pose(&model));
+    assert!(close(info[0].origin, [1.0, 2.0, 70.0]));
+    assert!(close(info[0].forward, [0.0, 1.0, 0.0]));
+    assert!(close(info[0].up, [0.0, 0.0, 1.0]));
+    // eyeballs without a valid bone stay in model space
+    assert_eq!(info[1].eyeball.name, "eye_left");
+    assert!(close(info[1].origin, [2.0, -1.0, 60.0]));
+    assert!(close(info[1].forward, [1.0, 0.0, 0.0]));
+}
+
+#[test]
+fn phy_header_out_of_bounds() {
+    let mut data = Vec::new();
+    for value in [64i32, 0, 0, 0] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    assert!(matches!(
+        Phy::read(&data),
+        Err(ModelError::OutOfBounds { offset: 64, .. })
+    ));
+
+    // the solid count isn't trusted for allocating
+    let mut data = Vec::new();
+    for value in [16i32, 0, i32::MAX, 0] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    assert!(Phy::read(&data).is_err());
+
+    // a broken phy doesn't prevent loading the model
+    let dir = std::env::temp_dir().join("vmdl-broken-phy");
+    fs::create_dir_all(&dir).unwrap();
+    for extension in ["mdl", "dx90.vtx", "vvd"] {
+        let name = format!("barrel01.{extension}");
+        fs::copy(format!("data/{name}"), dir.join(name)).unwrap();
+    }
+    fs::write(dir.join("barrel01.phy"), &data).unwrap();
+    let model = Model::from_path(dir.join("barrel01.mdl")).unwrap();
+    assert_eq!(model.physics_bones().count(), 0);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn physics_bones() {
+    let mut data = Vec::new();
+    for value in [16i32, 0, 1, 0, 4] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(b"VPHY");
+    data.extend_from_slice(
+        b"solid {\n\"index\" \"0\"\n\"name\" \"static_prop\"\n\"mass\" \"30.5\"\n\"surfaceprop\" \"Wood\"\n}\neditparams {\n\"totalmass\" \"30.5\"\n}\n\0",
+    );
+    let phy = Phy::read(&data).unwrap();
+    assert_eq!(phy.solids.len(), 1);
+    assert_eq!(phy.solids[0].mass, 30.5);
+    assert_eq!(phy.solids[0].surface_prop, "Wood");
+    assert_eq!(phy.solids[0].collision_data, b"VPHY");
+
+    let mut model = barrel();
+    assert_eq!(model.physics_bones().count(), 0);
+    model.set_phy(phy);
+    let pairs: Vec<_> = model.physics_bones().collect();
+    assert_eq!(pairs.len(), 1);
+    assert_eq!(pairs[0].bone.name, "static_prop");
+    assert_eq!(pairs[0].solid.index, 0);
+    assert_eq!(
+        model.solid_for_bone(0u8.into()).unwrap().name,
+        "static_prop"
+    );
+    assert_eq!(model.bone_for_solid(0).unwrap().key(), 0u8.into());
+}
+
+#[test]
+fn scene_instances() {
+    let model = barrel();
+    let mut scene = Scene::new();
+    let root = scene.add(
+        &model,
+        Matrix4::from_translation(Vector3::new(0.0, 0.0, 10.0)),
+    );
+    let merged = scene.add_bone_merged(&model, root);
+    assert!(scene
+        .add_attached(&model, root, "missing", Matrix4::from_scale(1.0))
+        .is_none());
+
+    let meshes = scene.meshes();
+    assert_eq!(meshes.len(), model.mesh_count() * 2);
+    let root_mesh = meshes.iter().find(|mesh| mesh.instance == root).unwrap();
+    let merged_mesh = meshes.iter().find(|mesh| mesh.instance == merged).unwrap();
+
+    // the bone merged instance follows the skeleton of the root instance
+    let original = model
+        .meshes()
+        .next()
+        .unwrap()
+        .vertices()
+        .next()
+        .unwrap()
+        .position;
+    let first = root_mesh.vertices[root_mesh.triangles[0][0]].position;
+    assert!((first.z - original.z - 10.0).abs() < 0.001);
+    assert!((first.x - original.x).abs() < 0.001);
+    let merged_first = merged_mesh.vertices[merged_mesh.triangles[0][0]].position;
+    assert!((merged_first - first).length() < 0.001);
+
+    // root bones that don't exist in the parent are placed relative to the parent instance
+    let mut mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    mdl.bones[0].name = "unmatched".into();
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let unmatched = Model::from_parts(mdl, vtx, vvd);
+    let unmatched = scene.add_bone_merged(&unmatched, root);
+    let meshes = scene.meshes();
+    let unmatched_mesh = meshes
+        .iter()
+        .find(|mesh| mesh.instance == unmatched)
+        .unwrap();
+    let unmatched_first = unmatched_mesh.vertices[unmatched_mesh.triangles[0][0]].position;
+    assert!((unmatched_first - first).length() < 0.001);
+}
+
+#[cfg(feature = "gltf")]
+#[test]
+fn scene_gltf_export() {
+    let model = barrel();
+    let mut scene = Scene::new();
+    let root = scene.add(&model, Matrix4::identity());
+    scene.add_bone_merged(&model, root);
+
+    let glb = vmdl::export::gltf::scene_to_glb(&scene);
+    assert_eq!(&glb[0..4], b"glTF");
+    assert_eq!(glb.len() % 4, 0);
+    let gltf = gltf::Gltf::from_slice(&glb).unwrap();
+    assert_eq!(gltf.nodes().count(), 2);
+    assert_eq!(gltf.meshes().count(), 2);
+    let meshes = scene.meshes();
+    for (gltf_mesh, instance_meshes) in gltf.meshes().zip(meshes.chunks(model.mesh_count())) {
+        assert_eq!(gltf_mesh.primitives().count(), instance_meshes.len());
+        for (primitive, mesh) in gltf_mesh.primitives().zip(instance_meshes) {
+            assert_eq!(
+                primitive.indices().unwrap().count(),
+                mesh.triangles.len() * 3
+            );
+            assert_eq!(
+                primitive.get(&gltf::Semantic::Positions).unwrap().count(),
+                mesh.vertices.len()
+            );
+        }
+    }
+    let material = gltf.materials().next().unwrap();
+    assert_eq!(material.name(), Some("models/props_badlands/barrel01"));
+
+    let single = vmdl::export::gltf::to_glb(&model, 0);
+    assert_eq!(gltf::Gltf::from_slice(&single).unwrap().nodes().count(), 1);
+}
+
+#[cfg(feature = "gltf")]
+#[test]
+fn gltf_material_variants() {
+    // move the skin table to the end of the file, with a second skin using a second texture
+    let mut data = fs::read("data/barrel01.mdl").unwrap();
+    let skin_offset = data.len() as i32;
+    data.extend_from_slice(&[0, 0, 1, 0]);
+    data[224..228].copy_from_slice(&2i32.to_le_bytes());
+    data[228..232].copy_from_slice(&skin_offset.to_le_bytes());
+    let mut mdl = Mdl::read(&data).unwrap();
+    mdl.textures.push(TextureInfo {
+        name: "barrel01_red".into(),
+        ..mdl.textures[0].clone()
+    });
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let model = Model::from_parts(mdl, vtx, vvd);
+    assert_eq!(model.skin_tables().count(), 2);
+
+    let glb = vmdl::export::gltf::to_glb(&model, 1);
+    let root = gltf::Gltf::from_slice(&glb).unwrap().document.into_json();
+    assert_eq!(root.extensions_used, ["KHR_materials_variants"]);
+    let variants = &root
+        .extensions
+        .as_ref()
+        .unwrap()
+        .khr_materials_variants
+        .as_ref()
+        .unwrap()
+        .variants;
+    assert_eq!(variants.len(), 2);
+
+    let material_name = |index: u32| root.materials[index as usize].name.as_deref();
+    let primitive = &root.meshes[0].primitives[0];
+    // the skin of the instance is the default material
+    assert_eq!(
+        material_name(primitive.material.unwrap().value() as u32),
+        Some("models/props_badlands/barrel01_red")
+    );
+    let mappings = &primitive
+        .extensions
+        .as_ref()
+        .unwrap()
+        .khr_materials_variants
+        .as_ref()
+        .unwrap()
+        .mappings;
+    assert_eq!(mappings.len(), 2);
+    for (variant, name) in ["barrel01", "barrel01_red"].into_iter().enumerate() {
+        let mapping = mappings
+            .iter()
+            .find(|mapping| mapping.variants == [variant as u32])
+            .unwrap();
+        assert_eq!(
+            material_name(mapping.material),
+            Some(format!("models/props_badlands/{name}").as_str())
+        );
+    }
+
+    // models with a single skin don't use the extension
+    let root = gltf::Gltf::from_slice(&vmdl::export::gltf::to_glb(&barrel(), 0))
+        .unwrap()
+        .document
+        .into_json();
+    assert!(root.extensions_used.is_empty());
+    assert!(root.meshes[0].primitives[0].extensions.is_none());
+}
+
+#[test]
+fn parse_progress() {
+    let mut reports = Vec::new();
+    Model::from_path_with_progress("data/barrel01.mdl", ParseOptions::default(), |progress| {
+        reports.push(progress)
+    })
+    .unwrap();
+    let total: usize = ["mdl", "dx90.vtx", "vvd"]
+        .iter()
+        .map(|extension| {
+            fs::metadata(format!("data/barrel01.{extension}"))
+                .unwrap()
+                .len() as usize
+        })
+        .sum();
+    assert!(reports
+        .windows(2)
+        .all(|pair| pair[0].bytes_processed <= pair[1].bytes_processed));
+    assert!(reports.iter().all(|report| report.total_bytes == total));
+    assert_eq!(reports[0].section, "header2");
+    let last = reports.last().unwrap();
+    assert_eq!(last.section, "vvd");
+    assert_eq!(last.bytes_processed, total);
+
+    // the last section of the mdl finishes the mdl
+    let mdl_size = fs::metadata("data/barrel01.mdl").unwrap().len() as usize;
+    let body_parts = reports.iter().find(|report| report.section == "body parts");
+    assert_eq!(body_parts.unwrap().bytes_processed, mdl_size);
+
+    // skipped sections don't count towards the progress
+    let mut reports = Vec::new();
+    let data = fs::read("data/barrel01.mdl").unwrap();
+    Mdl::read_with_progress(&data, ParseOptions::geometry_only(), |progress| {
+        reports.push(progress)
+    })
+    .unwrap();
+    assert!(!reports.iter().any(|report| report.section == "animations"));
+    let step = reports[0].bytes_processed;
+    assert!(step > mdl_size / 18);
+    assert_eq!(reports.last().unwrap().bytes_processed, mdl_size);
+}
+
+#[test]
+fn memory_usage() {
+    let model = barrel();
+    let usage = model.memory_usage();
+    assert!(usage.vertices >= model.vertices().len() * 48);
+    assert!(usage.tangents >= model.tangents().len() * 16);
+    assert!(usage.indices > 0);
+    assert!(usage.strings > 0);
+    assert_eq!(usage.physics, 0);
+    assert_eq!(
+        usage.total(),
+        usage.vertices
+            + usage.tangents
+            + usage.indices
+            + usage.flexes
+            + usage.animations
+            + usage.strings
+            + usage.other
+    );
+}
+
+#[test]
+fn hitbox_sets() {
+    let model = barrel();
+    assert_eq!(model.hitbox_sets().count(), 1);
+    let name = &model.hitbox_sets().next().unwrap().name;
+    let set = model.hitbox_set(&name.to_uppercase()).unwrap();
+    assert!(model.hitbox_set("special").is_none());
+
+    let posed = model.posed_hitboxes(set, &model.rest_pose());
+    assert_eq!(posed.len(), set.boxes.len());
+    let bone = model.bones().next().unwrap();
+    let rest = bone.pose_to_bone.matrix() * posed[0].transform;
+    for (column, expected) in [rest.x, rest.y, rest.z, rest.w].into_iter().zip([
+        Vector3::unit_x(),
+        Vector3::unit_y(),
+        Vector3::unit_z(),
+        Vector3::new(0.0, 0.0, 0.0),
+    ]) {
+        assert!((column.truncate() - expected).magnitude() < 0.001);
+    }
+}
+
+#[test]
+fn debug_geometry() {
+    let model = barrel();
+    let pose = model.rest_pose();
+    assert!(debug_geometry::skeleton(&model, &pose).triangles.is_empty());
+    assert_eq!(debug_geometry::bounding_boxes(&model).len(), 24);
+    let set = model.hitbox_sets().next().unwrap();
+    assert_eq!(
+        debug_geometry::hitboxes(&model, set, &pose).len(),
+        set.boxes.len() * 12
+    );
+    assert!(debug_geometry::attachment_axes(&model, &pose, 1.0).is_empty());
+
+    // add a child bone to get a bone between two joints
+    let mut mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let mut child = mdl.bones[0].clone();
+    child.parent = 0u8.into();
+    child.set_rest_position(Vector3::new(0.0, 0.0, 10.0).into());
+    mdl.bones.push(child);
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let model = Model::from_parts(mdl, vtx, vvd);
+    let pose = model.rest_pose();
+
+    let lines = debug_geometry::skeleton_lines(&model, &pose);
+    assert_eq!(lines.len(), 1);
+    let mesh = debug_geometry::skeleton(&model, &pose);
+    assert_eq!(mesh.positions.len(), 6);
+    assert_eq!(mesh.triangles.len(), 8);
+    assert_eq!(mesh.positions[0], lines[0].start);
+    assert_eq!(mesh.positions[5], lines[0].end);
+
+    // all faces point away from the center of the octahedron
+    let position = |index: usize| Vector3::from(mesh.positions[index]);
+    let center = mesh
+        .positions
+        .iter()
+        .fold(Vector3::new(0.0, 0.0, 0.0), |sum, position| {
+            sum + Vector3::from(*position) / 6.0
+        });
+    for [a, b, c] in mesh.triangles {
+        let normal = (position(b) - position(a)).cross(position(c) - position(a));
+        let centroid = (position(a) + position(b) + position(c)) / 3.0;
+        assert!(normal.dot(centroid - center) > 0.0);
+    }
+}
+
+#[test]
+fn pose_transforms() {
+    let mut mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let mut child = mdl.bones[0].clone();
+    child.parent = 0u8.into();
+    child.set_rest_position(Vector3::new(0.0, 0.0, 10.0).into());
+    mdl.bones.push(child);
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let model = Model::from_parts(mdl, vtx, vvd);
+
+    let mut pose = model.rest_pose();
+    assert_eq!(pose.len(), 2);
+    assert!(pose.is_dirty());
+    let root = pose.world_transform(BoneId::from(0u8)).unwrap();
+    let child = pose.world_transform(BoneId::from(1u8)).unwrap();
+    assert!(!pose.is_dirty());
+    let offset = root.invert().unwrap() * child;
+    assert!((offset.w.truncate() - Vector3::new(0.0, 0.0, 10.0)).magnitude() < 0.001);
+
+    // the rest pose leaves the vertices in place
+    let vertex = model.vertices()[0];
+    let skinned = pose.skin_vertex(&vertex);
+    assert!((Vector3::from(skinned.position) - Vector3::from(vertex.position)).magnitude() < 0.01);
+
+    let mut sample = *pose.sample(BoneId::from(1u8)).unwrap();
+    sample.position = Vector3::new(0.0, 0.0, 20.0).into();
+    pose.set(sample);
+    assert!(pose.is_dirty());
+    let child = pose.world_transform(BoneId::from(1u8)).unwrap();
+    let offset = root.invert().unwrap() * child;
+    assert!((offset.w.truncate() - Vector3::new(0.0, 0.0, 20.0)).magnitude() < 0.001);
+
+    // samples for bones outside the pose are ignored
+    sample.bone = BoneId::from(5u8);
+    pose.set(sample);
+    assert!(!pose.is_dirty());
+    assert_eq!(pose.len(), 2);
+}
+
+#[test]
+fn multiple_root_bones() {
+    // the first bone is a child of the last bone, next to the original root
+    let mut mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let mut root = mdl.bones[0].clone();
+    root.parent = BoneId::from(-1);
+    root.set_rest_position(Vector3::new(5.0, 0.0, 0.0).into());
+    let mut child = mdl.bones[0].clone();
+    child.parent = 2u8.into();
+    child.set_rest_position(Vector3::new(0.0, 0.0, 10.0).into());
+    mdl.bones.insert(0, child);
+    mdl.bones.push(root);
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let model = Model::from_parts(mdl, vtx, vvd);
+
+    let roots: Vec<_> = model.root_bones().map(|bone| bone.key()).collect();
+    assert_eq!(roots, [BoneId::from(1u8), BoneId::from(2u8)]);
+    let tree: Vec<_> = model.bone_tree().map(|bone| bone.key()).collect();
+    assert_eq!(tree, [1u8, 2, 0].map(BoneId::from));
+
+    let pose = model.rest_pose();
+    let root = pose.world_transform(BoneId::from(2u8)).unwrap();
+    let child = pose.world_transform(BoneId::from(0u8)).unwrap();
+    let offset = root.invert().unwrap() * child;
+    assert!((offset.w.truncate() - Vector3::new(0.0, 0.0, 10.0)).magnitude() < 0.001);
+}
+
+#[test]
+fn bone_chain() {
+    let mut mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    let root = mdl.bones[0].name.clone();
+    for (name, parent) in [("thigh", 0u8), ("calf", 1), ("foot", 2), ("loop", 4)] {
+        let mut bone = mdl.bones[0].clone();
+        bone.name = name.into();
+        bone.parent = parent.into();
+        mdl.bones.push(bone);
+    }
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let model = Model::from_parts(mdl, vtx, vvd);
+
+    let chain = model.bone_chain("Foot", 3).unwrap();
+    let names: Vec<_> = chain.iter().map(|bone| bone.name.as_str()).collect();
+    assert_eq!(names, ["foot", "calf", "thigh"]);
+    assert_eq!(model.bone_chain("foot", 4).unwrap()[3].name, root);
+    assert_eq!(
+        model.bone_chain("foot", 5).unwrap_err(),
+        BoneChainError::TooShort {
+            bone: "foot".into(),
+            length: 5,
+            available: 4
+        }
+    );
+    assert!(matches!(
+        model.bone_chain("foot", usize::MAX),
+        Err(BoneChainError::TooShort { available: 4, .. })
+    ));
+    assert_eq!(
+        model.bone_chain("hand", 2).unwrap_err(),
+        BoneChainError::UnknownBone("hand".into())
+    );
+    assert_eq!(
+        model.bone_chain("loop", 2).unwrap_err(),
+        BoneChainError::Cyclic {
+            bone: "loop".into()
+        }
+    );
+}
+
+/// The rest pose with the root bone raised and turned around the z axis
+fn turned_pose(model: &Model) -> Pose {
+    let mut pose = model.rest_pose();
+    let mut sample = *pose.sample(BoneId::from(0u8)).unwrap();
+    sample.position = sample.position + Vector3::new(0.0, 0.0, 10.0).into();
+    sample.rotation = (cgmath::Quaternion::from(sample.rotation)
+        * cgmath::Quaternion::from(cgmath::Matrix3::from_angle_z(Deg(90.0))))
+    .into();
+    pose.set(sample);
+    pose
+}
+
+/// An eyeball attached to a bone, looking along the x axis of the bone
+fn eyeball(bone: i32) -> Eyeball {
+    Eyeball {
+        name: "eye_right".into(),
+        bone,
+        origin: Vector::from([2.0, -1.0, 60.0]),
+        z_offset: 0.5,
+        radius: 0.6,
+        up: Vector::from([0.0, 0.0, 1.0]),
+        forward: Vector::from([1.0, 0.0, 0.0]),
+        texture: 0,
+        iris_scale: 2.5,
+        upper_flex_descriptors: [-1; 3],
+        lower_flex_descriptors: [-1; 3],
+        upper_targets: [0.0; 3],
+        lower_targets: [0.0; 3],
+        upper_lid_flex_descriptor: -1,
+        lower_lid_flex_descriptor: -1,
+    }
+}
+
+/// The barrel with flex data for the first vertices of its mesh
+fn flex_barrel(edit: impl FnOnce(&mut Mdl)) -> Model {
+    let mut mdl = Mdl::read(&fs::read("data/barrel_flex.mdl").unwrap()).unwrap();
+    edit(&mut mdl);
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    Model::from_parts(mdl, vtx, vvd)
+}
+
+#[test]
+fn rebind_pose() {
+    let model = barrel();
+    let pose = turned_pose(&model);
+
+    let rebound = model.rebind(&pose);
+    let close = |a: Vector3<f32>, b: Vector3<f32>| (a - b).magnitude() < 0.001;
+    for (original, vertex) in model.vertices().iter().zip(rebound.vertices()) {
+        let skinned = pose.skin_vertex(original);
+        assert!(close(skinned.position.into(), vertex.position.into()));
+        assert!(close(skinned.normal.into(), vertex.normal.into()));
+    }
+
+    // the new rest pose doesn't move the vertices
+    let skinning = rebound.rest_pose().skinning_transforms()[0];
+    let identity = Matrix4::<f32>::identity();
+    for (column, expected) in [skinning.x, skinning.y, skinning.z, skinning.w]
+        .into_iter()
+        .zip([identity.x, identity.y, identity.z, identity.w])
+    {
+        assert!((column - expected).magnitude() < 0.001);
+    }
+
+    // animations still produce the same pose
+    let animation = model.animations().next().unwrap();
+    let rebound_animation = rebound.animations().next().unwrap();
+    let original = model.animation_pose(animation, 0.0).world_transforms()[0];
+    let animated = rebound
+        .animation_pose(rebound_animation, 0.0)
+        .world_transforms()[0];
+    assert!(close(original.w.truncate(), animated.w.truncate()));
+    assert!(close(original.x.truncate(), animated.x.truncate()));
+}
+
+#[test]
+fn rebind_flexes_and_eyeballs() {
+    let model = flex_barrel(|mdl| {
+        mdl.body_parts[0].models[0].meshes[0].center = Vector::from([1.0, 0.0, 0.0]);
+        mdl.body_parts[0].models[0].eyeballs.push(eyeball(0));
+    });
+    let pose = turned_pose(&model);
+    let rebound = model.rebind(&pose);
+
+    let skinning = pose.skinning_transforms()[0];
+    let close = |a: Vector, b: Vector| (a - b).length() < 0.001;
+    let (original, mesh) = (
+        model.meshes().next().unwrap(),
+        rebound.meshes().next().unwrap(),
+    );
+    for (original, vertex) in original.flexes()[0]
+        .vertices
+        .iter()
+        .zip(&mesh.flexes()[0].vertices)
+    {
+        let rotate = |delta: Vector| (skinning * Vector3::from(delta).extend(0.0)).truncate();
+        assert!(close(rotate(original.delta).into(), vertex.delta));
+        assert!(close(
+            rotate(original.normal_delta).into(),
+            vertex.normal_delta
+        ));
+    }
+    assert!(close(
+        mesh.flexes()[0].vertices[1].delta,
+        Vector::from([0.0, 1.0, 0.0])
+    ));
+    let center = skinning * Vector3::from(original.center()).extend(1.0);
+    assert!(close(center.truncate().into(), mesh.center()));
+
+    // the eyeball is relative to its bone, so it follows the new rest pose of the bone
+    let expected = &model.eye_render_info(&pose)[0];
+    let eye = &rebound.eye_render_info(&rebound.rest_pose())[0];
+    assert!(close(expected.origin, eye.origin));
+    assert!(close(expected.forward, eye.forward));
+    assert!(close(expected.up, eye.up));
+}
+
+#[test]
+fn vertices_weighted_to_bone() {
+    let model = barrel();
+    let bone = BoneId::from(0u8);
+    assert_eq!(
+        model.vertices_weighted_to(bone, 0.5).count(),
+        model.vertices().len()
+    );
+    assert_eq!(model.vertices_weighted_to(bone, 1.0).count(), 0);
+    assert_eq!(
+        model.vertices_weighted_to(BoneId::from(1u8), 0.0).count(),
+        0
+    );
+}
+
+#[test]
+fn collision_hulls() {
+    let corners = (0..8).map(|corner| Vector {
+        x: if corner & 1 == 0 { -1.0 } else { 1.0 },
+        y: if corner & 2 == 0 { -1.0 } else { 1.0 },
+        z: if corner & 4 == 0 { -1.0 } else { 1.0 },
+    });
+    let points: Vec<Vector> = corners.chain([Vector::default()]).collect();
+    let cube = convex_hull(&points, 64).unwrap();
+    assert_eq!(cube.positions.len(), 8);
+    assert_eq!(cube.triangles.len(), 12);
+    for [a, b, c] in cube.triangles {
+        let [a, b, c] = [a, b, c].map(|index| Vector3::from(cube.positions[index]));
+        // facing away from the center
+        assert!((b - a).cross(c - a).dot(a) > 0.0);
+    }
+    let flat = &points[..4];
+    assert!(convex_hull(flat, 64).is_none());
+
+    let model = barrel();
+    let hulls = model.collision_hulls(HullOptions {
+        max_vertices: 16,
+        ..HullOptions::default()
+    });
+    assert_eq!(hulls.len(), 1);
+    let hull = &hulls[0].hull;
+    assert_eq!(hulls[0].bone, BoneId::from(0u8));
+    assert!(hull.positions.len() <= 16);
+    assert_eq!(hull.triangles.len(), hull.positions.len() * 2 - 4);
+    let full = model.collision_hulls(HullOptions::default());
+    assert!(full[0].hull.positions.len() > hull.positions.len());
+}
+
+#[test]
+fn referenced_files() {
+    let mut mdl = Mdl::read(&fs::read("data/barrel01.mdl").unwrap()).unwrap();
+    mdl.animation_block_source = "models\\props_badlands\\barrel01.ani".into();
+    mdl.include_models.push(IncludeModel {
+        label: String::new(),
+        name: "models/shared_anims.mdl".into(),
+    });
+    let vtx = Vtx::read(&fs::read("data/barrel01.dx90.vtx").unwrap()).unwrap();
+    let vvd = Vvd::read(&fs::read("data/barrel01.vvd").unwrap()).unwrap();
+    let model = Model::from_parts(mdl, vtx, vvd);
+
+    let files = model
+        .referenced_files()
+        .into_iter()
+        .map(|file| (file.kind, file.path))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        files,
+        [
+            (FileKind::Mdl, "models/props_badlands/barrel01.mdl".into()),
+            (FileKind::Vvd, "models/props_badlands/barrel01.vvd".into()),
+            (
+                FileKind::Vtx,
+                "models/props_badlands/barrel01.dx90.vtx".into()
+            ),
+            (
+                FileKind::Material,
+                "materials/models/props_badlands/barrel01.vmt".into()
+            ),
+            (FileKind::Ani, "models/props_badlands/barrel01.ani".into()),
+            (FileKind::IncludeModel, "models/shared_anims.mdl".into()),
+        ]
+    );
+}
+
+#[cfg(feature = "glam")]
+#[test]
+fn glam_conversions() {
+    use cgmath::{EuclideanSpace, Point3, Transform};
+    use glam::{Mat4, Quat, Vec3};
+    use vmdl::{Quaternion, RadianEuler, Transform3x4};
+
+    let vector = Vector {
+        x: 1.0,
+        y: -2.5,
+        z: 3.0,
+    };
+    assert_eq!(Vec3::from(vector), Vec3::new(1.0, -2.5, 3.0));
+    assert_eq!(Vector::from(Vec3::from(vector)), vector);
+
+    let euler = RadianEuler {
+        x: 0.3,
+        y: -0.7,
+        z: 1.2,
+    };
+    let quaternion = Quaternion::from(euler);
+    let quat = Quat::from(quaternion);
+    assert_eq!(
+        [quat.x, quat.y, quat.z, quat.w],
+        [quaternion.x, quaternion.y, quaternion.z, quaternion.w]
+    );
+    let round_trip = Quaternion::from(quat);
+    assert_eq!(
+        [round_trip.x, round_trip.y, round_trip.z, round_trip.w],
+        [quaternion.x, quaternion.y, quaternion.z, quaternion.w]
+    );
+    assert_eq!(Quat::from(euler), quat);
+
+    let transform = Transform3x4::from_matrix(
+        Matrix4::from_translation(Vector3::new(4.0, -2.0, 8.0)) * Matrix4::from_angle_z(Deg(30.0)),
+    );
+    let matrix = Matrix4::from(transform);
+    let mat = Mat4::from(transform);
+    let point = Vector3::new(1.0, 2.0, 3.0);
+    let expected = matrix.transform_point(Point3::from_vec(point));
+    let transformed = mat.transform_point3(Vec3::new(point.x, point.y, point.z));
+    assert!((transformed - Vec3::new(expected.x, expected.y, expected.z)).length() < 1e-5);
+}
+
+#[cfg(feature = "nalgebra")]
+#[test]
+fn nalgebra_conversions() {
+    use cgmath::{EuclideanSpace, Transform};
+    use nalgebra::{Isometry3, Point3, UnitQuaternion};
+    use vmdl::{Quaternion, RadianEuler, Transform3x4};
+
+    let vector = Vector {
+        x: 1.0,
+        y: -2.5,
+        z: 3.0,
+    };
+    assert_eq!(
+        nalgebra::Vector3::from(vector),
+        nalgebra::Vector3::new(1.0, -2.5, 3.0)
+    );
+    assert_eq!(Vector::from(nalgebra::Vector3::from(vector)), vector);
+    assert_eq!(Point3::from(vector), Point3::new(1.0, -2.5, 3.0));
+    assert_eq!(Vector::from(Point3::from(vector)), vector);
+
+    let euler = RadianEuler {
+        x: 0.3,
+        y: -0.7,
+        z: 1.2,
+    };
+    let quaternion = Quaternion::from(euler);
+    let unit = UnitQuaternion::from(quaternion);
+    assert!((unit.i - quaternion.x).abs() < 1e-6);
+    assert!((unit.j - quaternion.y).abs() < 1e-6);
+    assert!((unit.k - quaternion.z).abs() < 1e-6);
+    assert!((unit.w - quaternion.w).abs() < 1e-6);
+    let round_trip = Quaternion::from(unit);
+    assert!((round_trip.x - quaternion.x).abs() < 1e-6);
+    assert!((round_trip.y - quaternion.y).abs() < 1e-6);
+    assert!((round_trip.z - quaternion.z).abs() < 1e-6);
+    assert!((round_trip.w - quaternion.w).abs() < 1e-6);
+    assert_eq!(UnitQuaternion::from(euler), unit);
+
+    let transform = Transform3x4::from_matrix(
+        Matrix4::from_translation(Vector3::new(4.0, -2.0, 8.0)) * Matrix4::from_angle_z(Deg(30.0)),
+    );
+    let matrix = Matrix4::from(transform);
+    let isometry = Isometry3::from(transform);
+    let point = Vector3::new(1.0, 2.0, 3.0);
+    let expected = matrix.transform_point(cgmath::Point3::from_vec(point));
+    let transformed = isometry.transform_point(&Point3::new(point.x, point.y, point.z));
+    assert!((transformed - Point3::new(expected.x, expected.y, expected.z)).norm() < 1e-5);
+}
+
+#[cfg(feature = "bevy")]
+#[test]
+fn bevy_conversions() {
+    use bevy_render::mesh::{Indices, Mesh, VertexAttributeValues};
+    use std::collections::HashSet;
+
+    let model = barrel();
+    for mesh in model.meshes() {
+        let bevy_mesh = Mesh::from(&mesh);
+        let Some(Indices::U32(indices)) = bevy_mesh.indices() else {
+            panic!("expected 32 bit indices");
+        };
+        let triangles = mesh.triangles().flatten().collect::<Vec<_>>();
+        assert_eq!(indices.len(), triangles.len());
+
+        // vertices are deduplicated and ordered by first use
+        let vertex_count = triangles.iter().collect::<HashSet<_>>().len();
+        assert_eq!(bevy_mesh.count_vertices(), vertex_count);
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            bevy_mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("expected positions");
+        };
+        for (index, vertex) in indices.iter().zip(&triangles) {
+            let position: [f32; 3] = model.vertices()[*vertex].position.into();
+            assert_eq!(positions[*index as usize], position);
+        }
+
+        let Some(VertexAttributeValues::Float32x4(weights)) =
+            bevy_mesh.attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT)
+        else {
+            panic!("expected joint weights");
+        };
+        for weight in weights {
+            assert!((weight.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        }
+        assert!(bevy_mesh.attribute(Mesh::ATTRIBUTE_TANGENT).is_some());
+        assert!(bevy_mesh.attribute(Mesh::ATTRIBUTE_JOINT_INDEX).is_some());
+    }
+
+    let bindposes = model.bevy_inverse_bindposes();
+    assert_eq!(bindposes.len(), model.bones().count());
+    for (bindpose, bone) in bindposes.iter().zip(model.bones()) {
+        let matrix = bone.pose_to_bone.matrix();
+        let columns: &[f32; 16] = matrix.as_ref();
+        assert_eq!(&bindpose.to_cols_array(), columns);
+    }
+}
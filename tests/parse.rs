@@ -1,7 +1,13 @@
+use cgmath::{AbsDiffEq, Matrix4, Rad, SquareMatrix, Transform, Vector3};
 use std::fs::read;
-use vmdl::mdl::Mdl;
+use vmdl::mdl::{
+    Animation, AnimationDescription, AnimationFlags, Bone, BoneId, CompressionOptions, EncodeError,
+    HitGroup, LinkedFiles, Mdl, ModelFlags, ParseOptions, Patch, PositionData, ProceduralBone,
+    RotationData, StudioAnimFlags, ValidationError, WriteError, WriteOptions,
+};
 use vmdl::vtx::Vtx;
 use vmdl::vvd::Vvd;
+use vmdl::{Handle, ModelError, Pose, Quaternion, RadianEuler, Transform3x4, Vector};
 
 #[test]
 fn parse_mdl() {
@@ -20,3 +26,828 @@ fn parse_vvd() {
     let data = read("data/barrel01.vvd").unwrap();
     Vvd::read(&data).unwrap();
 }
+
+#[test]
+fn validate_mdl() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mdl = Mdl::read(&data).unwrap();
+    assert!(mdl.validate().is_empty());
+}
+
+#[test]
+fn validate_track_lengths() {
+    let mut data = read("data/barrel01.mdl").unwrap();
+    let description = i32::from_le_bytes(data[184..188].try_into().unwrap()) as usize;
+    data[description + 16..description + 20].copy_from_slice(&2i32.to_le_bytes());
+    let animation_index = (data.len() - description) as i32;
+    data[description + 56..description + 60].copy_from_slice(&animation_index.to_le_bytes());
+    // bone 0 with an animated position, only the x axis has values with a single run of 3 frames
+    data.extend_from_slice(&[0, 0x04, 0, 0]);
+    for pointer in [6u16, 0, 0] {
+        data.extend_from_slice(&pointer.to_le_bytes());
+    }
+    data.extend_from_slice(&[1, 3]);
+    data.extend_from_slice(&5u16.to_le_bytes());
+
+    let mdl = Mdl::read(&data).unwrap();
+    let animation = &mdl.local_animations[0].animations[0];
+    assert_eq!(animation.stored_frame_counts(), [None, Some(3)]);
+    assert_eq!(animation.position_data().frame_count(), Some(2));
+    assert!(matches!(
+        mdl.validate().as_slice(),
+        [ValidationError::TrackLength {
+            track: "position",
+            frames: 3,
+            expected: 2,
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn validate_blend_grids() {
+    let mut data = read("data/barrel01.mdl").unwrap();
+    let sequence = i32::from_le_bytes(data[192..196].try_into().unwrap()) as usize;
+    data[sequence + 56..sequence + 60].copy_from_slice(&2i32.to_le_bytes());
+
+    let mdl = Mdl::read(&data).unwrap();
+    assert_eq!(mdl.animation_sequences[0].animation_indices.len(), 1);
+    assert!(matches!(
+        mdl.validate().as_slice(),
+        [ValidationError::BlendGridSize {
+            count: 2,
+            width: 1,
+            height: 1,
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn body_part_names() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mdl = Mdl::read(&data).unwrap();
+    assert_eq!(mdl.body_parts[0].name, "Body");
+}
+
+#[test]
+fn read_partial() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let (partial, errors) = Mdl::read_partial(&data).unwrap();
+    assert!(errors.is_empty());
+    assert!(partial.is_complete());
+    assert_eq!(partial.mdl.bones.len(), 1);
+
+    // only the header remains intact
+    let (partial, errors) = Mdl::read_partial(&data[..408]).unwrap();
+    assert!(Mdl::read(&data[..408]).is_err());
+    assert!(!partial.is_complete());
+    assert_eq!(partial.failed_sections.len(), errors.len());
+    assert!(!partial.failed_sections.contains(&"header"));
+    assert_eq!(
+        partial.mdl.name.as_str(),
+        Mdl::read(&data).unwrap().name.as_str()
+    );
+
+    let (partial, errors) = Mdl::read_partial(&data[..16]).unwrap();
+    assert_eq!(partial.failed_sections, vec!["header"]);
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn parse_geometry_only() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mdl = Mdl::read_with_options(&data, ParseOptions::geometry_only()).unwrap();
+    assert!(mdl.local_animations.is_empty());
+    assert!(mdl.animation_sequences.is_empty());
+    assert!(mdl.hit_boxes.is_empty());
+    assert_eq!(mdl.bones.len(), 1);
+    assert_eq!(
+        mdl.body_parts.len(),
+        Mdl::read(&data).unwrap().body_parts.len()
+    );
+
+    let options = ParseOptions {
+        skip_hitboxes: true,
+        ..ParseOptions::default()
+    };
+    let mdl = Mdl::read_with_options(&data, options).unwrap();
+    assert!(mdl.hit_boxes.is_empty());
+    assert_eq!(mdl.local_animations.len(), 1);
+}
+
+#[test]
+fn hit_groups() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mdl = Mdl::read(&data).unwrap();
+    for hitbox in mdl.hit_boxes.iter().flat_map(|set| &set.boxes) {
+        assert_eq!(i32::from(hitbox.hit_group()), hitbox.group);
+    }
+
+    assert_eq!(HitGroup::from(1), HitGroup::Head);
+    assert_eq!(HitGroup::from(10), HitGroup::Gear);
+    assert_eq!(HitGroup::from(8), HitGroup::Other(8));
+    assert_eq!(i32::from(HitGroup::RightLeg), 7);
+}
+
+#[cfg(feature = "legacy")]
+#[test]
+fn parse_legacy_version() {
+    let mut data = read("data/barrel01.mdl").unwrap();
+    let mdl = Mdl::read(&data).unwrap();
+    assert!(!mdl.header.is_legacy());
+
+    // with a single bone and texture the legacy layouts line up with the current ones
+    data[4..8].copy_from_slice(&37i32.to_le_bytes());
+    let legacy = Mdl::read(&data).unwrap();
+    assert!(legacy.header.is_legacy());
+    assert_eq!(legacy.bones.len(), mdl.bones.len());
+    assert_eq!(legacy.bones[0].name, mdl.bones[0].name);
+    assert_eq!(legacy.bones[0].surface_prop, mdl.bones[0].surface_prop);
+    assert_eq!(legacy.textures[0].name, mdl.textures[0].name);
+
+    // with multiple bones and textures every header is read with the legacy stride
+    let legacy = Mdl::read(&read("data/legacy_v37.mdl").unwrap()).unwrap();
+    assert!(legacy.header.is_legacy());
+    let bones: Vec<_> = legacy
+        .bones
+        .iter()
+        .map(|bone| (bone.name.as_str(), bone.surface_prop.as_str()))
+        .collect();
+    assert_eq!(
+        bones,
+        [("root", "metal"), ("spine", "wood"), ("head", "flesh")]
+    );
+    assert_eq!(legacy.bones[2].parent, BoneId::from(1u8));
+    assert_eq!(legacy.bones[2].physics_bone, 2);
+    assert_eq!(
+        legacy.bones[2].rest_position(),
+        Vector::from([0.0, 2.0, 16.0])
+    );
+    let textures: Vec<_> = legacy
+        .textures
+        .iter()
+        .map(|texture| texture.name.as_str())
+        .collect();
+    assert_eq!(textures, ["barrel01", "barrel01_rust", "barrel01_lid"]);
+}
+
+#[test]
+fn respawn_version_error() {
+    let mut data = read("data/barrel01.mdl").unwrap();
+    data[4..8].copy_from_slice(&53i32.to_le_bytes());
+    assert!(matches!(
+        Mdl::read(&data),
+        Err(ModelError::UnsupportedVersion(53))
+    ));
+    let (_, errors) = Mdl::read_partial(&data).unwrap();
+    assert!(matches!(
+        &errors[0],
+        ModelError::Section {
+            section: "header",
+            ..
+        }
+    ));
+}
+
+#[cfg(feature = "respawn")]
+#[test]
+fn parse_respawn_skeleton() {
+    use vmdl::mdl::respawn::RespawnMdl;
+
+    let data = read("data/barrel01.mdl").unwrap();
+    let mdl = Mdl::read(&data).unwrap();
+
+    // version 52 shares the header start and bone layout
+    let mut v52 = data.clone();
+    v52[4..8].copy_from_slice(&52i32.to_le_bytes());
+    let respawn = RespawnMdl::read(&v52).unwrap();
+    assert_eq!(respawn.version, 52);
+    assert_eq!(respawn.name.as_str(), mdl.name.as_str());
+    assert_eq!(respawn.bones.len(), mdl.bones.len());
+    assert_eq!(respawn.bones[0].name, mdl.bones[0].name);
+
+    assert_eq!(respawn.bone_scales, [Vector::from([1.0; 3])]);
+
+    // version 53 adds a name offset to the header and a scale before the compression scales of every bone
+    let v53 = read("data/respawn_v53.mdl").unwrap();
+    let respawn = RespawnMdl::read(&v53).unwrap();
+    assert_eq!(respawn.version, 53);
+    assert_eq!(respawn.name.as_str(), "respawn_test");
+    assert_eq!(respawn.hull_box[1], Vector::from([16.0, 16.0, 72.0]));
+    let names: Vec<_> = respawn
+        .bones
+        .iter()
+        .map(|bone| bone.name.as_str())
+        .collect();
+    assert_eq!(names, ["root", "child"]);
+    assert_eq!(respawn.bones[1].parent, BoneId::from(0u8));
+    assert_eq!(
+        respawn.bones[1].rest_position(),
+        Vector::from([0.0, 0.0, 10.0])
+    );
+    assert_eq!(respawn.bones[1].pos_scale, Vector::from([1.0 / 512.0; 3]));
+    assert_eq!(respawn.bones[1].surface_prop, "flesh");
+    assert_eq!(respawn.bones[1].physics_bone, 1);
+    assert_eq!(
+        respawn.bone_scales,
+        [Vector::from([1.0; 3]), Vector::from([2.0, 2.0, 0.5])]
+    );
+}
+
+#[test]
+fn parse_animation_in_block() {
+    let mut data = read("data/barrel01.mdl").unwrap();
+    let description = i32::from_le_bytes(data[184..188].try_into().unwrap()) as usize;
+    let animation_index =
+        i32::from_le_bytes(data[description + 56..description + 60].try_into().unwrap());
+    data[description + 52..description + 56].copy_from_slice(&1i32.to_le_bytes());
+
+    let mdl = Mdl::read(&data).unwrap();
+    let location = mdl.local_animations[0].animation_block.unwrap();
+    assert_eq!(location.block, 1);
+    assert_eq!(location.offset, animation_index as usize);
+    assert!(mdl.local_animations[0].animations.is_empty());
+}
+
+#[test]
+fn parse_pose_keys() {
+    let mut data = read("data/barrel01.mdl").unwrap();
+    let sequence = i32::from_le_bytes(data[192..196].try_into().unwrap()) as usize;
+    let pose_key_offset = (data.len() - sequence) as i32;
+    data[sequence + 160..sequence + 164].copy_from_slice(&pose_key_offset.to_le_bytes());
+    data.extend_from_slice(&(-45.0f32).to_le_bytes());
+    data.extend_from_slice(&12.0f32.to_le_bytes());
+
+    let mdl = Mdl::read(&data).unwrap();
+    let sequence = &mdl.animation_sequences[0];
+    assert_eq!(sequence.blend_size, [1, 1]);
+    assert_eq!(sequence.pose_keys, [vec![-45.0], vec![12.0]]);
+
+    let mut grid = sequence.clone();
+    grid.blend_size = [3, 1];
+    assert_eq!(grid.blend_cell(0, 0.75), (1, 0.5));
+    grid.pose_keys = [vec![0.0, 10.0, 40.0], vec![]];
+    assert_eq!(grid.blend_cell(0, 25.0), (1, 0.5));
+    assert_eq!(grid.blend_cell(0, -5.0), (0, 0.0));
+    assert_eq!(grid.blend_cell(1, 5.0), (0, 0.0));
+}
+
+#[test]
+fn parse_header2_name() {
+    let mut data = read("data/barrel01.mdl").unwrap();
+    let mdl = Mdl::read(&data).unwrap();
+    assert!(mdl.header2.is_some());
+    assert_eq!(mdl.full_name, None);
+
+    let header2 = i32::from_le_bytes(data[400..404].try_into().unwrap()) as usize;
+    let name_offset = (data.len() - header2) as i32;
+    data[header2 + 20..header2 + 24].copy_from_slice(&name_offset.to_le_bytes());
+    data.extend_from_slice(b"props_c17/oildrum001_explosive_with_a_very_long_name.mdl\0");
+    let mdl = Mdl::read(&data).unwrap();
+    assert_eq!(
+        mdl.full_name.as_deref(),
+        Some("props_c17/oildrum001_explosive_with_a_very_long_name.mdl")
+    );
+}
+
+#[test]
+fn parse_unknown_procedural_bone() {
+    let mut data = read("data/barrel01.mdl").unwrap();
+    let bone = i32::from_le_bytes(data[160..164].try_into().unwrap()) as usize;
+    let rules_offset = (data.len() - bone) as i32;
+    data[bone + 164..bone + 168].copy_from_slice(&9i32.to_le_bytes());
+    data[bone + 168..bone + 172].copy_from_slice(&rules_offset.to_le_bytes());
+    data.extend_from_slice(&[1, 2, 3, 4]);
+
+    let mdl = Mdl::read(&data).unwrap();
+    let rules = mdl.bones[0].procedural_rules.as_ref().unwrap();
+    assert_eq!(rules.ty(), 9);
+    assert!(matches!(
+        rules,
+        ProceduralBone::Unknown { ty: 9, raw } if raw == &[1, 2, 3, 4]
+    ));
+}
+
+/// Point the animation of the barrel to an animated rotation with three frames
+fn animated_rotation() -> Vec<u8> {
+    let mut data = read("data/barrel01.mdl").unwrap();
+    let description = i32::from_le_bytes(data[184..188].try_into().unwrap()) as usize;
+    let animation_offset = (data.len() - description) as i32;
+    data[description + 16..description + 20].copy_from_slice(&3i32.to_le_bytes());
+    data[description + 56..description + 60].copy_from_slice(&animation_offset.to_le_bytes());
+    // bone 0 with STUDIO_ANIM_ANIMROT, followed by the value pointers
+    data.extend_from_slice(&[0, 0x08, 0, 0]);
+    for pointer in [6u16, 0, 0] {
+        data.extend_from_slice(&pointer.to_le_bytes());
+    }
+    // 2 frames with value 100, followed by a single frame with value 7
+    for value in [0x0201u16, 100, 0x0101, 7] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data
+}
+
+#[test]
+fn parse_animated_values() {
+    let mdl = Mdl::read(&animated_rotation()).unwrap();
+    let animation = &mdl.local_animations[0].animations[0];
+    let RotationData::Animated(values) = animation.rotation_data() else {
+        panic!("expected animated rotation");
+    };
+    assert_eq!(values.len(), 3);
+    assert_eq!(values[0].y, values[1].y);
+    assert_eq!(values[0].y * 7.0, values[2].y * 100.0);
+}
+
+#[test]
+fn parse_truncated_animation() {
+    let data = animated_rotation();
+    let description = i32::from_le_bytes(data[184..188].try_into().unwrap()) as usize;
+    for len in description..data.len() {
+        assert!(Mdl::read(&data[..len]).is_err());
+    }
+}
+
+#[test]
+fn error_offsets() {
+    // the offsets are relative to the start of the file, not to the structure being read
+    let data = animated_rotation();
+    let len = data.len() - 2;
+    assert!(matches!(
+        Mdl::read(&data[..len]),
+        Err(ModelError::Eof(offset)) if offset == len
+    ));
+
+    let mut data = read("data/barrel01.mdl").unwrap();
+    let bone = i32::from_le_bytes(data[160..164].try_into().unwrap()) as usize;
+    data[bone..bone + 4].copy_from_slice(&0x10000i32.to_le_bytes());
+    assert!(matches!(
+        Mdl::read(&data),
+        Err(ModelError::OutOfBounds { offset, .. }) if offset == bone + 0x10000
+    ));
+}
+
+#[cfg(feature = "miette")]
+#[test]
+fn error_diagnostic() {
+    use miette::Diagnostic;
+
+    let data = read("data/barrel01.mdl").unwrap();
+    let truncated = data[..600].to_vec();
+    let error = Mdl::read(&truncated).unwrap_err();
+    assert!(error.code().is_some());
+    // labels need the source data
+    assert!(error.labels().is_none());
+
+    // the second header starts at 408 and runs past the end of the data
+    let error = error.with_source_data("barrel01.mdl", truncated);
+    let label = error.labels().unwrap().next().unwrap();
+    assert_eq!(label.offset(), 408);
+    assert_eq!(label.len(), 600 - 408);
+    assert!(error.source_code().is_some());
+
+    // offsets past the end of the data point to the last byte
+    let mut data = data;
+    let bone = i32::from_le_bytes(data[160..164].try_into().unwrap()) as usize;
+    data[bone..bone + 4].copy_from_slice(&0x10000i32.to_le_bytes());
+    let error = Mdl::read(&data)
+        .unwrap_err()
+        .with_source_data("barrel01.mdl", data.clone());
+    let label = error.labels().unwrap().next().unwrap();
+    assert_eq!(label.offset(), data.len() - 1);
+    assert!(label
+        .label()
+        .unwrap()
+        .contains(&format!("{}", bone + 0x10000)));
+}
+
+#[test]
+fn animation_stats() {
+    let mdl = Mdl::read(&animated_rotation()).unwrap();
+    let stats = mdl.local_animations[0].stats();
+    assert_eq!(stats.bones, 1);
+    assert_eq!(stats.animated_bones, 1);
+    assert_eq!(stats.compressed_rotations, 1);
+    assert_eq!(stats.raw_rotations, 0);
+    // header, value pointers and two runs of a single value
+    assert_eq!(stats.track_bytes, 4 + 6 + 8);
+    assert!(stats.max_rotation_delta > 0.0);
+    assert_eq!(stats.max_position_delta, 0.0);
+}
+
+#[test]
+fn bone_transforms() {
+    let mut mdl = Mdl::read(&read("data/barrel01.mdl").unwrap()).unwrap();
+    let mut child = mdl.bones[0].clone();
+    child.parent = 0u8.into();
+    child.set_rest_position(Vector::from([0.0, 0.0, 10.0]));
+    child.set_rest_rotation(Quaternion::from(RadianEuler {
+        x: 0.0,
+        y: 0.0,
+        z: std::f32::consts::FRAC_PI_2,
+    }));
+    mdl.bones.push(child);
+
+    // the rest transform in model space is the inverse of pose_to_bone
+    let root = &mdl.bones[0];
+    let identity = root.pose_to_bone.matrix() * root.world_transform(&mdl);
+    assert!(identity.abs_diff_eq(&Matrix4::identity(), 0.001));
+
+    // children are placed relative to the parent, rotated first and then translated
+    let child = &mdl.bones[1];
+    let local = Matrix4::from_translation(Vector3::new(0.0, 0.0, 10.0))
+        * Matrix4::from_angle_z(Rad(std::f32::consts::FRAC_PI_2));
+    assert!(child.local_transform().abs_diff_eq(&local, 0.001));
+    let expected = root.world_transform(&mdl) * local;
+    assert!(child.world_transform(&mdl).abs_diff_eq(&expected, 0.001));
+}
+
+#[test]
+fn bone_paths() {
+    let mut mdl = Mdl::read(&read("data/barrel01.mdl").unwrap()).unwrap();
+    // 0 -> 1 -> 2 and 0 -> 3, with 4 as a separate root
+    for parent in [0u8, 1, 0] {
+        let mut bone = mdl.bones[0].clone();
+        bone.parent = parent.into();
+        mdl.bones.push(bone);
+    }
+    let mut root = mdl.bones[0].clone();
+    root.parent = BoneId::from(-1);
+    mdl.bones.push(root);
+    let bone = |index: u8| Handle::new(&mdl, &mdl.bones[index as usize], BoneId::from(index));
+    let keys = |path: Vec<Handle<Bone, BoneId>>| {
+        path.iter()
+            .map(|bone| usize::from(bone.key()))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        bone(2)
+            .lowest_common_ancestor(&bone(3))
+            .map(|bone| bone.key()),
+        Some(BoneId::from(0u8))
+    );
+    assert_eq!(
+        bone(2)
+            .lowest_common_ancestor(&bone(1))
+            .map(|bone| bone.key()),
+        Some(BoneId::from(1u8))
+    );
+    assert!(bone(2).lowest_common_ancestor(&bone(4)).is_none());
+
+    assert_eq!(keys(bone(2).path_to(&bone(3)).unwrap()), [2, 1, 0, 3]);
+    assert_eq!(keys(bone(0).path_to(&bone(2)).unwrap()), [0, 1, 2]);
+    assert_eq!(keys(bone(2).path_to(&bone(2)).unwrap()), [2]);
+    assert!(bone(3).path_to(&bone(4)).is_none());
+}
+
+#[test]
+fn write_unchanged() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mdl = Mdl::read(&data).unwrap();
+    assert_eq!(mdl.write(&data).unwrap(), data);
+}
+
+#[test]
+fn repath_materials() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mut mdl = Mdl::read(&data).unwrap();
+    assert_eq!(
+        mdl.repath_materials("Models\\props_badlands", "models/mymod"),
+        1
+    );
+    assert!(mdl.rename_texture("Barrel01.vmt", "barrel_red"));
+    assert!(!mdl.rename_texture("missing", "barrel_red"));
+
+    let written = mdl.write(&data).unwrap();
+    let mdl = Mdl::read(&written).unwrap();
+    assert!(mdl.validate().is_empty());
+    assert_eq!(mdl.texture_paths, ["models/mymod/"]);
+    assert_eq!(mdl.textures[0].name, "barrel_red");
+    assert_eq!(mdl.textures[0].search_paths, ["models/mymod/"]);
+    assert_eq!(mdl.body_parts[0].name, "Body");
+}
+
+#[test]
+fn recompute_hulls() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mut mdl = Mdl::read(&data).unwrap();
+    let vvd = Vvd::read(&read("data/barrel01.vvd").unwrap()).unwrap();
+    let positions = || vvd.vertices.iter().map(|vertex| vertex.position);
+    let min = positions().fold(positions().next().unwrap(), |a, b| a.min(b));
+    let max = positions().fold(positions().next().unwrap(), |a, b| a.max(b));
+
+    mdl.header.bounding_box = [Vector::default(); 2];
+    mdl.header.view_bounding_box = [Vector::default(); 2];
+    mdl.recompute_hulls(&vvd);
+    assert_eq!(mdl.header.hull_box(), [min, max]);
+    assert_eq!(mdl.header.view_box(), [min, max]);
+    assert!(!mdl.animation_sequences.is_empty());
+    for sequence in &mdl.animation_sequences {
+        assert_eq!(sequence.bounding_box, [min, max]);
+    }
+
+    let written = Mdl::read(&mdl.write(&data).unwrap()).unwrap();
+    assert_eq!(written.header.hull_box(), [min, max]);
+    assert_eq!(written.header.view_box(), [min, max]);
+    assert_eq!(written.animation_sequences[0].bounding_box, [min, max]);
+}
+
+#[test]
+fn recompute_hulls_delta() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mut mdl = Mdl::read(&data).unwrap();
+    let vvd = Vvd::read(&read("data/barrel01.vvd").unwrap()).unwrap();
+    let positions = (0..4)
+        .map(|frame| Vector::from([frame as f32 * 10.0, 0.0, 0.0]))
+        .collect();
+    mdl.local_animations[0] = AnimationDescription {
+        name: "delta".into(),
+        fps: 4.0,
+        flags: StudioAnimFlags::DELTA,
+        frame_count: 4,
+        zero_frame_stall_time: 0.0,
+        animation_block: None,
+        animations: vec![Animation::new(
+            BoneId::from(0u8),
+            AnimationFlags::STUDIO_ANIM_ANIMPOS | AnimationFlags::STUDIO_ANIM_DELTA,
+            RotationData::None,
+            PositionData::PositionValues(positions),
+        )],
+    };
+    // move the rest pose of the bone without moving the vertices
+    mdl.bones[0].set_rest_position(Vector::from([0.0, 0.0, 10.0]));
+    let world = Pose::rest(&mdl).world_transforms()[0];
+    mdl.bones[0].pose_to_bone = Transform3x4::from_matrix(world.invert().unwrap());
+    mdl.recompute_hulls(&vvd);
+
+    // the delta moves the bone from its rest position along the x axis of the bone
+    let rest = Pose::rest(&mdl);
+    let offset = rest.world_transforms()[0].transform_vector(Vector3::new(30.0, 0.0, 0.0));
+    let [min, max] = mdl.header.hull_box();
+    let [sequence_min, sequence_max] = mdl.animation_sequences[0].bounding_box;
+    let expected_min =
+        min + Vector::from([offset.x.min(0.0), offset.y.min(0.0), offset.z.min(0.0)]);
+    let expected_max =
+        max + Vector::from([offset.x.max(0.0), offset.y.max(0.0), offset.z.max(0.0)]);
+    assert!((sequence_min - expected_min).length() < 0.01);
+    assert!((sequence_max - expected_max).length() < 0.01);
+}
+
+#[test]
+fn write_animations() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mut mdl = Mdl::read(&data).unwrap();
+    let positions: Vec<Vector> = (0..4)
+        .map(|frame| Vector::from([frame as f32, 0.0, 0.5]))
+        .collect();
+    mdl.local_animations[0].frame_count = 4;
+    mdl.local_animations[0].animations = vec![Animation::new(
+        BoneId::from(0u8),
+        AnimationFlags::STUDIO_ANIM_ANIMPOS,
+        RotationData::None,
+        PositionData::PositionValues(positions.clone()),
+    )];
+
+    let written = mdl.write(&data).unwrap();
+    let animation = &Mdl::read(&written).unwrap().local_animations[0];
+    assert_eq!(animation.frame_count, 4);
+    for (frame, position) in positions.iter().enumerate() {
+        assert!((animation.animations[0].position(frame) - *position).length() < 0.01);
+    }
+
+    let options = WriteOptions {
+        compression: Some(CompressionOptions {
+            position_tolerance: 5.0,
+            ..CompressionOptions::default()
+        }),
+        ..WriteOptions::default()
+    };
+    let compressed = mdl.write_with_options(&data, options).unwrap();
+    assert!(compressed.len() < written.len());
+    let animation = &Mdl::read(&compressed).unwrap().local_animations[0];
+    assert_eq!(animation.animations[0].position(3), positions[0]);
+
+    mdl.local_animations[0].animations[0].bone = BoneId::from(3u8);
+    assert!(matches!(
+        mdl.write(&data),
+        Err(WriteError::Encode(EncodeError::InvalidBone(_)))
+    ));
+}
+
+#[test]
+fn patch_surface_prop() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mut mdl = Mdl::read(&data).unwrap();
+    let original = mdl.surface_prop.clone();
+    mdl.bones[0].surface_prop = original.clone();
+    mdl.set_surface_prop("wood");
+    assert_eq!(mdl.bones[0].surface_prop, "wood");
+    assert!(mdl.set_bone_surface_prop(BoneId::from(0u8), "wood_crate"));
+    assert!(!mdl.set_bone_surface_prop(BoneId::from(1u8), "wood_crate"));
+
+    let written = mdl.write(&data).unwrap();
+    let mdl = Mdl::read(&written).unwrap();
+    assert_ne!(original, "wood");
+    assert_eq!(mdl.surface_prop, "wood");
+    assert_eq!(mdl.bones[0].surface_prop, "wood_crate");
+    assert_eq!(mdl.bones[0].name, Mdl::read(&data).unwrap().bones[0].name);
+}
+
+#[test]
+fn patch_flags() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mut mdl = Mdl::read(&data).unwrap();
+    let checksum = mdl.header.checksum();
+    mdl.set_cast_shadows(false);
+    assert!(mdl.set_static_prop(true));
+    assert!(!mdl.set_flags(ModelFlags::VERT_ANIM_FIXED_POINT_SCALE, true));
+    assert!(mdl.set_flags(ModelFlags::NO_FORCED_FADE, true));
+
+    let kept = Mdl::read(&mdl.write(&data).unwrap()).unwrap();
+    assert_eq!(kept.header.checksum(), checksum);
+
+    let options = WriteOptions {
+        recompute_checksum: true,
+        ..WriteOptions::default()
+    };
+    let written = mdl.write_with_options(&data, options).unwrap();
+    assert_eq!(written, mdl.write_with_options(&data, options).unwrap());
+    let written = Mdl::read(&written).unwrap();
+    assert_ne!(written.header.checksum(), checksum);
+    assert!(written.header.flags.contains(
+        ModelFlags::DO_NOT_CAST_SHADOWS | ModelFlags::STATIC_PROP | ModelFlags::NO_FORCED_FADE
+    ));
+    assert!(!written
+        .header
+        .flags
+        .contains(ModelFlags::VERT_ANIM_FIXED_POINT_SCALE));
+
+    mdl.bones.push(mdl.bones[0].clone());
+    assert!(!mdl.set_static_prop(true));
+}
+
+/// Check that the editable parts of a written model match the model it was written from
+fn assert_written(mdl: &Mdl, written: &[u8]) {
+    let read = Mdl::read(written).unwrap();
+    let bits =
+        |[min, max]: [Vector; 2]| [min.x, min.y, min.z, max.x, max.y, max.z].map(f32::to_bits);
+    assert_eq!(read.surface_prop, mdl.surface_prop);
+    assert_eq!(read.header.flags.bits(), mdl.header.flags.bits());
+    assert_eq!(read.texture_paths, mdl.texture_paths);
+    for (read, bone) in read.bones.iter().zip(&mdl.bones) {
+        assert_eq!(read.surface_prop, bone.surface_prop);
+    }
+    for (read, texture) in read.textures.iter().zip(&mdl.textures) {
+        assert_eq!(read.name, texture.name);
+        assert_eq!(read.search_paths, texture.search_paths);
+    }
+    for (read, sequence) in read
+        .animation_sequences
+        .iter()
+        .zip(&mdl.animation_sequences)
+    {
+        assert_eq!(bits(read.bounding_box), bits(sequence.bounding_box));
+    }
+    // writing the written model without changes doesn't change it
+    assert_eq!(read.write(written).unwrap(), written);
+}
+
+#[test]
+fn apply_patches() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mut mdl = Mdl::read(&data).unwrap();
+    let bounding_box = [
+        Vector::from([-1.0, -2.0, -3.0]),
+        Vector::from([1.0, 2.0, 3.0]),
+    ];
+    let patches = [
+        Patch::SetSurfaceProp("metal".into()),
+        Patch::SetBoneSurfaceProp {
+            bone: BoneId::from(0u8),
+            surface_prop: "metal_barrel".into(),
+        },
+        Patch::RepathMaterials {
+            from: "models/props_badlands".into(),
+            to: "models/mymod".into(),
+        },
+        Patch::RenameTexture {
+            name: "barrel01".into(),
+            new_name: "barrel_red".into(),
+        },
+        Patch::SetFlags {
+            flags: ModelFlags::NO_FORCED_FADE,
+            enabled: true,
+        },
+        Patch::SetSequenceBoundingBox {
+            sequence: 0,
+            bounding_box,
+        },
+    ];
+    for patch in &patches {
+        assert!(mdl.apply(patch), "{patch:?} didn't apply");
+    }
+    assert!(!mdl.apply(&Patch::SetSequenceBoundingBox {
+        sequence: 100,
+        bounding_box,
+    }));
+    assert_eq!(mdl.animation_sequences[0].bounding_box, bounding_box);
+    assert_written(&mdl, &mdl.write(&data).unwrap());
+
+    // null bytes would truncate the stored strings
+    mdl.apply(&Patch::SetSurfaceProp("metal\0wood".into()));
+    assert!(matches!(mdl.write(&data), Err(WriteError::NullByte(_))));
+}
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn arbitrary_patches_round_trip() {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    let data = read("data/barrel01.mdl").unwrap();
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut written_count = 0;
+    for _ in 0..512 {
+        let bytes: Vec<u8> = (0..512)
+            .map(|_| {
+                // xorshift, any deterministic source of bytes works
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 32) as u8
+            })
+            .collect();
+        let mut input = Unstructured::new(&bytes);
+        let patches = Vec::<Patch>::arbitrary(&mut input).unwrap();
+        let options = WriteOptions::arbitrary(&mut input).unwrap();
+
+        let mut mdl = Mdl::read(&data).unwrap();
+        for patch in &patches {
+            mdl.apply(patch);
+        }
+        match mdl.write_with_options(&data, options) {
+            Ok(written) => {
+                assert_written(&mdl, &written);
+                written_count += 1;
+            }
+            Err(WriteError::NullByte(_)) => {}
+            Err(error) => panic!("failed to write {patches:?}: {error}"),
+        }
+    }
+    assert!(written_count > 0);
+}
+
+#[test]
+fn write_linked_checksum() {
+    let data = read("data/barrel01.mdl").unwrap();
+    let mut vtx = read("data/barrel01.dx90.vtx").unwrap();
+    let mut vvd = read("data/barrel01.vvd").unwrap();
+    let mut mdl = Mdl::read(&data).unwrap();
+    let checksum = mdl.header.checksum();
+    mdl.set_cast_shadows(false);
+
+    let linked = LinkedFiles {
+        vtx: vec![&mut vtx],
+        vvd: Some(&mut vvd),
+        phy: None,
+    };
+    let options = WriteOptions {
+        recompute_checksum: true,
+        ..WriteOptions::default()
+    };
+    let written = Mdl::read(&mdl.write_linked(&data, options, linked).unwrap()).unwrap();
+    assert_ne!(written.header.checksum(), checksum);
+    assert_eq!(
+        Vtx::read(&vtx).unwrap().header.checksum,
+        written.header.checksum()
+    );
+    assert_eq!(
+        Vvd::read(&vvd).unwrap().header.checksum,
+        written.header.checksum()
+    );
+
+    let linked = LinkedFiles {
+        vvd: Some(&mut vvd),
+        ..LinkedFiles::default()
+    };
+    let written = Mdl::read(
+        &mdl.write_linked(&data, WriteOptions::default(), linked)
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(written.header.checksum(), checksum);
+    assert_eq!(Vvd::read(&vvd).unwrap().header.checksum, checksum);
+
+    // none of the linked files are changed when one of them can't be read
+    let original_vtx = read("data/barrel01.dx90.vtx").unwrap();
+    let mut vtx = original_vtx.clone();
+    let mut phy = vec![0; 8];
+    let linked = LinkedFiles {
+        vtx: vec![&mut vtx],
+        vvd: None,
+        phy: Some(&mut phy),
+    };
+    assert!(mdl.write_linked(&data, options, linked).is_err());
+    assert_eq!(vtx, original_vtx);
+}